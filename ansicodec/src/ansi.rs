@@ -1330,6 +1330,11 @@ pub enum AnsiControlSequenceIntroducer {
     /// ESC[{code};{string};{...}p
     SetKeyboardStrings,
 
+    // MUD eXtension Protocol
+    /// MXP Line Mode
+    /// ESC[#z - switches the MXP mode, see [`MxpMode`](crate::mxp::MxpMode)
+    MXPLineMode(u8),
+
     /// Unknown or unsupported CSI command
     Unknown,
 }
@@ -1398,6 +1403,7 @@ impl AnsiControlSequenceIntroducer {
             AnsiControlSequenceIntroducer::TextCursorEnableMode => 6,
             AnsiControlSequenceIntroducer::AlternativeScreenBuffer => 7,
             AnsiControlSequenceIntroducer::SetKeyboardStrings => 3,
+            AnsiControlSequenceIntroducer::MXPLineMode(n) => 3 + self.count_digits(*n),
             AnsiControlSequenceIntroducer::Unknown => 0,
         }
     }
@@ -1470,6 +1476,7 @@ impl AnsiControlSequenceIntroducer {
     /// - **EraseInLine(mode)**: `ESC [ mode K`
     /// - **SaveCursorPosition**: `ESC [ s`
     /// - **RestoreCursorPosition**: `ESC [ u`
+    /// - **MXPLineMode(n)**: `ESC [ n z`
     /// - And others as documented in [`AnsiControlSequenceIntroducer`] variants
     ///
     /// # Examples
@@ -1598,6 +1605,10 @@ impl AnsiControlSequenceIntroducer {
                 writer.write_all(b"\x1b[p")?;
                 Ok(3)
             }
+            AnsiControlSequenceIntroducer::MXPLineMode(n) => {
+                writer.write_all(format!("\x1b[{}z", n).as_bytes())?;
+                Ok(format!("\x1b[{}z", n).len())
+            }
             AnsiControlSequenceIntroducer::Unknown => Ok(0),
         }
     }
@@ -1713,6 +1724,11 @@ impl std::fmt::Display for AnsiControlSequenceIntroducer {
                 write!(f, "\x1b[p")
             }
 
+            // MXP line mode
+            AnsiControlSequenceIntroducer::MXPLineMode(n) => {
+                write!(f, "\x1b[{}z", n)
+            }
+
             // Unknown commands
             AnsiControlSequenceIntroducer::Unknown => {
                 // Don't output anything for unknown commands
@@ -0,0 +1,214 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Character sets negotiated with the Telnet CHARSET option (RFC 2066)
+
+/// Character set used for text on the wire
+///
+/// Text is transcoded between the connection's charset and Rust strings, so
+/// handlers always see Unicode while the peer receives bytes it understands.
+/// Characters a charset cannot represent are sent as `?`.
+///
+/// # Examples
+///
+/// ```
+/// use termionix_ansicodec::Charset;
+///
+/// let charset = Charset::from_name("cp437").unwrap();
+/// assert_eq!(charset, Charset::Cp437);
+/// assert_eq!(charset.decode_byte(0xB0), '░');
+/// assert_eq!(charset.encode_char('░'), Some(0xB0));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Charset {
+    /// UTF-8, the default
+    #[default]
+    Utf8,
+    /// 7-bit US-ASCII
+    Ascii,
+    /// ISO-8859-1 (Latin-1)
+    Latin1,
+    /// IBM code page 437, the original PC character set
+    Cp437,
+    /// Windows code page 1252
+    Windows1252,
+}
+
+impl Charset {
+    /// All supported charsets
+    pub const ALL: [Charset; 5] = [
+        Charset::Utf8,
+        Charset::Ascii,
+        Charset::Latin1,
+        Charset::Cp437,
+        Charset::Windows1252,
+    ];
+
+    /// IANA name used in CHARSET negotiation
+    pub fn name(&self) -> &'static str {
+        match self {
+            Charset::Utf8 => "UTF-8",
+            Charset::Ascii => "US-ASCII",
+            Charset::Latin1 => "ISO-8859-1",
+            Charset::Cp437 => "IBM437",
+            Charset::Windows1252 => "WINDOWS-1252",
+        }
+    }
+
+    /// Looks up a charset by name or common alias, ignoring case
+    pub fn from_name(name: &str) -> Option<Charset> {
+        let name = name.trim().to_ascii_uppercase();
+        match name.as_str() {
+            "UTF-8" | "UTF8" => Some(Charset::Utf8),
+            "US-ASCII" | "ASCII" | "ANSI_X3.4-1968" => Some(Charset::Ascii),
+            "ISO-8859-1" | "ISO_8859-1" | "ISO8859-1" | "LATIN1" | "L1" => Some(Charset::Latin1),
+            "IBM437" | "CP437" | "437" => Some(Charset::Cp437),
+            "WINDOWS-1252" | "CP1252" => Some(Charset::Windows1252),
+            _ => None,
+        }
+    }
+
+    /// Whether each character is a single byte
+    pub fn is_single_byte(&self) -> bool {
+        !matches!(self, Charset::Utf8)
+    }
+
+    /// Decodes a byte of a single-byte charset
+    ///
+    /// ASCII bytes decode to themselves. Bytes above `0x7F` are meaningless in
+    /// UTF-8 and US-ASCII and decode to U+FFFD.
+    pub fn decode_byte(&self, byte: u8) -> char {
+        if byte.is_ascii() {
+            return byte as char;
+        }
+        match self {
+            Charset::Utf8 | Charset::Ascii => char::REPLACEMENT_CHARACTER,
+            Charset::Latin1 => byte as char,
+            Charset::Cp437 => CP437_HIGH[byte as usize - 0x80],
+            Charset::Windows1252 => match byte {
+                0x80..=0x9F => WINDOWS_1252_C1[byte as usize - 0x80],
+                _ => byte as char,
+            },
+        }
+    }
+
+    /// Encodes a character as a single byte, if this charset can represent it
+    ///
+    /// Always `None` for non-ASCII characters in UTF-8, which are multi-byte.
+    pub fn encode_char(&self, ch: char) -> Option<u8> {
+        if ch.is_ascii() {
+            return Some(ch as u8);
+        }
+        match self {
+            Charset::Utf8 | Charset::Ascii => None,
+            Charset::Latin1 => u8::try_from(u32::from(ch)).ok(),
+            Charset::Cp437 => CP437_HIGH
+                .iter()
+                .position(|&mapped| mapped == ch)
+                .map(|index| index as u8 + 0x80),
+            Charset::Windows1252 => match WINDOWS_1252_C1.iter().position(|&mapped| mapped == ch) {
+                Some(index) => Some(index as u8 + 0x80),
+                None => u8::try_from(u32::from(ch))
+                    .ok()
+                    .filter(|byte| *byte >= 0xA0),
+            },
+        }
+    }
+
+    /// Appends `text` encoded in this charset to `dst`
+    pub fn encode_str(&self, text: &str, dst: &mut Vec<u8>) {
+        match self {
+            Charset::Utf8 => dst.extend_from_slice(text.as_bytes()),
+            _ => dst.extend(text.chars().map(|ch| self.encode_char(ch).unwrap_or(b'?'))),
+        }
+    }
+}
+
+impl std::fmt::Display for Charset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Negotiation progress of [`AnsiCodec`](crate::AnsiCodec)'s CHARSET negotiator
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum CharsetNegotiation {
+    /// No request sent or answered yet
+    #[default]
+    Idle,
+    /// Our `REQUEST` awaits an answer
+    Requested,
+    /// A charset was agreed, or our request was rejected
+    Done,
+}
+
+/// CP437 characters `0x80` through `0xFF`
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+];
+
+/// Windows-1252 characters `0x80` through `0x9F`; unassigned bytes keep their C1 code point
+const WINDOWS_1252_C1: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}',
+    'ž', 'Ÿ',
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_byte_round_trip() {
+        for charset in [Charset::Latin1, Charset::Cp437, Charset::Windows1252] {
+            for byte in 0..=u8::MAX {
+                let ch = charset.decode_byte(byte);
+                assert_eq!(charset.encode_char(ch), Some(byte), "{charset} {byte:#x}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_unrepresentable_characters() {
+        let mut dst = Vec::new();
+        Charset::Latin1.encode_str("café ░ €", &mut dst);
+        assert_eq!(dst, b"caf\xE9 ? ?");
+
+        dst.clear();
+        Charset::Windows1252.encode_str("€5", &mut dst);
+        assert_eq!(dst, b"\x805");
+
+        dst.clear();
+        Charset::Ascii.encode_str("naïve", &mut dst);
+        assert_eq!(dst, b"na?ve");
+    }
+
+    #[test]
+    fn test_names() {
+        for charset in Charset::ALL {
+            assert_eq!(Charset::from_name(charset.name()), Some(charset));
+        }
+        assert_eq!(Charset::from_name("latin1"), Some(Charset::Latin1));
+        assert_eq!(Charset::from_name("KOI8-R"), None);
+    }
+}
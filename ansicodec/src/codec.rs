@@ -19,9 +19,13 @@ use crate::ansi::{
     AnsiDeviceControlString, AnsiOperatingSystemCommand, AnsiPrivacyMessage,
     AnsiSelectGraphicRendition, AnsiSequence, AnsiStartOfString, TelnetCommand,
 };
-use crate::{AnsiCodecError, AnsiCodecResult, AnsiConfig, AnsiParser};
-use termionix_telnetcodec::TelnetEvent;
-use tokio_util::bytes::BytesMut;
+use crate::charset::CharsetNegotiation;
+use crate::{AnsiCodecError, AnsiCodecResult, AnsiConfig, AnsiParser, Charset};
+use termionix_telnetcodec::{
+    NegotiationGate, TelnetArgument, TelnetCodecError, TelnetEvent, TelnetFrame, TelnetOption,
+    TelnetResponder, TelnetSide,
+};
+use tokio_util::bytes::{Buf, Bytes, BytesMut};
 use tokio_util::codec::{Decoder, Encoder};
 use tracing::instrument;
 
@@ -35,6 +39,14 @@ pub struct AnsiCodec<I> {
     config: AnsiConfig,
     parser: AnsiParser,
     inner: I,
+    /// Remainder of a `TelnetEvent::DataChunk` not yet fed to the parser
+    pending: Bytes,
+    /// Character set text is transcoded to and from
+    charset: Charset,
+    /// Charsets offered and accepted in CHARSET negotiation, in order of preference
+    offered_charsets: Vec<Charset>,
+    /// Progress of our CHARSET negotiation
+    negotiation: CharsetNegotiation,
 }
 
 impl<I> AnsiCodec<I> {
@@ -44,6 +56,10 @@ impl<I> AnsiCodec<I> {
             config,
             inner: codec,
             parser: AnsiParser::new(),
+            pending: Bytes::new(),
+            charset: Charset::Utf8,
+            offered_charsets: Vec::new(),
+            negotiation: CharsetNegotiation::Idle,
         }
     }
 
@@ -56,11 +72,145 @@ impl<I> AnsiCodec<I> {
     pub fn inner_mut(&mut self) -> &mut I {
         &mut self.inner
     }
+
+    /// Character set text is currently transcoded to and from
+    pub fn charset(&self) -> Charset {
+        self.charset
+    }
+
+    /// Switch the character set used for text in both directions
+    pub fn set_charset(&mut self, charset: Charset) {
+        self.charset = charset;
+    }
+
+    /// Charsets offered in CHARSET negotiation, in order of preference
+    pub fn offered_charsets(&self) -> &[Charset] {
+        &self.offered_charsets
+    }
+
+    /// Negotiate the connection's charset with the Telnet CHARSET option (RFC 2066)
+    ///
+    /// Once the peer agrees to our `WILL CHARSET` a `REQUEST` listing
+    /// `charsets` is sent, and the charset the peer accepts is switched to. A
+    /// `REQUEST` from the peer is answered with the first of its charsets that
+    /// is also in `charsets`, or rejected if there is none or our own request
+    /// is still outstanding. An empty list disables negotiation.
+    pub fn set_offered_charsets(&mut self, charsets: Vec<Charset>) {
+        self.offered_charsets = charsets;
+    }
+
+    /// Feed one byte of text to the parser, decoding it from the current charset
+    fn parse_byte(&mut self, byte: u8) -> AnsiCodecResult<Option<AnsiSequence>> {
+        if byte.is_ascii() || !self.charset.is_single_byte() {
+            return self.parser.next(byte);
+        }
+        let mut buf = [0; 4];
+        let mut sequence = None;
+        for byte in self.charset.decode_byte(byte).encode_utf8(&mut buf).bytes() {
+            sequence = self.parser.next(byte)?;
+        }
+        Ok(sequence)
+    }
+
+    /// Encode text in the current charset
+    fn transcode(&self, text: &str) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(text.len());
+        self.charset.encode_str(text, &mut bytes);
+        bytes
+    }
+}
+
+impl<I> AnsiCodec<I>
+where
+    I: TelnetResponder,
+{
+    /// Drive CHARSET negotiation from a received option change or subnegotiation
+    fn negotiate_charset(&mut self, event: &TelnetEvent) -> AnsiCodecResult<()> {
+        if self.offered_charsets.is_empty() {
+            return Ok(());
+        }
+        match event {
+            TelnetEvent::OptionStatus(TelnetOption::Charset, TelnetSide::Local, true)
+                if self.negotiation == CharsetNegotiation::Idle =>
+            {
+                let names = self
+                    .offered_charsets
+                    .iter()
+                    .map(|charset| BytesMut::from(charset.name()))
+                    .collect();
+                self.inner
+                    .queue_subnegotiation(TelnetArgument::CharsetRequest(names))?;
+                self.negotiation = CharsetNegotiation::Requested;
+            }
+            TelnetEvent::OptionStatus(TelnetOption::Charset, TelnetSide::Local, false) => {
+                self.negotiation = CharsetNegotiation::Idle;
+            }
+            TelnetEvent::Subnegotiate(TelnetArgument::CharsetAccepted(name)) => {
+                if let Some(charset) = std::str::from_utf8(name).ok().and_then(Charset::from_name) {
+                    self.charset = charset;
+                }
+                self.negotiation = CharsetNegotiation::Done;
+            }
+            TelnetEvent::Subnegotiate(
+                TelnetArgument::CharsetRejected | TelnetArgument::CharsetTTableRejected,
+            ) => {
+                self.negotiation = CharsetNegotiation::Done;
+            }
+            TelnetEvent::Subnegotiate(TelnetArgument::CharsetRequest(names)) => {
+                let accepted = if self.negotiation == CharsetNegotiation::Requested {
+                    None
+                } else {
+                    names.iter().find_map(|name| {
+                        let charset = std::str::from_utf8(name)
+                            .ok()
+                            .and_then(Charset::from_name)?;
+                        self.offered_charsets
+                            .contains(&charset)
+                            .then(|| (charset, name.clone()))
+                    })
+                };
+                match accepted {
+                    Some((charset, name)) => {
+                        self.inner
+                            .queue_subnegotiation(TelnetArgument::CharsetAccepted(name))?;
+                        self.charset = charset;
+                        self.negotiation = CharsetNegotiation::Done;
+                    }
+                    None => self
+                        .inner
+                        .queue_subnegotiation(TelnetArgument::CharsetRejected)?,
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl<I> TelnetResponder for AnsiCodec<I>
+where
+    I: TelnetResponder,
+{
+    fn has_pending_responses(&self) -> bool {
+        self.inner.has_pending_responses()
+    }
+
+    fn flush_responses(&mut self, dst: &mut BytesMut) -> Result<(), TelnetCodecError> {
+        self.inner.flush_responses(dst)
+    }
+
+    fn queue_subnegotiation(&mut self, argument: TelnetArgument) -> Result<(), TelnetCodecError> {
+        self.inner.queue_subnegotiation(argument)
+    }
+
+    fn set_negotiation_gate(&mut self, gate: Option<NegotiationGate>) {
+        self.inner.set_negotiation_gate(gate);
+    }
 }
 
 impl<I> Decoder for AnsiCodec<I>
 where
-    I: Decoder<Item = TelnetEvent>,
+    I: Decoder<Item = TelnetEvent> + TelnetResponder,
     AnsiCodecError: From<I::Error>,
 {
     type Item = AnsiSequence;
@@ -68,15 +218,30 @@ where
 
     #[instrument(skip_all)]
     fn decode(&mut self, src: &mut BytesMut) -> AnsiCodecResult<Option<Self::Item>> {
-        if let Some(event) = self.inner.decode(src)? {
-            match event {
+        loop {
+            // Drain any buffered data chunk before pulling the next Telnet event
+            while self.pending.has_remaining() {
+                let byte = self.pending.get_u8();
+                if let Some(sequence) = self.parse_byte(byte)? {
+                    return Ok(Some(sequence));
+                }
+            }
+            let Some(event) = self.inner.decode(src)? else {
+                return Ok(None);
+            };
+            self.negotiate_charset(&event)?;
+            return match event {
                 TelnetEvent::Data(byte) => {
                     // Process the byte through the ANSI mapper
-                    if let Some(sequence) = self.parser.next(byte)? {
+                    if let Some(sequence) = self.parse_byte(byte)? {
                         return Ok(Some(sequence));
                     }
                     // If we got no complete sequence, continue decoding
-                    self.decode(src)
+                    continue;
+                }
+                TelnetEvent::DataChunk(chunk) => {
+                    self.pending = chunk;
+                    continue;
                 }
                 TelnetEvent::NoOperation => Ok(Some(AnsiSequence::TelnetCommand(
                     TelnetCommand::NoOperation,
@@ -112,23 +277,26 @@ where
                 TelnetEvent::Subnegotiate(arg) => Ok(Some(AnsiSequence::TelnetCommand(
                     TelnetCommand::Subnegotiation(arg),
                 ))),
-            }
-        } else {
-            Ok(None)
+            };
         }
     }
 }
 
-impl<I> Encoder<char> for AnsiCodec<I>
+impl<I, E> Encoder<char> for AnsiCodec<I>
 where
-    I: Encoder<char>,
-    AnsiCodecError: From<I::Error>,
+    I: Encoder<char, Error = E> + Encoder<u8, Error = E>,
+    AnsiCodecError: From<E>,
 {
     type Error = AnsiCodecError;
 
     fn encode(&mut self, item: char, dst: &mut BytesMut) -> AnsiCodecResult<()> {
         // Encode plain text as telnet data
-        self.inner.encode(item, dst)?;
+        if self.charset.is_single_byte() {
+            let byte = self.charset.encode_char(item).unwrap_or(b'?');
+            self.inner.encode(byte, dst)?;
+        } else {
+            self.inner.encode(item, dst)?;
+        }
         Ok(())
     }
 }
@@ -141,8 +309,14 @@ where
     type Error = AnsiCodecError;
 
     fn encode(&mut self, item: &str, dst: &mut BytesMut) -> AnsiCodecResult<()> {
-        for byte in item.as_bytes() {
-            self.inner.encode(*byte, dst)?;
+        if self.charset.is_single_byte() {
+            for byte in self.transcode(item) {
+                self.inner.encode(byte, dst)?;
+            }
+        } else {
+            for byte in item.as_bytes() {
+                self.inner.encode(*byte, dst)?;
+            }
         }
         Ok(())
     }
@@ -315,25 +489,42 @@ where
 
 impl<I> Encoder<TelnetCommand> for AnsiCodec<I>
 where
-    I: Encoder<u8>,
+    I: Encoder<TelnetFrame>,
     AnsiCodecError: From<I::Error>,
 {
     type Error = AnsiCodecError;
 
     fn encode(&mut self, item: TelnetCommand, dst: &mut BytesMut) -> AnsiCodecResult<()> {
-        let mut buf = BytesMut::new();
-        item.encode(&mut buf)?;
-        for byte in buf.iter() {
-            self.inner.encode(*byte, dst)?;
+        // Commands are framed by the telnet layer so their IAC bytes are not escaped
+        if let Some(frame) = telnet_frame(item) {
+            self.inner.encode(frame, dst)?;
         }
         Ok(())
     }
 }
 
-impl<I> Encoder<AnsiSequence> for AnsiCodec<I>
+/// The frame sending a telnet command, `None` for status notifications
+fn telnet_frame(command: TelnetCommand) -> Option<TelnetFrame> {
+    match command {
+        TelnetCommand::NoOperation => Some(TelnetFrame::NoOperation),
+        TelnetCommand::DataMark => Some(TelnetFrame::DataMark),
+        TelnetCommand::Break => Some(TelnetFrame::Break),
+        TelnetCommand::InterruptProcess => Some(TelnetFrame::InterruptProcess),
+        TelnetCommand::AbortOutput => Some(TelnetFrame::AbortOutput),
+        TelnetCommand::AreYouThere => Some(TelnetFrame::AreYouThere),
+        TelnetCommand::EraseCharacter => Some(TelnetFrame::EraseCharacter),
+        TelnetCommand::EraseLine => Some(TelnetFrame::EraseLine),
+        TelnetCommand::GoAhead => Some(TelnetFrame::GoAhead),
+        TelnetCommand::EndOfRecord => Some(TelnetFrame::EndOfRecord),
+        TelnetCommand::OptionStatus(..) => None,
+        TelnetCommand::Subnegotiation(argument) => Some(TelnetFrame::Subnegotiate(argument)),
+    }
+}
+
+impl<I, E> Encoder<AnsiSequence> for AnsiCodec<I>
 where
-    I: Encoder<u8>,
-    AnsiCodecError: From<I::Error>,
+    I: Encoder<u8, Error = E> + Encoder<TelnetFrame, Error = E>,
+    AnsiCodecError: From<E>,
 {
     type Error = AnsiCodecError;
 
@@ -341,16 +532,14 @@ where
         match item {
             AnsiSequence::Character(ch) => {
                 let mut buf = [0u8; 4];
-                let s = ch.encode_utf8(&mut buf);
-                for byte in s.as_bytes() {
-                    self.inner.encode(*byte, dst)?;
+                for byte in self.transcode(ch.encode_utf8(&mut buf)) {
+                    self.inner.encode(byte, dst)?;
                 }
             }
             AnsiSequence::Unicode(ch) => {
                 let mut buf = [0u8; 4];
-                let s = ch.encode_utf8(&mut buf);
-                for byte in s.as_bytes() {
-                    self.inner.encode(*byte, dst)?;
+                for byte in self.transcode(ch.encode_utf8(&mut buf)) {
+                    self.inner.encode(byte, dst)?;
                 }
             }
             AnsiSequence::AnsiControlCode(code) => {
@@ -413,10 +602,8 @@ where
                 }
             }
             AnsiSequence::TelnetCommand(cmd) => {
-                let mut buf = BytesMut::new();
-                cmd.encode(&mut buf)?;
-                for byte in buf.iter() {
-                    self.inner.encode(*byte, dst)?;
+                if let Some(frame) = telnet_frame(cmd) {
+                    self.inner.encode(frame, dst)?;
                 }
             }
         }
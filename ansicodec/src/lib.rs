@@ -15,9 +15,11 @@
 //
 
 pub mod ansi;
+mod charset;
 mod codec;
 mod config;
 mod consts;
+pub mod mxp;
 mod parser;
 mod result;
 mod string;
@@ -29,6 +31,7 @@ pub use self::ansi::{
     AnsiDeviceControlString, AnsiOperatingSystemCommand, AnsiPrivacyMessage,
     AnsiSelectGraphicRendition, AnsiSequence, AnsiStartOfString, TelnetCommand,
 };
+pub use self::charset::Charset;
 pub use self::codec::AnsiCodec;
 pub use self::config::{AnsiConfig, ColorMode};
 pub use self::parser::AnsiParser;
@@ -37,9 +40,10 @@ pub use self::string::{Segment, SegmentedString};
 pub use self::style::{Blink, Color, Font, Ideogram, Intensity, SGRParameter, Script, Underline};
 pub use self::utility::{Span, SpannedString, StyledString, strip_ansi_codes};
 pub use termionix_telnetcodec::{
-    SubnegotiationErrorKind, TelnetArgument, TelnetCodec, TelnetCodecError, TelnetCodecResult,
-    TelnetEvent, TelnetFrame, TelnetOption, TelnetSide, gmcp, linemode, msdp, mssp, naocrd, naohts,
-    naws, status,
+    CodecLimits, NegotiationGate, OptionPolicy, SubnegotiationErrorKind, TelnetArgument,
+    TelnetCodec, TelnetCodecError, TelnetCodecResult, TelnetEvent, TelnetFrame, TelnetOption,
+    TelnetResponder, TelnetSide, atcp, gmcp, linemode, msdp, mssp, naocrd, naohts, naws,
+    new_environ, status, zmp,
 };
 
 #[cfg(test)]
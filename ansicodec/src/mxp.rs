@@ -0,0 +1,1618 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! MXP (MUD eXtension Protocol) parsing and generation
+//!
+//! Once MXP is negotiated (`TelnetOption::MXP`), servers mix tags such as
+//! `<SEND>`, `<A>` and `<COLOR>` into their text and switch between open,
+//! secure and locked modes with `ESC[#z` sequences
+//! ([`AnsiControlSequenceIntroducer::MXPLineMode`]).
+//!
+//! - [`MxpParser`] turns received text into a tree of [`MxpNode`]s, expanding
+//!   `<!ELEMENT>` definitions and decoding entities
+//! - [`MxpText::render`] flattens a tree into a [`StyledString`] with link spans
+//! - [`MxpBuilder`] writes MXP text, escaping plain text
+//!
+//! See the [MXP specification](https://www.zuggsoft.com/zmud/mxp.htm).
+
+use crate::ansi::{AnsiControlCode, AnsiControlSequenceIntroducer, AnsiSequence};
+use crate::string::{Segment, SegmentedString};
+use crate::style::{AnsiSelectGraphicRendition, Color, Intensity, Underline};
+use crate::utility::StyledString;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Longest tag or entity accepted before it is treated as plain text
+const MAX_MARKUP_LEN: usize = 4096;
+
+/// Deepest nesting of custom elements expanded into other custom elements
+const MAX_EXPANSION_DEPTH: usize = 8;
+
+/// Most elements one tag may open, counting those its expansion opens
+const MAX_EXPANSION_NODES: usize = 256;
+
+/// Deepest nesting of open elements; tags opened past it are ignored
+///
+/// This also bounds the depth of parsed nodes, so walking them cannot
+/// overflow the stack.
+const MAX_OPEN_ELEMENTS: usize = 64;
+
+/// Tags allowed in open mode
+const OPEN_TAGS: &[&str] = &[
+    "b",
+    "bold",
+    "strong",
+    "i",
+    "italic",
+    "em",
+    "u",
+    "underline",
+    "s",
+    "strikeout",
+    "c",
+    "color",
+    "h",
+    "high",
+    "font",
+    "nobr",
+    "p",
+    "br",
+    "sbr",
+];
+
+/// Tags that have no content and no closing tag
+const EMPTY_TAGS: &[&str] = &[
+    "br", "sbr", "hr", "image", "sound", "music", "expire", "gauge", "stat", "version", "support",
+    "relocate", "user", "password",
+];
+
+/// MXP mode selected with an `ESC[#z` sequence
+///
+/// Line modes (`Open`, `Secure`, `Locked`) last until the next newline,
+/// after which the default mode applies again. The `Lock*` modes change the
+/// default mode.
+///
+/// # Examples
+///
+/// ```
+/// use termionix_ansicodec::mxp::MxpMode;
+///
+/// assert_eq!(MxpMode::from_code(1), Some(MxpMode::Secure));
+/// assert_eq!(MxpMode::Secure.to_string(), "\x1b[1z");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MxpMode {
+    /// Only open tags such as `<B>` and `<COLOR>` until the end of the line
+    #[default]
+    Open,
+    /// All tags until the end of the line
+    Secure,
+    /// No tags until the end of the line; `<` and `&` are plain text
+    Locked,
+    /// Close all tags and return to open mode
+    Reset,
+    /// The next tag is secure
+    TempSecure,
+    /// Open mode until changed
+    LockOpen,
+    /// Secure mode until changed
+    LockSecure,
+    /// Locked mode until changed
+    LockLocked,
+}
+
+impl MxpMode {
+    /// Looks up a mode by its `ESC[#z` code
+    ///
+    /// Codes 10 through 99 select user-defined line tags and return `None`.
+    pub fn from_code(code: u8) -> Option<MxpMode> {
+        match code {
+            0 => Some(MxpMode::Open),
+            1 => Some(MxpMode::Secure),
+            2 => Some(MxpMode::Locked),
+            3 => Some(MxpMode::Reset),
+            4 => Some(MxpMode::TempSecure),
+            5 => Some(MxpMode::LockOpen),
+            6 => Some(MxpMode::LockSecure),
+            7 => Some(MxpMode::LockLocked),
+            _ => None,
+        }
+    }
+
+    /// The `ESC[#z` code of this mode
+    pub fn code(&self) -> u8 {
+        match self {
+            MxpMode::Open => 0,
+            MxpMode::Secure => 1,
+            MxpMode::Locked => 2,
+            MxpMode::Reset => 3,
+            MxpMode::TempSecure => 4,
+            MxpMode::LockOpen => 5,
+            MxpMode::LockSecure => 6,
+            MxpMode::LockLocked => 7,
+        }
+    }
+
+    /// The control sequence that selects this mode
+    pub fn sequence(&self) -> AnsiControlSequenceIntroducer {
+        AnsiControlSequenceIntroducer::MXPLineMode(self.code())
+    }
+}
+
+impl std::fmt::Display for MxpMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.sequence())
+    }
+}
+
+/// Attribute of an MXP tag, named (`hint="Look"`) or positional (`"look"`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MxpAttribute {
+    /// Attribute name, `None` for positional arguments
+    pub name: Option<String>,
+    /// Attribute value with entities decoded
+    pub value: String,
+}
+
+/// An MXP tag with its attributes
+///
+/// Tag names are stored in lowercase, as MXP ignores case.
+///
+/// # Examples
+///
+/// ```
+/// use termionix_ansicodec::mxp::MxpTag;
+///
+/// let tag = MxpTag::new("SEND").with_argument("look").with_attribute("hint", "Look around");
+/// assert_eq!(tag.name(), "send");
+/// assert_eq!(tag.argument(0), Some("look"));
+/// assert_eq!(tag.get("HINT"), Some("Look around"));
+/// assert_eq!(tag.to_string(), r#"<SEND "look" hint="Look around">"#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MxpTag {
+    name: String,
+    attributes: Vec<MxpAttribute>,
+}
+
+impl MxpTag {
+    /// Create a tag without attributes
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_ascii_lowercase(),
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Add a named attribute
+    pub fn with_attribute(mut self, name: &str, value: &str) -> Self {
+        self.attributes.push(MxpAttribute {
+            name: Some(name.to_string()),
+            value: value.to_string(),
+        });
+        self
+    }
+
+    /// Add a positional argument
+    pub fn with_argument(mut self, value: &str) -> Self {
+        self.attributes.push(MxpAttribute {
+            name: None,
+            value: value.to_string(),
+        });
+        self
+    }
+
+    /// The lowercase tag name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// All attributes in the order they appeared
+    pub fn attributes(&self) -> &[MxpAttribute] {
+        &self.attributes
+    }
+
+    /// The value of a named attribute, ignoring case
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attribute| {
+                attribute
+                    .name
+                    .as_deref()
+                    .is_some_and(|own| own.eq_ignore_ascii_case(name))
+            })
+            .map(|attribute| attribute.value.as_str())
+    }
+
+    /// The positional argument at `index`
+    pub fn argument(&self, index: usize) -> Option<&str> {
+        self.attributes
+            .iter()
+            .filter(|attribute| attribute.name.is_none())
+            .nth(index)
+            .map(|attribute| attribute.value.as_str())
+    }
+
+    /// Whether a positional argument equals `flag`, ignoring case, e.g. `PROMPT`
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.attributes
+            .iter()
+            .any(|attribute| attribute.name.is_none() && attribute.value.eq_ignore_ascii_case(flag))
+    }
+
+    /// A named attribute, or the positional argument at `index` without a name
+    fn value(&self, name: &str, index: usize) -> Option<&str> {
+        self.get(name).or_else(|| self.argument(index))
+    }
+}
+
+impl std::fmt::Display for MxpTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{}", self.name.to_ascii_uppercase())?;
+        for attribute in &self.attributes {
+            match &attribute.name {
+                Some(name) => write!(f, " {}=\"{}\"", name, escape(&attribute.value))?,
+                None => write!(f, " \"{}\"", escape(&attribute.value))?,
+            }
+        }
+        write!(f, ">")
+    }
+}
+
+/// A node of a parsed MXP document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MxpNode {
+    /// Plain text with entities decoded
+    Text(String),
+    /// A tag and the nodes it encloses
+    Element(MxpElement),
+}
+
+impl MxpNode {
+    /// The text of this node and all nodes it encloses
+    pub fn text(&self) -> String {
+        match self {
+            MxpNode::Text(text) => text.clone(),
+            MxpNode::Element(element) => element.text(),
+        }
+    }
+}
+
+/// A tag and the nodes it encloses
+///
+/// A custom element encloses the elements of its definition, which enclose
+/// its content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MxpElement {
+    /// The opening tag
+    pub tag: MxpTag,
+    /// Enclosed nodes
+    pub children: Vec<MxpNode>,
+}
+
+impl MxpElement {
+    /// The text of all enclosed nodes
+    pub fn text(&self) -> String {
+        self.children.iter().map(MxpNode::text).collect()
+    }
+}
+
+/// A custom element defined with `<!ELEMENT>`
+///
+/// `<!ELEMENT boldred "<COLOR &col;><B>" ATT="col=red" OPEN>` defines
+/// `<boldred>`, which expands to `<COLOR red><B>` unless a `col` attribute
+/// is given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MxpElementDefinition {
+    /// Lowercase element name
+    pub name: String,
+    /// The tags the element expands to, with `&attribute;` placeholders
+    pub definition: String,
+    /// Attribute names with their default values
+    pub attributes: Vec<(String, String)>,
+    /// Whether the element may be used in open mode
+    pub open: bool,
+    /// Whether the element has no content and no closing tag
+    pub empty: bool,
+}
+
+impl MxpElementDefinition {
+    /// Create a secure element that expands to `definition`
+    pub fn new(name: &str, definition: &str) -> Self {
+        Self {
+            name: name.to_ascii_lowercase(),
+            definition: definition.to_string(),
+            attributes: Vec::new(),
+            open: false,
+            empty: false,
+        }
+    }
+
+    /// Add an attribute and its default value
+    pub fn with_attribute(mut self, name: &str, default: &str) -> Self {
+        self.attributes
+            .push((name.to_ascii_lowercase(), default.to_string()));
+        self
+    }
+
+    /// Allow the element in open mode
+    pub fn with_open(mut self, open: bool) -> Self {
+        self.open = open;
+        self
+    }
+
+    /// Make the element empty
+    pub fn with_empty(mut self, empty: bool) -> Self {
+        self.empty = empty;
+        self
+    }
+
+    /// The definition with the placeholders of `tag`'s attributes substituted
+    fn expand(&self, tag: &MxpTag) -> String {
+        let mut expansion = self.definition.clone();
+        for (index, (name, default)) in self.attributes.iter().enumerate() {
+            let value = tag.value(name, index).unwrap_or(default);
+            expansion = expansion.replace(&format!("&{name};"), value);
+        }
+        expansion
+    }
+}
+
+impl std::fmt::Display for MxpElementDefinition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let quote = match self.definition.contains('"') {
+            true => '\'',
+            false => '"',
+        };
+        write!(
+            f,
+            "<!ELEMENT {} {quote}{}{quote}",
+            self.name, self.definition
+        )?;
+        if !self.attributes.is_empty() {
+            let attributes: Vec<String> = self
+                .attributes
+                .iter()
+                .map(|(name, default)| match default.is_empty() {
+                    true => name.clone(),
+                    false => format!("{name}={default}"),
+                })
+                .collect();
+            write!(f, " ATT='{}'", attributes.join(" "))?;
+        }
+        if self.open {
+            write!(f, " OPEN")?;
+        }
+        if self.empty {
+            write!(f, " EMPTY")?;
+        }
+        write!(f, ">")
+    }
+}
+
+/// What the parser is collecting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum MarkupState {
+    /// Plain text
+    #[default]
+    Text,
+    /// A tag after `<`, inside the given quote if any
+    Tag(Option<char>),
+    /// An entity after `&`
+    Entity,
+}
+
+/// An element waiting for its closing tag
+#[derive(Debug, Clone)]
+struct OpenElement {
+    element: MxpElement,
+    /// Closed at the end of the line
+    line: bool,
+}
+
+/// Streaming MXP parser
+///
+/// Feed it received text, mode changes and newlines, then collect finished
+/// top-level nodes with [`take_nodes`](Self::take_nodes). Elements stay open
+/// until closed, until the end of the line when opened in a line mode or
+/// open mode, or until [`finish`](Self::finish).
+///
+/// Secure tags are ignored in open mode and all markup is plain text in
+/// locked mode.
+///
+/// # Examples
+///
+/// ```
+/// use termionix_ansicodec::mxp::{MxpMode, MxpNode, MxpParser};
+///
+/// let mut parser = MxpParser::new();
+/// parser.set_mode(MxpMode::Secure);
+/// parser.push_str("You see <SEND \"get sword\">a sword</SEND> &amp; more.\n");
+///
+/// let nodes = parser.take_nodes();
+/// assert_eq!(nodes[0], MxpNode::Text("You see ".to_string()));
+/// match &nodes[1] {
+///     MxpNode::Element(element) => {
+///         assert_eq!(element.tag.name(), "send");
+///         assert_eq!(element.text(), "a sword");
+///     }
+///     other => panic!("unexpected {other:?}"),
+/// }
+/// assert_eq!(nodes[2], MxpNode::Text(" & more.\n".to_string()));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MxpParser {
+    default_mode: MxpMode,
+    line_mode: Option<MxpMode>,
+    temp_secure: bool,
+    state: MarkupState,
+    markup: String,
+    elements: HashMap<String, MxpElementDefinition>,
+    entities: HashMap<String, String>,
+    stack: Vec<OpenElement>,
+    nodes: Vec<MxpNode>,
+}
+
+impl MxpParser {
+    /// Create a parser in open mode without custom elements or entities
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The mode the current line is parsed in: `Open`, `Secure` or `Locked`
+    pub fn mode(&self) -> MxpMode {
+        if self.temp_secure {
+            MxpMode::Secure
+        } else {
+            self.line_mode.unwrap_or(self.default_mode)
+        }
+    }
+
+    /// Switch modes, as an `ESC[#z` sequence does
+    pub fn set_mode(&mut self, mode: MxpMode) {
+        match mode {
+            MxpMode::Open | MxpMode::Secure | MxpMode::Locked => self.line_mode = Some(mode),
+            MxpMode::Reset => {
+                self.close_all();
+                self.default_mode = MxpMode::Open;
+                self.line_mode = None;
+                self.temp_secure = false;
+            }
+            MxpMode::TempSecure => self.temp_secure = true,
+            MxpMode::LockOpen => self.lock(MxpMode::Open),
+            MxpMode::LockSecure => self.lock(MxpMode::Secure),
+            MxpMode::LockLocked => self.lock(MxpMode::Locked),
+        }
+    }
+
+    /// A custom element defined with `<!ELEMENT>`
+    pub fn element(&self, name: &str) -> Option<&MxpElementDefinition> {
+        self.elements.get(&name.to_ascii_lowercase())
+    }
+
+    /// Define a custom element, as `<!ELEMENT>` does
+    pub fn define_element(&mut self, definition: MxpElementDefinition) {
+        self.elements.insert(definition.name.clone(), definition);
+    }
+
+    /// The value of a custom entity defined with `<!ENTITY>`
+    pub fn entity(&self, name: &str) -> Option<&str> {
+        self.entities
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    /// Feed one character
+    pub fn push_char(&mut self, ch: char) {
+        match self.state {
+            MarkupState::Text => {
+                if self.temp_secure && ch != '<' {
+                    self.temp_secure = false;
+                }
+                match ch {
+                    '\n' => self.end_line(),
+                    '<' if self.mode() != MxpMode::Locked => {
+                        self.state = MarkupState::Tag(None);
+                        self.markup.clear();
+                    }
+                    '&' if self.mode() != MxpMode::Locked => {
+                        self.state = MarkupState::Entity;
+                        self.markup.clear();
+                    }
+                    _ => self.push_text(ch),
+                }
+            }
+            MarkupState::Tag(quote) => match (quote, ch) {
+                (_, '\n') => {
+                    // Tags never span lines
+                    self.abandon_markup('<');
+                    self.end_line();
+                }
+                (None, '>') if !self.in_comment() => {
+                    self.state = MarkupState::Text;
+                    let markup = std::mem::take(&mut self.markup);
+                    let secure = self.mode() == MxpMode::Secure;
+                    self.temp_secure = false;
+                    self.handle_markup(&markup, secure);
+                }
+                (None, '"' | '\'') => {
+                    self.state = MarkupState::Tag(Some(ch));
+                    self.markup.push(ch);
+                }
+                (Some(quote), _) if ch == quote => {
+                    self.state = MarkupState::Tag(None);
+                    self.markup.push(ch);
+                }
+                _ => {
+                    self.markup.push(ch);
+                    if self.markup.len() > MAX_MARKUP_LEN {
+                        self.abandon_markup('<');
+                    }
+                }
+            },
+            MarkupState::Entity => {
+                if ch == ';' {
+                    self.state = MarkupState::Text;
+                    let name = std::mem::take(&mut self.markup);
+                    match self.decode_entity(&name) {
+                        Some(value) => value.chars().for_each(|ch| self.push_text(ch)),
+                        None => format!("&{name};")
+                            .chars()
+                            .for_each(|ch| self.push_text(ch)),
+                    }
+                } else if is_entity_char(ch) && self.markup.len() < MAX_MARKUP_LEN {
+                    self.markup.push(ch);
+                } else {
+                    self.abandon_markup('&');
+                    self.push_char(ch);
+                }
+            }
+        }
+    }
+
+    /// Feed text
+    pub fn push_str(&mut self, text: &str) {
+        text.chars().for_each(|ch| self.push_char(ch));
+    }
+
+    /// Feed a sequence decoded by [`AnsiCodec`](crate::AnsiCodec)
+    ///
+    /// Characters, newlines, tabs and `ESC[#z` mode changes are processed;
+    /// other sequences, including colors set with SGR, are ignored.
+    pub fn push_sequence(&mut self, sequence: &AnsiSequence) {
+        match sequence {
+            AnsiSequence::Character(ch) | AnsiSequence::Unicode(ch) => self.push_char(*ch),
+            AnsiSequence::AnsiControlCode(code) => self.push_control(*code),
+            AnsiSequence::AnsiCSI(csi) => self.push_csi(csi),
+            _ => {}
+        }
+    }
+
+    /// Feed a segment of a line, see [`push_sequence`](Self::push_sequence)
+    pub fn push_segment(&mut self, segment: &Segment) {
+        match segment {
+            Segment::ASCII(text) | Segment::Unicode(text) => self.push_str(text),
+            Segment::Control(code) => self.push_control(*code),
+            Segment::CSI(csi) => self.push_csi(csi),
+            _ => {}
+        }
+    }
+
+    /// Feed a completed line, such as `TerminalEvent::LineCompleted`'s, and end it
+    pub fn push_line(&mut self, line: &SegmentedString) {
+        line.segments()
+            .for_each(|segment| self.push_segment(segment));
+        self.push_char('\n');
+    }
+
+    /// Take the top-level nodes that are complete
+    ///
+    /// Text and elements inside a still open element are returned once it
+    /// closes.
+    pub fn take_nodes(&mut self) -> Vec<MxpNode> {
+        std::mem::take(&mut self.nodes)
+    }
+
+    /// Close all open elements and take the remaining nodes
+    ///
+    /// An unfinished tag or entity is kept as plain text.
+    pub fn finish(&mut self) -> Vec<MxpNode> {
+        match self.state {
+            MarkupState::Tag(_) => self.abandon_markup('<'),
+            MarkupState::Entity => self.abandon_markup('&'),
+            MarkupState::Text => {}
+        }
+        self.close_all();
+        self.take_nodes()
+    }
+
+    fn lock(&mut self, mode: MxpMode) {
+        self.default_mode = mode;
+        self.line_mode = None;
+    }
+
+    fn push_control(&mut self, code: AnsiControlCode) {
+        match code {
+            AnsiControlCode::LF => self.push_char('\n'),
+            AnsiControlCode::HT => self.push_char('\t'),
+            _ => {}
+        }
+    }
+
+    fn push_csi(&mut self, csi: &AnsiControlSequenceIntroducer) {
+        if let AnsiControlSequenceIntroducer::MXPLineMode(code) = csi
+            && let Some(mode) = MxpMode::from_code(*code)
+        {
+            self.set_mode(mode);
+        }
+    }
+
+    /// Whether the markup is an unfinished `<!-- comment -->`
+    fn in_comment(&self) -> bool {
+        self.markup.starts_with("!--") && !(self.markup.len() >= 5 && self.markup.ends_with("--"))
+    }
+
+    /// Emit the markup collected so far as plain text
+    fn abandon_markup(&mut self, opener: char) {
+        self.state = MarkupState::Text;
+        let markup = std::mem::take(&mut self.markup);
+        self.push_text(opener);
+        markup.chars().for_each(|ch| self.push_text(ch));
+    }
+
+    /// Tags opened in a line mode or in open mode end with the line
+    fn end_line(&mut self) {
+        if let Some(position) = self.stack.iter().position(|open| open.line) {
+            while self.stack.len() > position {
+                self.pop();
+            }
+        }
+        self.push_text('\n');
+        self.line_mode = None;
+        self.temp_secure = false;
+    }
+
+    fn handle_markup(&mut self, markup: &str, secure: bool) {
+        if markup.starts_with("!--") {
+            return;
+        }
+        if let Some(directive) = markup.strip_prefix('!') {
+            if secure {
+                self.handle_directive(directive);
+            }
+            return;
+        }
+        if let Some(name) = markup.strip_prefix('/') {
+            // Closing an element that was allowed to open is always allowed
+            self.close(&name.trim().to_ascii_lowercase());
+            return;
+        }
+        let tag = parse_tag(markup, Some(self));
+        if tag.name().is_empty() || !(secure || self.is_open_tag(tag.name())) {
+            // Secure tags outside secure mode are ignored
+            return;
+        }
+        let mut budget = MAX_EXPANSION_NODES;
+        self.open(tag, 0, &mut budget);
+    }
+
+    fn handle_directive(&mut self, directive: &str) {
+        // Definitions keep their `&attribute;` placeholders
+        let tag = parse_tag(directive, None);
+        let Some(name) = tag.argument(0).map(str::to_ascii_lowercase) else {
+            return;
+        };
+        match tag.name() {
+            "element" | "el" => {
+                if tag.has_flag("delete") {
+                    self.elements.remove(&name);
+                    return;
+                }
+                let definition = MxpElementDefinition {
+                    name: name.clone(),
+                    definition: tag
+                        .argument(1)
+                        .filter(|definition| !is_element_flag(definition))
+                        .unwrap_or_default()
+                        .to_string(),
+                    attributes: tag.get("att").map(parse_attribute_list).unwrap_or_default(),
+                    open: tag.has_flag("open"),
+                    empty: tag.has_flag("empty"),
+                };
+                self.elements.insert(name, definition);
+            }
+            "attlist" | "at" => {
+                if let (Some(element), Some(attributes)) =
+                    (self.elements.get_mut(&name), tag.argument(1))
+                {
+                    element.attributes = parse_attribute_list(attributes);
+                }
+            }
+            "entity" | "en" => {
+                if tag.has_flag("delete") {
+                    self.entities.remove(&name);
+                } else {
+                    let value = decode_entities(tag.argument(1).unwrap_or_default(), self);
+                    self.entities.insert(name, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn is_open_tag(&self, name: &str) -> bool {
+        OPEN_TAGS.contains(&name) || self.elements.get(name).is_some_and(|element| element.open)
+    }
+
+    /// Open `tag`, expanding custom elements
+    ///
+    /// `budget` is the number of elements the tag being handled may still
+    /// open; once it runs out, the rest of the expansion is ignored.
+    fn open(&mut self, tag: MxpTag, depth: usize, budget: &mut usize) {
+        if tag.name() == "reset" {
+            self.close_all();
+            return;
+        }
+        if *budget == 0 {
+            return;
+        }
+        *budget -= 1;
+        let expansion = self
+            .elements
+            .get(tag.name())
+            .map(|definition| (definition.expand(&tag), definition.empty));
+        if expansion.is_none() && EMPTY_TAGS.contains(&tag.name()) {
+            self.append(MxpNode::Element(MxpElement {
+                tag,
+                children: Vec::new(),
+            }));
+            return;
+        }
+        if self.stack.len() >= MAX_OPEN_ELEMENTS {
+            // Too deeply nested; the content goes to the innermost element
+            return;
+        }
+        let name = tag.name().to_string();
+        let line = self.line_mode.is_some() || self.default_mode == MxpMode::Open;
+        self.stack.push(OpenElement {
+            element: MxpElement {
+                tag,
+                children: Vec::new(),
+            },
+            line,
+        });
+        let Some((expansion, empty)) = expansion else {
+            return;
+        };
+        if depth < MAX_EXPANSION_DEPTH {
+            for markup in split_tags(&expansion) {
+                if *budget == 0 {
+                    break;
+                }
+                if !markup.starts_with(['/', '!']) {
+                    let inner = parse_tag(markup, Some(self));
+                    if !inner.name().is_empty() {
+                        self.open(inner, depth + 1, budget);
+                    }
+                }
+            }
+        }
+        if empty {
+            self.close(&name);
+        }
+    }
+
+    /// Close the innermost element named `name` and everything inside it
+    fn close(&mut self, name: &str) {
+        if let Some(position) = self
+            .stack
+            .iter()
+            .rposition(|open| open.element.tag.name() == name)
+        {
+            while self.stack.len() > position {
+                self.pop();
+            }
+        }
+    }
+
+    fn close_all(&mut self) {
+        while !self.stack.is_empty() {
+            self.pop();
+        }
+    }
+
+    fn pop(&mut self) {
+        if let Some(open) = self.stack.pop() {
+            self.append(MxpNode::Element(open.element));
+        }
+    }
+
+    fn children(&mut self) -> &mut Vec<MxpNode> {
+        match self.stack.last_mut() {
+            Some(open) => &mut open.element.children,
+            None => &mut self.nodes,
+        }
+    }
+
+    fn append(&mut self, node: MxpNode) {
+        self.children().push(node);
+    }
+
+    fn push_text(&mut self, ch: char) {
+        let children = self.children();
+        match children.last_mut() {
+            Some(MxpNode::Text(text)) => text.push(ch),
+            _ => children.push(MxpNode::Text(ch.to_string())),
+        }
+    }
+
+    fn decode_entity(&self, name: &str) -> Option<String> {
+        if let Some(number) = name.strip_prefix('#') {
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            return char::from_u32(code).map(String::from);
+        }
+        let builtin = match name {
+            "lt" => "<",
+            "gt" => ">",
+            "amp" => "&",
+            "quot" => "\"",
+            "apos" => "'",
+            "nbsp" => "\u{A0}",
+            _ => return self.entity(name).map(str::to_string),
+        };
+        Some(builtin.to_string())
+    }
+}
+
+/// Link target of a span of rendered text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MxpAction {
+    /// `<SEND>`: commands to send when clicked, the first being the default
+    Send {
+        /// Commands, from `href` split on `|` or the element's text
+        commands: Vec<String>,
+        /// Tooltip and menu labels from `hint`, split on `|`
+        hints: Vec<String>,
+        /// Whether the command is put on the input line instead of sent
+        prompt: bool,
+    },
+    /// `<A>`: a URL to open when clicked
+    Url {
+        /// Target URL
+        href: String,
+        /// Tooltip
+        hint: Option<String>,
+    },
+}
+
+/// A span of rendered text with its link target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MxpLink {
+    /// Byte range in the stripped text
+    pub range: Range<usize>,
+    /// What the link does
+    pub action: MxpAction,
+}
+
+/// MXP nodes rendered as styled text with link spans
+///
+/// # Examples
+///
+/// ```
+/// use termionix_ansicodec::mxp::{MxpAction, MxpMode, MxpParser, MxpText};
+///
+/// let mut parser = MxpParser::new();
+/// parser.set_mode(MxpMode::Secure);
+/// parser.push_str("<B>Exits:</B> <SEND>north</SEND>");
+/// let rendered = MxpText::render(&parser.finish());
+///
+/// assert_eq!(rendered.text.stripped(), "Exits: north");
+/// let link = rendered.link_at(8).unwrap();
+/// assert_eq!(link.range, 7..12);
+/// assert!(matches!(&link.action, MxpAction::Send { commands, .. } if commands == &["north"]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MxpText {
+    /// The text with MXP formatting applied
+    pub text: StyledString,
+    /// Link spans, innermost last
+    pub links: Vec<MxpLink>,
+}
+
+impl MxpText {
+    /// Render parsed nodes
+    pub fn render(nodes: &[MxpNode]) -> MxpText {
+        let mut rendered = MxpText {
+            text: StyledString::empty(),
+            links: Vec::new(),
+        };
+        let mut offset = 0;
+        for node in nodes {
+            rendered.render_node(node, &AnsiSelectGraphicRendition::default(), &mut offset);
+        }
+        rendered
+    }
+
+    /// The innermost link covering the byte `offset`
+    pub fn link_at(&self, offset: usize) -> Option<&MxpLink> {
+        self.links
+            .iter()
+            .rev()
+            .find(|link| link.range.contains(&offset))
+    }
+
+    fn render_node(
+        &mut self,
+        node: &MxpNode,
+        style: &AnsiSelectGraphicRendition,
+        offset: &mut usize,
+    ) {
+        match node {
+            MxpNode::Text(text) => {
+                self.text.concat_with_style(text, style.clone());
+                *offset += text.len();
+            }
+            MxpNode::Element(element) => {
+                let start = *offset;
+                let style = element_style(&element.tag, style);
+                if matches!(element.tag.name(), "br" | "sbr") {
+                    self.render_node(&MxpNode::Text("\n".to_string()), &style, offset);
+                }
+                for child in &element.children {
+                    self.render_node(child, &style, offset);
+                }
+                if let Some(action) = element_action(element) {
+                    // Inner links were pushed first; keep the outer one before them
+                    let position = self
+                        .links
+                        .iter()
+                        .position(|link| link.range.start >= start)
+                        .unwrap_or(self.links.len());
+                    self.links.insert(
+                        position,
+                        MxpLink {
+                            range: start..*offset,
+                            action,
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Writes MXP text for a client that agreed to MXP
+///
+/// Plain text is escaped, so players cannot inject tags. When a secure tag
+/// such as `<SEND>` is used, every line of the output starts in secure line
+/// mode (`ESC[1z`); elements left open are closed by the client at the end
+/// of their line.
+///
+/// # Examples
+///
+/// ```
+/// use termionix_ansicodec::mxp::MxpBuilder;
+///
+/// let text = MxpBuilder::new()
+///     .text("You see ")
+///     .send("a <rusty> sword", "get sword")
+///     .text(".")
+///     .build();
+/// assert_eq!(
+///     text,
+///     "\x1b[1zYou see <SEND href=\"get sword\">a &lt;rusty&gt; sword</SEND>."
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MxpBuilder {
+    buffer: String,
+    secure: bool,
+}
+
+impl MxpBuilder {
+    /// Create an empty builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append plain text, escaping markup characters
+    pub fn text(mut self, text: &str) -> Self {
+        self.buffer.push_str(&escape(text));
+        self
+    }
+
+    /// Append an opening tag
+    pub fn open(mut self, tag: &MxpTag) -> Self {
+        self.secure |= !OPEN_TAGS.contains(&tag.name());
+        self.buffer.push_str(&tag.to_string());
+        self
+    }
+
+    /// Append a closing tag
+    pub fn close(mut self, name: &str) -> Self {
+        self.buffer
+            .push_str(&format!("</{}>", name.to_ascii_uppercase()));
+        self
+    }
+
+    /// Append `text` enclosed in `tag`
+    pub fn element(self, tag: &MxpTag, text: &str) -> Self {
+        self.open(tag).text(text).close(tag.name())
+    }
+
+    /// Append bold text
+    pub fn bold(self, text: &str) -> Self {
+        self.element(&MxpTag::new("b"), text)
+    }
+
+    /// Append italic text
+    pub fn italic(self, text: &str) -> Self {
+        self.element(&MxpTag::new("i"), text)
+    }
+
+    /// Append underlined text
+    pub fn underline(self, text: &str) -> Self {
+        self.element(&MxpTag::new("u"), text)
+    }
+
+    /// Append text in a color name such as `red` or an `#RRGGBB` value
+    pub fn color(self, text: &str, fore: &str) -> Self {
+        self.element(&MxpTag::new("color").with_attribute("fore", fore), text)
+    }
+
+    /// Append text that sends `command` when clicked
+    pub fn send(self, text: &str, command: &str) -> Self {
+        self.element(&MxpTag::new("send").with_attribute("href", command), text)
+    }
+
+    /// Append text with a menu of commands, labelled by `hints`
+    pub fn send_menu(self, text: &str, commands: &[&str], hints: &[&str]) -> Self {
+        let tag = MxpTag::new("send")
+            .with_attribute("href", &commands.join("|"))
+            .with_attribute("hint", &hints.join("|"));
+        self.element(&tag, text)
+    }
+
+    /// Append text that opens `href` when clicked
+    pub fn link(self, text: &str, href: &str) -> Self {
+        self.element(&MxpTag::new("a").with_attribute("href", href), text)
+    }
+
+    /// Append a custom element definition
+    pub fn define_element(mut self, definition: &MxpElementDefinition) -> Self {
+        self.secure = true;
+        self.buffer.push_str(&definition.to_string());
+        self
+    }
+
+    /// Append a custom entity definition
+    pub fn define_entity(mut self, name: &str, value: &str) -> Self {
+        self.secure = true;
+        self.buffer
+            .push_str(&format!("<!ENTITY {} \"{}\">", name, escape(value)));
+        self
+    }
+
+    /// Append a newline
+    pub fn newline(mut self) -> Self {
+        self.buffer.push('\n');
+        self
+    }
+
+    /// The finished MXP text
+    pub fn build(self) -> String {
+        if !self.secure {
+            return self.buffer;
+        }
+        let secure = MxpMode::Secure.to_string();
+        let mut output = String::with_capacity(self.buffer.len() + secure.len());
+        for line in self.buffer.split_inclusive('\n') {
+            output.push_str(&secure);
+            output.push_str(line);
+        }
+        output
+    }
+}
+
+/// Escape text so MXP clients show it literally
+///
+/// # Examples
+///
+/// ```
+/// use termionix_ansicodec::mxp::escape;
+///
+/// assert_eq!(escape("<b> & \"q\""), "&lt;b&gt; &amp; &quot;q&quot;");
+/// ```
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn is_entity_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || matches!(ch, '#' | '_' | '-' | '.')
+}
+
+fn is_element_flag(value: &str) -> bool {
+    ["open", "empty", "delete"]
+        .iter()
+        .any(|flag| value.eq_ignore_ascii_case(flag))
+}
+
+/// Decode the entities in an attribute value
+fn decode_entities(value: &str, parser: &MxpParser) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .map(|end| &rest[1..=end])
+            .filter(|name| name.chars().all(is_entity_char))
+            .and_then(|name| parser.decode_entity(name).map(|value| (name.len(), value)));
+        match entity {
+            Some((len, value)) => {
+                decoded.push_str(&value);
+                rest = &rest[len + 2..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Parse the text between `<` and `>`; attribute entities are decoded with `parser`
+fn parse_tag(markup: &str, parser: Option<&MxpParser>) -> MxpTag {
+    let mut tokens = tokenize(markup).into_iter();
+    let mut tag = MxpTag::new(tokens.next().unwrap_or_default());
+    for token in tokens {
+        let equals = token.find('=');
+        let quote = token.find(['"', '\'']);
+        let (name, value) = match equals {
+            Some(index) if quote.is_none_or(|quote| index < quote) => (
+                Some(token[..index].to_string()),
+                unquote(&token[index + 1..]),
+            ),
+            _ => (None, unquote(token)),
+        };
+        let value = match parser {
+            Some(parser) => decode_entities(value, parser),
+            None => value.to_string(),
+        };
+        tag.attributes.push(MxpAttribute { name, value });
+    }
+    tag
+}
+
+/// Split markup on whitespace outside quotes
+fn tokenize(markup: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut quote = None;
+    for (index, ch) in markup.char_indices() {
+        match quote {
+            Some(open) if ch == open => quote = None,
+            Some(_) => {}
+            None if ch.is_whitespace() => {
+                if let Some(begin) = start.take() {
+                    tokens.push(&markup[begin..index]);
+                }
+            }
+            None => {
+                if matches!(ch, '"' | '\'') {
+                    quote = Some(ch);
+                }
+                start.get_or_insert(index);
+            }
+        }
+    }
+    if let Some(begin) = start {
+        tokens.push(&markup[begin..]);
+    }
+    tokens
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|value| value.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+/// Split an element definition such as `<COLOR red><B>` into tag contents
+fn split_tags(definition: &str) -> Vec<&str> {
+    let mut tags = Vec::new();
+    let mut start = None;
+    let mut quote = None;
+    for (index, ch) in definition.char_indices() {
+        match (quote, ch) {
+            (Some(open), _) if ch == open => quote = None,
+            (Some(_), _) => {}
+            (None, '<') => start = Some(index + 1),
+            (None, '>') => {
+                if let Some(begin) = start.take() {
+                    tags.push(&definition[begin..index]);
+                }
+            }
+            (None, '"' | '\'') if start.is_some() => quote = Some(ch),
+            _ => {}
+        }
+    }
+    tags
+}
+
+/// Parse an `ATT` list such as `col=red size`
+fn parse_attribute_list(list: &str) -> Vec<(String, String)> {
+    tokenize(list)
+        .into_iter()
+        .map(|item| match item.split_once('=') {
+            Some((name, default)) => (name.to_ascii_lowercase(), unquote(default).to_string()),
+            None => (item.to_ascii_lowercase(), String::new()),
+        })
+        .collect()
+}
+
+/// The style of text inside `tag`
+fn element_style(tag: &MxpTag, style: &AnsiSelectGraphicRendition) -> AnsiSelectGraphicRendition {
+    let mut style = style.clone();
+    match tag.name() {
+        "b" | "bold" | "strong" | "h" | "high" => style.intensity = Some(Intensity::Bold),
+        "i" | "italic" | "em" => style.italic = Some(true),
+        "u" | "underline" => style.underline = Some(Underline::Single),
+        "s" | "strikeout" => style.strike = Some(true),
+        "c" | "color" => {
+            if let Some(fore) = tag.value("fore", 0).and_then(parse_color) {
+                style.foreground = Some(fore);
+            }
+            if let Some(back) = tag.value("back", 1).and_then(parse_color) {
+                style.background = Some(back);
+            }
+        }
+        "font" => {
+            if let Some(fore) = tag.get("color").and_then(parse_color) {
+                style.foreground = Some(fore);
+            }
+            if let Some(back) = tag.get("back").and_then(parse_color) {
+                style.background = Some(back);
+            }
+        }
+        _ => {}
+    }
+    style
+}
+
+/// The link target of `<SEND>` and `<A>` elements
+fn element_action(element: &MxpElement) -> Option<MxpAction> {
+    let tag = &element.tag;
+    match tag.name() {
+        "send" => {
+            let text = element.text();
+            let href = tag
+                .value("href", 0)
+                .filter(|href| !href.eq_ignore_ascii_case("prompt"))
+                .map(|href| href.replace("&text;", &text))
+                .unwrap_or_else(|| text.clone());
+            let hints = tag
+                .value("hint", 1)
+                .filter(|hint| !hint.eq_ignore_ascii_case("prompt"))
+                .map(|hint| hint.split('|').map(str::to_string).collect())
+                .unwrap_or_default();
+            Some(MxpAction::Send {
+                commands: href.split('|').map(str::to_string).collect(),
+                hints,
+                prompt: tag.has_flag("prompt"),
+            })
+        }
+        "a" => Some(MxpAction::Url {
+            href: tag.value("href", 0)?.to_string(),
+            hint: tag.get("hint").map(str::to_string),
+        }),
+        _ => None,
+    }
+}
+
+/// Parse an HTML color name or `#RRGGBB` value
+fn parse_color(value: &str) -> Option<Color> {
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+        return Some(Color::RGB(channel(0)?, channel(2)?, channel(4)?));
+    }
+    let color = match value.to_ascii_lowercase().as_str() {
+        "black" => Color::Black,
+        "maroon" => Color::Red,
+        "green" => Color::Green,
+        "olive" => Color::Yellow,
+        "navy" => Color::Blue,
+        "purple" => Color::Purple,
+        "teal" => Color::Cyan,
+        "silver" => Color::White,
+        "gray" | "grey" => Color::BrightBlack,
+        "red" => Color::BrightRed,
+        "lime" => Color::BrightGreen,
+        "yellow" => Color::BrightYellow,
+        "blue" => Color::BrightBlue,
+        "fuchsia" | "magenta" => Color::BrightPurple,
+        "aqua" | "cyan" => Color::BrightCyan,
+        "white" => Color::BrightWhite,
+        _ => return None,
+    };
+    Some(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(node: &MxpNode) -> &MxpElement {
+        match node {
+            MxpNode::Element(element) => element,
+            other => panic!("expected an element, got {other:?}"),
+        }
+    }
+
+    fn secure(text: &str) -> Vec<MxpNode> {
+        let mut parser = MxpParser::new();
+        parser.set_mode(MxpMode::Secure);
+        parser.push_str(text);
+        parser.finish()
+    }
+
+    #[test]
+    fn test_open_mode_ignores_secure_tags() {
+        let mut parser = MxpParser::new();
+        parser.push_str("<B>bold</B> <SEND>look</SEND>");
+        let nodes = parser.finish();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(element(&nodes[0]).tag.name(), "b");
+        assert_eq!(nodes[1], MxpNode::Text(" look".to_string()));
+    }
+
+    #[test]
+    fn test_locked_mode_is_plain_text() {
+        let mut parser = MxpParser::new();
+        parser.set_mode(MxpMode::Locked);
+        parser.push_str("<B>x</B> &amp;\n<B>y</B>");
+        let nodes = parser.finish();
+        assert_eq!(nodes[0], MxpNode::Text("<B>x</B> &amp;\n".to_string()));
+        assert_eq!(element(&nodes[1]).text(), "y");
+    }
+
+    #[test]
+    fn test_line_mode_closes_tags_at_newline() {
+        let mut parser = MxpParser::new();
+        parser.set_mode(MxpMode::Secure);
+        parser.push_str("<SEND>north\nafter <SEND>x</SEND>");
+        let nodes = parser.finish();
+        assert_eq!(element(&nodes[0]).text(), "north");
+        assert_eq!(nodes[1], MxpNode::Text("\nafter x".to_string()));
+    }
+
+    #[test]
+    fn test_locked_secure_mode_spans_lines() {
+        let mut parser = MxpParser::new();
+        parser.set_mode(MxpMode::LockSecure);
+        parser.push_str("<SEND>a\nb");
+        assert!(parser.take_nodes().is_empty());
+        parser.push_str("</SEND>");
+        let nodes = parser.take_nodes();
+        assert_eq!(element(&nodes[0]).text(), "a\nb");
+    }
+
+    #[test]
+    fn test_temp_secure_applies_to_next_tag() {
+        let mut parser = MxpParser::new();
+        parser.set_mode(MxpMode::TempSecure);
+        parser.push_str("<A href=\"https://example.com\">site</A> <SEND>x</SEND>");
+        let nodes = parser.finish();
+        assert_eq!(
+            element(&nodes[0]).tag.get("href"),
+            Some("https://example.com")
+        );
+        assert_eq!(nodes[1], MxpNode::Text(" x".to_string()));
+    }
+
+    #[test]
+    fn test_entities() {
+        let nodes = secure("<!ENTITY hp \"100\">&lt;&hp;&gt; &#65;&#x42; &bogus; & x");
+        assert_eq!(
+            nodes,
+            vec![MxpNode::Text("<100> AB &bogus; & x".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_attributes() {
+        let nodes = secure("<SEND 'cast &quot;heal&quot;' hint=\"Heal|Cast\" PROMPT>heal</SEND>");
+        let tag = &element(&nodes[0]).tag;
+        assert_eq!(tag.argument(0), Some("cast \"heal\""));
+        assert_eq!(tag.get("hint"), Some("Heal|Cast"));
+        assert!(tag.has_flag("prompt"));
+    }
+
+    #[test]
+    fn test_custom_element() {
+        let nodes = secure(
+            "<!ELEMENT warn '<COLOR &col;><B>' ATT='col=red' OPEN><warn>a</warn><warn col=blue>b</warn>",
+        );
+        assert_eq!(nodes.len(), 2);
+        let warn = element(&nodes[0]);
+        assert_eq!(warn.tag.name(), "warn");
+        let color = element(&warn.children[0]);
+        assert_eq!(color.tag.argument(0), Some("red"));
+        let bold = element(&color.children[0]);
+        assert_eq!(bold.tag.name(), "b");
+        assert_eq!(bold.children, vec![MxpNode::Text("a".to_string())]);
+
+        let color = element(&element(&nodes[1]).children[0]);
+        assert_eq!(color.tag.argument(0), Some("blue"));
+    }
+
+    /// Number of elements in `nodes` and the depth of the deepest one
+    fn measure(nodes: &[MxpNode]) -> (usize, usize) {
+        let mut count = 0;
+        let mut deepest = 0;
+        let mut pending: Vec<(&MxpNode, usize)> = nodes.iter().map(|node| (node, 1)).collect();
+        while let Some((node, depth)) = pending.pop() {
+            if let MxpNode::Element(element) = node {
+                count += 1;
+                deepest = deepest.max(depth);
+                pending.extend(element.children.iter().map(|child| (child, depth + 1)));
+            }
+        }
+        (count, deepest)
+    }
+
+    #[test]
+    fn test_recursive_element_expansion_is_bounded() {
+        let nodes = secure("<!ELEMENT x '<x><x><x><x><x><x><x><x>'><x>text");
+        let (count, deepest) = measure(&nodes);
+        assert!(count <= MAX_EXPANSION_NODES, "{count} elements");
+        assert!(deepest <= MAX_OPEN_ELEMENTS, "nested {deepest} deep");
+        assert_eq!(MxpText::render(&nodes).text.stripped(), "text");
+    }
+
+    #[test]
+    fn test_deep_nesting_is_bounded() {
+        let nodes = secure(&format!("{}text", "<b>".repeat(200_000)));
+        let (count, deepest) = measure(&nodes);
+        assert_eq!(count, MAX_OPEN_ELEMENTS);
+        assert_eq!(deepest, MAX_OPEN_ELEMENTS);
+        assert_eq!(MxpText::render(&nodes).text.stripped(), "text");
+    }
+
+    #[test]
+    fn test_custom_open_element_in_open_mode() {
+        let mut parser = MxpParser::new();
+        parser.define_element(MxpElementDefinition::new("hi", "<B>").with_open(true));
+        parser.define_element(MxpElementDefinition::new("room", "<B>"));
+        parser.push_str("<hi>a</hi><room>b</room>");
+        let nodes = parser.finish();
+        assert_eq!(element(&nodes[0]).text(), "a");
+        assert_eq!(nodes[1], MxpNode::Text("b".to_string()));
+    }
+
+    #[test]
+    fn test_comments_and_unfinished_markup() {
+        let nodes = secure("a<!-- <B> -->b <unclosed");
+        assert_eq!(nodes, vec![MxpNode::Text("ab <unclosed".to_string())]);
+    }
+
+    #[test]
+    fn test_render_styles_and_links() {
+        let nodes = secure(
+            "<COLOR red>Hi</COLOR> <A href=\"https://example.com\">site</A> <SEND href=\"n|look n\" hint=\"Go|Look\"><B>north</B></SEND>",
+        );
+        let rendered = MxpText::render(&nodes);
+        assert_eq!(rendered.text.stripped(), "Hi site north");
+        assert_eq!(rendered.links.len(), 2);
+        assert_eq!(rendered.links[0].range, 3..7);
+        assert_eq!(
+            rendered.links[0].action,
+            MxpAction::Url {
+                href: "https://example.com".to_string(),
+                hint: None,
+            }
+        );
+        assert_eq!(
+            rendered.link_at(9).map(|link| &link.action),
+            Some(&MxpAction::Send {
+                commands: vec!["n".to_string(), "look n".to_string()],
+                hints: vec!["Go".to_string(), "Look".to_string()],
+                prompt: false,
+            })
+        );
+        assert_eq!(rendered.link_at(2), None);
+    }
+
+    #[test]
+    fn test_push_line_with_mode_sequence() {
+        let mut line = SegmentedString::empty();
+        line.push_ansi_csi(MxpMode::Secure.sequence());
+        line.push_str("<SEND>look</SEND>");
+        let mut parser = MxpParser::new();
+        parser.push_line(&line);
+        let nodes = parser.take_nodes();
+        assert_eq!(element(&nodes[0]).text(), "look");
+        assert_eq!(parser.mode(), MxpMode::Open);
+    }
+
+    #[test]
+    fn test_builder_round_trip() {
+        let text = MxpBuilder::new()
+            .define_element(
+                &MxpElementDefinition::new("exit", "<SEND href='go &dir;'>")
+                    .with_attribute("dir", ""),
+            )
+            .newline()
+            .text("Exits: ")
+            .element(&MxpTag::new("exit").with_argument("north"), "north")
+            .text(" <none> & \"more\"")
+            .build();
+        assert!(
+            text.starts_with(
+                "\x1b[1z<!ELEMENT exit \"<SEND href='go &dir;'>\" ATT='dir'>\n\x1b[1z"
+            )
+        );
+
+        let mut parser = MxpParser::new();
+        for line in text.split("\x1b[1z").skip(1) {
+            parser.push_sequence(&AnsiSequence::AnsiCSI(MxpMode::Secure.sequence()));
+            parser.push_str(line);
+        }
+        let nodes = parser.finish();
+        let rendered = MxpText::render(&nodes);
+        assert_eq!(rendered.text.stripped(), "\nExits: north <none> & \"more\"");
+        assert_eq!(
+            rendered.links[0].action,
+            MxpAction::Send {
+                commands: vec!["go north".to_string()],
+                hints: Vec::new(),
+                prompt: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_builder_without_secure_tags() {
+        let text = MxpBuilder::new().bold("a<b").color("c", "red").build();
+        assert_eq!(text, "<B>a&lt;b</B><COLOR fore=\"red\">c</COLOR>");
+    }
+}
@@ -556,6 +556,10 @@ impl AnsiParser {
             b'X' => AnsiControlSequenceIntroducer::EraseCharacter,
             b's' => AnsiControlSequenceIntroducer::SaveCursorPosition,
             b'u' => AnsiControlSequenceIntroducer::RestoreCursorPosition,
+            // MXP line mode - default is 0 (open line)
+            b'z' => {
+                AnsiControlSequenceIntroducer::MXPLineMode(params.first().copied().unwrap_or(0))
+            }
             b'n' => {
                 if params_str == "6" {
                     AnsiControlSequenceIntroducer::DeviceStatusReport
@@ -912,6 +916,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_csi_mxp_line_mode() {
+        // ESC[1z - MXP secure line
+        let results = parse_bytes(b"\x1b[1z");
+        assert_eq!(
+            results,
+            vec![AnsiSequence::AnsiCSI(
+                AnsiControlSequenceIntroducer::MXPLineMode(1)
+            )]
+        );
+
+        // ESC[z - MXP open line
+        let results = parse_bytes(b"\x1b[z");
+        assert_eq!(
+            results,
+            vec![AnsiSequence::AnsiCSI(
+                AnsiControlSequenceIntroducer::MXPLineMode(0)
+            )]
+        );
+        assert_eq!(
+            AnsiControlSequenceIntroducer::MXPLineMode(6).to_string(),
+            "\x1b[6z"
+        );
+    }
+
     #[test]
     fn test_csi_device_status_report() {
         // ESC[6n - Device Status Report
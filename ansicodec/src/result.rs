@@ -27,6 +27,13 @@ pub type AnsiCodecResult<T> = Result<T, AnsiCodecError>;
 pub enum AnsiCodecError {
     /// IO Error
     IoError(std::io::Error),
+
+    /// Error from the underlying Telnet codec.
+    ///
+    /// Includes violated [`CodecLimits`](termionix_telnetcodec::CodecLimits), which
+    /// [`TelnetCodecError::is_limit_exceeded`](termionix_telnetcodec::TelnetCodecError::is_limit_exceeded)
+    /// identifies.
+    Telnet(termionix_telnetcodec::TelnetCodecError),
     /// Invalid UTF-8 sequence encountered at the specified position.
     ///
     /// This error occurs when the input contains bytes that don't form valid UTF-8.
@@ -104,6 +111,9 @@ impl std::fmt::Display for AnsiCodecError {
             AnsiCodecError::IoError(err) => {
                 write!(f, "IOError {}", err)
             }
+            AnsiCodecError::Telnet(err) => {
+                write!(f, "Telnet codec error: {}", err)
+            }
             AnsiCodecError::InvalidUtf8 { position } => {
                 write!(f, "Invalid UTF-8 sequence at position {}", position)
             }
@@ -163,7 +173,15 @@ impl std::fmt::Display for AnsiCodecError {
     }
 }
 
-impl std::error::Error for AnsiCodecError {}
+impl std::error::Error for AnsiCodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AnsiCodecError::IoError(err) => Some(err),
+            AnsiCodecError::Telnet(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl From<std::io::Error> for AnsiCodecError {
     fn from(error: std::io::Error) -> Self {
@@ -173,9 +191,6 @@ impl From<std::io::Error> for AnsiCodecError {
 
 impl From<termionix_telnetcodec::TelnetCodecError> for AnsiCodecError {
     fn from(error: termionix_telnetcodec::TelnetCodecError) -> Self {
-        AnsiCodecError::IoError(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Codec error: {:?}", error),
-        ))
+        AnsiCodecError::Telnet(error)
     }
 }
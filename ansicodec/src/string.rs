@@ -3086,7 +3086,7 @@ mod tests {
         let mut output = Vec::new();
 
         seg.write(&mut output, Some(&config)).unwrap();
-        assert_eq!(output, vec![]);
+        assert!(output.is_empty());
     }
 
     #[test]
@@ -18,10 +18,13 @@
 
 use termionix_ansicodec::ansi::{
     AnsiControlCode, AnsiControlSequenceIntroducer, AnsiDeviceControlString,
-    AnsiOperatingSystemCommand, AnsiSelectGraphicRendition, AnsiSequence,
+    AnsiOperatingSystemCommand, AnsiSelectGraphicRendition, AnsiSequence, TelnetCommand,
+};
+use termionix_ansicodec::{AnsiCodec, AnsiConfig, Charset, ColorMode};
+use termionix_telnetcodec::gmcp::GmcpMessage;
+use termionix_telnetcodec::{
+    OptionPolicy, TelnetArgument, TelnetCodec, TelnetOption, TelnetResponder,
 };
-use termionix_ansicodec::{AnsiCodec, AnsiConfig, ColorMode};
-use termionix_telnetcodec::TelnetCodec;
 use tokio_util::bytes::BytesMut;
 use tokio_util::codec::{Decoder, Encoder};
 
@@ -156,7 +159,8 @@ fn test_encode_sequence_control() {
     let seq = AnsiSequence::AnsiControlCode(AnsiControlCode::LF);
     codec.encode(seq, &mut buffer).unwrap();
 
-    assert_eq!(&buffer[..], b"\n");
+    // NVT newlines go out as CR LF until TRANSMIT-BINARY is enabled
+    assert_eq!(&buffer[..], b"\r\n");
 }
 
 #[test]
@@ -349,7 +353,8 @@ fn test_encode_all_c0_control_codes() {
         codec.encode(code, &mut buffer).unwrap();
     }
 
-    assert_eq!(buffer.len(), 14);
+    // LF is sent as CR LF; the NUL after the final CR waits for the next byte
+    assert_eq!(buffer.len(), 15);
 }
 
 #[test]
@@ -405,6 +410,30 @@ fn test_decode_partial_sequence() {
     assert_eq!(results.len(), 5);
 }
 
+#[test]
+fn test_decode_data_chunks_matches_byte_events() {
+    let input = &b"Hi \x1b[1mthere\x1b[0m\r\n\xFF\xF1ok"[..];
+
+    let mut bytewise = create_codec();
+    let mut buffer = BytesMut::from(input);
+    let mut expected = Vec::new();
+    while let Some(seq) = bytewise.decode(&mut buffer).unwrap() {
+        expected.push(seq);
+    }
+
+    let mut telnet_codec = TelnetCodec::new();
+    telnet_codec.set_data_chunks(true);
+    let mut chunked = AnsiCodec::new(AnsiConfig::default(), telnet_codec);
+    let mut buffer = BytesMut::from(input);
+    let mut results = Vec::new();
+    while let Some(seq) = chunked.decode(&mut buffer).unwrap() {
+        results.push(seq);
+    }
+
+    assert_eq!(results, expected);
+    assert_eq!(results.len(), 14);
+}
+
 #[test]
 fn test_encode_sequence_types() {
     let mut codec = create_codec();
@@ -428,6 +457,24 @@ fn test_encode_sequence_types() {
     assert!(buffer.len() > 0);
 }
 
+#[test]
+fn test_encode_telnet_command_is_not_escaped() {
+    let mut codec = create_codec();
+    let mut buffer = BytesMut::new();
+
+    codec
+        .encode(
+            AnsiSequence::TelnetCommand(TelnetCommand::Subnegotiation(TelnetArgument::GMCP(
+                GmcpMessage::new("Core.Ping", None::<String>),
+            ))),
+            &mut buffer,
+        )
+        .unwrap();
+    codec.encode(TelnetCommand::GoAhead, &mut buffer).unwrap();
+
+    assert_eq!(&buffer[..], b"\xFF\xFA\xC9Core.Ping\xFF\xF0\xFF\xF9");
+}
+
 #[test]
 fn test_codec_with_different_color_modes() {
     for color_mode in [
@@ -448,3 +495,75 @@ fn test_codec_with_different_color_modes() {
         assert!(!buffer.is_empty());
     }
 }
+
+#[test]
+fn test_transcodes_text_in_single_byte_charset() {
+    let mut codec = create_codec();
+    codec.set_charset(Charset::Latin1);
+
+    let mut buffer = BytesMut::new();
+    codec.encode("café ░", &mut buffer).unwrap();
+    codec.encode('ü', &mut buffer).unwrap();
+    assert_eq!(&buffer[..], b"caf\xE9 ?\xFC");
+
+    let mut buffer = BytesMut::from(&b"\xE9\xFC"[..]);
+    assert_eq!(
+        codec.decode(&mut buffer).unwrap(),
+        Some(AnsiSequence::Unicode('é'))
+    );
+    assert_eq!(
+        codec.decode(&mut buffer).unwrap(),
+        Some(AnsiSequence::Unicode('ü'))
+    );
+}
+
+fn charset_codec() -> AnsiCodec<TelnetCodec> {
+    let policy = OptionPolicy::default().accept(TelnetOption::Charset);
+    let mut codec = AnsiCodec::new(AnsiConfig::default(), TelnetCodec::with_policy(policy));
+    codec.set_offered_charsets(vec![Charset::Utf8, Charset::Latin1, Charset::Cp437]);
+    codec
+}
+
+#[test]
+fn test_charset_request_sent_and_accepted() {
+    let mut codec = charset_codec();
+
+    // IAC DO CHARSET
+    let mut buffer = BytesMut::from(&[0xFF, 0xFD, 42][..]);
+    while codec.decode(&mut buffer).unwrap().is_some() {}
+    let mut responses = BytesMut::new();
+    codec.flush_responses(&mut responses).unwrap();
+    assert_eq!(
+        &responses[..],
+        b"\xFF\xFB\x2A\xFF\xFA\x2A\x01;UTF-8;ISO-8859-1;IBM437\xFF\xF0"
+    );
+
+    // IAC SB CHARSET ACCEPTED "IBM437" IAC SE, then CP437 text
+    let mut buffer = BytesMut::from(&b"\xFF\xFA\x2A\x02IBM437\xFF\xF0\xB0"[..]);
+    let mut last = None;
+    while let Some(sequence) = codec.decode(&mut buffer).unwrap() {
+        last = Some(sequence);
+    }
+    assert_eq!(codec.charset(), Charset::Cp437);
+    assert_eq!(last, Some(AnsiSequence::Unicode('░')));
+}
+
+#[test]
+fn test_charset_request_from_peer_answered() {
+    let mut codec = charset_codec();
+
+    // IAC SB CHARSET REQUEST ";KOI8-R;latin1" IAC SE
+    let mut buffer = BytesMut::from(&b"\xFF\xFA\x2A\x01;KOI8-R;latin1\xFF\xF0"[..]);
+    while codec.decode(&mut buffer).unwrap().is_some() {}
+    let mut responses = BytesMut::new();
+    codec.flush_responses(&mut responses).unwrap();
+    assert_eq!(&responses[..], b"\xFF\xFA\x2A\x02latin1\xFF\xF0");
+    assert_eq!(codec.charset(), Charset::Latin1);
+
+    // Nothing we offer
+    let mut buffer = BytesMut::from(&b"\xFF\xFA\x2A\x01;KOI8-R\xFF\xF0"[..]);
+    while codec.decode(&mut buffer).unwrap().is_some() {}
+    let mut responses = BytesMut::new();
+    codec.flush_responses(&mut responses).unwrap();
+    assert_eq!(&responses[..], b"\xFF\xFA\x2A\x03\xFF\xF0");
+}
@@ -108,7 +108,7 @@ fn test_mixed_text_and_control_codes() {
         .filter(|s| matches!(s, AnsiSequence::AnsiControlCode(_)))
         .count();
 
-    assert_eq!(control_count, 4); // 2 CR + 2 LF
+    assert_eq!(control_count, 2); // each CR LF arrives as a single LF
 }
 
 #[test]
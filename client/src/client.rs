@@ -17,14 +17,22 @@
 //! Terminal-aware Telnet client implementation
 
 use crate::{ClientConfig, ClientError, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use termionix_service::new_environ::NewEnviron;
 use termionix_service::{
-    AnsiCodec, AnsiConfig, CompressionAlgorithm, SplitTerminalConnection, TelnetArgument,
-    TelnetCodec, TelnetOption, TerminalCodec, TerminalCommand, TerminalEvent,
+    AnsiCodec, AnsiConfig, CompressionAlgorithm, ConnectionError, SplitTerminalConnection,
+    StartTlsStream, StartTlsUpgrade, TelnetArgument, TelnetCodec, TelnetOption, TerminalCodec,
+    TerminalCommand, TerminalEvent,
 };
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 use tokio::sync::RwLock;
 use tokio::time::timeout;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::TlsConnector;
+use tokio_util::bytes::BytesMut;
+use tokio_util::codec::Encoder;
 use tracing::{error, info};
 
 /// Connection state
@@ -92,9 +100,15 @@ pub struct TerminalConnection {
     inner: Arc<TerminalConnectionInner>,
 }
 
+/// Read half of the transport, boxed so it can be swapped for TLS by START_TLS
+type TransportReader = Box<dyn AsyncRead + Unpin + Send>;
+
+/// Write half of the transport, boxed so it can be swapped for TLS by START_TLS
+type TransportWriter = Box<dyn AsyncWrite + Unpin + Send>;
+
 type ClientSplitConnection = SplitTerminalConnection<
-    tokio::io::ReadHalf<TcpStream>,
-    tokio::io::WriteHalf<TcpStream>,
+    TransportReader,
+    TransportWriter,
     TerminalCodec<AnsiCodec<TelnetCodec>>,
 >;
 
@@ -102,15 +116,17 @@ struct TerminalConnectionInner {
     config: ClientConfig,
     state: RwLock<ConnectionState>,
     split: ClientSplitConnection,
+    tls: Arc<AtomicBool>,
 }
 
 impl TerminalConnection {
-    fn new(config: ClientConfig, split: ClientSplitConnection) -> Self {
+    fn new(config: ClientConfig, split: ClientSplitConnection, tls: Arc<AtomicBool>) -> Self {
         Self {
             inner: Arc::new(TerminalConnectionInner {
                 config,
                 state: RwLock::new(ConnectionState::Disconnected),
                 split,
+                tls,
             }),
         }
     }
@@ -123,6 +139,11 @@ impl TerminalConnection {
         *self.inner.state.read().await == ConnectionState::Connected
     }
 
+    /// Check if the connection was upgraded to TLS with START_TLS
+    pub fn is_tls(&self) -> bool {
+        self.inner.tls.load(Ordering::Acquire)
+    }
+
     /// Send any message type that can be encoded by the terminal codec
     ///
     /// This generic method can handle:
@@ -163,6 +184,26 @@ impl TerminalConnection {
         self.send(cmd, true).await
     }
 
+    /// Answer a NEW-ENVIRON `SEND` request from the server
+    ///
+    /// The reply carries the requested variables from
+    /// [`ClientConfig::environment`]; requested variables that are not set are
+    /// reported as undefined. Requests are answered automatically while the
+    /// client runs, so this is only needed for connections driven manually.
+    /// Anything other than a `SEND` request is ignored.
+    pub async fn answer_environment(&self, request: &NewEnviron) -> Result<()> {
+        let defined = self
+            .inner
+            .config
+            .environment
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()));
+        match request.answer(defined) {
+            Some(reply) => self.send_command(TerminalCommand::NewEnviron(reply)).await,
+            None => Ok(()),
+        }
+    }
+
     pub async fn disconnect(&self) -> Result<()> {
         *self.inner.state.write().await = ConnectionState::ShuttingDown;
         self.inner
@@ -180,8 +221,10 @@ impl TerminalConnection {
     /// Set the compression algorithm for the connection
     ///
     /// This dynamically switches the compression algorithm used for both
-    /// reading and writing. Typically called in response to Telnet MCCP
-    /// (Mud Client Compression Protocol) negotiation.
+    /// reading and writing, without any Telnet negotiation. MCCP (Mud Client
+    /// Compression Protocol) does not need this: request it through the option
+    /// policy, e.g. `OptionPolicy::default().request_remote(TelnetOption::Compress2)`,
+    /// and the connection switches at the negotiated byte boundary by itself.
     ///
     /// # Parameters
     ///
@@ -193,7 +236,7 @@ impl TerminalConnection {
     /// # use termionix_client::TerminalConnection;
     /// # use termionix_compress::CompressionAlgorithm;
     /// # async fn example(conn: &TerminalConnection) -> Result<(), Box<dyn std::error::Error>> {
-    /// // Enable Gzip compression (MCCP2)
+    /// // Enable Gzip compression
     /// conn.set_compression_algorithm(CompressionAlgorithm::Gzip).await?;
     ///
     /// // Disable compression
@@ -215,11 +258,10 @@ impl TerminalConnection {
     }
 
     async fn next(&self) -> Result<Option<TerminalEvent>> {
-        self.inner
-            .split
-            .next()
-            .await
-            .map_err(|e| ClientError::Io(e.to_string()))
+        self.inner.split.next().await.map_err(|e| match e {
+            ConnectionError::StartTls(e) => ClientError::Tls(e.to_string()),
+            e => ClientError::Io(e.to_string()),
+        })
     }
 }
 
@@ -298,18 +340,44 @@ impl TerminalClient {
         info!("Connected to {}", stream.peer_addr()?);
 
         // Create codec stack: Terminal -> ANSI -> Telnet
-        let telnet_codec = TelnetCodec::new();
-        let ansi_codec = AnsiCodec::new(AnsiConfig::default(), telnet_codec);
+        let mut policy = self.config.option_policy.clone();
+        if self.config.start_tls.is_some() {
+            policy = policy.request_local(TelnetOption::StartTLS);
+        }
+        let mut telnet_codec = TelnetCodec::with_policy(policy);
+        telnet_codec.set_data_chunks(true);
+        telnet_codec.set_zmp(self.config.zmp.clone());
+        let mut requests = BytesMut::new();
+        for frame in telnet_codec.request_policy_options() {
+            telnet_codec.encode(frame, &mut requests)?;
+        }
+        let mut ansi_codec = AnsiCodec::new(AnsiConfig::default(), telnet_codec);
+        ansi_codec.set_offered_charsets(self.config.charsets.clone());
         let terminal_codec = TerminalCodec::new(ansi_codec);
 
-        // Create split connection with turbofish to help type inference
-        let split = SplitTerminalConnection::<
-            tokio::io::ReadHalf<TcpStream>,
-            tokio::io::WriteHalf<TcpStream>,
-            TerminalCodec<AnsiCodec<TelnetCodec>>,
-        >::from_stream(stream, terminal_codec.clone(), terminal_codec);
+        // Box the halves so START_TLS can swap them for TLS ones
+        let (reader, writer) = tokio::io::split(stream);
+        let split = ClientSplitConnection::new(
+            Box::new(reader) as TransportReader,
+            Box::new(writer) as TransportWriter,
+            terminal_codec,
+        );
+
+        let tls = Arc::new(AtomicBool::new(false));
+        if let Some(config) = &self.config.start_tls {
+            let upgrade = Self::start_tls_upgrade(&self.config.host, config, Arc::clone(&tls))?;
+            split
+                .enable_start_tls(upgrade)
+                .map_err(|e| ClientError::Io(e.to_string()))?;
+        }
+
+        if !requests.is_empty() {
+            split
+                .send_raw(requests.freeze(), true)
+                .map_err(|e| ClientError::Io(e.to_string()))?;
+        }
 
-        let connection = TerminalConnection::new(self.config.clone(), split);
+        let connection = TerminalConnection::new(self.config.clone(), split, tls);
         connection.set_state(ConnectionState::Connected).await;
         self.connection = Some(connection.clone());
 
@@ -318,6 +386,30 @@ impl TerminalClient {
         self.run_connection(connection, handler).await
     }
 
+    /// TLS handshake run on the transport once the server agrees to START_TLS
+    fn start_tls_upgrade(
+        host: &str,
+        config: &Arc<tokio_rustls::rustls::ClientConfig>,
+        tls: Arc<AtomicBool>,
+    ) -> Result<StartTlsUpgrade<TransportReader, TransportWriter>> {
+        let server_name =
+            ServerName::try_from(host.to_string()).map_err(|e| ClientError::Tls(e.to_string()))?;
+        let connector = TlsConnector::from(Arc::clone(config));
+        Ok(Box::new(
+            move |stream: StartTlsStream<TransportReader, TransportWriter>| {
+                Box::pin(async move {
+                    let stream = connector.connect(server_name, stream).await?;
+                    tls.store(true, Ordering::Release);
+                    let (reader, writer) = tokio::io::split(stream);
+                    Ok((
+                        Box::new(reader) as TransportReader,
+                        Box::new(writer) as TransportWriter,
+                    ))
+                }) as _
+            },
+        ))
+    }
+
     async fn run_connection<H: TerminalHandler>(
         &self,
         connection: TerminalConnection,
@@ -371,6 +463,9 @@ impl TerminalClient {
             TerminalEvent::ResizeWindow { new, .. } => {
                 handler.on_resize(connection, new.cols, new.rows).await;
             }
+            TerminalEvent::Subnegotiation(TelnetArgument::NewEnviron(request)) => {
+                connection.answer_environment(&request).await?;
+            }
             _ => {}
         }
 
@@ -381,5 +476,3 @@ impl TerminalClient {
         self.connection.as_ref()
     }
 }
-
-
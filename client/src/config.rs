@@ -16,7 +16,12 @@
 
 //! Client configuration
 
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use std::time::Duration;
+use termionix_service::zmp::ZmpRegistry;
+use termionix_service::{Charset, OptionPolicy};
+use tokio_rustls::rustls;
 
 /// Telnet client configuration
 #[derive(Debug, Clone)]
@@ -59,6 +64,37 @@ pub struct ClientConfig {
 
     /// Keepalive interval
     pub keepalive_interval: Duration,
+
+    /// Telnet option negotiation policy
+    ///
+    /// Decides which options the client accepts from the server and which
+    /// ones it requests as soon as the connection is established.
+    pub option_policy: OptionPolicy,
+
+    /// NEW-ENVIRON variables reported to the server
+    ///
+    /// Used to answer the server's NEW-ENVIRON `SEND` requests, e.g. the MNES
+    /// variables in [`new_environ::mnes`](termionix_service::new_environ::mnes).
+    pub environment: BTreeMap<String, String>,
+
+    /// Charsets accepted when the server requests CHARSET negotiation
+    ///
+    /// Empty leaves CHARSET requests unanswered and text in UTF-8. CHARSET
+    /// must also be accepted by [`option_policy`](Self::option_policy).
+    pub charsets: Vec<Charset>,
+
+    /// ZMP commands answered automatically when the server offers ZMP
+    ///
+    /// The default answers the standard `zmp.` package (`zmp.ping`,
+    /// `zmp.check`, ...).
+    pub zmp: ZmpRegistry,
+
+    /// TLS settings for START_TLS
+    ///
+    /// When set, the client offers START_TLS (`WILL START_TLS`) and upgrades
+    /// the connection to TLS in place once the server agrees. The server
+    /// certificate is verified for [`host`](Self::host).
+    pub start_tls: Option<Arc<rustls::ClientConfig>>,
 }
 
 impl Default for ClientConfig {
@@ -77,6 +113,11 @@ impl Default for ClientConfig {
             buffer_size: 8192,
             keepalive: true,
             keepalive_interval: Duration::from_secs(60),
+            option_policy: OptionPolicy::default(),
+            environment: BTreeMap::new(),
+            charsets: Vec::new(),
+            zmp: ZmpRegistry::default(),
+            start_tls: None,
         }
     }
 }
@@ -134,6 +175,36 @@ impl ClientConfig {
         self
     }
 
+    /// Set the Telnet option negotiation policy
+    pub fn with_option_policy(mut self, policy: OptionPolicy) -> Self {
+        self.option_policy = policy;
+        self
+    }
+
+    /// Set a NEW-ENVIRON variable reported to the server
+    pub fn with_environment(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.environment.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the charsets accepted in CHARSET negotiation, in order of preference
+    pub fn with_charsets(mut self, charsets: Vec<Charset>) -> Self {
+        self.charsets = charsets;
+        self
+    }
+
+    /// Set the ZMP commands answered automatically, e.g. with a `zmp.ident`
+    pub fn with_zmp(mut self, zmp: ZmpRegistry) -> Self {
+        self.zmp = zmp;
+        self
+    }
+
+    /// Upgrade the connection to TLS with START_TLS using `tls`
+    pub fn with_start_tls(mut self, tls: Arc<rustls::ClientConfig>) -> Self {
+        self.start_tls = Some(tls);
+        self
+    }
+
     /// Get the server address as a string
     pub fn address(&self) -> String {
         format!("{}:{}", self.host, self.port)
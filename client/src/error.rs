@@ -53,6 +53,9 @@ pub enum ClientError {
     /// Reconnection failed
     ReconnectionFailed(usize),
 
+    /// TLS error, e.g. a failed START_TLS handshake
+    Tls(String),
+
     /// Custom error
     Custom(String),
 }
@@ -72,6 +75,7 @@ impl fmt::Display for ClientError {
             Self::ReconnectionFailed(attempts) => {
                 write!(f, "Reconnection failed after {} attempts", attempts)
             }
+            Self::Tls(msg) => write!(f, "TLS error: {}", msg),
             Self::Custom(msg) => write!(f, "{}", msg),
         }
     }
@@ -90,16 +90,21 @@ pub use client::{ConnectionState, TerminalClient, TerminalConnection, TerminalHa
 pub use config::ClientConfig;
 pub use error::{ClientError, Result};
 
+// Re-export rustls for building START_TLS configurations
+pub use tokio_rustls::rustls;
+
 // Re-export types from termionix_service
 pub use termionix_service::{
-    gmcp, linemode, msdp, mssp, naocrd, naohts, naws, status, strip_ansi_codes,
-    terminal_word_unwrap, terminal_word_wrap, AnsiApplicationProgramCommand, AnsiCodec,
+    atcp, gmcp, linemode, msdp, mssp, mtts, mxp, naocrd, naohts, naws, new_environ, status,
+    strip_ansi_codes,
+    terminal_word_unwrap, terminal_word_wrap, zmp, AnsiApplicationProgramCommand, AnsiCodec,
     AnsiCodecError, AnsiCodecResult, AnsiConfig, AnsiControlCode, AnsiControlSequenceIntroducer,
     AnsiDeviceControlString, AnsiOperatingSystemCommand, AnsiParser, AnsiPrivacyMessage,
-    AnsiSelectGraphicRendition, AnsiSequence, AnsiStartOfString, Blink, Color, ColorMode,
-    CompressionAlgorithm, CursorPosition, Font, Ideogram, Intensity, SGRParameter, Script, Segment,
-    SegmentedString, Span, SpannedString, StyledString, SubnegotiationErrorKind, TelnetArgument,
-    TelnetCodec, TelnetCodecError, TelnetCodecResult, TelnetCommand, TelnetEvent, TelnetFrame,
-    TelnetOption, TelnetSide, TerminalBuffer, TerminalCodec, TerminalCommand, TerminalError,
-    TerminalEvent, TerminalResult, TerminalSize, Underline,
+    AnsiSelectGraphicRendition, AnsiSequence, AnsiStartOfString, Blink, Charset, CodecLimits, Color,
+    ColorMode, CompressionAlgorithm, CursorPosition, Font, Ideogram, Intensity, OptionPolicy,
+    SGRParameter, Script, Segment, SegmentedString, Span, SpannedString, StyledString,
+    SubnegotiationErrorKind, TelnetArgument, TelnetCodec, TelnetCodecError, TelnetCodecResult,
+    TelnetCommand, TelnetEvent, TelnetFrame, TelnetOption, TelnetResponder, TelnetSide,
+    TerminalBuffer, TerminalCapabilities, TerminalCodec, TerminalCommand, TerminalError, TerminalEvent, TerminalResult,
+    TerminalSize, Underline,
 };
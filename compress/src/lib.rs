@@ -137,7 +137,7 @@ use async_compression::tokio::write::{
 };
 use pin_project_lite::pin_project;
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::task::{Context, Poll, ready};
 use tokio::io::{self, AsyncRead, AsyncWrite, ReadBuf};
 
 /// Compression algorithm selection for stream processing.
@@ -894,11 +894,43 @@ where
     W: AsyncWrite + Unpin + Send,
 {
     None(W),
-    Gzip(GzipEncoder<W>),
-    Deflate(DeflateEncoder<W>),
-    Brotli(BrotliEncoder<W>),
-    Zlib(ZlibEncoder<W>),
-    Zstd(ZstdEncoder<W>),
+    Gzip(GzipEncoder<KeepOpen<W>>),
+    Deflate(DeflateEncoder<KeepOpen<W>>),
+    Brotli(BrotliEncoder<KeepOpen<W>>),
+    Zlib(ZlibEncoder<KeepOpen<W>>),
+    Zstd(ZstdEncoder<KeepOpen<W>>),
+}
+
+/// Writer adapter that turns `shutdown` into `flush`
+///
+/// Encoders shut down the writer they wrap once the compressed stream is
+/// finished. Wrapping the base writer in `KeepOpen` lets [`CompressionWriter`]
+/// end a compressed stream (e.g. when switching algorithms) while the
+/// underlying connection stays open.
+struct KeepOpen<W>(W);
+
+impl<W> AsyncWrite for KeepOpen<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
 }
 
 impl<W> CompressionWriter<W>
@@ -913,8 +945,9 @@ where
     }
 
     fn wrap_writer(writer: W, algorithm: CompressionAlgorithm) -> InnerWriter<W> {
+        let writer = KeepOpen(writer);
         match algorithm {
-            CompressionAlgorithm::None => InnerWriter::None(writer),
+            CompressionAlgorithm::None => InnerWriter::None(writer.0),
             CompressionAlgorithm::Gzip => InnerWriter::Gzip(GzipEncoder::new(writer)),
             CompressionAlgorithm::Deflate => InnerWriter::Deflate(DeflateEncoder::new(writer)),
             CompressionAlgorithm::Brotli => InnerWriter::Brotli(BrotliEncoder::new(writer)),
@@ -937,8 +970,10 @@ where
 
     /// Switch to a new compression algorithm
     ///
-    /// This flushes and shuts down the current compressor, extracts the underlying
-    /// writer, and wraps it with a new compressor using the specified algorithm.
+    /// This finishes the current compressed stream (writing its trailer), extracts
+    /// the underlying writer, and wraps it with a new compressor using the specified
+    /// algorithm. The underlying writer is flushed but not shut down, so bytes written
+    /// after the switch follow the finished stream directly.
     pub async fn switch_algorithm(&mut self, algorithm: CompressionAlgorithm) -> io::Result<()> {
         use tokio::io::AsyncWriteExt;
 
@@ -947,8 +982,15 @@ where
             return Ok(());
         }
 
-        // Shutdown current compressor to finalize compression state
-        self.shutdown().await?;
+        // Finish the current compressed stream without closing the base writer
+        match self.inner.as_mut().expect("inner writer missing") {
+            InnerWriter::None(w) => w.flush().await?,
+            InnerWriter::Gzip(w) => w.shutdown().await?,
+            InnerWriter::Deflate(w) => w.shutdown().await?,
+            InnerWriter::Brotli(w) => w.shutdown().await?,
+            InnerWriter::Zlib(w) => w.shutdown().await?,
+            InnerWriter::Zstd(w) => w.shutdown().await?,
+        }
 
         // Take the inner writer, extract the base writer, and recreate with new algorithm
         let old_inner = self.inner.take().expect("inner writer missing");
@@ -961,11 +1003,11 @@ where
     fn extract_writer(inner: InnerWriter<W>) -> W {
         match inner {
             InnerWriter::None(w) => w,
-            InnerWriter::Gzip(w) => w.into_inner(),
-            InnerWriter::Deflate(w) => w.into_inner(),
-            InnerWriter::Brotli(w) => w.into_inner(),
-            InnerWriter::Zlib(w) => w.into_inner(),
-            InnerWriter::Zstd(w) => w.into_inner(),
+            InnerWriter::Gzip(w) => w.into_inner().0,
+            InnerWriter::Deflate(w) => w.into_inner().0,
+            InnerWriter::Brotli(w) => w.into_inner().0,
+            InnerWriter::Zlib(w) => w.into_inner().0,
+            InnerWriter::Zstd(w) => w.into_inner().0,
         }
     }
 
@@ -973,11 +1015,11 @@ where
     pub fn get_ref(&self) -> &W {
         match self.inner.as_ref().expect("inner writer missing") {
             InnerWriter::None(w) => w,
-            InnerWriter::Gzip(w) => w.get_ref(),
-            InnerWriter::Deflate(w) => w.get_ref(),
-            InnerWriter::Brotli(w) => w.get_ref(),
-            InnerWriter::Zlib(w) => w.get_ref(),
-            InnerWriter::Zstd(w) => w.get_ref(),
+            InnerWriter::Gzip(w) => &w.get_ref().0,
+            InnerWriter::Deflate(w) => &w.get_ref().0,
+            InnerWriter::Brotli(w) => &w.get_ref().0,
+            InnerWriter::Zlib(w) => &w.get_ref().0,
+            InnerWriter::Zstd(w) => &w.get_ref().0,
         }
     }
 
@@ -985,11 +1027,11 @@ where
     pub fn get_mut(&mut self) -> &mut W {
         match self.inner.as_mut().expect("inner writer missing") {
             InnerWriter::None(w) => w,
-            InnerWriter::Gzip(w) => w.get_mut(),
-            InnerWriter::Deflate(w) => w.get_mut(),
-            InnerWriter::Brotli(w) => w.get_mut(),
-            InnerWriter::Zlib(w) => w.get_mut(),
-            InnerWriter::Zstd(w) => w.get_mut(),
+            InnerWriter::Gzip(w) => &mut w.get_mut().0,
+            InnerWriter::Deflate(w) => &mut w.get_mut().0,
+            InnerWriter::Brotli(w) => &mut w.get_mut().0,
+            InnerWriter::Zlib(w) => &mut w.get_mut().0,
+            InnerWriter::Zstd(w) => &mut w.get_mut().0,
         }
     }
 
@@ -1033,14 +1075,17 @@ where
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<(), io::Error>> {
-        match self.inner.as_mut().expect("inner writer missing") {
-            InnerWriter::None(w) => Pin::new(w).poll_shutdown(cx),
+        // Finish the compressed stream first, then shut down the base writer itself
+        let finished = match self.inner.as_mut().expect("inner writer missing") {
+            InnerWriter::None(w) => return Pin::new(w).poll_shutdown(cx),
             InnerWriter::Gzip(w) => Pin::new(w).poll_shutdown(cx),
             InnerWriter::Deflate(w) => Pin::new(w).poll_shutdown(cx),
             InnerWriter::Brotli(w) => Pin::new(w).poll_shutdown(cx),
             InnerWriter::Zlib(w) => Pin::new(w).poll_shutdown(cx),
             InnerWriter::Zstd(w) => Pin::new(w).poll_shutdown(cx),
-        }
+        };
+        ready!(finished)?;
+        Pin::new(self.get_mut().get_mut()).poll_shutdown(cx)
     }
 }
 
@@ -1048,6 +1093,10 @@ where
 ///
 /// This wraps an `AsyncRead` stream and decompresses all data read from it.
 /// The decompression algorithm can be switched dynamically.
+///
+/// When a compressed stream ends, the reader falls back to
+/// [`CompressionAlgorithm::None`] and continues with the bytes that follow it,
+/// so a peer can stop compressing without closing the connection.
 pub struct CompressionReader<R>
 where
     R: AsyncRead + Unpin + Send,
@@ -1059,12 +1108,55 @@ enum InnerReader<R>
 where
     R: AsyncRead + Unpin + Send,
 {
-    None(R),
-    Gzip(async_compression::tokio::bufread::GzipDecoder<tokio::io::BufReader<R>>),
-    Deflate(async_compression::tokio::bufread::DeflateDecoder<tokio::io::BufReader<R>>),
-    Brotli(async_compression::tokio::bufread::BrotliDecoder<tokio::io::BufReader<R>>),
-    Zlib(async_compression::tokio::bufread::ZlibDecoder<tokio::io::BufReader<R>>),
-    Zstd(async_compression::tokio::bufread::ZstdDecoder<tokio::io::BufReader<R>>),
+    None(Rewind<R>),
+    Gzip(async_compression::tokio::bufread::GzipDecoder<tokio::io::BufReader<Rewind<R>>>),
+    Deflate(async_compression::tokio::bufread::DeflateDecoder<tokio::io::BufReader<Rewind<R>>>),
+    Brotli(async_compression::tokio::bufread::BrotliDecoder<tokio::io::BufReader<Rewind<R>>>),
+    Zlib(async_compression::tokio::bufread::ZlibDecoder<tokio::io::BufReader<Rewind<R>>>),
+    Zstd(async_compression::tokio::bufread::ZstdDecoder<tokio::io::BufReader<Rewind<R>>>),
+}
+
+/// Reader adapter that replays pushed back bytes before reading from `R`
+///
+/// Decoders read ahead of the end of a compressed stream, and callers may hold
+/// bytes that were read before a switch point. Both are pushed back here so no
+/// input is lost when the algorithm changes.
+struct Rewind<R> {
+    pending: Vec<u8>,
+    reader: R,
+}
+
+impl<R> Rewind<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            pending: Vec::new(),
+            reader,
+        }
+    }
+
+    /// Push bytes back so they are read before anything already pending
+    fn unread(&mut self, bytes: &[u8]) {
+        self.pending.splice(0..0, bytes.iter().copied());
+    }
+}
+
+impl<R> AsyncRead for Rewind<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.pending.is_empty() {
+            return Pin::new(&mut self.reader).poll_read(cx, buf);
+        }
+        let count = self.pending.len().min(buf.remaining());
+        buf.put_slice(&self.pending[..count]);
+        self.pending.drain(..count);
+        Poll::Ready(Ok(()))
+    }
 }
 
 impl<R> CompressionReader<R>
@@ -1074,11 +1166,11 @@ where
     /// Create a new compression reader with the specified algorithm
     pub fn new(reader: R, algorithm: CompressionAlgorithm) -> Self {
         Self {
-            inner: Some(Self::wrap_reader(reader, algorithm)),
+            inner: Some(Self::wrap_reader(Rewind::new(reader), algorithm)),
         }
     }
 
-    fn wrap_reader(reader: R, algorithm: CompressionAlgorithm) -> InnerReader<R> {
+    fn wrap_reader(reader: Rewind<R>, algorithm: CompressionAlgorithm) -> InnerReader<R> {
         use async_compression::tokio::bufread::{
             BrotliDecoder, DeflateDecoder, GzipDecoder, ZlibDecoder, ZstdDecoder,
         };
@@ -1119,29 +1211,99 @@ where
     /// Switch to a new decompression algorithm
     ///
     /// This extracts the underlying reader and wraps it with a new decompressor
-    /// using the specified algorithm.
+    /// using the specified algorithm. Input the old decompressor had buffered but
+    /// not yet consumed is kept and read by the new one.
     pub fn switch_algorithm(&mut self, algorithm: CompressionAlgorithm) -> io::Result<()> {
-        // Early return if already using this algorithm
-        if self.algorithm() == algorithm {
+        self.switch_algorithm_with_pending(algorithm, &[])
+    }
+
+    /// Switch to a new decompression algorithm, feeding `pending` through it first
+    ///
+    /// `pending` holds raw bytes that were already read from this reader but lie
+    /// after the switch point, such as the rest of a read buffer whose first part
+    /// announced the start of a compressed stream. They are read (and decompressed)
+    /// before any further input.
+    pub fn switch_algorithm_with_pending(
+        &mut self,
+        algorithm: CompressionAlgorithm,
+        pending: &[u8],
+    ) -> io::Result<()> {
+        // Early return if already using this algorithm and nothing needs replaying
+        if self.algorithm() == algorithm && pending.is_empty() {
             return Ok(());
         }
 
         // Take the inner reader, extract the base reader, and recreate with new algorithm
         let old_inner = self.inner.take().expect("inner reader missing");
-        let base_reader = Self::extract_reader(old_inner);
+        let mut base_reader = Self::extract_reader(old_inner);
+        base_reader.unread(pending);
         self.inner = Some(Self::wrap_reader(base_reader, algorithm));
 
         Ok(())
     }
 
-    fn extract_reader(inner: InnerReader<R>) -> R {
-        match inner {
-            InnerReader::None(r) => r,
-            InnerReader::Gzip(r) => r.into_inner().into_inner(),
-            InnerReader::Deflate(r) => r.into_inner().into_inner(),
-            InnerReader::Brotli(r) => r.into_inner().into_inner(),
-            InnerReader::Zlib(r) => r.into_inner().into_inner(),
-            InnerReader::Zstd(r) => r.into_inner().into_inner(),
+    fn extract_reader(inner: InnerReader<R>) -> Rewind<R> {
+        let buffered = match inner {
+            InnerReader::None(r) => return r,
+            InnerReader::Gzip(r) => r.into_inner(),
+            InnerReader::Deflate(r) => r.into_inner(),
+            InnerReader::Brotli(r) => r.into_inner(),
+            InnerReader::Zlib(r) => r.into_inner(),
+            InnerReader::Zstd(r) => r.into_inner(),
+        };
+        // Keep read-ahead the decompressor has not consumed
+        let leftover = buffered.buffer().to_vec();
+        let mut reader = buffered.into_inner();
+        reader.unread(&leftover);
+        reader
+    }
+
+    /// Get a reference to the underlying reader
+    pub fn get_ref(&self) -> &R {
+        match self.inner.as_ref().expect("inner reader missing") {
+            InnerReader::None(r) => &r.reader,
+            InnerReader::Gzip(r) => &r.get_ref().get_ref().reader,
+            InnerReader::Deflate(r) => &r.get_ref().get_ref().reader,
+            InnerReader::Brotli(r) => &r.get_ref().get_ref().reader,
+            InnerReader::Zlib(r) => &r.get_ref().get_ref().reader,
+            InnerReader::Zstd(r) => &r.get_ref().get_ref().reader,
+        }
+    }
+
+    /// Get a mutable reference to the underlying reader
+    ///
+    /// Reading from it directly skips input this reader has buffered, which
+    /// must be read through this reader first.
+    pub fn get_mut(&mut self) -> &mut R {
+        match self.inner.as_mut().expect("inner reader missing") {
+            InnerReader::None(r) => &mut r.reader,
+            InnerReader::Gzip(r) => &mut r.get_mut().get_mut().reader,
+            InnerReader::Deflate(r) => &mut r.get_mut().get_mut().reader,
+            InnerReader::Brotli(r) => &mut r.get_mut().get_mut().reader,
+            InnerReader::Zlib(r) => &mut r.get_mut().get_mut().reader,
+            InnerReader::Zstd(r) => &mut r.get_mut().get_mut().reader,
+        }
+    }
+
+    /// Consume this reader and return the underlying reader with its unread input
+    ///
+    /// The returned bytes were already read from the underlying reader but not
+    /// yet consumed, such as read-ahead past the end of a compressed stream or
+    /// bytes passed to [`switch_algorithm_with_pending`](Self::switch_algorithm_with_pending).
+    /// They must be read before anything else the underlying reader yields.
+    pub fn into_inner_with_pending(mut self) -> (R, Vec<u8>) {
+        let reader = Self::extract_reader(self.inner.take().expect("inner reader missing"));
+        (reader.reader, reader.pending)
+    }
+
+    fn poll_inner(&mut self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.inner.as_mut().expect("inner reader missing") {
+            InnerReader::None(r) => Pin::new(r).poll_read(cx, buf),
+            InnerReader::Gzip(r) => Pin::new(r).poll_read(cx, buf),
+            InnerReader::Deflate(r) => Pin::new(r).poll_read(cx, buf),
+            InnerReader::Brotli(r) => Pin::new(r).poll_read(cx, buf),
+            InnerReader::Zlib(r) => Pin::new(r).poll_read(cx, buf),
+            InnerReader::Zstd(r) => Pin::new(r).poll_read(cx, buf),
         }
     }
 }
@@ -1155,13 +1317,18 @@ where
         cx: &mut Context<'_>,
         buf: &mut ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
-        match self.inner.as_mut().expect("inner reader missing") {
-            InnerReader::None(r) => Pin::new(r).poll_read(cx, buf),
-            InnerReader::Gzip(r) => Pin::new(r).poll_read(cx, buf),
-            InnerReader::Deflate(r) => Pin::new(r).poll_read(cx, buf),
-            InnerReader::Brotli(r) => Pin::new(r).poll_read(cx, buf),
-            InnerReader::Zlib(r) => Pin::new(r).poll_read(cx, buf),
-            InnerReader::Zstd(r) => Pin::new(r).poll_read(cx, buf),
+        loop {
+            let filled = buf.filled().len();
+            ready!(self.poll_inner(cx, buf))?;
+            if self.algorithm() == CompressionAlgorithm::None
+                || buf.filled().len() > filled
+                || buf.remaining() == 0
+            {
+                return Poll::Ready(Ok(()));
+            }
+            // A decompressor only reports an empty read once its stream has ended
+            // (a truncated stream is an error), so carry on with the raw bytes after it.
+            self.switch_algorithm(CompressionAlgorithm::None)?;
         }
     }
 }
@@ -1179,6 +1346,7 @@ mod tests {
         read_buf: Vec<u8>,
         write_buf: Vec<u8>,
         read_pos: usize,
+        shut_down: bool,
     }
 
     impl MockStream {
@@ -1187,6 +1355,7 @@ mod tests {
                 read_buf: Vec::new(),
                 write_buf: Vec::new(),
                 read_pos: 0,
+                shut_down: false,
             }
         }
 
@@ -1195,6 +1364,7 @@ mod tests {
                 read_buf: data,
                 write_buf: Vec::new(),
                 read_pos: 0,
+                shut_down: false,
             }
         }
 
@@ -1232,9 +1402,10 @@ mod tests {
         }
 
         fn poll_shutdown(
-            self: Pin<&mut Self>,
+            mut self: Pin<&mut Self>,
             _cx: &mut Context<'_>,
         ) -> Poll<Result<(), io::Error>> {
+            self.shut_down = true;
             Poll::Ready(Ok(()))
         }
     }
@@ -1698,4 +1869,118 @@ mod tests {
         // After that should be gzip compressed data
         assert_eq!(&data[12..14], &[0x1f, 0x8b]); // Gzip magic
     }
+
+    // ============================================================================
+    // CompressionWriter / CompressionReader Tests
+    // ============================================================================
+
+    /// Writes `plain`, a zlib stream containing `packed`, then `tail` uncompressed.
+    async fn write_plain_packed_tail() -> MockStream {
+        let mut writer = CompressionWriter::new(MockStream::new(), CompressionAlgorithm::None);
+        writer.write_all(b"plain").await.unwrap();
+        writer
+            .switch_algorithm(CompressionAlgorithm::Zlib)
+            .await
+            .unwrap();
+        writer.write_all(b"packed").await.unwrap();
+        writer
+            .switch_algorithm(CompressionAlgorithm::None)
+            .await
+            .unwrap();
+        writer.write_all(b"tail").await.unwrap();
+        writer.flush().await.unwrap();
+        writer.into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_writer_switch_keeps_base_open() {
+        let stream = write_plain_packed_tail().await;
+        let written = stream.written_data();
+
+        assert!(!stream.shut_down);
+        assert_eq!(&written[..5], b"plain");
+        assert_eq!(written[5], 0x78); // Zlib header
+        assert_eq!(&written[written.len() - 4..], b"tail");
+    }
+
+    #[tokio::test]
+    async fn test_writer_shutdown_closes_base() {
+        let mut writer = CompressionWriter::new(MockStream::new(), CompressionAlgorithm::Zlib);
+        writer.write_all(b"data").await.unwrap();
+        writer.shutdown().await.unwrap();
+
+        assert!(writer.get_ref().shut_down);
+    }
+
+    #[tokio::test]
+    async fn test_reader_falls_back_after_stream_end() {
+        let written = write_plain_packed_tail().await.written_data().to_vec();
+        let mut reader = CompressionReader::new(
+            MockStream::with_read_data(written),
+            CompressionAlgorithm::None,
+        );
+
+        let mut plain = [0u8; 5];
+        reader.read_exact(&mut plain).await.unwrap();
+        assert_eq!(&plain, b"plain");
+
+        reader.switch_algorithm(CompressionAlgorithm::Zlib).unwrap();
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+
+        assert_eq!(rest, b"packedtail");
+        assert_eq!(reader.algorithm(), CompressionAlgorithm::None);
+    }
+
+    #[tokio::test]
+    async fn test_reader_switch_with_pending() {
+        let written = write_plain_packed_tail().await.written_data().to_vec();
+        let mut reader = CompressionReader::new(
+            MockStream::with_read_data(written),
+            CompressionAlgorithm::None,
+        );
+
+        // Read everything up front, as a framed reader would
+        let mut buffered = vec![0u8; 1024];
+        let count = reader.read(&mut buffered).await.unwrap();
+        assert_eq!(&buffered[..5], b"plain");
+
+        reader
+            .switch_algorithm_with_pending(CompressionAlgorithm::Zlib, &buffered[5..count])
+            .unwrap();
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+
+        assert_eq!(rest, b"packedtail");
+    }
+
+    #[tokio::test]
+    async fn test_reader_into_inner_with_pending() {
+        let mut reader = CompressionReader::new(
+            MockStream::with_read_data(b"tail".to_vec()),
+            CompressionAlgorithm::None,
+        );
+        reader
+            .switch_algorithm_with_pending(CompressionAlgorithm::None, b"head")
+            .unwrap();
+
+        let (mut inner, pending) = reader.into_inner_with_pending();
+        let mut rest = Vec::new();
+        inner.read_to_end(&mut rest).await.unwrap();
+
+        assert_eq!(pending, b"head");
+        assert_eq!(rest, b"tail");
+    }
+
+    #[tokio::test]
+    async fn test_reader_truncated_stream_is_error() {
+        let written = write_plain_packed_tail().await.written_data().to_vec();
+        let mut reader = CompressionReader::new(
+            MockStream::with_read_data(written[5..8].to_vec()),
+            CompressionAlgorithm::Zlib,
+        );
+
+        let mut rest = Vec::new();
+        assert!(reader.read_to_end(&mut rest).await.is_err());
+    }
 }
@@ -16,8 +16,13 @@
 
 //! Server configuration
 
+use crate::{MsspConfig, TlsConfig};
 use std::net::SocketAddr;
 use std::time::Duration;
+use termionix_service::zmp::ZmpRegistry;
+use termionix_service::{
+    Charset, CodecLimits, ConnectionConfig, OptionPolicy, OverflowPolicy, RateLimit, TelnetOption,
+};
 
 /// Server configuration
 ///
@@ -35,6 +40,16 @@ use std::time::Duration;
 ///     .with_idle_timeout(Duration::from_secs(600))
 ///     .with_compression(true);
 /// ```
+///
+/// Listeners that need a different feature set can use their own option policy:
+///
+/// ```
+/// use termionix_server::{OptionPolicy, ServerConfig, TelnetOption};
+///
+/// // A plain admin port without GMCP
+/// let admin = ServerConfig::default()
+///     .with_option_policy(OptionPolicy::default().refuse(TelnetOption::GMCP));
+/// ```
 #[derive(Debug, Clone)]
 pub struct ServerConfig {
     /// Address to bind the server to
@@ -66,8 +81,78 @@ pub struct ServerConfig {
 
     /// Enable compression (MCCP)
     ///
-    /// When enabled, the server will negotiate compression with clients that support it.
+    /// When enabled, the server offers `WILL COMPRESS2` and `WILL COMPRESS3` on
+    /// connect, compressing its output for clients that accept MCCP2 and
+    /// decompressing input from clients that start MCCP3.
     pub enable_compression: bool,
+
+    /// Telnet option negotiation policy
+    ///
+    /// Decides which options connections accept from clients and which ones
+    /// the server requests as soon as a client connects.
+    pub option_policy: OptionPolicy,
+
+    /// Maximum number of outgoing messages queued per connection
+    pub write_queue_size: usize,
+
+    /// What to do with output for a connection whose write queue is full
+    ///
+    /// The queue fills up when a client reads slower than the server writes.
+    pub overflow_policy: OverflowPolicy,
+
+    /// Send keepalive probes to idle clients
+    ///
+    /// Clients that stop answering are disconnected with
+    /// [`TelnetError::KeepaliveTimeout`](crate::TelnetError::KeepaliveTimeout).
+    pub keepalive: bool,
+
+    /// Time without input from a client before it is probed
+    pub keepalive_interval: Duration,
+
+    /// Time a client has to answer a keepalive probe
+    pub keepalive_timeout: Duration,
+
+    /// Limits on input from each client (None for no limits)
+    ///
+    /// See [`RateLimitAction`](termionix_service::RateLimitAction) for what
+    /// happens to input over a limit.
+    pub rate_limit: Option<RateLimit>,
+
+    /// Limits on subnegotiation length, negotiation loops and queued replies
+    ///
+    /// Clients that exceed one are disconnected with
+    /// [`TelnetError::LimitExceeded`](crate::TelnetError::LimitExceeded).
+    pub codec_limits: CodecLimits,
+
+    /// Charsets offered in CHARSET (RFC 2066) negotiation, in order of preference
+    ///
+    /// When not empty the server offers `WILL CHARSET`, requests these
+    /// charsets and transcodes text to and from the one the client accepts.
+    pub charsets: Vec<Charset>,
+
+    /// Variables reported to MSSP crawlers
+    ///
+    /// When not empty the server offers `WILL MSSP` and answers `DO MSSP`
+    /// with these variables plus the current `PLAYERS` and `UPTIME`.
+    pub mssp: MsspConfig,
+
+    /// ZMP commands answered automatically for clients that agree to ZMP
+    ///
+    /// The default answers the standard `zmp.` package (`zmp.ping`,
+    /// `zmp.check`, ...). Register game commands or an ident here.
+    pub zmp: ZmpRegistry,
+
+    /// TLS settings
+    ///
+    /// When set, [`TelnetServer::new`](crate::TelnetServer::new) only accepts
+    /// TLS connections on [`bind_address`](Self::bind_address).
+    pub tls: Option<TlsConfig>,
+
+    /// START_TLS settings
+    ///
+    /// When set, plain connections are offered an in-place upgrade to TLS
+    /// (`DO START_TLS`), so one port serves both plain and encrypted clients.
+    pub start_tls: Option<TlsConfig>,
 }
 
 impl Default for ServerConfig {
@@ -80,6 +165,19 @@ impl Default for ServerConfig {
             write_timeout: Duration::from_secs(10),
             shutdown_timeout: Duration::from_secs(30),
             enable_compression: false,
+            option_policy: OptionPolicy::default(),
+            write_queue_size: 8192,
+            overflow_policy: OverflowPolicy::default(),
+            keepalive: true,
+            keepalive_interval: Duration::from_secs(60),
+            keepalive_timeout: Duration::from_secs(30),
+            rate_limit: None,
+            codec_limits: CodecLimits::default(),
+            charsets: Vec::new(),
+            mssp: MsspConfig::default(),
+            zmp: ZmpRegistry::default(),
+            tls: None,
+            start_tls: None,
         }
     }
 }
@@ -131,6 +229,122 @@ impl ServerConfig {
         self
     }
 
+    /// Set the Telnet option negotiation policy
+    pub fn with_option_policy(mut self, policy: OptionPolicy) -> Self {
+        self.option_policy = policy;
+        self
+    }
+
+    /// Set the maximum number of outgoing messages queued per connection
+    pub fn with_write_queue_size(mut self, size: usize) -> Self {
+        self.write_queue_size = size;
+        self
+    }
+
+    /// Set the policy applied when a connection's write queue is full
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Enable or disable keepalive probes
+    pub fn with_keepalive(mut self, enabled: bool) -> Self {
+        self.keepalive = enabled;
+        self
+    }
+
+    /// Set the time without input before a client is probed
+    pub fn with_keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive_interval = interval;
+        self
+    }
+
+    /// Set the time a client has to answer a keepalive probe
+    pub fn with_keepalive_timeout(mut self, timeout: Duration) -> Self {
+        self.keepalive_timeout = timeout;
+        self
+    }
+
+    /// Limit input from each client
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// Set the Telnet codec limits
+    pub fn with_codec_limits(mut self, limits: CodecLimits) -> Self {
+        self.codec_limits = limits;
+        self
+    }
+
+    /// Negotiate one of `charsets` with each client, e.g. UTF-8 falling back to Latin-1 and CP437
+    pub fn with_charsets(mut self, charsets: Vec<Charset>) -> Self {
+        self.charsets = charsets;
+        self
+    }
+
+    /// Set the variables reported to MSSP crawlers
+    pub fn with_mssp(mut self, mssp: MsspConfig) -> Self {
+        self.mssp = mssp;
+        self
+    }
+
+    /// Set the ZMP commands answered automatically
+    pub fn with_zmp(mut self, zmp: ZmpRegistry) -> Self {
+        self.zmp = zmp;
+        self
+    }
+
+    /// Serve TLS on the bind address
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Offer plain connections an upgrade to TLS with START_TLS
+    pub fn with_start_tls(mut self, tls: TlsConfig) -> Self {
+        self.start_tls = Some(tls);
+        self
+    }
+
+    /// Connection settings used for new connections
+    pub fn connection_config(&self) -> ConnectionConfig {
+        ConnectionConfig::default()
+            .with_buffer_size(self.write_queue_size)
+            .with_overflow_policy(self.overflow_policy)
+            .with_keepalive(self.keepalive)
+            .with_keepalive_interval(self.keepalive_interval)
+            .with_keepalive_timeout(self.keepalive_timeout)
+            .with_rate_limit(self.rate_limit)
+            .with_codec_limits(self.codec_limits.clone())
+            .with_charsets(self.charsets.clone())
+            .with_zmp(self.zmp.clone())
+    }
+
+    /// Option policy used for new connections
+    ///
+    /// This is [`option_policy`](Self::option_policy) with MCCP2 and MCCP3
+    /// requested when [`enable_compression`](Self::enable_compression) is set,
+    /// CHARSET requested when [`charsets`](Self::charsets) is not empty, and
+    /// MSSP offered when [`mssp`](Self::mssp) is not empty.
+    pub fn connection_policy(&self) -> OptionPolicy {
+        let mut policy = self.option_policy.clone();
+        if self.enable_compression {
+            policy = policy
+                .request_local(TelnetOption::Compress2)
+                .request_local(TelnetOption::Compress3);
+        }
+        if !self.charsets.is_empty() {
+            policy = policy
+                .request_local(TelnetOption::Charset)
+                .accept_remote(TelnetOption::Charset);
+        }
+        if !self.mssp.is_empty() {
+            policy = policy.request_local(TelnetOption::MSSP);
+        }
+        policy
+    }
+
     /// Validate the configuration
     ///
     /// Returns an error if the configuration is invalid.
@@ -155,6 +369,17 @@ impl ServerConfig {
             return Err("shutdown_timeout must be greater than 0".to_string());
         }
 
+        if self.write_queue_size == 0 {
+            return Err("write_queue_size must be greater than 0".to_string());
+        }
+
+        if self.keepalive && (self.keepalive_interval.is_zero() || self.keepalive_timeout.is_zero())
+        {
+            return Err(
+                "keepalive_interval and keepalive_timeout must be greater than 0".to_string(),
+            );
+        }
+
         Ok(())
     }
 }
@@ -184,6 +409,137 @@ mod tests {
         assert!(config.enable_compression);
     }
 
+    #[test]
+    fn test_option_policy() {
+        let config = ServerConfig::default();
+        assert_eq!(config.option_policy, OptionPolicy::default());
+
+        let config = ServerConfig::default().with_option_policy(
+            OptionPolicy::default()
+                .refuse(TelnetOption::GMCP)
+                .request_local(TelnetOption::Echo),
+        );
+        assert!(!config.option_policy.accepts_local(TelnetOption::GMCP));
+        assert!(config.option_policy.requests_local(TelnetOption::Echo));
+    }
+
+    #[test]
+    fn test_connection_policy_compression() {
+        let config = ServerConfig::default();
+        assert!(
+            !config
+                .connection_policy()
+                .requests_local(TelnetOption::Compress2)
+        );
+
+        let config = ServerConfig::default().with_compression(true);
+        let policy = config.connection_policy();
+        assert!(policy.requests_local(TelnetOption::Compress2));
+        assert!(policy.requests_local(TelnetOption::Compress3));
+        assert!(!policy.accepts_remote(TelnetOption::Compress2));
+    }
+
+    #[test]
+    fn test_connection_config_write_queue() {
+        let config = ServerConfig::default()
+            .with_write_queue_size(64)
+            .with_overflow_policy(OverflowPolicy::DropOldest);
+        let connection = config.connection_config();
+        assert_eq!(connection.buffer_size, 64);
+        assert_eq!(connection.overflow_policy, OverflowPolicy::DropOldest);
+
+        let config = config.with_write_queue_size(0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_connection_config_keepalive() {
+        let config = ServerConfig::default()
+            .with_keepalive_interval(Duration::from_secs(15))
+            .with_keepalive_timeout(Duration::from_secs(5));
+        let connection = config.connection_config();
+        assert!(connection.keepalive);
+        assert_eq!(connection.keepalive_interval, Duration::from_secs(15));
+        assert_eq!(connection.keepalive_timeout, Duration::from_secs(5));
+
+        assert!(
+            config
+                .clone()
+                .with_keepalive_timeout(Duration::ZERO)
+                .validate()
+                .is_err()
+        );
+        let config = config
+            .with_keepalive(false)
+            .with_keepalive_timeout(Duration::ZERO);
+        assert!(config.validate().is_ok());
+        assert!(!config.connection_config().keepalive);
+    }
+
+    #[test]
+    fn test_connection_config_rate_limit() {
+        let config = ServerConfig::default();
+        assert!(config.connection_config().rate_limit.is_none());
+
+        let limit = RateLimit::new().with_lines_per_second(10);
+        let config = config.with_rate_limit(limit);
+        assert_eq!(config.connection_config().rate_limit, Some(limit));
+    }
+
+    #[test]
+    fn test_connection_config_codec_limits() {
+        let config = ServerConfig::default();
+        assert_eq!(
+            config.connection_config().codec_limits,
+            CodecLimits::default()
+        );
+
+        let limits = CodecLimits::unlimited().with_max_subnegotiation_length(Some(256));
+        let config = config.with_codec_limits(limits.clone());
+        assert_eq!(config.connection_config().codec_limits, limits);
+    }
+
+    #[test]
+    fn test_charsets_request_charset_option() {
+        let config = ServerConfig::default();
+        assert!(
+            !config
+                .connection_policy()
+                .requests_local(TelnetOption::Charset)
+        );
+
+        let charsets = vec![Charset::Utf8, Charset::Latin1, Charset::Cp437];
+        let config = config.with_charsets(charsets.clone());
+        assert!(
+            config
+                .connection_policy()
+                .requests_local(TelnetOption::Charset)
+        );
+        assert!(
+            config
+                .connection_policy()
+                .accepts_remote(TelnetOption::Charset)
+        );
+        assert_eq!(config.connection_config().charsets, charsets);
+    }
+
+    #[test]
+    fn test_mssp_offers_mssp_option() {
+        let config = ServerConfig::default();
+        assert!(
+            !config
+                .connection_policy()
+                .requests_local(TelnetOption::MSSP)
+        );
+
+        let config = config.with_mssp(MsspConfig::new().with_field("NAME", "Example MUD"));
+        assert!(
+            config
+                .connection_policy()
+                .requests_local(TelnetOption::MSSP)
+        );
+    }
+
     #[test]
     fn test_validation() {
         let mut config = ServerConfig::default();
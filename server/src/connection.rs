@@ -16,25 +16,35 @@
 
 //! Telnet connection implementation for
 
-use crate::{ConnectionId, Result, TelnetError};
+use crate::{ConnectionId, PeerAddress, Result, TelnetError, TlsConfig, TlsInfo, Transport};
 use metrics::{counter, gauge, histogram};
 use std::any::Any;
 use std::collections::HashMap;
-use std::net::SocketAddr;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, OnceLock, RwLock};
 use std::time::Instant;
+use termionix_service::new_environ::NewEnviron;
 use termionix_service::{
-    AnsiCodec, AnsiConfig, FlushStrategy, SplitTerminalConnection, TelnetCodec, TerminalCodec,
-    TerminalCommand, TerminalEvent,
+    AnsiCodec, AnsiConfig, Charset, ConnectionConfig, ConnectionError, FlushStrategy, OptionPolicy,
+    SplitTerminalConnection, StartTlsStream, TelnetCodec, TelnetOption, TerminalCapabilities,
+    TerminalCodec, TerminalCommand, TerminalEvent,
 };
-use tokio::io::{ReadHalf, WriteHalf};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
+use tokio_rustls::TlsAcceptor;
+use tokio_util::bytes::BytesMut;
+use tokio_util::codec::Encoder;
 use tracing::{debug, error, info, instrument, trace, warn};
 
 /// Type alias for the complete codec stack
 type FullTerminalCodec = TerminalCodec<AnsiCodec<TelnetCodec>>;
 
+/// Read half of a transport, boxed so connections over any stream share one type
+type TransportReader = Box<dyn AsyncRead + Unpin + Send>;
+
+/// Write half of a transport, boxed so connections over any stream share one type
+type TransportWriter = Box<dyn AsyncWrite + Unpin + Send>;
+
 /// A Telnet connection with split read/write architecture and integrated compression
 ///
 /// This connection uses the unified SplitConnection architecture which separates
@@ -47,11 +57,12 @@ type FullTerminalCodec = TerminalCodec<AnsiCodec<TelnetCodec>>;
 pub struct TelnetConnection {
     // Core I/O - Split connection with independent read/write workers
     // Compression is integrated internally via CompressionReader/CompressionWriter
-    split: SplitTerminalConnection<ReadHalf<TcpStream>, WriteHalf<TcpStream>, FullTerminalCodec>,
+    split: SplitTerminalConnection<TransportReader, TransportWriter, FullTerminalCodec>,
 
     // Metadata (lock-free access)
     id: ConnectionId,
-    peer_addr: SocketAddr,
+    peer_addr: PeerAddress,
+    tls_info: Arc<OnceLock<TlsInfo>>,
     created_at: Instant,
 
     // Metrics (lock-free)
@@ -66,10 +77,54 @@ pub struct TelnetConnection {
 
 impl TelnetConnection {
     /// Wrap a TCP stream into a TelnetConnection with split read/write architecture
-    #[instrument(skip(socket), fields(connection_id = %id))]
+    ///
+    /// Options are negotiated according to [`OptionPolicy::default`].
     pub fn wrap(socket: TcpStream, id: ConnectionId) -> Result<Self> {
-        let peer_addr = socket.peer_addr()?;
+        Self::wrap_with_policy(socket, id, OptionPolicy::default())
+    }
+
+    /// Wrap a TCP stream into a TelnetConnection that negotiates options according to `policy`
+    ///
+    /// The options the policy requests proactively are sent to the client
+    /// before anything else is written to the connection.
+    pub fn wrap_with_policy(
+        socket: TcpStream,
+        id: ConnectionId,
+        policy: OptionPolicy,
+    ) -> Result<Self> {
+        Self::wrap_with_config(socket, id, policy, &ConnectionConfig::default())
+    }
+
+    /// Wrap a TCP stream into a TelnetConnection with an option policy and connection settings
+    ///
+    /// `config` sizes the outgoing write queue, decides what happens when a
+    /// slow client lets it fill up and sets the Telnet codec's limits.
+    pub fn wrap_with_config(
+        socket: TcpStream,
+        id: ConnectionId,
+        policy: OptionPolicy,
+        config: &ConnectionConfig,
+    ) -> Result<Self> {
+        let peer_addr = PeerAddress::Tcp(socket.peer_addr()?);
+        Self::wrap_stream(socket, peer_addr, id, policy, config)
+    }
 
+    /// Wrap any byte stream into a TelnetConnection
+    ///
+    /// This is how connections over Unix domain sockets, TLS or in-memory
+    /// pipes are created; `peer_addr` is reported by [`peer_addr`](Self::peer_addr)
+    /// and the TLS session of an encrypted stream by [`tls_info`](Self::tls_info).
+    #[instrument(skip(stream, policy, config), fields(connection_id = %id))]
+    pub fn wrap_stream<S>(
+        stream: S,
+        peer_addr: PeerAddress,
+        id: ConnectionId,
+        policy: OptionPolicy,
+        config: &ConnectionConfig,
+    ) -> Result<Self>
+    where
+        S: Transport,
+    {
         info!(
             peer_addr = %peer_addr,
             "Creating new telnet connection with split architecture"
@@ -79,20 +134,43 @@ impl TelnetConnection {
         counter!("termionix.connections.total").increment(1);
         gauge!("termionix.connections.active").increment(1.0);
 
+        let tls_info = Arc::new(OnceLock::new());
+        if let Some(info) = stream.tls_info() {
+            let _ = tls_info.set(info);
+        }
+
         // Create the codec stack: TelnetCodec -> AnsiCodec -> TerminalCodec
-        let telnet_codec = TelnetCodec::new();
-        let ansi_codec = AnsiCodec::new(AnsiConfig::default(), telnet_codec);
-        let terminal_codec = TerminalCodec::new(ansi_codec);
+        let mut telnet_codec = TelnetCodec::with_policy(policy);
+        telnet_codec.set_data_chunks(true);
+        telnet_codec.set_limits(config.codec_limits.clone());
+        telnet_codec.set_zmp(config.zmp.clone());
+        let mut requests = BytesMut::new();
+        for frame in telnet_codec.request_policy_options() {
+            telnet_codec.encode(frame, &mut requests)?;
+        }
+        let mut ansi_codec = AnsiCodec::new(AnsiConfig::default(), telnet_codec);
+        ansi_codec.set_offered_charsets(config.charsets.clone());
+        let mut terminal_codec = TerminalCodec::new(ansi_codec);
+        terminal_codec.set_terminal_type_cycling(true);
 
         debug!("Codec stack initialized: TelnetCodec -> AnsiCodec -> TerminalCodec");
 
         // Create split connection with integrated compression support
         // CompressionReader and CompressionWriter wrap the read/write halves internally
-        let split = SplitTerminalConnection::<
-            tokio::io::ReadHalf<TcpStream>,
-            tokio::io::WriteHalf<TcpStream>,
-            TerminalCodec<AnsiCodec<TelnetCodec>>,
-        >::from_stream(socket, terminal_codec.clone(), terminal_codec);
+        let (reader, writer) = tokio::io::split(stream);
+        let split = SplitTerminalConnection::new_with_config(
+            Box::new(reader) as TransportReader,
+            Box::new(writer) as TransportWriter,
+            terminal_codec,
+            config,
+        );
+
+        if !requests.is_empty() {
+            debug!(bytes = requests.len(), "Requesting policy options");
+            split
+                .send_raw(requests.freeze(), true)
+                .map_err(|e| TelnetError::Io(std::io::Error::other(e.to_string())))?;
+        }
 
         // Set flush strategy to OnNewline for line-based protocols
         tokio::spawn({
@@ -106,6 +184,7 @@ impl TelnetConnection {
             split,
             id,
             peer_addr,
+            tls_info,
             created_at: Instant::now(),
             bytes_sent: Arc::new(AtomicU64::new(0)),
             bytes_received: Arc::new(AtomicU64::new(0)),
@@ -115,14 +194,90 @@ impl TelnetConnection {
         })
     }
 
+    /// Number of outgoing messages waiting in the write queue
+    pub fn queue_depth(&self) -> usize {
+        self.split.queue_depth()
+    }
+
     /// Get the connection ID
     pub fn id(&self) -> ConnectionId {
         self.id
     }
 
     /// Get the peer address
-    pub fn peer_addr(&self) -> SocketAddr {
-        self.peer_addr
+    pub fn peer_addr(&self) -> PeerAddress {
+        self.peer_addr.clone()
+    }
+
+    /// Get the TLS session details, or `None` for an unencrypted connection
+    ///
+    /// Connections upgraded with START_TLS report their session once the
+    /// handshake has completed.
+    pub fn tls_info(&self) -> Option<&TlsInfo> {
+        self.tls_info.get()
+    }
+
+    /// Check if the connection is encrypted with TLS
+    pub fn is_tls(&self) -> bool {
+        self.tls_info.get().is_some()
+    }
+
+    /// Offer to upgrade this connection to TLS in place with START_TLS
+    ///
+    /// Sends `DO START_TLS` and accepts a client's `WILL START_TLS`. Once the
+    /// client agrees, the server sends `FOLLOWS`, waits for the client's and
+    /// then performs the handshake with `tls`, keeping the codec state. Until
+    /// then output waits in the write queue. The peer's `FOLLOWS` is delivered
+    /// as a [`TerminalEvent::Subnegotiation`] after the handshake, from when on
+    /// [`tls_info`](Self::tls_info) reports the session.
+    ///
+    /// Does nothing on connections that are already encrypted.
+    pub fn enable_start_tls(&self, tls: &TlsConfig) -> Result<()> {
+        if self.is_tls() {
+            return Ok(());
+        }
+        let acceptor = TlsAcceptor::from(tls.server_config.clone());
+        let handshake_timeout = tls.handshake_timeout;
+        let tls_info = Arc::clone(&self.tls_info);
+        let upgrade = move |stream: StartTlsStream<TransportReader, TransportWriter>| {
+            Box::pin(async move {
+                let stream = tokio::time::timeout(handshake_timeout, acceptor.accept(stream))
+                    .await
+                    .map_err(|_| {
+                        std::io::Error::new(std::io::ErrorKind::TimedOut, "TLS handshake timed out")
+                    })??;
+                let _ = tls_info.set(TlsInfo::from_session(stream.get_ref().1));
+                let (reader, writer) = tokio::io::split(stream);
+                Ok((
+                    Box::new(reader) as TransportReader,
+                    Box::new(writer) as TransportWriter,
+                ))
+            }) as _
+        };
+        self.split
+            .enable_start_tls(Box::new(upgrade))
+            .map_err(|e| TelnetError::Tls(e.to_string()))?;
+
+        let mut request = BytesMut::new();
+        self.split.with_codec(|codec| {
+            let telnet = codec.codec_mut().inner_mut();
+            telnet.set_policy(
+                telnet
+                    .policy()
+                    .clone()
+                    .accept_remote(TelnetOption::StartTLS),
+            );
+            match telnet.enable_remote(TelnetOption::StartTLS) {
+                Some(frame) => telnet.encode(frame, &mut request),
+                None => Ok(()),
+            }
+        })?;
+        if !request.is_empty() {
+            self.split
+                .send_raw(request.freeze(), true)
+                .map_err(|_| TelnetError::ConnectionClosed)?;
+        }
+        Ok(())
     }
 
     /// Get when the connection was created
@@ -242,6 +397,53 @@ impl TelnetConnection {
         Ok(())
     }
 
+    /// Ask the client for NEW-ENVIRON variables (always flushes immediately)
+    ///
+    /// An empty list requests every variable. The client must have agreed to
+    /// NEW-ENVIRON, e.g. through
+    /// `OptionPolicy::default().request_remote(TelnetOption::NewEnvironment)`.
+    /// Its answers arrive as [`TerminalEvent::Subnegotiation`] and are stored
+    /// for [`environment`](Self::environment).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use termionix_server::{TelnetConnection, new_environ::mnes};
+    /// # async fn example(conn: &TelnetConnection) -> termionix_server::Result<()> {
+    /// conn.request_environment(&mnes::VARIABLES).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn request_environment(&self, names: &[&str]) -> Result<()> {
+        let request = NewEnviron::send(names.iter().copied());
+        self.send_command(&TerminalCommand::NewEnviron(request))
+            .await
+    }
+
+    /// Value of a NEW-ENVIRON variable the client has reported
+    pub fn environment(&self, name: &str) -> Option<String> {
+        self.split
+            .with_codec(|codec| codec.buffer().get_environment(name).cloned())
+    }
+
+    /// Capabilities of the client terminal
+    ///
+    /// Once the client enables TTYPE (see [`OptionPolicy::request_remote`]), the
+    /// server requests terminal types until the client repeats one, collecting
+    /// its name, terminal type and MTTS bitvector. Each response is delivered
+    /// as [`TerminalEvent::TerminalType`];
+    /// [`TerminalCapabilities::complete`] is set once the list has been read.
+    pub fn capabilities(&self) -> TerminalCapabilities {
+        self.split.with_codec(|codec| codec.capabilities().clone())
+    }
+
+    /// Character set text is transcoded to and from
+    ///
+    /// UTF-8 until CHARSET negotiation settles on another charset, see
+    /// [`ServerConfig::with_charsets`](crate::ServerConfig::with_charsets).
+    pub fn charset(&self) -> Charset {
+        self.split.with_codec(|codec| codec.codec().charset())
+    }
+
     /// Receive the next event
     #[instrument(skip(self), fields(connection_id = %self.id))]
     pub async fn next(&mut self) -> Result<Option<TerminalEvent>> {
@@ -265,6 +467,26 @@ impl TelnetConnection {
                 gauge!("termionix.connections.active").decrement(1.0);
                 Ok(None)
             }
+            Err(ConnectionError::KeepaliveTimeout) => {
+                counter!("termionix.errors.receive").increment(1);
+                debug!("Peer stopped answering keepalive probes");
+                Err(TelnetError::KeepaliveTimeout)
+            }
+            Err(ConnectionError::RateLimited(kind)) => {
+                counter!("termionix.errors.receive").increment(1);
+                debug!(%kind, "Peer exceeded rate limit");
+                Err(TelnetError::RateLimited(kind))
+            }
+            Err(ConnectionError::LimitExceeded(error)) => {
+                counter!("termionix.errors.receive").increment(1);
+                debug!(%error, "Peer exceeded codec limit");
+                Err(TelnetError::LimitExceeded(error))
+            }
+            Err(ConnectionError::StartTls(error)) => {
+                counter!("termionix.errors.receive").increment(1);
+                warn!(%error, "START_TLS upgrade failed");
+                Err(TelnetError::Tls(error.to_string()))
+            }
             Err(e) => {
                 counter!("termionix.errors.receive").increment(1);
                 error!("Error receiving event");
@@ -403,6 +625,7 @@ impl std::fmt::Debug for TelnetConnection {
         f.debug_struct("TelnetConnection")
             .field("id", &self.id)
             .field("peer_addr", &self.peer_addr)
+            .field("tls", &self.is_tls())
             .field("created_at", &self.created_at)
             .finish()
     }
@@ -17,7 +17,7 @@
 //! Error types for the  Telnet server
 
 use crate::types::ConnectionId;
-use termionix_service::{TelnetCodecError, TerminalError};
+use termionix_service::{RateLimitKind, TelnetCodecError, TerminalError};
 use thiserror::Error;
 
 /// Result type for operations
@@ -50,6 +50,26 @@ pub enum TelnetError {
     #[error("Operation timed out")]
     Timeout,
 
+    /// Peer stopped answering keepalive probes
+    #[error("Keepalive timeout")]
+    KeepaliveTimeout,
+
+    /// Peer went over a rate limit set to disconnect
+    #[error("Rate limit exceeded: {0}")]
+    RateLimited(RateLimitKind),
+
+    /// Peer went over a Telnet codec limit, such as an oversized subnegotiation
+    #[error("Codec limit exceeded: {0}")]
+    LimitExceeded(TelnetCodecError),
+
+    /// Client has not enabled the GMCP package with `Core.Supports`
+    #[error("GMCP package not supported by client: {0}")]
+    GmcpNotSupported(String),
+
+    /// GMCP data could not be serialized or deserialized
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
     /// Server is not running
     #[error("Server not running")]
     ServerNotRunning,
@@ -62,6 +82,10 @@ pub enum TelnetError {
     #[error("Maximum connections ({0}) reached")]
     MaxConnectionsReached(usize),
 
+    /// TLS configuration error
+    #[error("TLS error: {0}")]
+    Tls(String),
+
     /// Resource cleanup failed
     #[error("Resource cleanup failed: {0}")]
     CleanupFailed(String),
@@ -87,13 +111,19 @@ impl TelnetError {
     pub fn is_connection_error(&self) -> bool {
         matches!(
             self,
-            TelnetError::ConnectionNotFound(_) | TelnetError::ConnectionClosed | TelnetError::Io(_)
+            TelnetError::ConnectionNotFound(_)
+                | TelnetError::ConnectionClosed
+                | TelnetError::KeepaliveTimeout
+                | TelnetError::Io(_)
         )
     }
 
     /// Check if the error is a sidechannel error
     pub fn is_protocol_error(&self) -> bool {
-        matches!(self, TelnetError::Protocol(_) | TelnetError::Terminal(_))
+        matches!(
+            self,
+            TelnetError::Protocol(_) | TelnetError::Terminal(_) | TelnetError::LimitExceeded(_)
+        )
     }
 }
 
@@ -113,6 +143,7 @@ mod tests {
     fn test_error_is_connection_error() {
         assert!(TelnetError::ConnectionNotFound(ConnectionId::new(1)).is_connection_error());
         assert!(TelnetError::ConnectionClosed.is_connection_error());
+        assert!(TelnetError::KeepaliveTimeout.is_connection_error());
         assert!(!TelnetError::Timeout.is_connection_error());
     }
 
@@ -0,0 +1,284 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! GMCP package registry
+//!
+//! Routes incoming GMCP messages to typed handlers and tracks the packages
+//! each client enabled with `Core.Supports.Set`, `Core.Supports.Add` and
+//! `Core.Supports.Remove`, so the server only sends packages a client asked
+//! for.
+
+use crate::{ConnectionId, Result, TelnetConnection, TelnetError};
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use termionix_service::TerminalCommand;
+use termionix_service::gmcp::{CoreHello, GmcpMessage};
+use tracing::{debug, warn};
+
+/// Decodes a message and runs a typed handler, `None` if the data did not decode
+type Handler =
+    Arc<dyn Fn(TelnetConnection, &GmcpMessage) -> Option<BoxFuture<'static, ()>> + Send + Sync>;
+
+/// GMCP state of one connection
+#[derive(Default)]
+struct GmcpSession {
+    hello: Option<CoreHello>,
+    supports: BTreeMap<String, u32>,
+}
+
+#[derive(Default)]
+struct GmcpInner {
+    handlers: RwLock<HashMap<String, Handler>>,
+    sessions: DashMap<ConnectionId, GmcpSession>,
+}
+
+/// Registry of GMCP package handlers and the packages each client supports
+///
+/// Cloning is cheap; clones share the registry. Feed every GMCP
+/// subnegotiation to [`handle`](Self::handle), send through
+/// [`send_gmcp`](Self::send_gmcp) or [`send_json`](Self::send_json), and
+/// forget connections in [`remove_connection`](Self::remove_connection).
+/// Package names are matched without regard to case.
+///
+/// # Example
+/// ```no_run
+/// use async_trait::async_trait;
+/// use termionix_server::{
+///     ConnectionId, GmcpService, ServerHandler, TelnetArgument, TelnetConnection,
+/// };
+///
+/// #[derive(serde::Deserialize)]
+/// struct Login {
+///     name: String,
+/// }
+///
+/// struct Game {
+///     gmcp: GmcpService,
+/// }
+///
+/// #[async_trait]
+/// impl ServerHandler for Game {
+///     async fn on_subnegotiation(
+///         &self,
+///         _id: ConnectionId,
+///         conn: &TelnetConnection,
+///         subneg: TelnetArgument,
+///     ) {
+///         if let TelnetArgument::GMCP(message) = subneg {
+///             self.gmcp.handle(conn, &message).await;
+///         }
+///     }
+///
+///     async fn on_disconnect(&self, id: ConnectionId, _conn: &TelnetConnection) {
+///         self.gmcp.remove_connection(id);
+///     }
+/// }
+///
+/// # async fn example(gmcp: GmcpService) {
+/// gmcp.on("Char.Login", |conn: TelnetConnection, login: Login| async move {
+///     let _ = conn.send_line(&format!("Welcome, {}", login.name)).await;
+/// });
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct GmcpService {
+    inner: Arc<GmcpInner>,
+}
+
+impl GmcpService {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a handler for a package
+    ///
+    /// The message data is deserialized into `T`; messages whose data does
+    /// not decode are logged and dropped. Registering a package again
+    /// replaces its handler. `Core.Hello` and `Core.Supports.*` are tracked
+    /// whether or not a handler is registered for them.
+    pub fn on<T, F, Fut>(&self, package: &str, handler: F)
+    where
+        T: DeserializeOwned + Send + 'static,
+        F: Fn(TelnetConnection, T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let handler: Handler = Arc::new(move |conn, message| match message.json::<T>() {
+            Ok(value) => Some(Box::pin(handler(conn, value))),
+            Err(e) => {
+                warn!(package = %message.package(), error = %e, "Dropping undecodable GMCP message");
+                None
+            }
+        });
+        self.inner
+            .handlers
+            .write()
+            .unwrap()
+            .insert(package.to_ascii_lowercase(), handler);
+    }
+
+    /// Process a GMCP message from a client
+    ///
+    /// Updates the client's `Core.Hello` and supported packages, then runs
+    /// the handler registered for the package. Returns whether a handler ran.
+    pub async fn handle(&self, conn: &TelnetConnection, message: &GmcpMessage) -> bool {
+        let package = message.package().to_ascii_lowercase();
+        debug!(connection_id = %conn.id(), package = %message.package(), "GMCP message");
+        match package.as_str() {
+            "core.hello" => match message.json::<CoreHello>() {
+                Ok(hello) => self.session(conn.id()).hello = Some(hello),
+                Err(e) => warn!(error = %e, "Invalid Core.Hello"),
+            },
+            "core.supports.set" | "core.supports.add" | "core.supports.remove" => {
+                match message.json::<Vec<String>>() {
+                    Ok(entries) => {
+                        let mut session = self.session(conn.id());
+                        if package == "core.supports.set" {
+                            session.supports.clear();
+                        }
+                        for entry in entries {
+                            let (module, version) = parse_support(&entry);
+                            session
+                                .supports
+                                .retain(|name, _| !name.eq_ignore_ascii_case(module));
+                            if package != "core.supports.remove" {
+                                session.supports.insert(module.to_string(), version);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        warn!(package = %message.package(), error = %e, "Invalid Core.Supports")
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let handler = self.inner.handlers.read().unwrap().get(&package).cloned();
+        match handler.and_then(|handler| handler(conn.clone(), message)) {
+            Some(future) => {
+                future.await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The `Core.Hello` a client sent
+    pub fn hello(&self, id: ConnectionId) -> Option<CoreHello> {
+        self.inner
+            .sessions
+            .get(&id)
+            .and_then(|session| session.hello.clone())
+    }
+
+    /// Packages a client enabled, with their versions
+    pub fn supports(&self, id: ConnectionId) -> BTreeMap<String, u32> {
+        self.inner
+            .sessions
+            .get(&id)
+            .map(|session| session.supports.clone())
+            .unwrap_or_default()
+    }
+
+    /// Whether a client enabled the module a package belongs to
+    ///
+    /// `Core` packages are always supported. A module covers its
+    /// submodules, so `Char` enables `Char.Vitals` and `Char.Skills.List`.
+    pub fn is_supported(&self, id: ConnectionId, package: &str) -> bool {
+        let module = package.split('.').next().unwrap_or_default();
+        if module.eq_ignore_ascii_case("Core") {
+            return true;
+        }
+        let Some(session) = self.inner.sessions.get(&id) else {
+            return false;
+        };
+        session.supports.keys().any(|module| {
+            package
+                .get(..module.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(module))
+                && matches!(package.as_bytes().get(module.len()), None | Some(b'.'))
+        })
+    }
+
+    /// Send a message if the client supports its package
+    ///
+    /// Fails with [`TelnetError::GmcpNotSupported`] otherwise. The client
+    /// must also have agreed to GMCP, e.g. through
+    /// `OptionPolicy::default().request_local(TelnetOption::GMCP)`.
+    pub async fn send_gmcp(&self, conn: &TelnetConnection, message: GmcpMessage) -> Result<()> {
+        if !self.is_supported(conn.id(), message.package()) {
+            return Err(TelnetError::GmcpNotSupported(message.package().to_string()));
+        }
+        conn.send_command(&TerminalCommand::GMCP(message)).await
+    }
+
+    /// Send `value` as the JSON data of a package, see [`send_gmcp`](Self::send_gmcp)
+    pub async fn send_json<T: Serialize + ?Sized>(
+        &self,
+        conn: &TelnetConnection,
+        package: &str,
+        value: &T,
+    ) -> Result<()> {
+        self.send_gmcp(conn, GmcpMessage::from_json(package, value)?)
+            .await
+    }
+
+    /// Forget a connection's hello and supported packages
+    pub fn remove_connection(&self, id: ConnectionId) {
+        self.inner.sessions.remove(&id);
+    }
+
+    fn session(
+        &self,
+        id: ConnectionId,
+    ) -> dashmap::mapref::one::RefMut<'_, ConnectionId, GmcpSession> {
+        self.inner.sessions.entry(id).or_default()
+    }
+}
+
+impl std::fmt::Debug for GmcpService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut packages: Vec<String> = self
+            .inner
+            .handlers
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        packages.sort();
+        f.debug_struct("GmcpService")
+            .field("packages", &packages)
+            .field("connections", &self.inner.sessions.len())
+            .finish()
+    }
+}
+
+/// Splits a `Core.Supports` entry such as `"Char.Skills 1"` into module and version
+///
+/// A missing or invalid version counts as version 1.
+fn parse_support(entry: &str) -> (&str, u32) {
+    match entry.trim().split_once(' ') {
+        Some((module, version)) => (module, version.trim().parse().unwrap_or(1)),
+        None => (entry.trim(), 1),
+    }
+}
@@ -18,7 +18,7 @@
 
 use crate::{ConnectionId, TelnetConnection, TelnetError};
 use async_trait::async_trait;
-use termionix_service::{TelnetArgument, TelnetOption, TerminalEvent};
+use termionix_service::{RateLimitKind, TelnetArgument, TelnetOption, TerminalEvent};
 
 /// Server event handler trait
 ///
@@ -57,13 +57,16 @@ pub trait ServerHandler: Send + Sync + 'static {
     /// Called when a terminal event is received
     ///
     /// This is the main event processing method. It will be called for every
-    /// event received from the client.
+    /// event received from the client, including option changes and
+    /// subnegotiations after their dedicated hooks below.
     async fn on_event(&self, _id: ConnectionId, _conn: &TelnetConnection, _event: TerminalEvent) {}
 
     /// Called when a Telnet option state changes
     ///
     /// This is called when an option negotiation completes successfully,
-    /// whether the option is being enabled or disabled.
+    /// whether the option is being enabled or disabled. The change is also
+    /// delivered to [`on_event`](Self::on_event) as
+    /// [`TerminalEvent::OptionChanged`].
     ///
     /// # Arguments
     ///
@@ -86,7 +89,9 @@ pub trait ServerHandler: Send + Sync + 'static {
     ///
     /// This is called when a complete subnegotiation sequence is received
     /// from the client. Subnegotiations provide additional parameters for
-    /// negotiated options (e.g., window size for NAWS, terminal type for TTYPE).
+    /// negotiated options (e.g., window size for NAWS, terminal type for TTYPE,
+    /// GMCP and MSDP messages). It is also delivered to
+    /// [`on_event`](Self::on_event) as [`TerminalEvent::Subnegotiation`].
     ///
     /// # Arguments
     ///
@@ -101,6 +106,26 @@ pub trait ServerHandler: Send + Sync + 'static {
     ) {
     }
 
+    /// Called when a client goes over a rate limit set to warn
+    ///
+    /// This is called once each time input from the client exceeds a limit
+    /// whose action is [`RateLimitAction::Warn`](termionix_service::RateLimitAction::Warn),
+    /// before that input is delivered. It is also delivered to
+    /// [`on_event`](Self::on_event) as [`TerminalEvent::RateLimited`].
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The connection ID
+    /// * `conn` - The connection handle
+    /// * `kind` - The limit that was exceeded
+    async fn on_rate_limited(
+        &self,
+        _id: ConnectionId,
+        _conn: &TelnetConnection,
+        _kind: RateLimitKind,
+    ) {
+    }
+
     /// Called when an error occurs on a connection
     ///
     /// This is called when an error occurs during event processing. The
@@ -149,6 +174,10 @@ pub enum EventHandler {
     },
 }
 
+/// Callback of a [`CallbackHandler`] hook receiving what happened on a connection
+type ConnectionCallback<T> =
+    Box<dyn Fn(ConnectionId, &TelnetConnection, T) + Send + Sync + 'static>;
+
 /// Callback-based handler implementation
 ///
 /// This provides a flexible way to implement handlers using closures instead
@@ -189,6 +218,10 @@ pub struct CallbackHandler {
                 + 'static,
         >,
     >,
+    /// Called on subnegotiation
+    pub on_subnegotiation: Option<ConnectionCallback<TelnetArgument>>,
+    /// Called on rate limit warning
+    pub on_rate_limited: Option<ConnectionCallback<RateLimitKind>>,
     /// Called on error
     pub on_error:
         Option<Box<dyn Fn(ConnectionId, &TelnetConnection, TelnetError) + Send + Sync + 'static>>,
@@ -207,6 +240,8 @@ impl Default for CallbackHandler {
             on_connect: None,
             on_event: EventHandler::None,
             on_option_changed: None,
+            on_subnegotiation: None,
+            on_rate_limited: None,
             on_error: None,
             on_timeout: None,
             on_idle_timeout: None,
@@ -261,6 +296,28 @@ impl ServerHandler for CallbackHandler {
         }
     }
 
+    async fn on_subnegotiation(
+        &self,
+        id: ConnectionId,
+        conn: &TelnetConnection,
+        subneg: TelnetArgument,
+    ) {
+        if let Some(ref f) = self.on_subnegotiation {
+            f(id, conn, subneg);
+        }
+    }
+
+    async fn on_rate_limited(
+        &self,
+        id: ConnectionId,
+        conn: &TelnetConnection,
+        kind: RateLimitKind,
+    ) {
+        if let Some(ref f) = self.on_rate_limited {
+            f(id, conn, kind);
+        }
+    }
+
     async fn on_error(&self, id: ConnectionId, conn: &TelnetConnection, error: TelnetError) {
         if let Some(ref f) = self.on_error {
             f(id, conn, error);
@@ -71,33 +71,50 @@
 mod config;
 mod connection;
 mod error;
+mod gmcp_service;
 mod handler;
 mod manager;
 mod metrics;
+mod msdp_service;
+mod mssp_config;
 mod server;
+mod tls;
+mod transport;
 mod types;
 mod worker;
 
 pub use config::ServerConfig;
 pub use connection::TelnetConnection;
 pub use error::{Result, TelnetError};
+pub use gmcp_service::GmcpService;
 pub use handler::{CallbackHandler, EventHandler, ServerHandler};
 pub use manager::{BroadcastResult, ConnectionManager};
 pub use metrics::{MetricsSnapshot, ServerMetrics};
+pub use msdp_service::MsdpService;
+pub use mssp_config::MsspConfig;
 pub use server::TelnetServer;
+pub use tls::{TlsConfig, TlsInfo, TlsListener};
+pub use transport::{
+    Accepted, Listener, MemoryConnector, MemoryListener, PeerAddress, Transport, memory_listener,
+};
 pub use types::{ConnectionId, ConnectionInfo, ConnectionState, ServerSnapshot};
 pub use worker::{ConnectionWorker, ControlMessage, WorkerConfig};
 
+// Re-export rustls for building custom TLS configurations
+pub use tokio_rustls::rustls;
+
 // Re-export types from termionix_service
 pub use termionix_service::{
     AnsiApplicationProgramCommand, AnsiCodec, AnsiCodecError, AnsiCodecResult, AnsiConfig,
     AnsiControlCode, AnsiControlSequenceIntroducer, AnsiDeviceControlString,
     AnsiOperatingSystemCommand, AnsiParser, AnsiPrivacyMessage, AnsiSelectGraphicRendition,
-    AnsiSequence, AnsiStartOfString, Blink, Color, ColorMode, CompressionAlgorithm, CursorPosition,
-    Font, Ideogram, Intensity, SGRParameter, Script, Segment, SegmentedString, Span, SpannedString,
-    StyledString, SubnegotiationErrorKind, TelnetArgument, TelnetCodec, TelnetCodecError,
-    TelnetCodecResult, TelnetCommand, TelnetEvent, TelnetFrame, TelnetOption, TelnetSide,
-    TerminalBuffer, TerminalCodec, TerminalCommand, TerminalError, TerminalEvent, TerminalResult,
-    TerminalSize, Underline, gmcp, linemode, msdp, mssp, naocrd, naohts, naws, status,
-    strip_ansi_codes, terminal_word_unwrap, terminal_word_wrap,
+    AnsiSequence, AnsiStartOfString, Blink, Charset, CodecLimits, Color, ColorMode,
+    CompressionAlgorithm, ConnectionConfig, CursorPosition, Font, Ideogram, Intensity,
+    OptionPolicy, OverflowPolicy, RateLimit, RateLimitAction, RateLimitKind, SGRParameter, Script,
+    Segment, SegmentedString, Span, SpannedString, StyledString, SubnegotiationErrorKind,
+    TelnetArgument, TelnetCodec, TelnetCodecError, TelnetCodecResult, TelnetCommand, TelnetEvent,
+    TelnetFrame, TelnetOption, TelnetResponder, TelnetSide, TerminalBuffer, TerminalCapabilities,
+    TerminalCodec, TerminalCommand, TerminalError, TerminalEvent, TerminalResult, TerminalSize,
+    Underline, atcp, gmcp, linemode, msdp, mssp, mtts, mxp, naocrd, naohts, naws, new_environ,
+    status, strip_ansi_codes, terminal_word_unwrap, terminal_word_wrap, zmp,
 };
@@ -24,13 +24,13 @@
 //! - Connection lifecycle tracking
 
 use crate::{
-    ConnectionId, ConnectionInfo, ConnectionState, ControlMessage, Result, ServerHandler,
-    ServerMetrics, TelnetConnection, TelnetError, WorkerConfig,
+    ConnectionId, ConnectionInfo, ConnectionState, ControlMessage, MsspConfig, Result,
+    ServerHandler, ServerMetrics, TelnetConnection, TelnetError, WorkerConfig,
 };
 use dashmap::DashMap;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
 use termionix_service::TerminalCommand;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
@@ -123,6 +123,8 @@ pub struct ConnectionManager {
     metrics: Arc<ServerMetrics>,
     /// Worker configuration
     worker_config: WorkerConfig,
+    /// MSSP variables and the time they count uptime from (None if MSSP is off)
+    mssp: Option<(Arc<MsspConfig>, SystemTime)>,
 }
 
 impl ConnectionManager {
@@ -133,9 +135,20 @@ impl ConnectionManager {
             next_id: Arc::new(AtomicU64::new(1)),
             metrics,
             worker_config,
+            mssp: None,
         }
     }
 
+    /// Answer `DO MSSP` on new connections with `mssp`
+    ///
+    /// `PLAYERS` is the [`connection_count`](Self::connection_count) and
+    /// `UPTIME` the time this method was called. An empty configuration
+    /// turns MSSP off.
+    pub fn with_mssp(mut self, mssp: MsspConfig) -> Self {
+        self.mssp = (!mssp.is_empty()).then(|| (Arc::new(mssp), SystemTime::now()));
+        self
+    }
+
     /// Get the next connection ID
     fn next_connection_id(&self) -> ConnectionId {
         let id = self.next_id.fetch_add(1, Ordering::SeqCst);
@@ -162,13 +175,18 @@ impl ConnectionManager {
 
         // Create worker
         let worker_connection = connection.clone();
-        let (worker, control_tx) = crate::ConnectionWorker::new(
+        let (mut worker, control_tx) = crate::ConnectionWorker::new(
             id,
             worker_connection,
             handler,
             self.worker_config.clone(),
             worker_state,
         );
+        if let Some((mssp, started)) = &self.mssp {
+            let (mssp, started) = (mssp.clone(), *started);
+            let connections = self.connections.clone();
+            worker = worker.with_mssp(Arc::new(move || mssp.status(connections.len(), started)));
+        }
 
         // Spawn worker task
         let connections = self.connections.clone();
@@ -0,0 +1,365 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! MSDP variable registry
+//!
+//! Implements the command layer of the Mud Server Data Protocol on top of
+//! [`TerminalCommand::MSDP`]. Games register variables once, clients `LIST`,
+//! `SEND`, `REPORT`, `UNREPORT` and `RESET` them, and [`MsdpService::update`]
+//! pushes new values to every connection reporting the variable.
+
+use crate::{ConnectionId, Result, TelnetConnection};
+use dashmap::DashMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, RwLock};
+use termionix_service::TerminalCommand;
+use termionix_service::msdp::{MudServerData, MudServerDataArray, MudServerDataValue};
+use tracing::{debug, warn};
+
+/// Commands a client may send
+const COMMANDS: [&str; 5] = ["LIST", "REPORT", "RESET", "SEND", "UNREPORT"];
+
+/// Lists a client may `LIST`
+const LISTS: [&str; 5] = [
+    "COMMANDS",
+    "LISTS",
+    "REPORTABLE_VARIABLES",
+    "REPORTED_VARIABLES",
+    "SENDABLE_VARIABLES",
+];
+
+/// Computes a variable's value for a connection
+type Getter = Arc<dyn Fn(&TelnetConnection) -> Option<MudServerDataValue> + Send + Sync>;
+
+/// Where a registered variable's value comes from
+#[derive(Clone)]
+enum Variable {
+    /// Computed per connection
+    Getter(Getter),
+    /// Shared by all connections, replaced by [`MsdpService::update`]
+    Value(MudServerDataValue),
+}
+
+/// MSDP state of one connection
+struct MsdpSession {
+    connection: TelnetConnection,
+    reported: BTreeSet<String>,
+}
+
+#[derive(Default)]
+struct MsdpInner {
+    variables: RwLock<BTreeMap<String, Variable>>,
+    sessions: DashMap<ConnectionId, MsdpSession>,
+}
+
+/// Registry of MSDP variables and the connections reporting them
+///
+/// Cloning is cheap; clones share the registry. Feed every MSDP
+/// subnegotiation to [`handle`](Self::handle) and forget connections in
+/// [`remove_connection`](Self::remove_connection). The client must have
+/// agreed to MSDP, e.g. through
+/// `OptionPolicy::default().request_local(TelnetOption::MSDP)`.
+///
+/// # Example
+/// ```no_run
+/// use async_trait::async_trait;
+/// use termionix_server::{
+///     ConnectionId, MsdpService, ServerHandler, TelnetArgument, TelnetConnection,
+///     msdp::MudServerDataValue,
+/// };
+///
+/// struct Game {
+///     msdp: MsdpService,
+/// }
+///
+/// #[async_trait]
+/// impl ServerHandler for Game {
+///     async fn on_subnegotiation(
+///         &self,
+///         _id: ConnectionId,
+///         conn: &TelnetConnection,
+///         subneg: TelnetArgument,
+///     ) {
+///         if let TelnetArgument::MudServerData(request) = subneg {
+///             let _ = self.msdp.handle(conn, &request).await;
+///         }
+///     }
+///
+///     async fn on_disconnect(&self, id: ConnectionId, _conn: &TelnetConnection) {
+///         self.msdp.remove_connection(id);
+///     }
+/// }
+///
+/// # async fn example(msdp: MsdpService) {
+/// msdp.register_value("HEALTH", MudServerDataValue::string("100"));
+/// // Every client reporting HEALTH receives the new value
+/// msdp.update("HEALTH", MudServerDataValue::string("90")).await;
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct MsdpService {
+    inner: Arc<MsdpInner>,
+}
+
+impl MsdpService {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a variable computed for each connection
+    ///
+    /// A getter returning `None` leaves the variable out of `SEND` and
+    /// `REPORT` answers.
+    pub fn register<F>(&self, name: &str, getter: F)
+    where
+        F: Fn(&TelnetConnection) -> Option<MudServerDataValue> + Send + Sync + 'static,
+    {
+        self.inner
+            .variables
+            .write()
+            .unwrap()
+            .insert(name.to_string(), Variable::Getter(Arc::new(getter)));
+    }
+
+    /// Register a variable shared by all connections
+    pub fn register_value(&self, name: &str, value: MudServerDataValue) {
+        self.inner
+            .variables
+            .write()
+            .unwrap()
+            .insert(name.to_string(), Variable::Value(value));
+    }
+
+    /// Remove a variable, ending all reports of it
+    pub fn unregister(&self, name: &str) -> bool {
+        for mut session in self.inner.sessions.iter_mut() {
+            session.reported.remove(name);
+        }
+        self.inner.variables.write().unwrap().remove(name).is_some()
+    }
+
+    /// Names of all registered variables
+    pub fn variables(&self) -> Vec<String> {
+        self.inner
+            .variables
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// Variables a connection is reporting
+    pub fn reported(&self, id: ConnectionId) -> Vec<String> {
+        self.inner
+            .sessions
+            .get(&id)
+            .map(|session| session.reported.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Set a variable and push it to every connection reporting it
+    ///
+    /// Shared variables keep the new value for later `SEND` requests; for
+    /// per-connection variables the value is only pushed. Unregistered names
+    /// are ignored. Returns the number of connections the value was sent to.
+    pub async fn update(&self, name: &str, value: MudServerDataValue) -> usize {
+        {
+            let mut variables = self.inner.variables.write().unwrap();
+            match variables.get_mut(name) {
+                Some(Variable::Value(current)) => *current = value.clone(),
+                Some(Variable::Getter(_)) => {}
+                None => return 0,
+            }
+        }
+
+        let connections: Vec<TelnetConnection> = self
+            .inner
+            .sessions
+            .iter()
+            .filter(|session| session.reported.contains(name))
+            .map(|session| session.connection.clone())
+            .collect();
+
+        let mut data = MudServerData::new();
+        data.set(name, value);
+        let command = TerminalCommand::MSDP(data);
+        let mut sent = 0;
+        for connection in connections {
+            match connection.send_command(&command).await {
+                Ok(()) => sent += 1,
+                Err(e) => {
+                    warn!(connection_id = %connection.id(), error = %e, "Failed to report MSDP variable")
+                }
+            }
+        }
+        sent
+    }
+
+    /// Push a value to one connection if it is reporting the variable
+    ///
+    /// Returns whether the value was sent.
+    pub async fn update_for(
+        &self,
+        conn: &TelnetConnection,
+        name: &str,
+        value: MudServerDataValue,
+    ) -> Result<bool> {
+        let reporting = self
+            .inner
+            .sessions
+            .get(&conn.id())
+            .is_some_and(|session| session.reported.contains(name));
+        if !reporting {
+            return Ok(false);
+        }
+        let mut data = MudServerData::new();
+        data.set(name, value);
+        conn.send_command(&TerminalCommand::MSDP(data)).await?;
+        Ok(true)
+    }
+
+    /// Answer an MSDP request from a client
+    ///
+    /// Handles `LIST`, `SEND`, `REPORT`, `UNREPORT` and `RESET`; other
+    /// variables are ignored. Each command takes a single name or an array
+    /// of names.
+    pub async fn handle(&self, conn: &TelnetConnection, request: &MudServerData) -> Result<()> {
+        let mut response = MudServerData::new();
+        for (command, value) in request.iter() {
+            let names = names(value);
+            debug!(connection_id = %conn.id(), command = %command, ?names, "MSDP request");
+            match command.as_str() {
+                "LIST" => {
+                    for list in &names {
+                        if let Some(items) = self.list(conn.id(), list) {
+                            response.set(list, array(items));
+                        }
+                    }
+                }
+                "SEND" => self.collect(conn, &names, &mut response),
+                "REPORT" => {
+                    let reportable: Vec<String> = {
+                        let variables = self.inner.variables.read().unwrap();
+                        names
+                            .into_iter()
+                            .filter(|name| variables.contains_key(name))
+                            .collect()
+                    };
+                    self.inner
+                        .sessions
+                        .entry(conn.id())
+                        .or_insert_with(|| MsdpSession {
+                            connection: conn.clone(),
+                            reported: BTreeSet::new(),
+                        })
+                        .reported
+                        .extend(reportable.iter().cloned());
+                    // Reporting starts with the current value
+                    self.collect(conn, &reportable, &mut response);
+                }
+                "UNREPORT" => {
+                    if let Some(mut session) = self.inner.sessions.get_mut(&conn.id()) {
+                        for name in &names {
+                            session.reported.remove(name);
+                        }
+                    }
+                }
+                "RESET" => {
+                    let resets_reports = names
+                        .iter()
+                        .any(|list| list == "REPORTABLE_VARIABLES" || list == "REPORTED_VARIABLES");
+                    if resets_reports {
+                        if let Some(mut session) = self.inner.sessions.get_mut(&conn.id()) {
+                            session.reported.clear();
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if !response.is_empty() {
+            conn.send_command(&TerminalCommand::MSDP(response)).await?;
+        }
+        Ok(())
+    }
+
+    /// Forget a connection's reports
+    pub fn remove_connection(&self, id: ConnectionId) {
+        self.inner.sessions.remove(&id);
+    }
+
+    /// Contents of a `LIST`, or `None` for unknown lists
+    fn list(&self, id: ConnectionId, list: &str) -> Option<Vec<String>> {
+        match list {
+            "COMMANDS" => Some(COMMANDS.iter().map(|s| s.to_string()).collect()),
+            "LISTS" => Some(LISTS.iter().map(|s| s.to_string()).collect()),
+            "REPORTABLE_VARIABLES" | "SENDABLE_VARIABLES" => Some(self.variables()),
+            "REPORTED_VARIABLES" => Some(self.reported(id)),
+            _ => None,
+        }
+    }
+
+    /// Adds the current values of `names` to `response`
+    fn collect(&self, conn: &TelnetConnection, names: &[String], response: &mut MudServerData) {
+        let variables = self.inner.variables.read().unwrap();
+        for name in names {
+            let value = match variables.get(name) {
+                Some(Variable::Value(value)) => Some(value.clone()),
+                Some(Variable::Getter(getter)) => getter(conn),
+                None => None,
+            };
+            if let Some(value) = value {
+                response.set(name, value);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for MsdpService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MsdpService")
+            .field("variables", &self.variables())
+            .field("connections", &self.inner.sessions.len())
+            .finish()
+    }
+}
+
+/// Names given to a command, either a single string or an array of strings
+fn names(value: &MudServerDataValue) -> Vec<String> {
+    match value {
+        MudServerDataValue::String(name) => vec![name.clone()],
+        MudServerDataValue::Array(array) => array
+            .iter()
+            .filter_map(|item| match item {
+                MudServerDataValue::String(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect(),
+        MudServerDataValue::Table(_) => Vec::new(),
+    }
+}
+
+/// Builds an array value from strings
+fn array(items: Vec<String>) -> MudServerDataValue {
+    let mut array = MudServerDataArray::new();
+    for item in items {
+        array.push(MudServerDataValue::string(&item));
+    }
+    MudServerDataValue::array(array)
+}
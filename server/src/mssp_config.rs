@@ -0,0 +1,144 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! MSSP (Mud Server Status Protocol) responder configuration
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use termionix_service::mssp::MudServerStatus;
+
+/// Computes the value of a dynamic MSSP variable
+type DynamicField = Arc<dyn Fn() -> String + Send + Sync>;
+
+/// Variables the server reports to MSSP crawlers
+///
+/// When any variable is configured the server offers `WILL MSSP` and answers
+/// a crawler's `DO MSSP` with the static variables, `PLAYERS` (the number of
+/// connections) and `UPTIME` (the Unix time the server started), followed by
+/// the dynamic variables computed when the crawler asks. Later variables
+/// replace earlier ones of the same name. See `doc/mssp.md` for the standard
+/// variable names.
+///
+/// # Example
+///
+/// ```
+/// use termionix_server::{MsspConfig, ServerConfig};
+///
+/// let config = ServerConfig::default().with_mssp(
+///     MsspConfig::new()
+///         .with_field("NAME", "Example MUD")
+///         .with_field("CODEBASE", "Termionix")
+///         .with_field("WEBSITE", "https://example.com")
+///         .with_dynamic("AREAS", || "42".to_string()),
+/// );
+/// ```
+#[derive(Clone, Default)]
+pub struct MsspConfig {
+    fields: MudServerStatus,
+    dynamic: Vec<(String, DynamicField)>,
+}
+
+impl MsspConfig {
+    /// Create a configuration without variables, which disables MSSP
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a static variable; adding a name again reports several values
+    pub fn with_field(mut self, name: &str, value: &str) -> Self {
+        self.fields.add(name, value);
+        self
+    }
+
+    /// Add a variable computed each time a crawler asks
+    pub fn with_dynamic<F>(mut self, name: &str, value: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.dynamic.push((name.to_string(), Arc::new(value)));
+        self
+    }
+
+    /// Whether no variables are configured
+    pub fn is_empty(&self) -> bool {
+        self.fields.iter().next().is_none() && self.dynamic.is_empty()
+    }
+
+    /// The status reported to a crawler
+    ///
+    /// `players` is the number of connections and `started` the time the
+    /// server started.
+    pub fn status(&self, players: usize, started: SystemTime) -> MudServerStatus {
+        let mut status = self.fields.clone();
+        status.set("PLAYERS", vec![players.to_string()]);
+        let uptime = started
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        status.set("UPTIME", vec![uptime.to_string()]);
+        for (name, value) in &self.dynamic {
+            status.set(name, vec![value()]);
+        }
+        status
+    }
+}
+
+impl std::fmt::Debug for MsspConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let dynamic: Vec<&str> = self.dynamic.iter().map(|(name, _)| name.as_str()).collect();
+        f.debug_struct("MsspConfig")
+            .field("fields", &self.fields)
+            .field("dynamic", &dynamic)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_status_fields() {
+        let config = MsspConfig::new()
+            .with_field("NAME", "Example MUD")
+            .with_field("PORT", "4000")
+            .with_field("PORT", "4001")
+            .with_dynamic("AREAS", || "42".to_string());
+        assert!(!config.is_empty());
+
+        let started = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let status = config.status(3, started);
+        assert_eq!(status.get("NAME"), Some(&["Example MUD".to_string()][..]));
+        assert_eq!(
+            status.get("PORT"),
+            Some(&["4000".to_string(), "4001".to_string()][..])
+        );
+        assert_eq!(status.get("PLAYERS"), Some(&["3".to_string()][..]));
+        assert_eq!(status.get("UPTIME"), Some(&["1700000000".to_string()][..]));
+        assert_eq!(status.get("AREAS"), Some(&["42".to_string()][..]));
+    }
+
+    #[test]
+    fn test_empty_config() {
+        assert!(MsspConfig::new().is_empty());
+        assert!(
+            !MsspConfig::new()
+                .with_dynamic("AREAS", String::new)
+                .is_empty()
+        );
+    }
+}
@@ -17,12 +17,12 @@
 //! Telnet server implementation
 //!
 //! The TelnetServer is the main entry point for the  implementation.
-//! It manages the TCP listener, accepts connections, and coordinates
+//! It manages one or more listeners, accepts connections, and coordinates
 //! with the ConnectionManager.
 
 use crate::{
-    ConnectionId, ConnectionManager, Result, ServerConfig, ServerHandler, ServerMetrics,
-    ServerSnapshot, TelnetConnection, TelnetError, WorkerConfig,
+    ConnectionId, ConnectionManager, Listener, PeerAddress, Result, ServerConfig, ServerHandler,
+    ServerMetrics, ServerSnapshot, TelnetConnection, TelnetError, TlsListener, WorkerConfig,
 };
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -33,9 +33,25 @@ use tokio::sync::Notify;
 use tokio::task::JoinHandle;
 use tracing::instrument;
 
+/// Starts the accept loop of a listener added before the server was started
+type PendingListener =
+    Box<dyn FnOnce(AcceptContext, Arc<dyn ServerHandler>) -> JoinHandle<()> + Send>;
+
+/// State shared by every accept loop
+#[derive(Clone)]
+struct AcceptContext {
+    manager: Arc<ConnectionManager>,
+    metrics: Arc<ServerMetrics>,
+    config: ServerConfig,
+    running: Arc<AtomicBool>,
+    shutdown_notify: Arc<Notify>,
+}
+
 /// Telnet server ( implementation)
 ///
 /// This is the main server that accepts connections and manages their lifecycle.
+/// Connections can come from any number of [`Listener`]s, such as TCP, Unix
+/// domain sockets or in-memory pipes, all served by the same handler.
 ///
 /// # Example
 ///
@@ -71,8 +87,12 @@ pub struct TelnetServer {
     manager: Arc<ConnectionManager>,
     /// Server metrics
     metrics: Arc<ServerMetrics>,
-    /// TCP listener (wrapped in Arc<Mutex> for sharing with accept loop)
-    listener: Arc<tokio::sync::Mutex<TcpListener>>,
+    /// Listeners waiting for `start()`
+    pending_listeners: std::sync::Mutex<Vec<PendingListener>>,
+    /// Local addresses of every listener
+    local_addresses: std::sync::Mutex<Vec<PeerAddress>>,
+    /// Handler given to `start()`, used for listeners added while running
+    handler: std::sync::Mutex<Option<Arc<dyn ServerHandler>>>,
     /// Actual bind address
     bind_address: SocketAddr,
     /// Server start time
@@ -81,19 +101,40 @@ pub struct TelnetServer {
     running: Arc<AtomicBool>,
     /// Shutdown notification
     shutdown_notify: Arc<Notify>,
-    /// Accept loop task handle
-    accept_handle: Arc<tokio::sync::Mutex<Option<JoinHandle<()>>>>,
+    /// Accept loop task handles
+    accept_handles: Arc<tokio::sync::Mutex<Vec<JoinHandle<()>>>>,
 }
 
 impl TelnetServer {
     /// Create a new server with the given configuration
     ///
-    /// This binds to the configured address but does not start accepting connections.
-    /// Call `start()` to begin accepting connections.
+    /// This binds a TCP listener to the configured address but does not start
+    /// accepting connections. Call `start()` to begin accepting connections.
+    /// If [`ServerConfig::tls`] is set, the listener only accepts TLS connections.
     pub async fn new(config: ServerConfig) -> Result<Self> {
         // Bind to the configured address
         let listener = TcpListener::bind(config.bind_address).await?;
-        let actual_addr = listener.local_addr()?;
+        tracing::info!("Telnet server bound to {}", listener.local_addr()?);
+        match config.tls.clone() {
+            Some(tls) => {
+                let listener = TlsListener::new(listener, &tls)?;
+                Ok(Self::with_listener(config, listener))
+            }
+            None => Ok(Self::with_listener(config, listener)),
+        }
+    }
+
+    /// Create a new server accepting connections from `listener` instead of binding TCP
+    ///
+    /// [`ServerConfig::bind_address`] is not used unless `listener` is a TCP
+    /// listener, in which case [`bind_address`](Self::bind_address) reports its
+    /// local address.
+    pub fn with_listener<L: Listener>(config: ServerConfig, listener: L) -> Self {
+        let local_address = listener.local_addr().ok();
+        let bind_address = local_address
+            .as_ref()
+            .and_then(PeerAddress::socket_addr)
+            .unwrap_or(config.bind_address);
 
         // Create metrics
         let metrics = Arc::new(ServerMetrics::new());
@@ -107,130 +148,204 @@ impl TelnetServer {
         };
 
         // Create connection manager
-        let manager = Arc::new(ConnectionManager::new(metrics.clone(), worker_config));
+        let manager = Arc::new(
+            ConnectionManager::new(metrics.clone(), worker_config).with_mssp(config.mssp.clone()),
+        );
 
-        tracing::info!("Telnet server bound to {}", actual_addr);
-
-        Ok(Self {
+        Self {
             config,
             manager,
             metrics,
-            listener: Arc::new(tokio::sync::Mutex::new(listener)),
-            bind_address: actual_addr,
+            pending_listeners: std::sync::Mutex::new(vec![Self::pending(listener)]),
+            local_addresses: std::sync::Mutex::new(local_address.into_iter().collect()),
+            handler: std::sync::Mutex::new(None),
+            bind_address,
             started_at: Instant::now(),
             running: Arc::new(AtomicBool::new(false)),
             shutdown_notify: Arc::new(Notify::new()),
-            accept_handle: Arc::new(tokio::sync::Mutex::new(None)),
-        })
+            accept_handles: Arc::new(tokio::sync::Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Accept connections from another listener as well
+    ///
+    /// Connections from every listener share the server's handler, limits and
+    /// configuration. If the server is already running the listener starts
+    /// accepting immediately, otherwise when `start()` is called.
+    pub async fn add_listener<L: Listener>(&self, listener: L) -> Result<()> {
+        let local_address = listener.local_addr()?;
+        tracing::info!("Telnet server listening on {}", local_address);
+        self.local_addresses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(local_address);
+
+        let handler = self
+            .handler
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        match handler {
+            Some(handler) if self.is_running() => {
+                let handle = tokio::spawn(Self::accept_loop(listener, self.context(), handler));
+                self.accept_handles.lock().await.push(handle);
+            }
+            _ => self
+                .pending_listeners
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(Self::pending(listener)),
+        }
+        Ok(())
     }
 
     /// Start the server with the given handler
     ///
-    /// This begins accepting connections and spawns a task to handle the accept loop.
-    /// The server will continue running until `shutdown()` is called.
+    /// This begins accepting connections and spawns a task per listener to handle
+    /// the accept loop. The server will continue running until `shutdown()` is called.
     pub async fn start(&self, handler: Arc<dyn ServerHandler>) -> Result<()> {
         if self.running.swap(true, Ordering::SeqCst) {
             return Err(TelnetError::Other("Server already running".to_string()));
         }
 
-        tracing::info!("Starting Telnet server on {}", self.config.bind_address);
+        tracing::info!("Starting Telnet server on {}", self.bind_address);
+
+        *self.handler.lock().unwrap_or_else(|e| e.into_inner()) = Some(handler.clone());
 
-        // Spawn accept loop
-        let handle = self.spawn_accept_loop(handler).await;
-        *self.accept_handle.lock().await = Some(handle);
+        // Spawn accept loops
+        let pending = std::mem::take(
+            &mut *self
+                .pending_listeners
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()),
+        );
+        let mut handles = self.accept_handles.lock().await;
+        for spawn in pending {
+            handles.push(spawn(self.context(), handler.clone()));
+        }
 
         Ok(())
     }
 
-    /// Spawn the accept loop task
-    #[instrument(skip(self, handler))]
-    async fn spawn_accept_loop(&self, handler: Arc<dyn ServerHandler>) -> JoinHandle<()> {
-        let listener = self.listener.clone();
-        let manager = self.manager.clone();
-        let metrics = self.metrics.clone();
-        let config = self.config.clone();
-        let running = self.running.clone();
-        let shutdown_notify = self.shutdown_notify.clone();
-
-        tokio::spawn(async move {
-            loop {
-                // Check if we should shutdown
-                if !running.load(Ordering::SeqCst) {
-                    break;
-                }
+    /// Local addresses of every listener
+    pub fn local_addresses(&self) -> Vec<PeerAddress> {
+        self.local_addresses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
 
-                // Accept connection with timeout
-                let accept_result = tokio::select! {
-                    result = async {
-                        listener.lock().await.accept().await
-                    } => result,
-                    _ = shutdown_notify.notified() => break,
-                };
-
-                match accept_result {
-                    Ok((socket, peer_addr)) => {
-                        tracing::debug!("Accepted connection from {}", peer_addr);
-
-                        // Set TCP_NODELAY to reduce latency
-                        if let Err(e) = socket.set_nodelay(true) {
-                            tracing::warn!(
-                                "Failed to set TCP_NODELAY on connection from {}: {}",
-                                peer_addr,
-                                e
-                            );
-                        }
+    /// State handed to accept loops
+    fn context(&self) -> AcceptContext {
+        AcceptContext {
+            manager: self.manager.clone(),
+            metrics: self.metrics.clone(),
+            config: self.config.clone(),
+            running: self.running.clone(),
+            shutdown_notify: self.shutdown_notify.clone(),
+        }
+    }
 
-                        // Check connection limit
-                        if manager.connection_count() >= config.max_connections {
-                            tracing::warn!(
-                                "Connection limit reached ({}), rejecting connection from {}",
-                                config.max_connections,
-                                peer_addr
-                            );
-                            metrics.connection_error();
-                            drop(socket);
-                            continue;
-                        }
+    /// Defer the accept loop of `listener` until the server starts
+    fn pending<L: Listener>(listener: L) -> PendingListener {
+        Box::new(move |context, handler| {
+            tokio::spawn(Self::accept_loop(listener, context, handler))
+        })
+    }
 
-                        // Create connection ID (will be assigned by manager)
-                        let temp_id = ConnectionId::new(0);
-
-                        // Wrap socket in TelnetConnection
-                        match TelnetConnection::wrap(socket, temp_id) {
-                            Ok(connection) => {
-                                // Add to manager
-                                match manager.add_connection(connection, handler.clone()) {
-                                    Ok(id) => {
-                                        tracing::info!(
-                                            "Connection {} established from {}",
-                                            id,
-                                            peer_addr
-                                        );
-                                    }
-                                    Err(e) => {
-                                        tracing::error!("Failed to add connection: {}", e);
-                                        metrics.connection_error();
-                                    }
-                                }
+    /// Accept loop of a single listener
+    #[instrument(skip_all)]
+    async fn accept_loop<L: Listener>(
+        mut listener: L,
+        context: AcceptContext,
+        handler: Arc<dyn ServerHandler>,
+    ) {
+        let AcceptContext {
+            manager,
+            metrics,
+            config,
+            running,
+            shutdown_notify,
+        } = context;
+
+        loop {
+            // Check if we should shutdown
+            if !running.load(Ordering::SeqCst) {
+                break;
+            }
+
+            // Accept connection with timeout
+            let accept_result = tokio::select! {
+                result = listener.accept() => result,
+                _ = shutdown_notify.notified() => break,
+            };
+
+            match accept_result {
+                Ok((stream, peer_addr)) => {
+                    tracing::debug!("Accepted connection from {}", peer_addr);
+
+                    // Check connection limit
+                    if manager.connection_count() >= config.max_connections {
+                        tracing::warn!(
+                            "Connection limit reached ({}), rejecting connection from {}",
+                            config.max_connections,
+                            peer_addr
+                        );
+                        metrics.connection_error();
+                        drop(stream);
+                        continue;
+                    }
+
+                    // Create connection ID (will be assigned by manager)
+                    let temp_id = ConnectionId::new(0);
+
+                    // Wrap stream in TelnetConnection
+                    match TelnetConnection::wrap_stream(
+                        stream,
+                        peer_addr.clone(),
+                        temp_id,
+                        config.connection_policy(),
+                        &config.connection_config(),
+                    ) {
+                        Ok(connection) => {
+                            if let Some(tls) = &config.start_tls
+                                && let Err(e) = connection.enable_start_tls(tls)
+                            {
+                                tracing::warn!("Failed to offer START_TLS: {}", e);
                             }
-                            Err(e) => {
-                                tracing::error!("Failed to wrap connection: {}", e);
-                                metrics.connection_error();
+
+                            // Add to manager
+                            match manager.add_connection(connection, handler.clone()) {
+                                Ok(id) => {
+                                    tracing::info!(
+                                        "Connection {} established from {}",
+                                        id,
+                                        peer_addr
+                                    );
+                                }
+                                Err(e) => {
+                                    tracing::error!("Failed to add connection: {}", e);
+                                    metrics.connection_error();
+                                }
                             }
                         }
+                        Err(e) => {
+                            tracing::error!("Failed to wrap connection: {}", e);
+                            metrics.connection_error();
+                        }
                     }
-                    Err(e) => {
-                        tracing::error!("Failed to accept connection: {}", e);
-                        metrics.connection_error();
+                }
+                Err(e) => {
+                    tracing::error!("Failed to accept connection: {}", e);
+                    metrics.connection_error();
 
-                        // Back off on errors to avoid tight loop
-                        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                    }
+                    // Back off on errors to avoid tight loop
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
                 }
             }
+        }
 
-            tracing::info!("Accept loop terminated");
-        })
+        tracing::info!("Accept loop terminated");
     }
 
     /// Shutdown the server gracefully
@@ -247,8 +362,8 @@ impl TelnetServer {
         // Notify accept loop to stop
         self.shutdown_notify.notify_waiters();
 
-        // Wait for accept loop to finish
-        if let Some(handle) = self.accept_handle.lock().await.take() {
+        // Wait for accept loops to finish
+        for handle in self.accept_handles.lock().await.drain(..) {
             let _ = tokio::time::timeout(std::time::Duration::from_secs(5), handle).await;
         }
 
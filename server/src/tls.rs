@@ -0,0 +1,362 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! TLS support for the server
+//!
+//! [`TlsConfig`] holds the certificate and key a server presents to clients.
+//! [`TlsListener`] wraps any [`Listener`] and performs the TLS handshake on
+//! each accepted stream, so encrypted connections run through the same codec
+//! stack as plain ones. Plain connections can also be upgraded in place with
+//! START_TLS when [`ServerConfig::start_tls`](crate::ServerConfig::start_tls)
+//! is set. Details of a connection's TLS session are available from
+//! [`TelnetConnection::tls_info`](crate::TelnetConnection::tls_info).
+
+use crate::{Accepted, Listener, PeerAddress, Result, TelnetError, Transport};
+use async_trait::async_trait;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Semaphore, mpsc};
+use tokio::task::JoinHandle;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{self, CipherSuite, ProtocolVersion, RootCertStore, ServerConnection};
+use tokio_rustls::server::TlsStream;
+
+/// TLS settings for a listener
+///
+/// # Example
+///
+/// ```no_run
+/// use termionix_server::{ServerConfig, TlsConfig};
+///
+/// # fn example() -> termionix_server::Result<()> {
+/// let tls = TlsConfig::from_pem_files("server.crt", "server.key")?;
+/// let config = ServerConfig::new("0.0.0.0:4443".parse().unwrap()).with_tls(tls);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// rustls configuration used for handshakes
+    pub server_config: Arc<rustls::ServerConfig>,
+
+    /// Time a client has to complete the handshake before it is dropped
+    pub handshake_timeout: Duration,
+
+    /// Handshakes a [`TlsListener`] runs at once; further streams wait in the inner listener
+    pub max_pending_handshakes: usize,
+}
+
+impl TlsConfig {
+    /// Create a configuration from an existing rustls server configuration
+    pub fn from_rustls(server_config: Arc<rustls::ServerConfig>) -> Self {
+        Self {
+            server_config,
+            handshake_timeout: Duration::from_secs(10),
+            max_pending_handshakes: 64,
+        }
+    }
+
+    /// Create a configuration from a PEM certificate chain and private key
+    ///
+    /// `cert_chain` holds the server certificate followed by any intermediates.
+    pub fn from_pem(cert_chain: &[u8], private_key: &[u8]) -> Result<Self> {
+        let (certs, key) = parse_identity(cert_chain, private_key)?;
+        let server_config = builder()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|e| TelnetError::Tls(e.to_string()))?;
+        Ok(Self::from_rustls(Arc::new(server_config)))
+    }
+
+    /// Create a configuration from PEM certificate chain and private key files
+    pub fn from_pem_files(cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> Result<Self> {
+        let cert_chain = std::fs::read(cert_path)?;
+        let private_key = std::fs::read(key_path)?;
+        Self::from_pem(&cert_chain, &private_key)
+    }
+
+    /// Create a configuration that asks clients for a certificate
+    ///
+    /// Client certificates must be issued by one of the PEM certificates in
+    /// `client_roots`. Clients without a certificate are still accepted; the
+    /// certificates of those that present one are reported by
+    /// [`TlsInfo::peer_certificates`].
+    pub fn from_pem_with_client_auth(
+        cert_chain: &[u8],
+        private_key: &[u8],
+        client_roots: &[u8],
+    ) -> Result<Self> {
+        let (certs, key) = parse_identity(cert_chain, private_key)?;
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_slice_iter(client_roots) {
+            let cert = cert.map_err(|e| TelnetError::Tls(e.to_string()))?;
+            roots
+                .add(cert)
+                .map_err(|e| TelnetError::Tls(e.to_string()))?;
+        }
+        let verifier =
+            WebPkiClientVerifier::builder_with_provider(Arc::new(roots), Arc::new(provider()))
+                .allow_unauthenticated()
+                .build()
+                .map_err(|e| TelnetError::Tls(e.to_string()))?;
+        let server_config = builder()?
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(certs, key)
+            .map_err(|e| TelnetError::Tls(e.to_string()))?;
+        Ok(Self::from_rustls(Arc::new(server_config)))
+    }
+
+    /// Set the time a client has to complete the handshake
+    pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// Set the number of handshakes a [`TlsListener`] runs at once
+    pub fn with_max_pending_handshakes(mut self, max: usize) -> Self {
+        self.max_pending_handshakes = max.max(1);
+        self
+    }
+}
+
+/// Crypto provider used by configurations created from PEM data
+fn provider() -> rustls::crypto::CryptoProvider {
+    rustls::crypto::ring::default_provider()
+}
+
+/// Server configuration builder with the safe default protocol versions
+fn builder() -> Result<rustls::ConfigBuilder<rustls::ServerConfig, rustls::WantsVerifier>> {
+    rustls::ServerConfig::builder_with_provider(Arc::new(provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| TelnetError::Tls(e.to_string()))
+}
+
+/// Parse a PEM certificate chain and private key
+fn parse_identity(
+    cert_chain: &[u8],
+    private_key: &[u8],
+) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+    let certs = CertificateDer::pem_slice_iter(cert_chain)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| TelnetError::Tls(e.to_string()))?;
+    if certs.is_empty() {
+        return Err(TelnetError::Tls(
+            "no certificate found in PEM data".to_string(),
+        ));
+    }
+    let key =
+        PrivateKeyDer::from_pem_slice(private_key).map_err(|e| TelnetError::Tls(e.to_string()))?;
+    Ok((certs, key))
+}
+
+/// Details of an established TLS session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsInfo {
+    /// Negotiated protocol version
+    pub protocol_version: Option<ProtocolVersion>,
+    /// Negotiated cipher suite
+    pub cipher_suite: Option<CipherSuite>,
+    /// Host name the client asked for with SNI
+    pub server_name: Option<String>,
+    /// Certificate chain presented by the client, empty if it sent none
+    pub peer_certificates: Vec<CertificateDer<'static>>,
+}
+
+impl TlsInfo {
+    /// Details of the session established by `session`
+    pub(crate) fn from_session(session: &ServerConnection) -> Self {
+        Self {
+            protocol_version: session.protocol_version(),
+            cipher_suite: session.negotiated_cipher_suite().map(|suite| suite.suite()),
+            server_name: session.server_name().map(str::to_string),
+            peer_certificates: session
+                .peer_certificates()
+                .map(<[_]>::to_vec)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl<S: Transport> Transport for TlsStream<S> {
+    fn tls_info(&self) -> Option<TlsInfo> {
+        let (_, session) = self.get_ref();
+        Some(TlsInfo::from_session(session))
+    }
+}
+
+/// Accepted streams and errors forwarded by a [`TlsListener`]'s background task
+type Handshaked<S> = Accepted<TlsStream<S>>;
+
+/// Time the background task waits after the inner listener fails to accept
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Listener performing a TLS handshake on every stream accepted by another listener
+///
+/// Handshakes run concurrently in background tasks, so a slow client cannot
+/// hold up other connections. Clients that fail the handshake or don't finish
+/// it within [`TlsConfig::handshake_timeout`] are dropped without ever being
+/// returned by [`accept`](Listener::accept). At most
+/// [`TlsConfig::max_pending_handshakes`] handshakes run at once, and no more
+/// streams are accepted while that many completed ones wait to be returned.
+///
+/// # Example
+///
+/// ```no_run
+/// use termionix_server::{ServerConfig, TelnetServer, TlsConfig, TlsListener};
+/// use tokio::net::TcpListener;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let server = TelnetServer::new(ServerConfig::default()).await?;
+/// // Serve TLS on a separate port alongside plain Telnet
+/// let tls = TlsConfig::from_pem_files("server.crt", "server.key")?;
+/// let listener = TcpListener::bind("0.0.0.0:4443").await?;
+/// server.add_listener(TlsListener::new(listener, &tls)?).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TlsListener<L: Listener> {
+    inner: Option<L>,
+    acceptor: TlsAcceptor,
+    handshake_timeout: Duration,
+    max_pending_handshakes: usize,
+    local_addr: PeerAddress,
+    incoming: Option<mpsc::Receiver<Handshaked<L::Stream>>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl<L: Listener> TlsListener<L> {
+    /// Wrap `inner` so its connections are encrypted with `config`
+    pub fn new(inner: L, config: &TlsConfig) -> io::Result<Self> {
+        Ok(Self {
+            local_addr: inner.local_addr()?,
+            inner: Some(inner),
+            acceptor: TlsAcceptor::from(config.server_config.clone()),
+            handshake_timeout: config.handshake_timeout,
+            max_pending_handshakes: config.max_pending_handshakes.max(1),
+            incoming: None,
+            task: None,
+        })
+    }
+
+    /// Start accepting from the inner listener in the background
+    fn spawn(&mut self, mut inner: L) -> mpsc::Receiver<Handshaked<L::Stream>> {
+        let (sender, receiver) = mpsc::channel(self.max_pending_handshakes);
+        let acceptor = self.acceptor.clone();
+        let handshake_timeout = self.handshake_timeout;
+        // A permit is held from accept until the stream has been queued, so
+        // handshakes in flight and streams waiting for accept() are bounded together
+        let permits = Arc::new(Semaphore::new(self.max_pending_handshakes));
+        self.task = Some(tokio::spawn(async move {
+            loop {
+                let Ok(permit) = permits.clone().acquire_owned().await else {
+                    break;
+                };
+                let (stream, peer_addr) = match inner.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        if sender.send(Err(e)).await.is_err() {
+                            break;
+                        }
+                        tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                        continue;
+                    }
+                };
+                let acceptor = acceptor.clone();
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    match tokio::time::timeout(handshake_timeout, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = sender.send(Ok((stream, peer_addr))).await;
+                        }
+                        Ok(Err(e)) => {
+                            tracing::debug!("TLS handshake with {} failed: {}", peer_addr, e);
+                        }
+                        Err(_) => {
+                            tracing::debug!("TLS handshake with {} timed out", peer_addr);
+                        }
+                    }
+                });
+            }
+        }));
+        receiver
+    }
+}
+
+#[async_trait]
+impl<L: Listener> Listener for TlsListener<L> {
+    type Stream = TlsStream<L::Stream>;
+
+    async fn accept(&mut self) -> Accepted<Self::Stream> {
+        if let Some(inner) = self.inner.take() {
+            self.incoming = Some(self.spawn(inner));
+        }
+        match self.incoming.as_mut() {
+            Some(incoming) => match incoming.recv().await {
+                Some(result) => result,
+                None => Err(io::Error::other("TLS listener closed")),
+            },
+            None => Err(io::Error::other("TLS listener closed")),
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<PeerAddress> {
+        Ok(self.local_addr.clone())
+    }
+}
+
+impl<L: Listener> Drop for TlsListener<L> {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_pem_rejects_missing_certificate() {
+        let result = TlsConfig::from_pem(b"", b"");
+        assert!(matches!(result, Err(TelnetError::Tls(_))));
+    }
+
+    #[test]
+    fn test_from_pem_rejects_invalid_key() {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let result = TlsConfig::from_pem(cert.cert.pem().as_bytes(), b"not a key");
+        assert!(matches!(result, Err(TelnetError::Tls(_))));
+    }
+
+    #[test]
+    fn test_from_pem_self_signed() {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let config = TlsConfig::from_pem(
+            cert.cert.pem().as_bytes(),
+            cert.key_pair.serialize_pem().as_bytes(),
+        )
+        .unwrap()
+        .with_handshake_timeout(Duration::from_secs(3));
+        assert_eq!(config.handshake_timeout, Duration::from_secs(3));
+    }
+}
@@ -0,0 +1,238 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Transports and listeners the server can accept connections from
+//!
+//! A [`TelnetConnection`](crate::TelnetConnection) runs over any byte stream
+//! implementing `AsyncRead + AsyncWrite`. A [`Listener`] produces such streams
+//! for [`TelnetServer`](crate::TelnetServer), which can drive several listeners
+//! at once. Implementations are provided for TCP, Unix domain sockets and an
+//! in-memory listener for tests, and [`TlsListener`](crate::TlsListener) adds
+//! TLS on top of any of them.
+
+use crate::TlsInfo;
+use async_trait::async_trait;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// Address of the remote end of a connection
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PeerAddress {
+    /// TCP peer
+    Tcp(SocketAddr),
+    /// Unix domain socket peer, with its path if the socket is bound to one
+    Unix(Option<std::path::PathBuf>),
+    /// Any other transport, described by a label
+    Other(String),
+}
+
+impl PeerAddress {
+    /// Returns the socket address of a TCP peer
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        match self {
+            PeerAddress::Tcp(addr) => Some(*addr),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PeerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeerAddress::Tcp(addr) => write!(f, "{}", addr),
+            PeerAddress::Unix(Some(path)) => write!(f, "unix:{}", path.display()),
+            PeerAddress::Unix(None) => write!(f, "unix:(unnamed)"),
+            PeerAddress::Other(label) => write!(f, "{}", label),
+        }
+    }
+}
+
+impl From<SocketAddr> for PeerAddress {
+    fn from(addr: SocketAddr) -> Self {
+        PeerAddress::Tcp(addr)
+    }
+}
+
+/// A byte stream a connection can run over
+///
+/// Implemented for TCP and Unix domain socket streams, in-memory pipes and TLS
+/// streams. Other stream types only need an empty `impl` to be served.
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+    /// Details of the TLS session, if the stream is encrypted
+    fn tls_info(&self) -> Option<TlsInfo> {
+        None
+    }
+}
+
+impl Transport for tokio::net::TcpStream {}
+
+#[cfg(unix)]
+impl Transport for tokio::net::UnixStream {}
+
+impl Transport for DuplexStream {}
+
+/// Stream accepted by a [`Listener`] with the address of its peer
+pub type Accepted<S> = io::Result<(S, PeerAddress)>;
+
+/// A source of incoming connections
+///
+/// # Example
+///
+/// ```no_run
+/// use termionix_server::{ServerConfig, TelnetServer};
+/// use tokio::net::UnixListener;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let server = TelnetServer::new(ServerConfig::default()).await?;
+/// // Serve a Unix domain socket alongside the TCP port
+/// server.add_listener(UnixListener::bind("/tmp/telnet.sock")?).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[async_trait]
+pub trait Listener: Send + 'static {
+    /// Stream type of accepted connections
+    type Stream: Transport;
+
+    /// Wait for the next connection
+    async fn accept(&mut self) -> Accepted<Self::Stream>;
+
+    /// Address this listener accepts connections on
+    fn local_addr(&self) -> io::Result<PeerAddress>;
+}
+
+#[async_trait]
+impl Listener for TcpListener {
+    type Stream = tokio::net::TcpStream;
+
+    async fn accept(&mut self) -> Accepted<Self::Stream> {
+        let (socket, peer_addr) = TcpListener::accept(self).await?;
+        // Set TCP_NODELAY to reduce latency
+        if let Err(e) = socket.set_nodelay(true) {
+            tracing::warn!(
+                "Failed to set TCP_NODELAY on connection from {}: {}",
+                peer_addr,
+                e
+            );
+        }
+        Ok((socket, PeerAddress::Tcp(peer_addr)))
+    }
+
+    fn local_addr(&self) -> io::Result<PeerAddress> {
+        TcpListener::local_addr(self).map(PeerAddress::Tcp)
+    }
+}
+
+#[cfg(unix)]
+#[async_trait]
+impl Listener for tokio::net::UnixListener {
+    type Stream = tokio::net::UnixStream;
+
+    async fn accept(&mut self) -> Accepted<Self::Stream> {
+        let (socket, peer_addr) = tokio::net::UnixListener::accept(self).await?;
+        let path = peer_addr.as_pathname().map(std::path::Path::to_path_buf);
+        Ok((socket, PeerAddress::Unix(path)))
+    }
+
+    fn local_addr(&self) -> io::Result<PeerAddress> {
+        let addr = tokio::net::UnixListener::local_addr(self)?;
+        Ok(PeerAddress::Unix(
+            addr.as_pathname().map(std::path::Path::to_path_buf),
+        ))
+    }
+}
+
+/// In-memory listener accepting [`tokio::io::duplex`] pipes from a [`MemoryConnector`]
+///
+/// Created with [`memory_listener`]. Accepted peers are labelled `memory:<n>`.
+#[derive(Debug)]
+pub struct MemoryListener {
+    incoming: mpsc::UnboundedReceiver<DuplexStream>,
+    accepted: u64,
+}
+
+/// Client side of a [`MemoryListener`]
+#[derive(Debug, Clone)]
+pub struct MemoryConnector {
+    outgoing: mpsc::UnboundedSender<DuplexStream>,
+    buffer_size: usize,
+}
+
+/// Create an in-memory listener and the connector that opens connections to it
+///
+/// Each connection is a duplex pipe buffering up to `buffer_size` bytes per direction.
+///
+/// # Example
+///
+/// ```
+/// use termionix_server::{ServerConfig, TelnetServer, memory_listener};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let (listener, connector) = memory_listener(8192);
+/// let server = TelnetServer::with_listener(ServerConfig::default(), listener);
+/// let client = connector.connect()?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn memory_listener(buffer_size: usize) -> (MemoryListener, MemoryConnector) {
+    let (outgoing, incoming) = mpsc::unbounded_channel();
+    (
+        MemoryListener {
+            incoming,
+            accepted: 0,
+        },
+        MemoryConnector {
+            outgoing,
+            buffer_size,
+        },
+    )
+}
+
+impl MemoryConnector {
+    /// Open a connection to the listener, returning the client end
+    pub fn connect(&self) -> io::Result<DuplexStream> {
+        let (client, server) = tokio::io::duplex(self.buffer_size);
+        self.outgoing
+            .send(server)
+            .map_err(|_| io::Error::new(io::ErrorKind::ConnectionRefused, "listener closed"))?;
+        Ok(client)
+    }
+}
+
+#[async_trait]
+impl Listener for MemoryListener {
+    type Stream = DuplexStream;
+
+    async fn accept(&mut self) -> Accepted<Self::Stream> {
+        match self.incoming.recv().await {
+            Some(stream) => {
+                self.accepted += 1;
+                let peer = PeerAddress::Other(format!("memory:{}", self.accepted));
+                Ok((stream, peer))
+            }
+            // No connector is left; never resolve so the accept loop idles until shutdown
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<PeerAddress> {
+        Ok(PeerAddress::Other("memory".to_string()))
+    }
+}
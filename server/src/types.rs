@@ -16,6 +16,7 @@
 
 //! Core types for the  Telnet server
 
+use crate::PeerAddress;
 use std::fmt;
 use std::net::SocketAddr;
 use std::time::{Duration, Instant};
@@ -107,7 +108,7 @@ pub struct ConnectionInfo {
     /// Current state
     pub state: ConnectionState,
     /// Peer address
-    pub peer_addr: SocketAddr,
+    pub peer_addr: PeerAddress,
     /// When the connection was created
     pub created_at: Instant,
     /// Last activity timestamp
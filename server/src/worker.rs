@@ -28,12 +28,16 @@ use crate::{ConnectionId, ConnectionState, Result, ServerHandler, TelnetConnecti
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU8, Ordering};
 use std::time::{Duration, Instant};
-use termionix_service::TerminalCommand;
+use termionix_service::mssp::MudServerStatus;
+use termionix_service::{TelnetOption, TelnetSide, TerminalCommand, TerminalEvent};
 use tokio::select;
 use tokio::sync::mpsc;
 use tokio::time::timeout;
 use tracing::instrument;
 
+/// Builds the MSSP status sent when a client enables MSSP
+pub(crate) type MsspSource = Arc<dyn Fn() -> MudServerStatus + Send + Sync>;
+
 /// Control messages for the worker
 #[derive(Debug)]
 pub enum ControlMessage {
@@ -85,6 +89,8 @@ pub struct ConnectionWorker {
     control_rx: mpsc::Receiver<ControlMessage>,
     /// Last activity timestamp
     last_activity: Instant,
+    /// MSSP status source (None if MSSP is off)
+    mssp: Option<MsspSource>,
 }
 
 impl ConnectionWorker {
@@ -106,11 +112,18 @@ impl ConnectionWorker {
             state,
             control_rx,
             last_activity: Instant::now(),
+            mssp: None,
         };
 
         (worker, control_tx)
     }
 
+    /// Answer MSSP with the status from `mssp` once the client enables it
+    pub(crate) fn with_mssp(mut self, mssp: MsspSource) -> Self {
+        self.mssp = Some(mssp);
+        self
+    }
+
     /// Get the current state
     pub fn state(&self) -> ConnectionState {
         ConnectionState::from_u8(self.state.load(Ordering::Acquire))
@@ -126,6 +139,26 @@ impl ConnectionWorker {
         self.last_activity = Instant::now();
     }
 
+    /// Send the MSSP status to a crawler that enabled MSSP
+    async fn send_mssp(&self) {
+        let Some(mssp) = &self.mssp else {
+            return;
+        };
+        let command = TerminalCommand::MSSP(mssp());
+        match timeout(
+            self.config.write_timeout,
+            self.connection.send_command(&command),
+        )
+        .await
+        {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                tracing::warn!(connection_id = %self.id, error = %e, "Failed to send MSSP")
+            }
+            Err(_) => tracing::warn!(connection_id = %self.id, "Timed out sending MSSP"),
+        }
+    }
+
     /// Check if connection is idle
     fn is_idle(&self) -> bool {
         self.last_activity.elapsed() > self.config.idle_timeout
@@ -220,8 +253,38 @@ impl ConnectionWorker {
                                 self.set_state(ConnectionState::Active);
                             }
 
-                            // Check if this event type can generate sidechannel responses
-                            // Only telnet option negotiation events (TelnetOptionStatus) generate responses
+                            // Dedicated hooks run before the event reaches on_event
+                            match &event {
+                                TerminalEvent::OptionChanged { option, side, enabled } => {
+                                    if *option == TelnetOption::MSSP && *side == TelnetSide::Local && *enabled {
+                                        self.send_mssp().await;
+                                    }
+                                    self.handler
+                                        .on_option_changed(
+                                            self.id,
+                                            &self.connection,
+                                            *option,
+                                            *enabled,
+                                            *side == TelnetSide::Local,
+                                        )
+                                        .await;
+                                }
+                                TerminalEvent::Subnegotiation(argument) => {
+                                    self.handler
+                                        .on_subnegotiation(
+                                            self.id,
+                                            &self.connection,
+                                            argument.clone(),
+                                        )
+                                        .await;
+                                }
+                                TerminalEvent::RateLimited(kind) => {
+                                    self.handler
+                                        .on_rate_limited(self.id, &self.connection, *kind)
+                                        .await;
+                                }
+                                _ => {}
+                            }
                             self.handler.on_event(self.id, &self.connection, event).await;
 
                             // Note: With SplitConnection, flushing is handled automatically
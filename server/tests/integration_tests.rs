@@ -951,7 +951,7 @@ async fn test_connection_metadata() {
 
     let info = manager.get_connection_info(ids[0]).unwrap();
     assert_eq!(info.id, ids[0]);
-    assert!(info.peer_addr.port() > 0);
+    assert!(info.peer_addr.socket_addr().unwrap().port() > 0);
     assert_eq!(info.state, ConnectionState::Active);
 
     server.shutdown().await.unwrap();
@@ -1184,3 +1184,65 @@ async fn test_special_characters() {
     tokio::time::sleep(Duration::from_millis(100)).await;
     server.shutdown().await.unwrap();
 }
+
+#[tokio::test]
+async fn test_server_accepts_memory_connections() {
+    let (listener, connector) = termionix_server::memory_listener(8192);
+    let server = TelnetServer::with_listener(ServerConfig::default(), listener);
+
+    let handler = Arc::new(TestHandler::new());
+    server.start(handler.clone()).await.unwrap();
+
+    let client = connector.connect().unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(server.connection_count(), 1);
+    assert_eq!(handler.connect_count(), 1);
+    let info = &server.manager().get_all_connection_infos()[0];
+    assert_eq!(
+        info.peer_addr,
+        termionix_server::PeerAddress::Other("memory:1".to_string())
+    );
+
+    drop(client);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(handler.disconnect_count(), 1);
+    server.shutdown().await.unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_server_runs_multiple_listeners() {
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap());
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+
+    // Added before start
+    let (listener, connector) = termionix_server::memory_listener(8192);
+    server.add_listener(listener).await.unwrap();
+
+    let handler = Arc::new(TestHandler::new());
+    server.start(handler.clone()).await.unwrap();
+
+    // Added while running
+    let path = std::env::temp_dir().join(format!("termionix-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    server
+        .add_listener(tokio::net::UnixListener::bind(&path).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(server.local_addresses().len(), 3);
+
+    let tcp = TcpStream::connect(addr).await.unwrap();
+    let memory = connector.connect().unwrap();
+    let unix = tokio::net::UnixStream::connect(&path).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    assert_eq!(server.connection_count(), 3);
+    assert_eq!(handler.connect_count(), 3);
+
+    drop((tcp, memory, unix));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    server.shutdown().await.unwrap();
+    let _ = std::fs::remove_file(&path);
+}
@@ -19,7 +19,13 @@
 use async_trait::async_trait;
 use std::sync::Arc;
 use std::time::Duration;
-use termionix_server::{ServerConfig, ServerHandler, TelnetServer};
+use termionix_compress::{CompressionAlgorithm, CompressionReader, CompressionWriter};
+use termionix_server::{
+    CallbackHandler, ColorMode, ConnectionId, GmcpService, MsdpService, MsspConfig, OptionPolicy,
+    ServerConfig, ServerHandler, TelnetArgument, TelnetConnection, TelnetError, TelnetOption,
+    TelnetServer, TelnetSide, TerminalCapabilities, TerminalEvent, msdp::MudServerDataValue,
+    mssp::MudServerStatus, zmp::ZmpMessage, zmp::ZmpRegistry,
+};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 
@@ -28,6 +34,18 @@ struct ProtocolTestHandler;
 #[async_trait]
 impl ServerHandler for ProtocolTestHandler {}
 
+/// Replies to every completed line so compressed output can be observed
+struct MccpTestHandler;
+
+#[async_trait]
+impl ServerHandler for MccpTestHandler {
+    async fn on_event(&self, _id: ConnectionId, conn: &TelnetConnection, event: TerminalEvent) {
+        if let TerminalEvent::LineCompleted { line, .. } = event {
+            let _ = conn.send(format!("You see: {}\r\n", line), true).await;
+        }
+    }
+}
+
 // Telnet sidechannel constants
 const IAC: u8 = 255; // Interpret As Command
 const WILL: u8 = 251;
@@ -43,6 +61,18 @@ const SUPPRESS_GO_AHEAD: u8 = 3;
 const TERMINAL_TYPE: u8 = 24;
 const NAWS: u8 = 31; // Negotiate About Window Size
 const LINEMODE: u8 = 34;
+const COMPRESS2: u8 = 86;
+const COMPRESS3: u8 = 87;
+const GMCP: u8 = 201;
+const MSDP: u8 = 69;
+const MSSP: u8 = 70;
+const ZMP: u8 = 93;
+
+// MSDP markers
+const MSDP_VAR: u8 = 1;
+const MSDP_VAL: u8 = 2;
+const MSDP_ARRAY_OPEN: u8 = 5;
+const MSDP_ARRAY_CLOSE: u8 = 6;
 
 #[tokio::test]
 async fn test_telnet_iac_escape() {
@@ -408,3 +438,649 @@ async fn test_incomplete_telnet_command() {
     tokio::time::sleep(Duration::from_millis(100)).await;
     server.shutdown().await.unwrap();
 }
+
+#[tokio::test]
+async fn test_option_policy_requests_on_connect() {
+    let policy = OptionPolicy::default()
+        .request_local(TelnetOption::Echo)
+        .request_remote(TelnetOption::NAWS);
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap()).with_option_policy(policy);
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+
+    let handler = Arc::new(ProtocolTestHandler);
+    server.start(handler).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+
+    // The server requests its policy options without waiting for the client
+    let mut buf = [0u8; 6];
+    tokio::time::timeout(Duration::from_millis(500), client.read_exact(&mut buf))
+        .await
+        .expect("Timeout waiting for option requests")
+        .unwrap();
+    assert_eq!(buf, [IAC, WILL, ECHO, IAC, DO, NAWS]);
+
+    drop(client);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    server.shutdown().await.unwrap();
+}
+
+/// Start a compression enabled server and read its MCCP offers
+async fn start_mccp_server() -> (TelnetServer, TcpStream) {
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap())
+        .with_option_policy(OptionPolicy::new())
+        .with_compression(true);
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+    server.start(Arc::new(MccpTestHandler)).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    let mut buf = [0u8; 6];
+    tokio::time::timeout(Duration::from_millis(500), client.read_exact(&mut buf))
+        .await
+        .expect("Timeout waiting for MCCP offers")
+        .unwrap();
+    assert_eq!(buf, [IAC, WILL, COMPRESS2, IAC, WILL, COMPRESS3]);
+
+    (server, client)
+}
+
+#[tokio::test]
+async fn test_mccp2_compresses_output_after_marker() {
+    let (server, mut client) = start_mccp_server().await;
+
+    client.write_all(&[IAC, DO, COMPRESS2]).await.unwrap();
+
+    // The start marker itself is uncompressed
+    let mut marker = [0u8; 5];
+    tokio::time::timeout(Duration::from_millis(500), client.read_exact(&mut marker))
+        .await
+        .expect("Timeout waiting for MCCP2 start")
+        .unwrap();
+    assert_eq!(marker, [IAC, SB, COMPRESS2, IAC, SE]);
+
+    // Everything after it is a zlib stream
+    let (read_half, mut write_half) = client.into_split();
+    write_half.write_all(b"room\r\n").await.unwrap();
+    let mut reader = CompressionReader::new(read_half, CompressionAlgorithm::Zlib);
+    let expected = b"You see: room\r\n";
+    let mut reply = vec![0u8; expected.len()];
+    tokio::time::timeout(Duration::from_millis(500), reader.read_exact(&mut reply))
+        .await
+        .expect("Timeout waiting for compressed reply")
+        .unwrap();
+    assert_eq!(reply, expected);
+
+    // DONT ends the zlib stream and later output is plain again
+    write_half.write_all(&[IAC, DONT, COMPRESS2]).await.unwrap();
+    write_half.write_all(b"exit\r\n").await.unwrap();
+    let expected = b"You see: exit\r\n";
+    let mut reply = vec![0u8; expected.len()];
+    tokio::time::timeout(Duration::from_millis(500), reader.read_exact(&mut reply))
+        .await
+        .expect("Timeout waiting for reply after compression ended")
+        .unwrap();
+    assert_eq!(reply, expected);
+    assert_eq!(reader.algorithm(), CompressionAlgorithm::None);
+
+    drop(reader);
+    drop(write_half);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_mccp3_decompresses_input_after_marker() {
+    let (server, mut client) = start_mccp_server().await;
+
+    // Accept MCCP3 and start compressing in the same write as the marker
+    let mut compressed = CompressionWriter::new(Vec::new(), CompressionAlgorithm::Zlib);
+    compressed.write_all(b"north\r\n").await.unwrap();
+    compressed.flush().await.unwrap();
+    let mut packet = vec![IAC, DO, COMPRESS3, IAC, SB, COMPRESS3, IAC, SE];
+    packet.extend_from_slice(compressed.get_ref());
+    client.write_all(&packet).await.unwrap();
+
+    let expected = b"You see: north\r\n";
+    let mut reply = vec![0u8; expected.len()];
+    tokio::time::timeout(Duration::from_millis(500), client.read_exact(&mut reply))
+        .await
+        .expect("Timeout waiting for reply to compressed input")
+        .unwrap();
+    assert_eq!(reply, expected);
+
+    // End the compressed stream; the server must continue with plain input
+    let before = compressed.get_ref().len();
+    compressed
+        .switch_algorithm(CompressionAlgorithm::None)
+        .await
+        .unwrap();
+    let mut packet = compressed.get_ref()[before..].to_vec();
+    packet.extend_from_slice(b"south\r\n");
+    client.write_all(&packet).await.unwrap();
+
+    let expected = b"You see: south\r\n";
+    let mut reply = vec![0u8; expected.len()];
+    tokio::time::timeout(Duration::from_millis(500), client.read_exact(&mut reply))
+        .await
+        .expect("Timeout waiting for reply after compression ended")
+        .unwrap();
+    assert_eq!(reply, expected);
+
+    drop(client);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    server.shutdown().await.unwrap();
+}
+
+/// Records option changes and subnegotiations seen by the dedicated hooks
+#[derive(Default)]
+struct NegotiationHandler {
+    options: tokio::sync::Mutex<Vec<(TelnetOption, bool, bool)>>,
+    subnegotiations: tokio::sync::Mutex<Vec<TelnetArgument>>,
+    events: tokio::sync::Mutex<Vec<TerminalEvent>>,
+}
+
+#[async_trait]
+impl ServerHandler for NegotiationHandler {
+    async fn on_event(&self, _id: ConnectionId, _conn: &TelnetConnection, event: TerminalEvent) {
+        self.events.lock().await.push(event);
+    }
+
+    async fn on_option_changed(
+        &self,
+        _id: ConnectionId,
+        _conn: &TelnetConnection,
+        option: TelnetOption,
+        enabled: bool,
+        local: bool,
+    ) {
+        self.options.lock().await.push((option, enabled, local));
+    }
+
+    async fn on_subnegotiation(
+        &self,
+        _id: ConnectionId,
+        _conn: &TelnetConnection,
+        subneg: TelnetArgument,
+    ) {
+        self.subnegotiations.lock().await.push(subneg);
+    }
+}
+
+/// `IAC WILL SGA` followed by a GMCP `Core.Hello` message
+fn negotiation_bytes() -> Vec<u8> {
+    let mut bytes = vec![IAC, WILL, SUPPRESS_GO_AHEAD, IAC, SB, GMCP];
+    bytes.extend_from_slice(br#"Core.Hello {"client":"test"}"#);
+    bytes.extend_from_slice(&[IAC, SE]);
+    bytes
+}
+
+#[tokio::test]
+async fn test_option_changed_and_subnegotiation_dispatched() {
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap());
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+
+    let handler = Arc::new(NegotiationHandler::default());
+    server.start(handler.clone()).await.unwrap();
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(&negotiation_bytes()).await.unwrap();
+    client.flush().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    assert_eq!(
+        *handler.options.lock().await,
+        vec![(TelnetOption::SuppressGoAhead, true, false)]
+    );
+
+    let subnegotiations = handler.subnegotiations.lock().await;
+    assert_eq!(subnegotiations.len(), 1);
+    match &subnegotiations[0] {
+        TelnetArgument::GMCP(message) => {
+            assert_eq!(message.package(), "Core.Hello");
+            assert_eq!(message.data(), Some(r#"{"client":"test"}"#));
+        }
+        other => panic!("Expected GMCP message, got {:?}", other),
+    }
+
+    // on_event still sees both
+    let events = handler.events.lock().await;
+    assert!(events.iter().any(|event| matches!(
+        event,
+        TerminalEvent::OptionChanged {
+            option: TelnetOption::SuppressGoAhead,
+            side: TelnetSide::Remote,
+            enabled: true,
+        }
+    )));
+    assert!(
+        events
+            .iter()
+            .any(|event| matches!(event, TerminalEvent::Subnegotiation(_)))
+    );
+
+    drop(client);
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_callback_handler_negotiation_closures() {
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap());
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+
+    let options = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let packages = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let handler = Arc::new(CallbackHandler {
+        on_option_changed: Some(Box::new({
+            let options = options.clone();
+            move |_id, _conn, option, enabled, local| {
+                options.lock().unwrap().push((option, enabled, local));
+            }
+        })),
+        on_subnegotiation: Some(Box::new({
+            let packages = packages.clone();
+            move |_id, _conn, subneg| {
+                if let TelnetArgument::GMCP(message) = subneg {
+                    packages.lock().unwrap().push(message.package().to_string());
+                }
+            }
+        })),
+        ..Default::default()
+    });
+    server.start(handler).await.unwrap();
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(&negotiation_bytes()).await.unwrap();
+    client.flush().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    assert_eq!(
+        *options.lock().unwrap(),
+        vec![(TelnetOption::SuppressGoAhead, true, false)]
+    );
+    assert_eq!(*packages.lock().unwrap(), vec!["Core.Hello".to_string()]);
+
+    drop(client);
+    server.shutdown().await.unwrap();
+}
+
+/// Keeps the client capabilities seen after each terminal type
+#[derive(Default)]
+struct CapabilitiesHandler {
+    capabilities: tokio::sync::Mutex<Option<TerminalCapabilities>>,
+}
+
+#[async_trait]
+impl ServerHandler for CapabilitiesHandler {
+    async fn on_event(&self, _id: ConnectionId, conn: &TelnetConnection, event: TerminalEvent) {
+        if let TerminalEvent::TerminalType { .. } = event {
+            *self.capabilities.lock().await = Some(conn.capabilities());
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_mtts_terminal_type_cycling() {
+    let policy = OptionPolicy::new().request_remote(TelnetOption::TTYPE);
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap()).with_option_policy(policy);
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+
+    let handler = Arc::new(CapabilitiesHandler::default());
+    server.start(handler.clone()).await.unwrap();
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    let mut request = [0u8; 3];
+    tokio::time::timeout(Duration::from_millis(500), client.read_exact(&mut request))
+        .await
+        .expect("Timeout waiting for DO TTYPE")
+        .unwrap();
+    assert_eq!(request, [IAC, DO, TERMINAL_TYPE]);
+    client.write_all(&[IAC, WILL, TERMINAL_TYPE]).await.unwrap();
+
+    // Answer every SEND until the list repeats
+    for terminal_type in ["TINTIN++", "XTERM", "MTTS 2061", "MTTS 2061"] {
+        let mut send = [0u8; 6];
+        tokio::time::timeout(Duration::from_millis(500), client.read_exact(&mut send))
+            .await
+            .expect("Timeout waiting for TTYPE SEND")
+            .unwrap();
+        assert_eq!(send, [IAC, SB, TERMINAL_TYPE, 1, IAC, SE]);
+
+        let mut reply = vec![IAC, SB, TERMINAL_TYPE, 0];
+        reply.extend_from_slice(terminal_type.as_bytes());
+        reply.extend_from_slice(&[IAC, SE]);
+        client.write_all(&reply).await.unwrap();
+    }
+
+    // No further SEND once the client repeated itself
+    let mut extra = [0u8; 1];
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), client.read(&mut extra))
+            .await
+            .is_err()
+    );
+
+    let caps = handler.capabilities.lock().await.clone().unwrap();
+    assert!(caps.complete);
+    assert_eq!(caps.client_name.as_deref(), Some("TINTIN++"));
+    assert_eq!(caps.terminal_type.as_deref(), Some("XTERM"));
+    // 2061 = ANSI | UTF-8 | 256 COLORS | SSL
+    assert!(caps.ansi && caps.utf8 && caps.color_256 && caps.ssl);
+    assert_eq!(caps.color_mode(), ColorMode::FixedColor);
+
+    drop(client);
+    server.shutdown().await.unwrap();
+}
+
+/// Answers MSDP requests from a shared registry
+struct MsdpHandler {
+    msdp: MsdpService,
+}
+
+#[async_trait]
+impl ServerHandler for MsdpHandler {
+    async fn on_subnegotiation(
+        &self,
+        _id: ConnectionId,
+        conn: &TelnetConnection,
+        subneg: TelnetArgument,
+    ) {
+        if let TelnetArgument::MudServerData(request) = subneg {
+            self.msdp.handle(conn, &request).await.unwrap();
+        }
+    }
+
+    async fn on_disconnect(&self, id: ConnectionId, _conn: &TelnetConnection) {
+        self.msdp.remove_connection(id);
+    }
+}
+
+/// `IAC SB MSDP VAR <name> VAL <value> IAC SE`
+fn msdp_bytes(name: &str, value: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![IAC, SB, MSDP, MSDP_VAR];
+    bytes.extend_from_slice(name.as_bytes());
+    bytes.push(MSDP_VAL);
+    bytes.extend_from_slice(value);
+    bytes.extend_from_slice(&[IAC, SE]);
+    bytes
+}
+
+async fn read_exactly(client: &mut TcpStream, expected: &[u8]) {
+    let mut received = vec![0u8; expected.len()];
+    tokio::time::timeout(Duration::from_millis(500), client.read_exact(&mut received))
+        .await
+        .expect("Timeout waiting for server")
+        .unwrap();
+    assert_eq!(received, expected);
+}
+
+#[tokio::test]
+async fn test_msdp_report_send_and_list() {
+    let msdp = MsdpService::new();
+    msdp.register_value("HEALTH", MudServerDataValue::string("100"));
+    msdp.register("CHARACTER_NAME", |_conn| {
+        Some(MudServerDataValue::string("Alice"))
+    });
+
+    let policy = OptionPolicy::new().request_local(TelnetOption::MSDP);
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap()).with_option_policy(policy);
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+    let handler = Arc::new(MsdpHandler { msdp: msdp.clone() });
+    server.start(handler).await.unwrap();
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    read_exactly(&mut client, &[IAC, WILL, MSDP]).await;
+    client.write_all(&[IAC, DO, MSDP]).await.unwrap();
+
+    // SEND answers from the getter
+    client
+        .write_all(&msdp_bytes("SEND", b"CHARACTER_NAME"))
+        .await
+        .unwrap();
+    read_exactly(&mut client, &msdp_bytes("CHARACTER_NAME", b"Alice")).await;
+
+    // REPORT answers with the current value, then pushes updates
+    client
+        .write_all(&msdp_bytes("REPORT", b"HEALTH"))
+        .await
+        .unwrap();
+    read_exactly(&mut client, &msdp_bytes("HEALTH", b"100")).await;
+    let sent = msdp
+        .update("HEALTH", MudServerDataValue::string("90"))
+        .await;
+    assert_eq!(sent, 1);
+    read_exactly(&mut client, &msdp_bytes("HEALTH", b"90")).await;
+
+    let mut reported = vec![MSDP_ARRAY_OPEN, MSDP_VAL];
+    reported.extend_from_slice(b"HEALTH");
+    reported.push(MSDP_ARRAY_CLOSE);
+    client
+        .write_all(&msdp_bytes("LIST", b"REPORTED_VARIABLES"))
+        .await
+        .unwrap();
+    read_exactly(&mut client, &msdp_bytes("REPORTED_VARIABLES", &reported)).await;
+
+    // RESET ends all reports
+    client
+        .write_all(&msdp_bytes("RESET", b"REPORTABLE_VARIABLES"))
+        .await
+        .unwrap();
+    client
+        .write_all(&msdp_bytes("LIST", b"REPORTED_VARIABLES"))
+        .await
+        .unwrap();
+    let empty = [MSDP_ARRAY_OPEN, MSDP_ARRAY_CLOSE];
+    read_exactly(&mut client, &msdp_bytes("REPORTED_VARIABLES", &empty)).await;
+    let sent = msdp
+        .update("HEALTH", MudServerDataValue::string("80"))
+        .await;
+    assert_eq!(sent, 0);
+
+    drop(client);
+    server.shutdown().await.unwrap();
+}
+
+/// Routes GMCP messages through a shared registry
+struct GmcpHandler {
+    gmcp: GmcpService,
+}
+
+#[async_trait]
+impl ServerHandler for GmcpHandler {
+    async fn on_subnegotiation(
+        &self,
+        _id: ConnectionId,
+        conn: &TelnetConnection,
+        subneg: TelnetArgument,
+    ) {
+        if let TelnetArgument::GMCP(message) = subneg {
+            self.gmcp.handle(conn, &message).await;
+        }
+    }
+}
+
+/// `IAC SB GMCP <message> IAC SE`
+fn gmcp_bytes(message: &str) -> Vec<u8> {
+    let mut bytes = vec![IAC, SB, GMCP];
+    bytes.extend_from_slice(message.as_bytes());
+    bytes.extend_from_slice(&[IAC, SE]);
+    bytes
+}
+
+#[derive(serde::Deserialize)]
+struct Vitals {
+    hp: u32,
+}
+
+#[tokio::test]
+async fn test_gmcp_typed_handlers_and_supports() {
+    let gmcp = GmcpService::new();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    gmcp.on(
+        "Char.Vitals",
+        move |conn: TelnetConnection, vitals: Vitals| {
+            let tx = tx.clone();
+            async move {
+                let _ = tx.send((conn, vitals.hp));
+            }
+        },
+    );
+
+    let policy = OptionPolicy::new().request_local(TelnetOption::GMCP);
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap()).with_option_policy(policy);
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+    let handler = Arc::new(GmcpHandler { gmcp: gmcp.clone() });
+    server.start(handler).await.unwrap();
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    read_exactly(&mut client, &[IAC, WILL, GMCP]).await;
+    client.write_all(&[IAC, DO, GMCP]).await.unwrap();
+    client
+        .write_all(&gmcp_bytes(
+            r#"Core.Hello {"client":"Mudlet","version":"4.17"}"#,
+        ))
+        .await
+        .unwrap();
+    client
+        .write_all(&gmcp_bytes(r#"Core.Supports.Set ["Char 1", "Room 1"]"#))
+        .await
+        .unwrap();
+    client
+        .write_all(&gmcp_bytes(r#"Core.Supports.Remove ["Room"]"#))
+        .await
+        .unwrap();
+    client
+        .write_all(&gmcp_bytes(r#"Char.Vitals {"hp":42}"#))
+        .await
+        .unwrap();
+
+    let (conn, hp) = tokio::time::timeout(Duration::from_millis(500), rx.recv())
+        .await
+        .expect("Timeout waiting for Char.Vitals")
+        .unwrap();
+    assert_eq!(hp, 42);
+
+    let hello = gmcp.hello(conn.id()).unwrap();
+    assert_eq!(hello.client, "Mudlet");
+    assert_eq!(hello.version, "4.17");
+    assert_eq!(
+        gmcp.supports(conn.id()).into_iter().collect::<Vec<_>>(),
+        vec![("Char".to_string(), 1)]
+    );
+
+    // Packages outside the client's modules are refused
+    let refused = gmcp
+        .send_json(&conn, "Room.Info", &serde_json::json!({"num": 1}))
+        .await;
+    assert!(matches!(refused, Err(TelnetError::GmcpNotSupported(_))));
+
+    gmcp.send_json(&conn, "Char.Vitals", &serde_json::json!({"hp": 41}))
+        .await
+        .unwrap();
+    read_exactly(&mut client, &gmcp_bytes(r#"Char.Vitals {"hp":41}"#)).await;
+
+    drop(client);
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_mssp_answers_crawler() {
+    let mssp = MsspConfig::new()
+        .with_field("NAME", "Example MUD")
+        .with_field("CODEBASE", "Termionix")
+        .with_dynamic("AREAS", || "42".to_string());
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap())
+        .with_option_policy(OptionPolicy::new())
+        .with_mssp(mssp);
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+    server.start(Arc::new(ProtocolTestHandler)).await.unwrap();
+
+    let mut crawler = TcpStream::connect(addr).await.unwrap();
+    read_exactly(&mut crawler, &[IAC, WILL, MSSP]).await;
+    crawler.write_all(&[IAC, DO, MSSP]).await.unwrap();
+
+    // Read IAC SB MSSP <variables> IAC SE
+    read_exactly(&mut crawler, &[IAC, SB, MSSP]).await;
+    let mut payload = Vec::new();
+    while !payload.ends_with(&[IAC, SE]) {
+        let mut byte = [0u8; 1];
+        tokio::time::timeout(Duration::from_millis(500), crawler.read_exact(&mut byte))
+            .await
+            .expect("Timeout waiting for MSSP")
+            .unwrap();
+        payload.extend_from_slice(&byte);
+    }
+    payload.truncate(payload.len() - 2);
+    let status = MudServerStatus::decode(&mut &payload[..]).unwrap();
+
+    let value = |name: &str| status.get(name).map(|values| values.join(","));
+    assert_eq!(value("NAME").as_deref(), Some("Example MUD"));
+    assert_eq!(value("CODEBASE").as_deref(), Some("Termionix"));
+    assert_eq!(value("AREAS").as_deref(), Some("42"));
+    assert_eq!(value("PLAYERS").as_deref(), Some("1"));
+    let uptime: u64 = value("UPTIME").unwrap().parse().unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    assert!(uptime <= now && now - uptime < 60);
+
+    drop(crawler);
+    server.shutdown().await.unwrap();
+}
+
+/// `IAC SB ZMP <message> IAC SE`
+fn zmp_bytes(message: &ZmpMessage) -> Vec<u8> {
+    let mut bytes = vec![IAC, SB, ZMP];
+    bytes.extend_from_slice(&message.encode());
+    bytes.extend_from_slice(&[IAC, SE]);
+    bytes
+}
+
+#[tokio::test]
+async fn test_zmp_commands_answered_automatically() {
+    let zmp = ZmpRegistry::default()
+        .with_ident("Example MUD", "1.0", "A test server")
+        .with_handler("game.echo", |_, message| {
+            Some(ZmpMessage::new("game.echo").with_argument(message.argument(0)?))
+        });
+    let policy = OptionPolicy::new().request_local(TelnetOption::ZMP);
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap())
+        .with_option_policy(policy)
+        .with_zmp(zmp);
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+    server.start(Arc::new(ProtocolTestHandler)).await.unwrap();
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    read_exactly(&mut client, &[IAC, WILL, ZMP]).await;
+    client.write_all(&[IAC, DO, ZMP]).await.unwrap();
+    let ident = ZmpMessage::new("zmp.ident")
+        .with_argument("Example MUD")
+        .with_argument("1.0")
+        .with_argument("A test server");
+    read_exactly(&mut client, &zmp_bytes(&ident)).await;
+
+    let check = ZmpMessage::new("zmp.check").with_argument("game.");
+    client.write_all(&zmp_bytes(&check)).await.unwrap();
+    let support = ZmpMessage::new("zmp.support").with_argument("game.");
+    read_exactly(&mut client, &zmp_bytes(&support)).await;
+
+    let echo = ZmpMessage::new("game.echo").with_argument("hello");
+    client.write_all(&zmp_bytes(&echo)).await.unwrap();
+    read_exactly(&mut client, &zmp_bytes(&echo)).await;
+
+    drop(client);
+    server.shutdown().await.unwrap();
+}
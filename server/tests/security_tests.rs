@@ -19,8 +19,11 @@
 use async_trait::async_trait;
 use std::sync::Arc;
 use std::time::Duration;
-use termionix_server::{ConnectionId, ServerConfig, ServerHandler, TelnetConnection, TelnetServer};
-use tokio::io::AsyncWriteExt;
+use termionix_server::{
+    CodecLimits, ConnectionId, RateLimit, RateLimitAction, RateLimitKind, ServerConfig,
+    ServerHandler, TelnetCodecError, TelnetConnection, TelnetError, TelnetServer, TerminalEvent,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 
 struct SecurityTestHandler;
@@ -439,3 +442,143 @@ async fn test_unicode_edge_cases() {
     tokio::time::sleep(Duration::from_millis(100)).await;
     server.shutdown().await.unwrap();
 }
+
+/// Records rate limit warnings, lines and errors
+#[derive(Default)]
+struct FloodHandler {
+    warnings: tokio::sync::Mutex<Vec<RateLimitKind>>,
+    lines: tokio::sync::Mutex<Vec<String>>,
+    errors: tokio::sync::Mutex<Vec<String>>,
+}
+
+#[async_trait]
+impl ServerHandler for FloodHandler {
+    async fn on_event(&self, _id: ConnectionId, _conn: &TelnetConnection, event: TerminalEvent) {
+        if let TerminalEvent::LineCompleted { line, .. } = event {
+            self.lines.lock().await.push(line.to_string());
+        }
+    }
+
+    async fn on_rate_limited(
+        &self,
+        _id: ConnectionId,
+        _conn: &TelnetConnection,
+        kind: RateLimitKind,
+    ) {
+        self.warnings.lock().await.push(kind);
+    }
+
+    async fn on_error(&self, _id: ConnectionId, _conn: &TelnetConnection, error: TelnetError) {
+        self.errors.lock().await.push(error.to_string());
+    }
+}
+
+/// Start a server limiting each client to two lines per second
+async fn start_flood_server(
+    action: RateLimitAction,
+) -> (TelnetServer, Arc<FloodHandler>, TcpStream) {
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap()).with_rate_limit(
+        RateLimit::new()
+            .with_lines_per_second(2)
+            .with_action(action),
+    );
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+
+    let handler = Arc::new(FloodHandler::default());
+    server.start(handler.clone()).await.unwrap();
+
+    let client = TcpStream::connect(addr).await.unwrap();
+    (server, handler, client)
+}
+
+#[tokio::test]
+async fn test_line_flood_warns_handler() {
+    let (server, handler, mut client) = start_flood_server(RateLimitAction::Warn).await;
+
+    client.write_all(b"one\ntwo\nthree\nfour\n").await.unwrap();
+    client.flush().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Warned once, and every line is still delivered
+    assert_eq!(*handler.warnings.lock().await, vec![RateLimitKind::Lines]);
+    assert_eq!(handler.lines.lock().await.len(), 4);
+    assert_eq!(server.connection_count(), 1);
+
+    drop(client);
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_line_flood_disconnects_client() {
+    let (server, handler, mut client) = start_flood_server(RateLimitAction::Disconnect).await;
+
+    client.write_all(b"one\ntwo\nthree\nfour\n").await.unwrap();
+    client.flush().await.unwrap();
+
+    // The server closes the connection
+    let mut buf = [0u8; 256];
+    let closed = tokio::time::timeout(Duration::from_secs(2), async {
+        loop {
+            match client.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+    })
+    .await;
+    assert!(closed.is_ok(), "Server should close a flooding client");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(*handler.lines.lock().await, vec!["one", "two"]);
+    assert_eq!(
+        *handler.errors.lock().await,
+        vec![TelnetError::RateLimited(RateLimitKind::Lines).to_string()]
+    );
+    assert_eq!(server.connection_count(), 0);
+
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_oversized_subnegotiation_disconnects_client() {
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap())
+        .with_codec_limits(CodecLimits::default().with_max_subnegotiation_length(Some(64)));
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+
+    let handler = Arc::new(FloodHandler::default());
+    server.start(handler.clone()).await.unwrap();
+
+    // IAC SB GMCP followed by a payload that never ends
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(&[0xFF, 0xFA, 0xC9]).await.unwrap();
+    client.write_all(&[b'x'; 1024]).await.unwrap();
+    client.flush().await.unwrap();
+
+    // The server closes the connection
+    let mut buf = [0u8; 256];
+    let closed = tokio::time::timeout(Duration::from_secs(2), async {
+        loop {
+            match client.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+    })
+    .await;
+    assert!(
+        closed.is_ok(),
+        "Server should close a client over a codec limit"
+    );
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let error = TelnetError::LimitExceeded(TelnetCodecError::SubnegotiationTooLong {
+        option: 0xC9,
+        limit: 64,
+    });
+    assert_eq!(*handler.errors.lock().await, vec![error.to_string()]);
+    assert_eq!(server.connection_count(), 0);
+
+    server.shutdown().await.unwrap();
+}
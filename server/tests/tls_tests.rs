@@ -0,0 +1,429 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! TLS tests for the Telnet server, using self-signed certificates
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use termionix_server::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use termionix_server::rustls::{self, ProtocolVersion, RootCertStore};
+use termionix_server::{
+    ConnectionId, ServerConfig, ServerHandler, TelnetArgument, TelnetConnection, TelnetServer,
+    TerminalEvent, TlsConfig, TlsInfo, TlsListener,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
+
+const IAC: u8 = 255;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const WILL: u8 = 251;
+const DO: u8 = 253;
+const START_TLS: u8 = 46;
+const FOLLOWS: u8 = 1;
+
+/// Handler recording the TLS details of each connection and echoing lines
+///
+/// Connections upgraded with START_TLS are recorded again once upgraded.
+struct TlsHandler {
+    sessions: Arc<tokio::sync::Mutex<Vec<Option<TlsInfo>>>>,
+}
+
+impl TlsHandler {
+    fn new() -> Self {
+        Self {
+            sessions: Arc::new(tokio::sync::Mutex::new(Vec::new())),
+        }
+    }
+
+    async fn sessions(&self) -> Vec<Option<TlsInfo>> {
+        self.sessions.lock().await.clone()
+    }
+}
+
+#[async_trait]
+impl ServerHandler for TlsHandler {
+    async fn on_connect(&self, _id: ConnectionId, conn: &TelnetConnection) {
+        self.sessions.lock().await.push(conn.tls_info().cloned());
+        let _ = conn.send("Welcome\r\n", true).await;
+    }
+
+    async fn on_event(&self, _id: ConnectionId, conn: &TelnetConnection, event: TerminalEvent) {
+        match event {
+            TerminalEvent::LineCompleted { line, .. } => {
+                let _ = conn
+                    .send(format!("echo: {}\r\n", line).as_str(), true)
+                    .await;
+            }
+            TerminalEvent::Subnegotiation(TelnetArgument::StartTLSFollows) => {
+                self.sessions.lock().await.push(conn.tls_info().cloned());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Self-signed server certificate for `localhost`
+fn server_identity() -> rcgen::CertifiedKey {
+    rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap()
+}
+
+/// TLS settings for a server presenting `identity`
+fn server_tls(identity: &rcgen::CertifiedKey) -> TlsConfig {
+    TlsConfig::from_pem(
+        identity.cert.pem().as_bytes(),
+        identity.key_pair.serialize_pem().as_bytes(),
+    )
+    .unwrap()
+}
+
+/// Client configuration trusting `root`, optionally authenticating with a certificate
+fn client_config(
+    root: &rcgen::Certificate,
+    client_auth: Option<(&rcgen::Certificate, &rcgen::KeyPair)>,
+) -> rustls::ClientConfig {
+    let mut roots = RootCertStore::empty();
+    roots.add(root.der().clone()).unwrap();
+    let builder = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_root_certificates(roots);
+    match client_auth {
+        Some((cert, key)) => builder
+            .with_client_auth_cert(
+                vec![cert.der().clone()],
+                PrivateKeyDer::try_from(key.serialize_der()).unwrap(),
+            )
+            .unwrap(),
+        None => builder.with_no_client_auth(),
+    }
+}
+
+/// Connect to `addr` and complete a TLS handshake for `localhost`
+async fn connect_tls(
+    addr: std::net::SocketAddr,
+    config: rustls::ClientConfig,
+) -> TlsStream<TcpStream> {
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let connector = TlsConnector::from(Arc::new(config));
+    let server_name = ServerName::try_from("localhost").unwrap();
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        connector.connect(server_name, stream),
+    )
+    .await
+    .expect("TLS handshake timed out")
+    .unwrap()
+}
+
+/// Read from `stream` until the received bytes end with `expected`
+async fn read_until_bytes<S: AsyncRead + Unpin>(stream: &mut S, expected: &[u8]) {
+    let mut received = Vec::new();
+    let mut byte = [0u8; 1];
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !received.ends_with(expected) {
+            let n = stream.read(&mut byte).await.unwrap();
+            assert!(
+                n > 0,
+                "connection closed before {:?} was received",
+                expected
+            );
+            received.push(byte[0]);
+        }
+    })
+    .await
+    .unwrap_or_else(|_| {
+        panic!(
+            "timed out waiting for {:?}, received {:?}",
+            expected, received
+        )
+    });
+}
+
+/// Read from `stream` until the received text contains `expected`
+async fn read_until<S: AsyncRead + Unpin>(stream: &mut S, expected: &str) -> String {
+    let mut received = Vec::new();
+    let mut buf = [0u8; 1024];
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !String::from_utf8_lossy(&received).contains(expected) {
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(
+                n > 0,
+                "connection closed before {:?} was received",
+                expected
+            );
+            received.extend_from_slice(&buf[..n]);
+        }
+    })
+    .await
+    .unwrap_or_else(|_| {
+        panic!(
+            "timed out waiting for {:?}, received {:?}",
+            expected,
+            String::from_utf8_lossy(&received)
+        )
+    });
+    String::from_utf8_lossy(&received).into_owned()
+}
+
+#[tokio::test]
+async fn test_tls_server_round_trip() {
+    let identity = server_identity();
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap()).with_tls(server_tls(&identity));
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+
+    let handler = Arc::new(TlsHandler::new());
+    server.start(handler.clone()).await.unwrap();
+
+    let mut client = connect_tls(addr, client_config(&identity.cert, None)).await;
+    read_until(&mut client, "Welcome").await;
+
+    client.write_all(b"hello\r\n").await.unwrap();
+    read_until(&mut client, "echo: hello").await;
+
+    let sessions = handler.sessions().await;
+    assert_eq!(sessions.len(), 1);
+    let info = sessions[0]
+        .as_ref()
+        .expect("connection should be encrypted");
+    assert_eq!(info.protocol_version, Some(ProtocolVersion::TLSv1_3));
+    assert!(info.cipher_suite.is_some());
+    assert_eq!(info.server_name.as_deref(), Some("localhost"));
+    assert!(info.peer_certificates.is_empty());
+
+    drop(client);
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tls_client_certificate() {
+    let identity = server_identity();
+
+    let mut ca_params = rcgen::CertificateParams::new(Vec::<String>::new()).unwrap();
+    ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+    let ca_key = rcgen::KeyPair::generate().unwrap();
+    let ca_cert = ca_params.self_signed(&ca_key).unwrap();
+
+    let client_key = rcgen::KeyPair::generate().unwrap();
+    let client_cert = rcgen::CertificateParams::new(vec!["player".to_string()])
+        .unwrap()
+        .signed_by(&client_key, &ca_cert, &ca_key)
+        .unwrap();
+
+    let tls = TlsConfig::from_pem_with_client_auth(
+        identity.cert.pem().as_bytes(),
+        identity.key_pair.serialize_pem().as_bytes(),
+        ca_cert.pem().as_bytes(),
+    )
+    .unwrap();
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap()).with_tls(tls);
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+
+    let handler = Arc::new(TlsHandler::new());
+    server.start(handler.clone()).await.unwrap();
+
+    // With a client certificate
+    let config = client_config(&identity.cert, Some((&client_cert, &client_key)));
+    let mut client = connect_tls(addr, config).await;
+    read_until(&mut client, "Welcome").await;
+
+    // Without one
+    let mut anonymous = connect_tls(addr, client_config(&identity.cert, None)).await;
+    read_until(&mut anonymous, "Welcome").await;
+
+    let sessions = handler.sessions().await;
+    assert_eq!(sessions.len(), 2);
+    let info = sessions[0].as_ref().unwrap();
+    assert_eq!(
+        info.peer_certificates,
+        vec![CertificateDer::from(client_cert.der().to_vec())]
+    );
+    assert!(sessions[1].as_ref().unwrap().peer_certificates.is_empty());
+
+    drop(client);
+    drop(anonymous);
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tls_listener_alongside_plain_telnet() {
+    let identity = server_identity();
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap());
+    let server = TelnetServer::new(config).await.unwrap();
+    let plain_addr = server.bind_address();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let tls_addr = listener.local_addr().unwrap();
+    server
+        .add_listener(TlsListener::new(listener, &server_tls(&identity)).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(server.local_addresses().len(), 2);
+
+    let handler = Arc::new(TlsHandler::new());
+    server.start(handler.clone()).await.unwrap();
+
+    let mut plain = TcpStream::connect(plain_addr).await.unwrap();
+    read_until(&mut plain, "Welcome").await;
+
+    let mut encrypted = connect_tls(tls_addr, client_config(&identity.cert, None)).await;
+    read_until(&mut encrypted, "Welcome").await;
+
+    let sessions = handler.sessions().await;
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions[0].is_none());
+    assert!(sessions[1].is_some());
+
+    drop(plain);
+    drop(encrypted);
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tls_failed_handshakes_do_not_block_others() {
+    let identity = server_identity();
+    let tls = server_tls(&identity).with_handshake_timeout(Duration::from_millis(500));
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap()).with_tls(tls);
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+
+    let handler = Arc::new(TlsHandler::new());
+    server.start(handler.clone()).await.unwrap();
+
+    // A client that never starts the handshake
+    let _idle = TcpStream::connect(addr).await.unwrap();
+
+    // A plain Telnet client on the TLS port
+    let mut plain = TcpStream::connect(addr).await.unwrap();
+    plain.write_all(b"hello\r\n").await.unwrap();
+
+    let mut client = connect_tls(addr, client_config(&identity.cert, None)).await;
+    read_until(&mut client, "Welcome").await;
+
+    tokio::time::sleep(Duration::from_millis(700)).await;
+    assert_eq!(server.connection_count(), 1);
+    assert_eq!(handler.sessions().await.len(), 1);
+
+    drop(client);
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tls_handshakes_wait_for_a_free_slot() {
+    let identity = server_identity();
+    let tls = server_tls(&identity)
+        .with_handshake_timeout(Duration::from_millis(400))
+        .with_max_pending_handshakes(1);
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap()).with_tls(tls);
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+
+    let handler = Arc::new(TlsHandler::new());
+    server.start(handler.clone()).await.unwrap();
+
+    // The only handshake slot is taken by a client that never starts it
+    let _idle = TcpStream::connect(addr).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let start = std::time::Instant::now();
+    let mut client = connect_tls(addr, client_config(&identity.cert, None)).await;
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    read_until(&mut client, "Welcome").await;
+    assert_eq!(server.connection_count(), 1);
+
+    drop(client);
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tls_config_from_pem_files() {
+    let identity = server_identity();
+    let dir = std::env::temp_dir().join(format!("termionix-tls-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cert_path = dir.join("server.crt");
+    let key_path = dir.join("server.key");
+    std::fs::write(&cert_path, identity.cert.pem()).unwrap();
+    std::fs::write(&key_path, identity.key_pair.serialize_pem()).unwrap();
+
+    let tls = TlsConfig::from_pem_files(&cert_path, &key_path).unwrap();
+    let config = ServerConfig::new("127.0.0.1:0".parse().unwrap()).with_tls(tls);
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+    server.start(Arc::new(TlsHandler::new())).await.unwrap();
+
+    let mut client = connect_tls(addr, client_config(&identity.cert, None)).await;
+    read_until(&mut client, "Welcome").await;
+
+    assert!(TlsConfig::from_pem_files(dir.join("missing.crt"), &key_path).is_err());
+
+    drop(client);
+    server.shutdown().await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_start_tls_upgrades_plain_connection() {
+    let identity = server_identity();
+    let config =
+        ServerConfig::new("127.0.0.1:0".parse().unwrap()).with_start_tls(server_tls(&identity));
+    let server = TelnetServer::new(config).await.unwrap();
+    let addr = server.bind_address();
+
+    let handler = Arc::new(TlsHandler::new());
+    server.start(handler.clone()).await.unwrap();
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    read_until_bytes(&mut client, &[IAC, DO, START_TLS]).await;
+
+    // Half a line before the upgrade, the rest after it
+    client.write_all(b"hel").await.unwrap();
+    client.write_all(&[IAC, WILL, START_TLS]).await.unwrap();
+    read_until_bytes(&mut client, &[IAC, SB, START_TLS, FOLLOWS, IAC, SE]).await;
+    client
+        .write_all(&[IAC, SB, START_TLS, FOLLOWS, IAC, SE])
+        .await
+        .unwrap();
+
+    let connector = TlsConnector::from(Arc::new(client_config(&identity.cert, None)));
+    let server_name = ServerName::try_from("localhost").unwrap();
+    let mut client = tokio::time::timeout(
+        Duration::from_secs(5),
+        connector.connect(server_name, client),
+    )
+    .await
+    .expect("TLS handshake timed out")
+    .unwrap();
+
+    client.write_all(b"lo\r\n").await.unwrap();
+    read_until(&mut client, "echo: hello").await;
+
+    let sessions = handler.sessions().await;
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions[0].is_none());
+    let info = sessions[1].as_ref().expect("connection should be upgraded");
+    assert_eq!(info.protocol_version, Some(ProtocolVersion::TLSv1_3));
+    assert_eq!(info.server_name.as_deref(), Some("localhost"));
+
+    drop(client);
+    server.shutdown().await.unwrap();
+}
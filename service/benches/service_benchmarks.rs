@@ -94,7 +94,7 @@ fn bench_connection_operations(c: &mut Criterion) {
             let (stream1, _stream2) = duplex(8192);
             let codec = create_codec();
             let (r, w) = tokio::io::split(stream1);
-            black_box(SplitTerminalConnection::new(r, w, codec.clone(), codec))
+            black_box(SplitTerminalConnection::new(r, w, codec))
         })
     });
 
@@ -103,7 +103,7 @@ fn bench_connection_operations(c: &mut Criterion) {
             let (stream1, _stream2) = duplex(8192);
             let codec = create_codec();
             let (r, w) = tokio::io::split(stream1);
-            let conn = SplitTerminalConnection::new(r, w, codec.clone(), codec);
+            let conn = SplitTerminalConnection::new(r, w, codec);
             black_box(conn.flush_strategy().await)
         })
     });
@@ -113,7 +113,7 @@ fn bench_connection_operations(c: &mut Criterion) {
             let (stream1, _stream2) = duplex(8192);
             let codec = create_codec();
             let (r, w) = tokio::io::split(stream1);
-            let conn = SplitTerminalConnection::new(r, w, codec.clone(), codec);
+            let conn = SplitTerminalConnection::new(r, w, codec);
             conn.set_flush_strategy(black_box(FlushStrategy::Immediate))
                 .await
         })
@@ -124,7 +124,7 @@ fn bench_connection_operations(c: &mut Criterion) {
             let (stream1, _stream2) = duplex(8192);
             let codec = create_codec();
             let (r, w) = tokio::io::split(stream1);
-            let conn = SplitTerminalConnection::new(r, w, codec.clone(), codec);
+            let conn = SplitTerminalConnection::new(r, w, codec);
             black_box(conn.clone())
         })
     });
@@ -141,7 +141,7 @@ fn bench_send_operations(c: &mut Criterion) {
             let (stream1, _stream2) = duplex(8192);
             let codec = create_codec();
             let (r, w) = tokio::io::split(stream1);
-            let conn = SplitTerminalConnection::new(r, w, codec.clone(), codec);
+            let conn = SplitTerminalConnection::new(r, w, codec);
             conn.send(black_box(TerminalCommand::Text("Hello".to_string())), true)
                 .await
                 .unwrap()
@@ -153,7 +153,7 @@ fn bench_send_operations(c: &mut Criterion) {
             let (stream1, _stream2) = duplex(8192);
             let codec = create_codec();
             let (r, w) = tokio::io::split(stream1);
-            let conn = SplitTerminalConnection::new(r, w, codec.clone(), codec);
+            let conn = SplitTerminalConnection::new(r, w, codec);
             let msg = "A".repeat(1024);
             conn.send(black_box(TerminalCommand::Text(msg)), true)
                 .await
@@ -166,7 +166,7 @@ fn bench_send_operations(c: &mut Criterion) {
             let (stream1, _stream2) = duplex(16384);
             let codec = create_codec();
             let (r, w) = tokio::io::split(stream1);
-            let conn = SplitTerminalConnection::new(r, w, codec.clone(), codec);
+            let conn = SplitTerminalConnection::new(r, w, codec);
             let msg = "A".repeat(8192);
             conn.send(black_box(TerminalCommand::Text(msg.to_string())), true)
                 .await
@@ -179,7 +179,7 @@ fn bench_send_operations(c: &mut Criterion) {
             let (stream1, _stream2) = duplex(8192);
             let codec = create_codec();
             let (r, w) = tokio::io::split(stream1);
-            let conn = SplitTerminalConnection::new(r, w, codec.clone(), codec);
+            let conn = SplitTerminalConnection::new(r, w, codec);
             conn.send(black_box(TerminalCommand::Text("Hello".to_string())), false)
                 .await
                 .unwrap()
@@ -191,7 +191,7 @@ fn bench_send_operations(c: &mut Criterion) {
             let (stream1, _stream2) = duplex(8192);
             let codec = create_codec();
             let (r, w) = tokio::io::split(stream1);
-            let conn = SplitTerminalConnection::new(r, w, codec.clone(), codec);
+            let conn = SplitTerminalConnection::new(r, w, codec);
             conn.send(TerminalCommand::Text("Hello".to_string()), false)
                 .await
                 .unwrap();
@@ -212,7 +212,7 @@ fn bench_throughput(c: &mut Criterion) {
             let (stream1, _stream2) = duplex(8192);
             let codec = create_codec();
             let (r, w) = tokio::io::split(stream1);
-            let conn = SplitTerminalConnection::new(r, w, codec.clone(), codec);
+            let conn = SplitTerminalConnection::new(r, w, codec);
             for i in 0..100 {
                 conn.send(
                     black_box(TerminalCommand::Text(format!("Message {}", i))),
@@ -230,7 +230,7 @@ fn bench_throughput(c: &mut Criterion) {
             let (stream1, _stream2) = duplex(16384);
             let codec = create_codec();
             let (r, w) = tokio::io::split(stream1);
-            let conn = SplitTerminalConnection::new(r, w, codec.clone(), codec);
+            let conn = SplitTerminalConnection::new(r, w, codec);
             let msg = "A".repeat(1024);
             for _ in 0..100 {
                 conn.send(black_box(TerminalCommand::Text(msg.clone())), true)
@@ -252,7 +252,7 @@ fn bench_concurrent_operations(c: &mut Criterion) {
             let (stream1, _stream2) = duplex(8192);
             let codec = create_codec();
             let (r, w) = tokio::io::split(stream1);
-            let conn = SplitTerminalConnection::new(r, w, codec.clone(), codec);
+            let conn = SplitTerminalConnection::new(r, w, codec);
             let conn_clone = conn.clone();
 
             let task1 = tokio::spawn(async move {
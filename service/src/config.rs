@@ -45,6 +45,8 @@
 //! ```
 
 use std::time::Duration;
+use termionix_terminal::zmp::ZmpRegistry;
+use termionix_terminal::{Charset, CodecLimits};
 
 /// Common connection configuration shared by both client and server
 #[derive(Debug, Clone)]
@@ -58,15 +60,48 @@ pub struct ConnectionConfig {
     /// Terminal height in rows
     pub terminal_height: u16,
 
-    /// Buffer size for incoming/outgoing data
+    /// Capacity of the outgoing write queue, in queued commands
     pub buffer_size: usize,
 
-    /// Enable TCP keepalive
+    /// What to do with output when the write queue is full
+    pub overflow_policy: OverflowPolicy,
+
+    /// Send Telnet keepalive probes on idle connections
     pub keepalive: bool,
 
-    /// Keepalive interval
+    /// Time without incoming traffic before a keepalive probe is sent
     pub keepalive_interval: Duration,
 
+    /// Time the peer has to answer a probe before it is considered dead
+    ///
+    /// Only probes that require an answer (`AYT` and `TIMING-MARK`) are timed out.
+    pub keepalive_timeout: Duration,
+
+    /// Which Telnet command is sent as a keepalive probe
+    pub keepalive_probe: KeepaliveProbe,
+
+    /// Limits on incoming lines, bytes and negotiations (None for no limits)
+    pub rate_limit: Option<RateLimit>,
+
+    /// Limits on subnegotiation length, negotiation loops and queued replies
+    ///
+    /// Applied to the Telnet codec by whoever builds the connection's codec
+    /// stack; reads fail with [`ConnectionError::LimitExceeded`](crate::ConnectionError::LimitExceeded)
+    /// once one is exceeded.
+    pub codec_limits: CodecLimits,
+
+    /// Charsets offered in CHARSET negotiation, in order of preference
+    ///
+    /// Applied to the ANSI codec by whoever builds the connection's codec
+    /// stack; empty disables negotiation and text stays UTF-8.
+    pub charsets: Vec<Charset>,
+
+    /// ZMP commands answered automatically once ZMP is negotiated
+    ///
+    /// Applied to the Telnet codec by whoever builds the connection's codec
+    /// stack; the default answers the standard `zmp.` package.
+    pub zmp: ZmpRegistry,
+
     /// Read timeout (None for no timeout)
     pub read_timeout: Option<Duration>,
 }
@@ -78,8 +113,15 @@ impl Default for ConnectionConfig {
             terminal_width: 80,
             terminal_height: 24,
             buffer_size: 8192,
+            overflow_policy: OverflowPolicy::default(),
             keepalive: true,
             keepalive_interval: Duration::from_secs(60),
+            keepalive_timeout: Duration::from_secs(30),
+            keepalive_probe: KeepaliveProbe::default(),
+            rate_limit: None,
+            codec_limits: CodecLimits::default(),
+            charsets: Vec::new(),
+            zmp: ZmpRegistry::default(),
             read_timeout: Some(Duration::from_secs(300)), // 5 minutes
         }
     }
@@ -105,6 +147,12 @@ impl ConnectionConfig {
         self
     }
 
+    /// Set the write queue overflow policy
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
     /// Enable or disable keepalive
     pub fn with_keepalive(mut self, enabled: bool) -> Self {
         self.keepalive = enabled;
@@ -117,6 +165,42 @@ impl ConnectionConfig {
         self
     }
 
+    /// Set the time the peer has to answer a keepalive probe
+    pub fn with_keepalive_timeout(mut self, timeout: Duration) -> Self {
+        self.keepalive_timeout = timeout;
+        self
+    }
+
+    /// Set the keepalive probe
+    pub fn with_keepalive_probe(mut self, probe: KeepaliveProbe) -> Self {
+        self.keepalive_probe = probe;
+        self
+    }
+
+    /// Set the input rate limits
+    pub fn with_rate_limit(mut self, limit: Option<RateLimit>) -> Self {
+        self.rate_limit = limit;
+        self
+    }
+
+    /// Set the Telnet codec limits
+    pub fn with_codec_limits(mut self, limits: CodecLimits) -> Self {
+        self.codec_limits = limits;
+        self
+    }
+
+    /// Set the charsets offered in CHARSET negotiation
+    pub fn with_charsets(mut self, charsets: Vec<Charset>) -> Self {
+        self.charsets = charsets;
+        self
+    }
+
+    /// Set the ZMP commands answered automatically
+    pub fn with_zmp(mut self, zmp: ZmpRegistry) -> Self {
+        self.zmp = zmp;
+        self
+    }
+
     /// Set the read timeout
     pub fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
         self.read_timeout = timeout;
@@ -278,9 +362,28 @@ impl ServerConnectionConfig {
     }
 
     /// Enable rate limiting
+    ///
+    /// Also sets [`ConnectionConfig::rate_limit`] in [`common`](Self::common)
+    /// to limit completed lines to `max_per_second`. Use
+    /// [`with_rate_limit`](Self::with_rate_limit) for byte and negotiation limits.
     pub fn with_rate_limiting(mut self, enabled: bool, max_per_second: Option<usize>) -> Self {
         self.rate_limiting = enabled;
         self.max_messages_per_second = max_per_second;
+        self.common.rate_limit = enabled.then(|| RateLimit {
+            lines_per_second: max_per_second.map(|max| max.try_into().unwrap_or(u32::MAX)),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Set the input rate limits
+    ///
+    /// Keeps [`rate_limiting`](Self::rate_limiting) and
+    /// [`max_messages_per_second`](Self::max_messages_per_second) in sync.
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limiting = true;
+        self.max_messages_per_second = limit.lines_per_second.map(|max| max as usize);
+        self.common.rate_limit = Some(limit);
         self
     }
 
@@ -370,19 +473,36 @@ impl From<ServerConnectionConfig> for Config {
 }
 
 /// Flush strategy determines when buffered data should be flushed
+///
+/// A send with `force_flush` set, [`flush`](crate::SplitTerminalConnection::flush)
+/// and negotiation replies always flush regardless of the strategy.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FlushStrategy {
     /// Never auto-flush, manual flush only
+    ///
+    /// The write buffer is still flushed once it outgrows its 8 KiB
+    /// backpressure boundary.
     Manual,
 
     /// Flush on every send operation
     Immediate,
 
     /// Flush when newline is detected
+    ///
+    /// Like `Manual`, the write buffer is also flushed at 8 KiB.
     OnNewline,
 
     /// Flush when buffer reaches threshold (in bytes)
     OnThreshold(usize),
+
+    /// Flush when `max_bytes` are buffered, or `max_latency` after the first
+    /// unflushed write, whichever comes first
+    Batched {
+        /// Buffered bytes that trigger a flush
+        max_bytes: usize,
+        /// Longest time output may wait in the buffer
+        max_latency: Duration,
+    },
 }
 
 impl Default for FlushStrategy {
@@ -390,3 +510,133 @@ impl Default for FlushStrategy {
         Self::OnNewline
     }
 }
+
+/// Overflow policy determines what happens to output sent while the write queue is full
+///
+/// The queue fills up when a peer reads slower than output is produced. Its
+/// capacity is [`ConnectionConfig::buffer_size`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Wait until the writer has made room in the queue
+    #[default]
+    Wait,
+
+    /// Discard the oldest queued output to make room
+    DropOldest,
+
+    /// Discard all queued output and close the connection
+    Disconnect,
+
+    /// Reject the output with [`ConnectionError::QueueFull`](crate::ConnectionError::QueueFull)
+    Error,
+}
+
+/// Telnet command sent to an idle peer to check that it is still there
+///
+/// Any traffic keeps NAT mappings alive, but only probes the peer must answer
+/// can detect a dead peer. Such peers are reported as
+/// [`ConnectionError::KeepaliveTimeout`](crate::ConnectionError::KeepaliveTimeout).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeepaliveProbe {
+    /// `TIMING-MARK` once the peer has sent any Telnet command, `NOP` otherwise
+    #[default]
+    Auto,
+
+    /// `IAC NOP`, which expects no answer
+    NoOperation,
+
+    /// `IAC AYT`, for peers that answer "Are You There"
+    AreYouThere,
+
+    /// `IAC DO TIMING-MARK`, which every RFC 860 peer answers with `WILL` or `WONT`
+    TimingMark,
+}
+
+/// Input rate limits for a connection
+///
+/// Each limit is a token bucket refilled at its per-second rate and holding up
+/// to one second of input, so short bursts pass while sustained floods are
+/// limited. Limits left at `None` are not enforced.
+///
+/// # Example
+///
+/// ```
+/// use termionix_service::{RateLimit, RateLimitAction};
+///
+/// let limit = RateLimit::new()
+///     .with_lines_per_second(10)
+///     .with_bytes_per_second(4096)
+///     .with_negotiations_per_second(50)
+///     .with_action(RateLimitAction::Drop);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RateLimit {
+    /// Completed lines per second
+    pub lines_per_second: Option<u32>,
+
+    /// Bytes per second, including Telnet commands
+    pub bytes_per_second: Option<u32>,
+
+    /// Telnet `DO`, `DONT`, `WILL` and `WONT` commands per second
+    ///
+    /// Kept separate from the other limits so negotiation storms can be
+    /// stopped without limiting regular input.
+    pub negotiations_per_second: Option<u32>,
+
+    /// What to do with input over a limit
+    pub action: RateLimitAction,
+}
+
+impl RateLimit {
+    /// Create rate limits with no limits set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit completed lines per second
+    pub fn with_lines_per_second(mut self, max: u32) -> Self {
+        self.lines_per_second = Some(max);
+        self
+    }
+
+    /// Limit bytes per second
+    pub fn with_bytes_per_second(mut self, max: u32) -> Self {
+        self.bytes_per_second = Some(max);
+        self
+    }
+
+    /// Limit Telnet negotiation commands per second
+    pub fn with_negotiations_per_second(mut self, max: u32) -> Self {
+        self.negotiations_per_second = Some(max);
+        self
+    }
+
+    /// Set what happens to input over a limit
+    pub fn with_action(mut self, action: RateLimitAction) -> Self {
+        self.action = action;
+        self
+    }
+}
+
+/// Rate limit action determines what happens to input over a [`RateLimit`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitAction {
+    /// Stop reading until the input is back within its limits
+    ///
+    /// The peer is slowed down by TCP backpressure.
+    #[default]
+    Delay,
+
+    /// Discard lines and characters over the limit
+    ///
+    /// `DO` and `WILL` requests from the peer are ignored while negotiations
+    /// are over their limit, leaving those options disabled on both sides.
+    Drop,
+
+    /// Deliver the input after a
+    /// [`TerminalEvent::RateLimited`](crate::TerminalEvent::RateLimited) event
+    Warn,
+
+    /// Fail reads with [`ConnectionError::RateLimited`](crate::ConnectionError::RateLimited)
+    Disconnect,
+}
@@ -29,6 +29,46 @@
 //! This separation ensures that reads never block writes and vice versa, solving
 //! the common problem where buffered writes wait for read timeouts.
 //!
+//! Both workers share a single codec, so Telnet negotiation state is the same
+//! on either side. Replies the decoder queues while reading (e.g. `DONT` for a
+//! refused `WILL`) are written by the write worker as soon as they are queued.
+//!
+//! # Backpressure
+//!
+//! Output waits in a bounded write queue whose capacity is
+//! [`ConnectionConfig::buffer_size`]. When a slow peer lets the queue fill up,
+//! the connection's [`OverflowPolicy`] decides whether senders wait, the oldest
+//! output is dropped, the connection is closed or the send fails. Negotiation
+//! replies and other protocol traffic are never dropped.
+//!
+//! # Compression
+//!
+//! MCCP2 (option 86) and MCCP3 (option 87) are handled automatically once
+//! negotiated through the codec's option policy:
+//!
+//! - When MCCP2 is enabled locally, or MCCP3 remotely, the writer emits
+//!   `IAC SB <option> IAC SE` and compresses everything after it with zlib.
+//! - When the peer sends that marker, the reader decompresses everything after it.
+//! - Disabling the option ends the outgoing zlib stream; when the peer ends its
+//!   stream the reader returns to uncompressed input.
+//!
+//! # START_TLS
+//!
+//! Once [`enable_start_tls`](SplitTerminalConnection::enable_start_tls) was
+//! called, the transport is upgraded in place when START_TLS (option 46) is
+//! negotiated:
+//!
+//! - The side the peer agreed to enable START_TLS for (`DO` answered with
+//!   `WILL`) sends `IAC SB START_TLS FOLLOWS IAC SE` and stops writing.
+//! - The other side answers the first `FOLLOWS` with its own and stops writing.
+//! - After the peer's `FOLLOWS`, the reader hands both halves and any bytes
+//!   read ahead to the [`StartTlsUpgrade`], which runs the TLS handshake.
+//!
+//! The codec is kept, so negotiated options survive the upgrade. Outgoing
+//! compression ends before `FOLLOWS` and, if MCCP was active, restarts with a
+//! new marker inside the encrypted stream. Output sent meanwhile waits in the
+//! write queue.
+//!
 //! # Examples
 //!
 //! ```no_run
@@ -46,11 +86,7 @@
 //! let ansi_codec = AnsiCodec::new(AnsiConfig::default(), telnet_codec);
 //! let codec = TerminalCodec::new(ansi_codec);
 //!
-//! let conn = SplitTerminalConnection::from_stream(
-//!     stream,
-//!     codec.clone(),
-//!     codec,
-//! );
+//! let conn = SplitTerminalConnection::from_stream(stream, codec);
 //!
 //! // Send command (never blocks on reads)
 //! conn.send(TerminalCommand::Text("Hello".to_string()), true).await?;
@@ -63,28 +99,425 @@
 //! # }
 //! ```
 
-use crate::{ConnectionError, ConnectionResult, FlushStrategy};
+use crate::keepalive::{Keepalive, KeepaliveReader};
+use crate::ratelimit::{RateLimiter, Verdict};
+use crate::starttls::{StartTlsStream, StartTlsUpgrade};
+use crate::{ConnectionConfig, ConnectionError, ConnectionResult, FlushStrategy, OverflowPolicy};
+use bytes::{Bytes, BytesMut};
 use futures_util::{SinkExt, StreamExt};
+use std::collections::VecDeque;
 use std::sync::Arc;
 use termionix_compress::{CompressionAlgorithm, CompressionReader, CompressionWriter};
-use termionix_terminal::{TerminalCommand, TerminalEvent};
-use tokio::io::{AsyncRead, AsyncWrite};
-use tokio::sync::{Mutex, RwLock, mpsc};
-use tokio_util::codec::{Encoder, FramedRead, FramedWrite};
+use termionix_terminal::{
+    NegotiationGate, TelnetArgument, TelnetCodec, TelnetCodecError, TelnetFrame, TelnetOption,
+    TelnetResponder, TelnetSide, TerminalCommand, TerminalEvent,
+};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{Mutex, Notify, RwLock, mpsc, oneshot};
+use tokio::time::Instant;
+use tokio_util::codec::{Decoder, Encoder, FramedRead, FramedWrite};
 
 /// Write command for terminal output
 enum WriteCommand {
     Send(TerminalCommand, bool), // (command, force_flush)
+    Raw(Bytes, bool),            // (pre-encoded bytes, force_flush)
+    Responses,                   // Negotiation responses were queued by the decoder
     Flush,
     Close,
     SetCompression(CompressionAlgorithm), // Set compression algorithm
+    StartTls(Option<TelnetOption>), // Send FOLLOWS and hand over the transport (MCCP to re-arm)
 }
 
 /// Read command for terminal input
-enum ReadCommand {
-    ReadNext(tokio::sync::oneshot::Sender<ConnectionResult<Option<TerminalEvent>>>),
+enum ReadCommand<R, W> {
+    ReadNext(oneshot::Sender<ConnectionResult<Option<TerminalEvent>>>),
     Close,
     SetCompression(CompressionAlgorithm), // Set decompression algorithm
+    StartTls(StartTlsUpgrade<R, W>),      // Upgrade the transport once START_TLS is agreed
+}
+
+/// Reason a command was not accepted by the write queue
+enum PushError {
+    /// The writer has stopped
+    Closed,
+    /// The queue is full under [`OverflowPolicy::Error`]
+    Full,
+    /// The queue was full under [`OverflowPolicy::Disconnect`] and is now closing
+    Disconnected,
+}
+
+/// Bounded queue of write commands consumed by the writer task
+///
+/// Only output sent through [`SplitTerminalConnection::send`] counts against
+/// the capacity. Control commands and protocol traffic are always accepted, so
+/// negotiation replies can never be dropped or block the reader task.
+struct WriteQueue {
+    state: std::sync::Mutex<WriteQueueState>,
+    capacity: usize,
+    overflow: OverflowPolicy,
+    /// Signalled when a command is queued or the last sender is dropped
+    readable: Notify,
+    /// Signalled when output leaves the queue or the queue is closed
+    writable: Notify,
+}
+
+#[derive(Default)]
+struct WriteQueueState {
+    /// Queued commands, flagged when they count against the capacity
+    commands: VecDeque<(WriteCommand, bool)>,
+    /// Number of queued commands counting against the capacity
+    depth: usize,
+    /// Number of live [`WriteSender`] handles
+    senders: usize,
+    /// No further commands are accepted
+    closed: bool,
+}
+
+impl WriteQueue {
+    fn new(capacity: usize, overflow: OverflowPolicy) -> Self {
+        Self {
+            state: std::sync::Mutex::default(),
+            capacity: capacity.max(1),
+            overflow,
+            readable: Notify::new(),
+            writable: Notify::new(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, WriteQueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue a command without applying the capacity
+    fn push_control(&self, cmd: WriteCommand) -> Result<(), PushError> {
+        let mut state = self.lock();
+        if state.closed {
+            return Err(PushError::Closed);
+        }
+        state.commands.push_back((cmd, false));
+        drop(state);
+        self.readable.notify_one();
+        Ok(())
+    }
+
+    /// Queue output, applying the overflow policy while the queue is full
+    async fn push(&self, cmd: WriteCommand) -> Result<(), PushError> {
+        loop {
+            let writable = self.writable.notified();
+            tokio::pin!(writable);
+            writable.as_mut().enable();
+            {
+                let mut state = self.lock();
+                if state.closed {
+                    return Err(PushError::Closed);
+                }
+                if state.depth >= self.capacity {
+                    match self.overflow {
+                        OverflowPolicy::Wait => {}
+                        OverflowPolicy::DropOldest => {
+                            if let Some(index) = state.commands.iter().position(|(_, out)| *out) {
+                                state.commands.remove(index);
+                                state.depth -= 1;
+                            }
+                        }
+                        OverflowPolicy::Disconnect => {
+                            state.closed = true;
+                            state.commands.retain(|(_, out)| !*out);
+                            state.depth = 0;
+                            state.commands.push_back((WriteCommand::Close, false));
+                            drop(state);
+                            self.readable.notify_one();
+                            self.writable.notify_waiters();
+                            return Err(PushError::Disconnected);
+                        }
+                        OverflowPolicy::Error => return Err(PushError::Full),
+                    }
+                }
+                if state.depth < self.capacity {
+                    state.commands.push_back((cmd, true));
+                    state.depth += 1;
+                    drop(state);
+                    self.readable.notify_one();
+                    return Ok(());
+                }
+            }
+            writable.await;
+        }
+    }
+
+    /// Take the next command, or `None` once every sender is gone or the queue is closed
+    async fn recv(&self) -> Option<WriteCommand> {
+        loop {
+            let readable = self.readable.notified();
+            tokio::pin!(readable);
+            readable.as_mut().enable();
+            {
+                let mut state = self.lock();
+                if let Some((cmd, out)) = state.commands.pop_front() {
+                    if out {
+                        state.depth -= 1;
+                        drop(state);
+                        self.writable.notify_one();
+                    }
+                    return Some(cmd);
+                }
+                if state.closed || state.senders == 0 {
+                    return None;
+                }
+            }
+            readable.await;
+        }
+    }
+
+    /// Stop accepting commands and release any waiting senders
+    fn close(&self) {
+        let mut state = self.lock();
+        state.closed = true;
+        state.commands.clear();
+        state.depth = 0;
+        drop(state);
+        self.writable.notify_waiters();
+    }
+
+    fn depth(&self) -> usize {
+        self.lock().depth
+    }
+}
+
+/// Sending handle to a [`WriteQueue`]
+///
+/// The writer task stops once the last handle is dropped and the queue is empty.
+struct WriteSender {
+    queue: Arc<WriteQueue>,
+}
+
+impl WriteSender {
+    fn new(queue: Arc<WriteQueue>) -> Self {
+        queue.lock().senders += 1;
+        Self { queue }
+    }
+
+    /// Queue a control command or protocol traffic
+    fn send(&self, cmd: WriteCommand) -> Result<(), PushError> {
+        self.queue.push_control(cmd)
+    }
+
+    /// Queue output subject to the overflow policy
+    async fn send_output(&self, cmd: WriteCommand) -> Result<(), PushError> {
+        self.queue.push(cmd).await
+    }
+}
+
+impl Clone for WriteSender {
+    fn clone(&self) -> Self {
+        Self::new(Arc::clone(&self.queue))
+    }
+}
+
+impl Drop for WriteSender {
+    fn drop(&mut self) {
+        let mut state = self.queue.lock();
+        state.senders -= 1;
+        let last = state.senders == 0;
+        drop(state);
+        if last {
+            self.queue.readable.notify_one();
+        }
+    }
+}
+
+impl std::fmt::Debug for WriteSender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WriteSender")
+            .field("depth", &self.queue.depth())
+            .field("capacity", &self.queue.capacity)
+            .finish()
+    }
+}
+
+/// MCCP negotiation state observed by the reader task
+///
+/// MCCP2 compresses the stream written by the side that sent `WILL`, MCCP3 the
+/// stream written by the side that sent `DO`.
+#[derive(Debug, Default)]
+struct MccpState {
+    /// MCCP2 enabled on the peer's side; its output may be compressed
+    compress2_remote: bool,
+    /// MCCP3 enabled on our side; the peer's output may be compressed
+    compress3_local: bool,
+    /// Option our output is compressed under, if any
+    output: Option<TelnetOption>,
+}
+
+impl MccpState {
+    /// Returns true if the peer may start compressing with `option`
+    fn accepts_input(&self, option: TelnetOption) -> bool {
+        match option {
+            TelnetOption::Compress2 => self.compress2_remote,
+            TelnetOption::Compress3 => self.compress3_local,
+            _ => false,
+        }
+    }
+}
+
+/// Encode `IAC SB <option> IAC SE`, the marker after which MCCP output is compressed
+fn compression_marker(option: TelnetOption) -> Bytes {
+    let mut marker = BytesMut::new();
+    let frame = TelnetFrame::Subnegotiate(TelnetArgument::Unknown(option, BytesMut::new()));
+    TelnetCodec::new()
+        .encode(frame, &mut marker)
+        .expect("empty subnegotiation always encodes");
+    marker.freeze()
+}
+
+/// Encode `IAC SB START_TLS FOLLOWS IAC SE`
+fn start_tls_follows() -> Bytes {
+    let mut follows = BytesMut::new();
+    let frame = TelnetFrame::Subnegotiate(TelnetArgument::StartTLSFollows);
+    TelnetCodec::new()
+        .encode(frame, &mut follows)
+        .expect("FOLLOWS always encodes");
+    follows.freeze()
+}
+
+/// Wait for the error the writer task stopped with
+///
+/// Never completes if the writer stops without one.
+async fn writer_failure(
+    failed: &mut Option<oneshot::Receiver<ConnectionError>>,
+) -> ConnectionError {
+    if let Some(rx) = failed.as_mut() {
+        let result = rx.await;
+        *failed = None;
+        if let Ok(error) = result {
+            return error;
+        }
+    }
+    std::future::pending().await
+}
+
+/// START_TLS progress observed by the reader task
+///
+/// The write half travels from the writer task to the reader task through
+/// `detached` once `FOLLOWS` is sent, and back through `attached` once the
+/// handshake is done. If the writer fails to send `FOLLOWS`, its error takes
+/// the place of the write half.
+struct StartTlsState<R, W> {
+    /// Set by [`SplitTerminalConnection::enable_start_tls`], taken by the upgrade
+    upgrade: Option<StartTlsUpgrade<R, W>>,
+    detached: Option<oneshot::Receiver<std::io::Result<W>>>,
+    attached: Option<oneshot::Sender<W>>,
+    /// Our `FOLLOWS` was queued
+    follows_sent: bool,
+    /// The peer's `FOLLOWS` was received; the transport must be upgraded before reading on
+    ready: bool,
+}
+
+impl<R, W> StartTlsState<R, W> {
+    fn new(detached: oneshot::Receiver<std::io::Result<W>>, attached: oneshot::Sender<W>) -> Self {
+        Self {
+            upgrade: None,
+            detached: Some(detached),
+            attached: Some(attached),
+            follows_sent: false,
+            ready: false,
+        }
+    }
+}
+
+/// Reader task's view of the transport: decompressed, then decoded
+type EventReader<R, C> = FramedRead<CompressionReader<KeepaliveReader<R>>, SharedCodec<C>>;
+
+/// Handle to the codec shared by the reader and writer tasks
+///
+/// The lock is only held for a single decode or encode call. The reader's
+/// handle carries a sender that wakes the writer whenever decoding left
+/// negotiation responses in the codec's queue, records incoming traffic for
+/// keepalive and charges it against the rate limits.
+struct SharedCodec<C> {
+    codec: Arc<std::sync::Mutex<C>>,
+    responses_tx: Option<WriteSender>,
+    keepalive: Option<Arc<Keepalive>>,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl<C> SharedCodec<C> {
+    fn lock(&self) -> std::sync::MutexGuard<'_, C> {
+        self.codec.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record bytes about to be decoded as traffic from the peer
+    ///
+    /// `FramedRead` only decodes after reading new bytes or returning a frame,
+    /// so a non-empty buffer means the peer sent something recently.
+    fn record_input(&self, src: &BytesMut) {
+        if let Some(keepalive) = &self.keepalive
+            && !src.is_empty()
+        {
+            keepalive.record(src);
+        }
+        if let Some(limiter) = &self.limiter {
+            limiter.charge_input(src);
+        }
+    }
+}
+
+impl<C> SharedCodec<C>
+where
+    C: TelnetResponder,
+{
+    /// Wake the writer if the last decode queued negotiation responses
+    ///
+    /// `consumed` is the number of bytes decoded. Requests over a negotiation
+    /// limit set to drop them never reach the queue; the codec's
+    /// [`NegotiationGate`] ignores them before the option state changes.
+    fn notify_responses(&self, codec: &C, consumed: usize) {
+        if let Some(limiter) = &self.limiter {
+            limiter.consumed(consumed);
+        }
+        if let Some(tx) = &self.responses_tx
+            && codec.has_pending_responses()
+        {
+            let _ = tx.send(WriteCommand::Responses);
+        }
+    }
+}
+
+impl<C> Decoder for SharedCodec<C>
+where
+    C: Decoder + TelnetResponder,
+{
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.record_input(src);
+        let received = src.len();
+        let mut codec = self.lock();
+        let result = codec.decode(src);
+        self.notify_responses(&codec, received.saturating_sub(src.len()));
+        result
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.record_input(src);
+        let received = src.len();
+        let mut codec = self.lock();
+        let result = codec.decode_eof(src);
+        self.notify_responses(&codec, received.saturating_sub(src.len()));
+        result
+    }
+}
+
+impl<C> Encoder<TerminalCommand> for SharedCodec<C>
+where
+    C: Encoder<TerminalCommand>,
+{
+    type Error = C::Error;
+
+    fn encode(&mut self, item: TerminalCommand, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.lock().encode(item, dst)
+    }
 }
 
 /// Terminal-specific split connection
@@ -101,13 +534,19 @@ pub struct SplitTerminalConnection<R, W, C>
 where
     R: AsyncRead + Unpin + Send + 'static,
     W: AsyncWrite + Unpin + Send + 'static,
-    C: Encoder<TerminalCommand> + Clone + Send + 'static,
+    C: Encoder<TerminalCommand> + Send + 'static,
 {
-    /// Command sender for write operations
-    write_tx: mpsc::UnboundedSender<WriteCommand>,
+    /// Codec shared by the reader and writer tasks
+    codec: Arc<std::sync::Mutex<C>>,
+
+    /// Command queue for write operations
+    write_tx: WriteSender,
 
     /// Command sender for read operations
-    read_tx: mpsc::UnboundedSender<ReadCommand>,
+    ///
+    /// Left unbounded: every `ReadNext` is awaited by its caller, so the queue
+    /// never holds more requests than there are concurrent readers.
+    read_tx: mpsc::UnboundedSender<ReadCommand<R, W>>,
 
     /// Flush strategy
     flush_strategy: Arc<RwLock<FlushStrategy>>,
@@ -118,20 +557,17 @@ where
     /// Writer task handle
     writer_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
 
-    /// Phantom data
-    _phantom: std::marker::PhantomData<(R, W, C)>,
+    /// Phantom data; the halves live in the worker tasks, so handles are
+    /// `Send` and `Sync` whatever `R` and `W` are
+    _phantom: std::marker::PhantomData<fn() -> (R, W, C)>,
 }
 
 impl<R, W, C> SplitTerminalConnection<R, W, C>
 where
     R: AsyncRead + Unpin + Send + 'static,
     W: AsyncWrite + Unpin + Send + 'static,
-    C: tokio_util::codec::Decoder<Item = TerminalEvent>
-        + Encoder<TerminalCommand>
-        + Clone
-        + Send
-        + 'static,
-    <C as tokio_util::codec::Decoder>::Error: std::error::Error + Send + Sync + 'static,
+    C: Decoder<Item = TerminalEvent> + Encoder<TerminalCommand> + TelnetResponder + Send + 'static,
+    <C as Decoder>::Error: std::error::Error + Send + Sync + 'static,
     <C as Encoder<TerminalCommand>>::Error: std::error::Error + Send + Sync + 'static,
 {
     /// Create a new terminal split connection with compression support
@@ -139,33 +575,106 @@ where
     /// The reader and writer are wrapped with `CompressionReader` and `CompressionWriter`
     /// respectively, starting with `Algorithm::None`. Compression can be enabled later
     /// via `set_compression_algorithm`.
-    pub fn new(reader: R, writer: W, codec_read: C, codec_write: C) -> Self
+    ///
+    /// The codec is shared by both halves, so options negotiated while reading
+    /// are visible when writing and vice versa.
+    pub fn new(reader: R, writer: W, codec: C) -> Self
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        Self::new_with_config(reader, writer, codec, &ConnectionConfig::default())
+    }
+
+    /// Create a new terminal split connection using the write queue settings of `config`
+    ///
+    /// The write queue holds up to [`ConnectionConfig::buffer_size`] commands and
+    /// applies [`ConnectionConfig::overflow_policy`] once full. With
+    /// [`ConnectionConfig::keepalive`] set, idle peers are probed every
+    /// [`ConnectionConfig::keepalive_interval`] and reads fail with
+    /// [`ConnectionError::KeepaliveTimeout`] once a probe goes unanswered.
+    /// Input is limited by [`ConnectionConfig::rate_limit`], if set.
+    pub fn new_with_config(reader: R, writer: W, codec: C, config: &ConnectionConfig) -> Self
     where
         R: AsyncRead + Unpin + Send + 'static,
         W: AsyncWrite + Unpin + Send + 'static,
     {
-        let (write_tx, write_rx) = mpsc::unbounded_channel();
+        let mut codec = codec;
+        let limiter = config
+            .rate_limit
+            .as_ref()
+            .map(|limit| Arc::new(RateLimiter::new(limit)));
+        if let Some(limiter) = &limiter {
+            // Ignore requests over the negotiation limit before they change
+            // any option state, so both sides still agree on every option
+            let limiter = Arc::clone(limiter);
+            codec.set_negotiation_gate(Some(NegotiationGate::new(move |_| {
+                !limiter.drops_negotiations()
+            })));
+        }
+        let codec = Arc::new(std::sync::Mutex::new(codec));
+        let write_queue = Arc::new(WriteQueue::new(config.buffer_size, config.overflow_policy));
+        let write_tx = WriteSender::new(Arc::clone(&write_queue));
         let (read_tx, read_rx) = mpsc::unbounded_channel();
+        let (detached_tx, detached_rx) = oneshot::channel();
+        let (attached_tx, attached_rx) = oneshot::channel();
+        let (failed_tx, failed_rx) = oneshot::channel();
         let flush_strategy = Arc::new(RwLock::new(FlushStrategy::default()));
+        let keepalive = Keepalive::new(config).map(Arc::new);
 
         // Wrap reader and writer with compression support
-        let compressed_reader = CompressionReader::new(reader, CompressionAlgorithm::None);
+        let compressed_reader = CompressionReader::new(
+            KeepaliveReader::new(reader, keepalive.clone()),
+            CompressionAlgorithm::None,
+        );
         let compressed_writer = CompressionWriter::new(writer, CompressionAlgorithm::None);
 
         // Spawn reader task
+        let codec_read = SharedCodec {
+            codec: Arc::clone(&codec),
+            responses_tx: Some(write_tx.clone()),
+            keepalive: keepalive.clone(),
+            limiter: limiter.clone(),
+        };
         let reader_handle = tokio::spawn(Self::reader_task(
             FramedRead::new(compressed_reader, codec_read),
             read_rx,
+            write_tx.clone(),
+            keepalive.clone(),
+            limiter,
+            StartTlsState::new(detached_rx, attached_tx),
+            failed_rx,
         ));
 
+        // Spawn keepalive task; it writes to the queue directly so it doesn't
+        // keep the writer alive once every handle is gone
+        if let Some(keepalive) = keepalive.clone() {
+            let queue = Arc::clone(&write_queue);
+            tokio::spawn(async move {
+                keepalive
+                    .run(|probe| queue.push_control(WriteCommand::Raw(probe, true)).is_ok())
+                    .await;
+            });
+        }
+
         // Spawn writer task
+        let codec_write = SharedCodec {
+            codec: Arc::clone(&codec),
+            responses_tx: None,
+            keepalive: None,
+            limiter: None,
+        };
         let writer_handle = tokio::spawn(Self::writer_task(
             FramedWrite::new(compressed_writer, codec_write),
-            write_rx,
+            write_queue,
             Arc::clone(&flush_strategy),
+            keepalive,
+            (detached_tx, attached_rx),
+            failed_tx,
         ));
 
         Self {
+            codec,
             write_tx,
             read_tx,
             flush_strategy,
@@ -180,34 +689,99 @@ where
     /// The stream is split and each half is wrapped with compression support.
     pub fn from_stream<S>(
         stream: S,
-        codec_read: C,
-        codec_write: C,
+        codec: C,
+    ) -> SplitTerminalConnection<tokio::io::ReadHalf<S>, tokio::io::WriteHalf<S>, C>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (reader, writer) = tokio::io::split(stream);
+        SplitTerminalConnection::new(reader, writer, codec)
+    }
+
+    /// Create from a bidirectional stream using the write queue settings of `config`
+    pub fn from_stream_with_config<S>(
+        stream: S,
+        codec: C,
+        config: &ConnectionConfig,
     ) -> SplitTerminalConnection<tokio::io::ReadHalf<S>, tokio::io::WriteHalf<S>, C>
     where
         S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
     {
         let (reader, writer) = tokio::io::split(stream);
-        SplitTerminalConnection::new(reader, writer, codec_read, codec_write)
+        SplitTerminalConnection::new_with_config(reader, writer, codec, config)
     }
 
     /// Reader task with compression support
+    ///
+    /// A read in progress when the writer task fails returns the writer's
+    /// error, and the reader stops with it.
     async fn reader_task(
-        mut reader: FramedRead<CompressionReader<R>, C>,
-        mut rx: mpsc::UnboundedReceiver<ReadCommand>,
+        mut reader: EventReader<R, C>,
+        mut rx: mpsc::UnboundedReceiver<ReadCommand<R, W>>,
+        write_tx: WriteSender,
+        keepalive: Option<Arc<Keepalive>>,
+        limiter: Option<Arc<RateLimiter>>,
+        mut start_tls: StartTlsState<R, W>,
+        failed: oneshot::Receiver<ConnectionError>,
     ) {
-        while let Some(cmd) = rx.recv().await {
+        let mut failed = Some(failed);
+        let mut mccp = MccpState::default();
+        // Event held back while a rate limit warning is delivered
+        let mut pending = None;
+        loop {
+            // Keep receiving while the application isn't reading, so keepalive sees answers
+            let cmd = if reader.get_ref().get_ref().can_read_ahead() {
+                tokio::select! {
+                    cmd = rx.recv() => cmd,
+                    _ = reader.get_mut().get_mut().read_ahead() => continue,
+                }
+            } else {
+                rx.recv().await
+            };
+            let Some(cmd) = cmd else {
+                break;
+            };
             match cmd {
                 ReadCommand::ReadNext(response_tx) => {
-                    let result = match reader.next().await {
-                        Some(Ok(item)) => Ok(Some(item)),
-                        Some(Err(e)) => Err(ConnectionError::Codec(e.to_string())),
-                        None => Ok(None),
+                    let result = match pending.take() {
+                        Some(event) => Ok(Some(event)),
+                        None => tokio::select! {
+                            result = Self::read_event(
+                                &mut reader,
+                                &write_tx,
+                                &mut mccp,
+                                &mut start_tls,
+                                keepalive.as_deref(),
+                                limiter.as_deref(),
+                                &mut pending,
+                            ) => result,
+                            error = writer_failure(&mut failed) => {
+                                let _ = response_tx.send(Err(error));
+                                break;
+                            }
+                        },
                     };
+                    if start_tls.ready {
+                        // The peer's FOLLOWS is returned once the handshake is done
+                        match Self::upgrade_reader(reader, &mut start_tls, keepalive.clone()).await
+                        {
+                            Ok(upgraded) => reader = upgraded,
+                            Err(e) => {
+                                let _ = response_tx.send(Err(ConnectionError::StartTls(e)));
+                                break;
+                            }
+                        }
+                    }
                     let _ = response_tx.send(result);
                 }
+                ReadCommand::StartTls(upgrade) => start_tls.upgrade = Some(upgrade),
                 ReadCommand::SetCompression(algorithm) => {
-                    // Switch decompression algorithm
-                    if let Err(e) = reader.get_mut().switch_algorithm(algorithm) {
+                    // Switch decompression algorithm, including bytes already read ahead
+                    let pending = reader.read_buffer_mut().split();
+                    if let Err(e) = reader
+                        .get_mut()
+                        .switch_algorithm_with_pending(algorithm, &pending)
+                    {
                         eprintln!("Failed to switch decompression algorithm: {:?}", e);
                     }
                 }
@@ -216,47 +790,422 @@ where
         }
     }
 
+    /// Read the next event, applying keepalive and rate limits
+    ///
+    /// Events dropped by a rate limit are skipped. A rate limit warning is
+    /// returned in place of the event that caused it, which is left in `pending`.
+    async fn read_event(
+        reader: &mut EventReader<R, C>,
+        write_tx: &WriteSender,
+        mccp: &mut MccpState,
+        start_tls: &mut StartTlsState<R, W>,
+        keepalive: Option<&Keepalive>,
+        limiter: Option<&RateLimiter>,
+        pending: &mut Option<TerminalEvent>,
+    ) -> ConnectionResult<Option<TerminalEvent>> {
+        if let Some(kind) = limiter.and_then(RateLimiter::disconnected) {
+            return Err(ConnectionError::RateLimited(kind));
+        }
+        loop {
+            // `None` if the peer stopped answering keepalive probes
+            let next = match keepalive {
+                Some(keepalive) => tokio::select! {
+                    next = reader.next() => Some(next),
+                    _ = keepalive.dead() => None,
+                },
+                None => Some(reader.next().await),
+            };
+            let event = match next {
+                Some(Some(Ok(event))) => event,
+                Some(Some(Err(e))) => return Err(Self::decode_error(&e)),
+                Some(None) => return Ok(None),
+                None => return Err(ConnectionError::KeepaliveTimeout),
+            };
+            Self::track_compression(reader, write_tx, mccp, &event)?;
+            if Self::track_start_tls(write_tx, mccp, start_tls, &event) {
+                // Nothing after the peer's FOLLOWS may be decoded before the upgrade
+                return Ok(Some(event));
+            }
+
+            let Some(limiter) = limiter else {
+                return Ok(Some(event));
+            };
+            match limiter.check(&event) {
+                Verdict::Pass => return Ok(Some(event)),
+                Verdict::Delay(wait) => {
+                    // Nothing is read meanwhile, so the peer sees TCP backpressure
+                    tokio::time::sleep(wait).await;
+                    return Ok(Some(event));
+                }
+                Verdict::Drop => continue,
+                Verdict::Warn(kind) => {
+                    *pending = Some(event);
+                    return Ok(Some(TerminalEvent::RateLimited(kind)));
+                }
+                Verdict::Disconnect(kind) => return Err(ConnectionError::RateLimited(kind)),
+            }
+        }
+    }
+
+    /// Convert a decode error, keeping violated codec limits typed
+    ///
+    /// The codec stack wraps the Telnet codec's errors, so the source chain is
+    /// searched for the [`TelnetCodecError`] that caused it.
+    fn decode_error(error: &<C as Decoder>::Error) -> ConnectionError {
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(error);
+        while let Some(error) = source {
+            if let Some(telnet) = error.downcast_ref::<TelnetCodecError>()
+                && telnet.is_limit_exceeded()
+            {
+                return ConnectionError::LimitExceeded(telnet.clone());
+            }
+            source = error.source();
+        }
+        ConnectionError::Codec(error.to_string())
+    }
+
+    /// Apply MCCP2/MCCP3 state changes carried by a decoded event
+    ///
+    /// This runs before the next event is decoded, so the read buffer holds
+    /// exactly the bytes that follow a compression start marker. Failing to
+    /// start decompression fails the read with [`ConnectionError::Io`].
+    fn track_compression(
+        reader: &mut EventReader<R, C>,
+        write_tx: &WriteSender,
+        mccp: &mut MccpState,
+        event: &TerminalEvent,
+    ) -> std::io::Result<()> {
+        match event {
+            &TerminalEvent::OptionChanged {
+                option,
+                side,
+                enabled,
+            } => {
+                // Our output is compressed under local MCCP2 or remote MCCP3
+                let output = match (option, side) {
+                    (TelnetOption::Compress2, TelnetSide::Local) => true,
+                    (TelnetOption::Compress2, TelnetSide::Remote) => {
+                        mccp.compress2_remote = enabled;
+                        false
+                    }
+                    (TelnetOption::Compress3, TelnetSide::Local) => {
+                        mccp.compress3_local = enabled;
+                        false
+                    }
+                    (TelnetOption::Compress3, TelnetSide::Remote) => true,
+                    _ => false,
+                };
+                if !output {
+                    return Ok(());
+                }
+                mccp.output = enabled.then_some(option);
+                if enabled {
+                    let _ = write_tx.send(WriteCommand::Raw(compression_marker(option), false));
+                    let _ = write_tx.send(WriteCommand::SetCompression(CompressionAlgorithm::Zlib));
+                } else {
+                    let _ = write_tx.send(WriteCommand::SetCompression(CompressionAlgorithm::None));
+                }
+            }
+            TerminalEvent::CompressionStarted(option) if mccp.accepts_input(*option) => {
+                let pending = reader.read_buffer_mut().split();
+                reader
+                    .get_mut()
+                    .switch_algorithm_with_pending(CompressionAlgorithm::Zlib, &pending)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Apply START_TLS negotiation carried by a decoded event
+    ///
+    /// Queues our `FOLLOWS` once the peer agreed to START_TLS or sent its own,
+    /// and returns true once the peer's `FOLLOWS` was received.
+    fn track_start_tls(
+        write_tx: &WriteSender,
+        mccp: &MccpState,
+        start_tls: &mut StartTlsState<R, W>,
+        event: &TerminalEvent,
+    ) -> bool {
+        if start_tls.upgrade.is_none() {
+            return false;
+        }
+        let follows = match event {
+            TerminalEvent::OptionChanged {
+                option: TelnetOption::StartTLS,
+                side: TelnetSide::Remote,
+                enabled: true,
+            } => false,
+            TerminalEvent::Subnegotiation(TelnetArgument::StartTLSFollows) => true,
+            _ => return false,
+        };
+        if !start_tls.follows_sent {
+            start_tls.follows_sent = true;
+            let _ = write_tx.send(WriteCommand::StartTls(mccp.output));
+        }
+        start_tls.ready = follows;
+        follows
+    }
+
+    /// Hand the transport to the START_TLS upgrade and continue reading from its result
+    ///
+    /// Waits for the writer to send `FOLLOWS` and release its half, then runs
+    /// the upgrade on both halves and the bytes read ahead of the decoder.
+    async fn upgrade_reader(
+        reader: EventReader<R, C>,
+        start_tls: &mut StartTlsState<R, W>,
+        keepalive: Option<Arc<Keepalive>>,
+    ) -> std::io::Result<EventReader<R, C>> {
+        start_tls.ready = false;
+        let (Some(upgrade), Some(detached), Some(attached)) = (
+            start_tls.upgrade.take(),
+            start_tls.detached.take(),
+            start_tls.attached.take(),
+        ) else {
+            return Err(std::io::Error::other("START_TLS already completed"));
+        };
+        if reader.get_ref().algorithm() != CompressionAlgorithm::None {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "peer sent FOLLOWS inside a compressed stream",
+            ));
+        }
+        let writer = detached
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))??;
+
+        // Bytes the codec has not decoded yet precede those the readers hold back
+        let parts = reader.into_parts();
+        let (inner, unread) = parts.io.into_inner_with_pending();
+        let (inner, ahead) = inner.into_parts();
+        let mut prefix = parts.read_buf;
+        prefix.extend_from_slice(&unread);
+        prefix.extend_from_slice(&ahead);
+
+        let (inner, writer) = upgrade(StartTlsStream::new(prefix, inner, writer)).await?;
+        attached
+            .send(writer)
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        Ok(FramedRead::new(
+            CompressionReader::new(
+                KeepaliveReader::new(inner, keepalive),
+                CompressionAlgorithm::None,
+            ),
+            parts.codec,
+        ))
+    }
+
     /// Writer task with compression support
+    ///
+    /// Output is encoded into the write buffer and flushed according to the
+    /// current [`FlushStrategy`], or when the sender forces a flush. Failing to
+    /// answer the peer's negotiation or to finish START_TLS is sent to the
+    /// reader through `failed`, which returns it from the next read.
     async fn writer_task(
-        mut writer: FramedWrite<CompressionWriter<W>, C>,
-        mut rx: mpsc::UnboundedReceiver<WriteCommand>,
-        _flush_strategy: Arc<RwLock<FlushStrategy>>,
+        mut writer: FramedWrite<CompressionWriter<W>, SharedCodec<C>>,
+        queue: Arc<WriteQueue>,
+        flush_strategy: Arc<RwLock<FlushStrategy>>,
+        keepalive: Option<Arc<Keepalive>>,
+        handoff: (oneshot::Sender<std::io::Result<W>>, oneshot::Receiver<W>),
+        failed: oneshot::Sender<ConnectionError>,
     ) {
-        while let Some(cmd) = rx.recv().await {
-            match cmd {
-                WriteCommand::Send(item, force_flush) => {
-                    // Send by reference since codec implements Encoder<&TerminalCommand>
-                    if let Err(e) = writer.send(item).await {
-                        eprintln!("Write error: {:?}", e);
-                        break;
-                    }
-                    if force_flush {
+        let mut handoff = Some(handoff);
+        // When buffered output must be flushed under `FlushStrategy::Batched`
+        let mut deadline: Option<Instant> = None;
+        loop {
+            let cmd = match deadline {
+                Some(at) => tokio::select! {
+                    cmd = queue.recv() => cmd,
+                    _ = tokio::time::sleep_until(at) => {
+                        deadline = None;
                         if let Err(e) = writer.flush().await {
                             eprintln!("Flush error: {:?}", e);
                             break;
                         }
+                        continue;
+                    }
+                },
+                None => queue.recv().await,
+            };
+            let Some(cmd) = cmd else {
+                break;
+            };
+            // Queued negotiation responses go out ahead of anything written after them;
+            // the peer can't be answered correctly anymore if they fail to encode
+            if let Err(e) = Self::write_responses(&mut writer) {
+                let _ = failed.send(ConnectionError::Codec(e.to_string()));
+                break;
+            }
+            let flush = match cmd {
+                WriteCommand::Send(item, force_flush) => {
+                    let start = writer.write_buffer().len();
+                    if let Err(e) = Self::encode(&mut writer, item) {
+                        eprintln!("Write error: {:?}", e);
+                        break;
                     }
+                    let strategy = *flush_strategy.read().await;
+                    force_flush || Self::should_flush(&writer, strategy, start, &mut deadline)
+                }
+                WriteCommand::Raw(bytes, force_flush) => {
+                    // Append after any frames already encoded so ordering is preserved
+                    let start = writer.write_buffer().len();
+                    writer.write_buffer_mut().extend_from_slice(&bytes);
+                    let strategy = *flush_strategy.read().await;
+                    force_flush || Self::should_flush(&writer, strategy, start, &mut deadline)
                 }
-                WriteCommand::Flush => {
+                WriteCommand::Responses | WriteCommand::Flush => true,
+                WriteCommand::SetCompression(algorithm) => {
+                    // Flush pending output so the switch lands on an exact byte boundary
                     if let Err(e) = writer.flush().await {
                         eprintln!("Flush error: {:?}", e);
                         break;
                     }
-                }
-                WriteCommand::SetCompression(algorithm) => {
-                    // Switch compression algorithm
+                    deadline = None;
                     if let Err(e) = writer.get_mut().switch_algorithm(algorithm).await {
                         eprintln!("Failed to switch compression algorithm: {:?}", e);
                     }
+                    false
+                }
+                WriteCommand::StartTls(rearm) => {
+                    deadline = None;
+                    let Some(handoff) = handoff.take() else {
+                        continue;
+                    };
+                    match Self::upgrade_writer(writer, handoff, rearm).await {
+                        Ok(upgraded) => writer = upgraded,
+                        Err(e) => {
+                            if let Some(e) = e {
+                                let _ = failed.send(ConnectionError::StartTls(e));
+                            }
+                            break;
+                        }
+                    }
+                    false
                 }
                 WriteCommand::Close => {
                     let _ = writer.flush().await;
                     break;
                 }
+            };
+            if flush {
+                deadline = None;
+                if let Err(e) = writer.flush().await {
+                    eprintln!("Flush error: {:?}", e);
+                    break;
+                }
+            }
+        }
+        queue.close();
+        if let Some(keepalive) = keepalive {
+            keepalive.stop();
+        }
+    }
+
+    /// Send `FOLLOWS`, release the transport to the reader and continue on the upgraded one
+    ///
+    /// Compressed output ends before `FOLLOWS`, so the peer reads it and the
+    /// handshake in the clear. With `rearm` set, compression restarts under
+    /// that MCCP option once the transport is upgraded.
+    ///
+    /// An error before the transport is released goes to the reader, which
+    /// returns it as [`ConnectionError::StartTls`]; the upgrade itself fails
+    /// there too. Both fail here with `None`, as does a reader that is gone.
+    /// Failing to re-arm compression fails with the error.
+    async fn upgrade_writer(
+        writer: FramedWrite<CompressionWriter<W>, SharedCodec<C>>,
+        (detached, attached): (oneshot::Sender<std::io::Result<W>>, oneshot::Receiver<W>),
+        rearm: Option<TelnetOption>,
+    ) -> Result<FramedWrite<CompressionWriter<W>, SharedCodec<C>>, Option<std::io::Error>> {
+        let (io, codec) = match Self::detach_writer(writer).await {
+            Ok(parts) => parts,
+            Err(e) => {
+                let _ = detached.send(Err(e));
+                return Err(None);
+            }
+        };
+        detached.send(Ok(io)).map_err(|_| None)?;
+
+        let upgraded = attached.await.map_err(|_| None)?;
+        let mut writer = FramedWrite::new(
+            CompressionWriter::new(upgraded, CompressionAlgorithm::None),
+            codec,
+        );
+        if let Some(option) = rearm {
+            let io = writer.get_mut();
+            io.write_all(&compression_marker(option)).await?;
+            io.flush().await?;
+            io.switch_algorithm(CompressionAlgorithm::Zlib).await?;
+        }
+        Ok(writer)
+    }
+
+    /// Flush pending output, end compression and send `FOLLOWS` on the bare transport
+    async fn detach_writer(
+        mut writer: FramedWrite<CompressionWriter<W>, SharedCodec<C>>,
+    ) -> std::io::Result<(W, SharedCodec<C>)> {
+        writer.flush().await.map_err(std::io::Error::other)?;
+        let mut parts = writer.into_parts();
+        parts
+            .io
+            .switch_algorithm(CompressionAlgorithm::None)
+            .await?;
+        parts.io.write_all(&start_tls_follows()).await?;
+        parts.io.flush().await?;
+        Ok((parts.io.into_inner(), parts.codec))
+    }
+
+    /// Encode a command into the write buffer without flushing
+    fn encode(
+        writer: &mut FramedWrite<CompressionWriter<W>, SharedCodec<C>>,
+        item: TerminalCommand,
+    ) -> Result<(), <C as Encoder<TerminalCommand>>::Error> {
+        let codec = Arc::clone(&writer.encoder().codec);
+        let mut codec = codec.lock().unwrap_or_else(|e| e.into_inner());
+        codec.encode(item, writer.write_buffer_mut())
+    }
+
+    /// Decide whether output appended to the write buffer at `start` should be flushed now
+    ///
+    /// Starts the latency timer of `FlushStrategy::Batched` if it is not already running.
+    fn should_flush(
+        writer: &FramedWrite<CompressionWriter<W>, SharedCodec<C>>,
+        strategy: FlushStrategy,
+        start: usize,
+        deadline: &mut Option<Instant>,
+    ) -> bool {
+        let buffered = writer.write_buffer();
+        match strategy {
+            FlushStrategy::Manual => buffered.len() >= writer.backpressure_boundary(),
+            FlushStrategy::Immediate => true,
+            FlushStrategy::OnNewline => {
+                buffered[start..].contains(&b'\n')
+                    || buffered.len() >= writer.backpressure_boundary()
+            }
+            FlushStrategy::OnThreshold(threshold) => buffered.len() >= threshold,
+            FlushStrategy::Batched {
+                max_bytes,
+                max_latency,
+            } => {
+                if buffered.len() >= max_bytes {
+                    return true;
+                }
+                if deadline.is_none() && !buffered.is_empty() {
+                    *deadline = Some(Instant::now() + max_latency);
+                }
+                false
             }
         }
     }
 
+    /// Move negotiation responses queued in the shared codec into the write buffer
+    fn write_responses(
+        writer: &mut FramedWrite<CompressionWriter<W>, SharedCodec<C>>,
+    ) -> Result<(), TelnetCodecError> {
+        let codec = Arc::clone(&writer.encoder().codec);
+        let mut codec = codec.lock().unwrap_or_else(|e| e.into_inner());
+        codec.flush_responses(writer.write_buffer_mut())
+    }
+
     /// Send a terminal command
     pub async fn send(
         &self,
@@ -264,7 +1213,46 @@ where
         force_flush: bool,
     ) -> ConnectionResult<()> {
         self.write_tx
-            .send(WriteCommand::Send(data.into(), force_flush))
+            .send_output(WriteCommand::Send(data.into(), force_flush))
+            .await
+            .map_err(|e| self.push_error(e))
+    }
+
+    /// Map a rejected write to the error returned to the caller
+    fn push_error(&self, error: PushError) -> ConnectionError {
+        match error {
+            PushError::Closed => ConnectionError::Closed,
+            PushError::Full => ConnectionError::QueueFull,
+            PushError::Disconnected => {
+                // The writer is already closing; stop the reader as well
+                let _ = self.read_tx.send(ReadCommand::Close);
+                ConnectionError::Closed
+            }
+        }
+    }
+
+    /// Number of outgoing commands waiting in the write queue
+    pub fn queue_depth(&self) -> usize {
+        self.write_tx.queue.depth()
+    }
+
+    /// Maximum number of outgoing commands the write queue holds
+    pub fn queue_capacity(&self) -> usize {
+        self.write_tx.queue.capacity
+    }
+
+    /// Send bytes that are already encoded for the wire
+    ///
+    /// The bytes bypass the codec and are written verbatim, in order with any
+    /// previously sent commands. This is used for protocol traffic such as
+    /// Telnet option negotiation that the codec has already serialized.
+    ///
+    /// Unlike [`send`](Self::send) this does not need to be awaited, so it can
+    /// be used while a connection is being constructed. Raw bytes do not count
+    /// against the write queue capacity and are never dropped.
+    pub fn send_raw(&self, bytes: impl Into<Bytes>, force_flush: bool) -> ConnectionResult<()> {
+        self.write_tx
+            .send(WriteCommand::Raw(bytes.into(), force_flush))
             .map_err(|_| ConnectionError::Closed)?;
         Ok(())
     }
@@ -290,6 +1278,21 @@ where
         Ok(())
     }
 
+    /// Run `f` with exclusive access to the codec shared by both halves
+    ///
+    /// Use this to query or change Telnet negotiation state. Responses the
+    /// codec queues meanwhile are written automatically; frames it returns
+    /// (e.g. from `enable_local`) must be encoded and passed to
+    /// [`send_raw`](Self::send_raw).
+    pub fn with_codec<T>(&self, f: impl FnOnce(&mut C) -> T) -> T {
+        let mut codec = self.codec.lock().unwrap_or_else(|e| e.into_inner());
+        let result = f(&mut codec);
+        if codec.has_pending_responses() {
+            let _ = self.write_tx.send(WriteCommand::Responses);
+        }
+        result
+    }
+
     /// Set flush strategy
     pub async fn set_flush_strategy(&self, strategy: FlushStrategy) {
         *self.flush_strategy.write().await = strategy;
@@ -303,7 +1306,10 @@ where
     /// Set the compression algorithm for both read and write operations
     ///
     /// This dynamically switches the compression algorithm used by the connection.
-    /// The change takes effect immediately for all subsequent data.
+    /// The change takes effect immediately for all subsequent data. No Telnet
+    /// negotiation takes place, so both peers must agree out of band; MCCP2 and
+    /// MCCP3 are instead switched automatically when negotiated (see the
+    /// [module documentation](self)).
     ///
     /// # Parameters
     ///
@@ -338,6 +1344,19 @@ where
         Ok(())
     }
 
+    /// Upgrade the transport with `upgrade` once START_TLS is negotiated
+    ///
+    /// START_TLS must also be allowed by the codec's option policy: servers
+    /// request it from the peer (`DO`), clients offer or accept it (`WILL`).
+    /// The upgrade runs at most once; see the [module documentation](self)
+    /// for the exchange. Reads return the peer's `FOLLOWS` subnegotiation
+    /// after the handshake completed, or [`ConnectionError::StartTls`] if it failed.
+    pub fn enable_start_tls(&self, upgrade: StartTlsUpgrade<R, W>) -> ConnectionResult<()> {
+        self.read_tx
+            .send(ReadCommand::StartTls(upgrade))
+            .map_err(|_| ConnectionError::Closed)
+    }
+
     /// Close the connection
     pub async fn close(&self) -> ConnectionResult<()> {
         let _ = self.write_tx.send(WriteCommand::Close);
@@ -360,10 +1379,11 @@ impl<R, W, C> Clone for SplitTerminalConnection<R, W, C>
 where
     R: AsyncRead + Unpin + Send + 'static,
     W: AsyncWrite + Unpin + Send + 'static,
-    C: Encoder<TerminalCommand> + Clone + Send + 'static,
+    C: Encoder<TerminalCommand> + Send + 'static,
 {
     fn clone(&self) -> Self {
         Self {
+            codec: Arc::clone(&self.codec),
             write_tx: self.write_tx.clone(),
             read_tx: self.read_tx.clone(),
             flush_strategy: Arc::clone(&self.flush_strategy),
@@ -0,0 +1,380 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Keepalive probes for idle connections
+//!
+//! The reader records every batch of incoming bytes, reading ahead while the
+//! application is not reading so an answer still counts. When nothing arrives for
+//! [`ConnectionConfig::keepalive_interval`] a probe is written; if the probe
+//! requires an answer and nothing arrives within
+//! [`ConnectionConfig::keepalive_timeout`], the peer is marked dead and
+//! pending reads fail with [`ConnectionError::KeepaliveTimeout`].
+//!
+//! [`ConnectionError::KeepaliveTimeout`]: crate::ConnectionError::KeepaliveTimeout
+
+use crate::{ConnectionConfig, KeepaliveProbe};
+use bytes::{Buf, Bytes, BytesMut};
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use termionix_terminal::{TelnetCodec, TelnetFrame, TelnetOption};
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tokio_util::codec::Encoder;
+
+/// Interpret As Command, the byte starting every Telnet command
+const IAC: u8 = 0xFF;
+
+/// Bytes a [`KeepaliveReader`] asks the transport for at a time while reading ahead
+const READ_AHEAD: usize = 4096;
+
+/// Bytes a [`KeepaliveReader`] holds before it stops reading ahead
+const MAX_READ_AHEAD: usize = 64 * 1024;
+
+/// Incoming traffic seen by the reader
+#[derive(Debug)]
+struct Activity {
+    /// When bytes were last received
+    last: Instant,
+    /// Whether the peer has sent any Telnet command
+    telnet_peer: bool,
+}
+
+/// Keepalive state shared by the reader, the writer and the probe task
+#[derive(Debug)]
+pub(crate) struct Keepalive {
+    interval: Duration,
+    timeout: Duration,
+    probe: KeepaliveProbe,
+    activity: std::sync::Mutex<Activity>,
+    dead: AtomicBool,
+    dead_notify: Notify,
+    stop: Notify,
+}
+
+impl Keepalive {
+    /// Create the keepalive state for `config`, or `None` if keepalive is disabled
+    pub(crate) fn new(config: &ConnectionConfig) -> Option<Self> {
+        config.keepalive.then(|| Self {
+            interval: config.keepalive_interval,
+            timeout: config.keepalive_timeout,
+            probe: config.keepalive_probe,
+            activity: std::sync::Mutex::new(Activity {
+                last: Instant::now(),
+                telnet_peer: false,
+            }),
+            dead: AtomicBool::new(false),
+            dead_notify: Notify::new(),
+            stop: Notify::new(),
+        })
+    }
+
+    fn activity(&self) -> std::sync::MutexGuard<'_, Activity> {
+        self.activity.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record bytes received from the peer
+    pub(crate) fn record(&self, received: &[u8]) {
+        let mut activity = self.activity();
+        activity.last = Instant::now();
+        if !activity.telnet_peer && received.contains(&IAC) {
+            activity.telnet_peer = true;
+        }
+    }
+
+    /// Record that bytes arrived from the peer, before they are decoded
+    fn touch(&self) {
+        self.activity().last = Instant::now();
+    }
+
+    /// Returns true once the peer failed to answer a probe
+    pub(crate) fn is_dead(&self) -> bool {
+        self.dead.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the peer is marked dead
+    pub(crate) async fn dead(&self) {
+        loop {
+            // Created before the check so a concurrent notification isn't missed
+            let notified = self.dead_notify.notified();
+            if self.is_dead() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Stop the probe task
+    pub(crate) fn stop(&self) {
+        self.stop.notify_one();
+    }
+
+    /// Encoded probe to send next, and whether the peer must answer it
+    ///
+    /// The probe bypasses the connection's codec; this is safe because
+    /// TIMING-MARK never changes negotiation state.
+    fn next_probe(&self, telnet_peer: bool) -> (Bytes, bool) {
+        let (frame, expects_answer) = match self.probe {
+            KeepaliveProbe::Auto if telnet_peer => {
+                (TelnetFrame::Do(TelnetOption::TimingMark), true)
+            }
+            KeepaliveProbe::Auto | KeepaliveProbe::NoOperation => (TelnetFrame::NoOperation, false),
+            KeepaliveProbe::AreYouThere => (TelnetFrame::AreYouThere, true),
+            KeepaliveProbe::TimingMark => (TelnetFrame::Do(TelnetOption::TimingMark), true),
+        };
+        let mut bytes = BytesMut::new();
+        TelnetCodec::new()
+            .encode(frame, &mut bytes)
+            .expect("Telnet commands always encode");
+        (bytes.freeze(), expects_answer)
+    }
+
+    /// Probe task: sends probes on idle connections and detects dead peers
+    ///
+    /// `send_probe` writes the encoded probe and returns false once the
+    /// connection is closed, which ends the task, as does [`stop`](Self::stop).
+    pub(crate) async fn run(&self, send_probe: impl Fn(Bytes) -> bool) {
+        let mut last_probe: Option<Instant> = None;
+        let mut awaiting_answer = false;
+        loop {
+            let (last, telnet_peer) = {
+                let activity = self.activity();
+                (activity.last, activity.telnet_peer)
+            };
+            if awaiting_answer && last_probe.is_some_and(|sent| last > sent) {
+                awaiting_answer = false;
+            }
+
+            let now = Instant::now();
+            let wake = match last_probe {
+                Some(sent) if awaiting_answer => {
+                    let deadline = sent + self.timeout;
+                    if now >= deadline {
+                        self.dead.store(true, Ordering::SeqCst);
+                        self.dead_notify.notify_waiters();
+                        return;
+                    }
+                    deadline
+                }
+                _ => {
+                    // Probes without an answer restart the interval themselves
+                    let quiet_since = last_probe.map_or(last, |sent| sent.max(last));
+                    let due = quiet_since + self.interval;
+                    if now >= due {
+                        let (probe, expects_answer) = self.next_probe(telnet_peer);
+                        if !send_probe(probe) {
+                            return;
+                        }
+                        last_probe = Some(now);
+                        awaiting_answer = expects_answer;
+                        continue;
+                    }
+                    due
+                }
+            };
+
+            tokio::select! {
+                _ = tokio::time::sleep_until(wake) => {}
+                _ = self.stop.notified() => return,
+            }
+        }
+    }
+}
+
+/// Transport reader recording incoming bytes for keepalive
+///
+/// The connection only reads while the application waits for an event, so
+/// [`read_ahead`](Self::read_ahead) lets the reader task keep receiving in the
+/// meantime. Bytes read ahead are returned by the next reads; once
+/// `MAX_READ_AHEAD` bytes are waiting, the transport is left unread so the peer
+/// sees backpressure.
+#[derive(Debug)]
+pub(crate) struct KeepaliveReader<R> {
+    inner: R,
+    keepalive: Option<Arc<Keepalive>>,
+    /// Bytes read ahead that have not been returned yet
+    ahead: BytesMut,
+    /// Error met while reading ahead, returned once `ahead` is drained
+    error: Option<io::Error>,
+    /// Whether reading ahead reached the end of the stream
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> KeepaliveReader<R> {
+    pub(crate) fn new(inner: R, keepalive: Option<Arc<Keepalive>>) -> Self {
+        Self {
+            inner,
+            keepalive,
+            ahead: BytesMut::new(),
+            error: None,
+            eof: false,
+        }
+    }
+
+    /// Whether [`read_ahead`](Self::read_ahead) has anything to do
+    pub(crate) fn can_read_ahead(&self) -> bool {
+        self.keepalive.is_some()
+            && self.ahead.len() < MAX_READ_AHEAD
+            && self.error.is_none()
+            && !self.eof
+    }
+
+    /// Read the next bytes into the read-ahead buffer
+    ///
+    /// Cancel safe: bytes are only taken from the transport when the read completes.
+    pub(crate) async fn read_ahead(&mut self) {
+        self.ahead.reserve(READ_AHEAD);
+        match self.inner.read_buf(&mut self.ahead).await {
+            Ok(0) => self.eof = true,
+            Ok(_) => {
+                if let Some(keepalive) = &self.keepalive {
+                    keepalive.touch();
+                }
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Split into the transport reader and the bytes read ahead of it
+    pub(crate) fn into_parts(self) -> (R, BytesMut) {
+        (self.inner, self.ahead)
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for KeepaliveReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if !self.ahead.is_empty() {
+            let count = self.ahead.len().min(buf.remaining());
+            buf.put_slice(&self.ahead[..count]);
+            self.ahead.advance(count);
+            return Poll::Ready(Ok(()));
+        }
+        if let Some(error) = self.error.take() {
+            return Poll::Ready(Err(error));
+        }
+        if self.eof {
+            return Poll::Ready(Ok(()));
+        }
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if buf.filled().len() > filled
+            && let Some(keepalive) = &self.keepalive
+        {
+            keepalive.touch();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keepalive(probe: KeepaliveProbe) -> Keepalive {
+        Keepalive::new(&ConnectionConfig::default().with_keepalive_probe(probe)).unwrap()
+    }
+
+    #[test]
+    fn test_disabled_keepalive() {
+        let config = ConnectionConfig::default().with_keepalive(false);
+        assert!(Keepalive::new(&config).is_none());
+    }
+
+    #[test]
+    fn test_auto_probe_follows_peer() {
+        let keepalive = keepalive(KeepaliveProbe::Auto);
+        assert_eq!(
+            keepalive.next_probe(false),
+            (Bytes::from_static(&[IAC, 241]), false)
+        );
+
+        keepalive.record(b"look\r\n");
+        assert!(!keepalive.activity().telnet_peer);
+        keepalive.record(&[IAC, 251, 31]);
+        assert!(keepalive.activity().telnet_peer);
+        assert_eq!(
+            keepalive.next_probe(true),
+            (Bytes::from_static(&[IAC, 253, 6]), true)
+        );
+    }
+
+    #[test]
+    fn test_explicit_probes() {
+        assert_eq!(
+            keepalive(KeepaliveProbe::NoOperation).next_probe(true),
+            (Bytes::from_static(&[IAC, 241]), false)
+        );
+        assert_eq!(
+            keepalive(KeepaliveProbe::AreYouThere).next_probe(false),
+            (Bytes::from_static(&[IAC, 246]), true)
+        );
+        assert_eq!(
+            keepalive(KeepaliveProbe::TimingMark).next_probe(false),
+            (Bytes::from_static(&[IAC, 253, 6]), true)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_unanswered_probe_marks_peer_dead() {
+        let keepalive = Keepalive::new(
+            &ConnectionConfig::default()
+                .with_keepalive_interval(Duration::from_secs(60))
+                .with_keepalive_timeout(Duration::from_secs(30))
+                .with_keepalive_probe(KeepaliveProbe::TimingMark),
+        )
+        .unwrap();
+        let sent = std::sync::Mutex::new(Vec::new());
+        let started = Instant::now();
+
+        keepalive
+            .run(|probe| {
+                sent.lock().unwrap().push((started.elapsed(), probe));
+                true
+            })
+            .await;
+
+        assert!(keepalive.is_dead());
+        assert_eq!(started.elapsed(), Duration::from_secs(90));
+        let sent = sent.into_inner().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, Duration::from_secs(60));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_nop_probes_repeat_without_timing_out() {
+        let keepalive = keepalive(KeepaliveProbe::NoOperation);
+        let sent = std::sync::Mutex::new(0);
+
+        keepalive
+            .run(|_| {
+                let mut sent = sent.lock().unwrap();
+                *sent += 1;
+                *sent < 3
+            })
+            .await;
+
+        assert!(!keepalive.is_dead());
+        assert_eq!(*sent.lock().unwrap(), 3);
+    }
+}
@@ -52,11 +52,7 @@
 //! let codec = TerminalCodec::new(ansi_codec);
 //!
 //! // Create connection
-//! let conn = SplitTerminalConnection::from_stream(
-//!     stream,
-//!     codec.clone(),
-//!     codec,
-//! );
+//! let conn = SplitTerminalConnection::from_stream(stream, codec);
 //!
 //! // Send data (never blocks on reads)
 //! conn.send(TerminalCommand::Text("Hello\n".to_string()), true).await?;
@@ -81,13 +77,18 @@
 
 mod config;
 mod connection;
+mod keepalive;
+mod ratelimit;
 mod result;
+mod starttls;
 
 pub use config::{
-    ClientConnectionConfig, Config, ConnectionConfig, FlushStrategy, ServerConnectionConfig,
+    ClientConnectionConfig, Config, ConnectionConfig, FlushStrategy, KeepaliveProbe,
+    OverflowPolicy, RateLimit, RateLimitAction, ServerConnectionConfig,
 };
 pub use connection::SplitTerminalConnection;
 pub use result::{ConnectionError, ConnectionResult};
+pub use starttls::{StartTlsStream, StartTlsUpgrade};
 
 // Re-export terminal types for convenience
 pub use termionix_compress::{CompressionAlgorithm, CompressionStream};
@@ -95,11 +96,12 @@ pub use termionix_terminal::{
     AnsiApplicationProgramCommand, AnsiCodec, AnsiCodecError, AnsiCodecResult, AnsiConfig,
     AnsiControlCode, AnsiControlSequenceIntroducer, AnsiDeviceControlString,
     AnsiOperatingSystemCommand, AnsiParser, AnsiPrivacyMessage, AnsiSelectGraphicRendition,
-    AnsiSequence, AnsiStartOfString, Blink, Color, ColorMode, CursorPosition, Font, Ideogram,
-    Intensity, SGRParameter, Script, Segment, SegmentedString, Span, SpannedString, StyledString,
-    SubnegotiationErrorKind, TelnetArgument, TelnetCodec, TelnetCodecError, TelnetCodecResult,
-    TelnetCommand, TelnetEvent, TelnetFrame, TelnetOption, TelnetSide, TerminalBuffer,
+    AnsiSequence, AnsiStartOfString, Blink, Charset, CodecLimits, Color, ColorMode, CursorPosition,
+    Font, Ideogram, Intensity, NegotiationGate, OptionPolicy, RateLimitKind, SGRParameter, Script,
+    Segment, SegmentedString, Span, SpannedString, StyledString, SubnegotiationErrorKind,
+    TelnetArgument, TelnetCodec, TelnetCodecError, TelnetCodecResult, TelnetCommand, TelnetEvent,
+    TelnetFrame, TelnetOption, TelnetResponder, TelnetSide, TerminalBuffer, TerminalCapabilities,
     TerminalCodec, TerminalCommand, TerminalError, TerminalEvent, TerminalResult, TerminalSize,
-    Underline, gmcp, linemode, msdp, mssp, naocrd, naohts, naws, status, strip_ansi_codes,
-    terminal_word_unwrap, terminal_word_wrap,
+    Underline, atcp, gmcp, linemode, msdp, mssp, mtts, mxp, naocrd, naohts, naws, new_environ,
+    status, strip_ansi_codes, terminal_word_unwrap, terminal_word_wrap, zmp,
 };
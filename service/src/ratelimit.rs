@@ -0,0 +1,389 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Input rate limiting
+//!
+//! The reader's codec charges bytes and Telnet negotiation commands as soon as
+//! they are read; lines are charged as they are decoded. After each decoded
+//! event the reader asks [`RateLimiter::check`] what to do with it, according
+//! to the configured [`RateLimitAction`].
+
+use crate::{RateLimit, RateLimitAction};
+use bytes::BytesMut;
+use std::time::Duration;
+use termionix_terminal::{RateLimitKind, TerminalEvent};
+use tokio::time::Instant;
+
+/// Interpret As Command, the byte starting every Telnet command
+const IAC: u8 = 0xFF;
+
+/// `WILL`, `WONT`, `DO` and `DONT`
+const NEGOTIATIONS: std::ops::RangeInclusive<u8> = 251..=254;
+
+/// What the reader does with a decoded event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Verdict {
+    /// Deliver the event
+    Pass,
+    /// Deliver the event after the given time, without reading meanwhile
+    Delay(Duration),
+    /// Discard the event
+    Drop,
+    /// Report the exceeded limit, then deliver the event
+    Warn(RateLimitKind),
+    /// Fail the read
+    Disconnect(RateLimitKind),
+}
+
+/// Token bucket holding up to one second of input
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    /// Lowest balance; debt beyond one second is forgiven unless input is delayed
+    floor: f64,
+    updated: Instant,
+    /// Whether the current stretch over the limit was reported
+    warned: bool,
+}
+
+impl TokenBucket {
+    fn new(rate: u32, action: RateLimitAction, now: Instant) -> Self {
+        let rate = f64::from(rate.max(1));
+        Self {
+            rate,
+            tokens: rate,
+            floor: match action {
+                RateLimitAction::Delay => f64::NEG_INFINITY,
+                _ => -rate,
+            },
+            updated: now,
+            warned: false,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.updated = now;
+    }
+
+    fn charge(&mut self, amount: f64, now: Instant) {
+        self.refill(now);
+        self.tokens = (self.tokens - amount).max(self.floor);
+    }
+
+    fn is_exceeded(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens < 0.0
+    }
+
+    /// Time until the bucket is no longer in debt
+    fn wait(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        if self.tokens < 0.0 {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    lines: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+    negotiations: Option<TokenBucket>,
+    /// Bytes at the front of the read buffer that were already charged
+    scanned: usize,
+    /// Whether the last charged byte was an unescaped IAC
+    iac: bool,
+    /// Limit that disconnected the peer
+    disconnected: Option<RateLimitKind>,
+}
+
+impl State {
+    fn buckets_mut(&mut self) -> impl Iterator<Item = (RateLimitKind, &mut TokenBucket)> {
+        [
+            (RateLimitKind::Lines, self.lines.as_mut()),
+            (RateLimitKind::Bytes, self.bytes.as_mut()),
+            (RateLimitKind::Negotiations, self.negotiations.as_mut()),
+        ]
+        .into_iter()
+        .filter_map(|(kind, bucket)| bucket.map(|bucket| (kind, bucket)))
+    }
+}
+
+/// Rate limit state shared by the reader task and its codec
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    action: RateLimitAction,
+    state: std::sync::Mutex<State>,
+}
+
+impl RateLimiter {
+    /// Create a limiter enforcing `limit`
+    pub(crate) fn new(limit: &RateLimit) -> Self {
+        let now = Instant::now();
+        let bucket = |rate: Option<u32>| rate.map(|rate| TokenBucket::new(rate, limit.action, now));
+        Self {
+            action: limit.action,
+            state: std::sync::Mutex::new(State {
+                lines: bucket(limit.lines_per_second),
+                bytes: bucket(limit.bytes_per_second),
+                negotiations: bucket(limit.negotiations_per_second),
+                scanned: 0,
+                iac: false,
+                disconnected: None,
+            }),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Charge bytes and negotiation commands added to the read buffer
+    ///
+    /// Bytes are only charged the first time they are seen; call
+    /// [`consumed`](Self::consumed) after decoding removes bytes from `src`.
+    pub(crate) fn charge_input(&self, src: &BytesMut) {
+        let mut state = self.state();
+        let start = state.scanned.min(src.len());
+        let received = &src[start..];
+        state.scanned = src.len();
+        if received.is_empty() {
+            return;
+        }
+
+        let mut negotiations = 0u32;
+        let mut iac = state.iac;
+        for &byte in received {
+            if iac {
+                if NEGOTIATIONS.contains(&byte) {
+                    negotiations += 1;
+                }
+                iac = false;
+            } else if byte == IAC {
+                iac = true;
+            }
+        }
+        state.iac = iac;
+
+        let now = Instant::now();
+        if let Some(bytes) = &mut state.bytes {
+            bytes.charge(received.len() as f64, now);
+        }
+        if negotiations > 0
+            && let Some(bucket) = &mut state.negotiations
+        {
+            bucket.charge(f64::from(negotiations), now);
+        }
+    }
+
+    /// Forget `count` charged bytes removed from the front of the read buffer
+    pub(crate) fn consumed(&self, count: usize) {
+        let mut state = self.state();
+        state.scanned = state.scanned.saturating_sub(count);
+    }
+
+    /// Returns true if negotiations the peer starts should be ignored
+    pub(crate) fn drops_negotiations(&self) -> bool {
+        self.action == RateLimitAction::Drop
+            && self
+                .state()
+                .negotiations
+                .as_mut()
+                .is_some_and(|bucket| bucket.is_exceeded(Instant::now()))
+    }
+
+    /// The limit that disconnected the peer, if any
+    pub(crate) fn disconnected(&self) -> Option<RateLimitKind> {
+        self.state().disconnected
+    }
+
+    /// Charge a decoded event and decide what to do with it
+    pub(crate) fn check(&self, event: &TerminalEvent) -> Verdict {
+        let mut state = self.state();
+        if let Some(kind) = state.disconnected {
+            return Verdict::Disconnect(kind);
+        }
+
+        let now = Instant::now();
+        let is_line = matches!(event, TerminalEvent::LineCompleted { .. });
+        if is_line && let Some(lines) = &mut state.lines {
+            lines.charge(1.0, now);
+        }
+
+        match self.action {
+            RateLimitAction::Delay => {
+                let wait = state
+                    .buckets_mut()
+                    .map(|(_, bucket)| bucket.wait(now))
+                    .max()
+                    .unwrap_or_default();
+                if wait.is_zero() {
+                    Verdict::Pass
+                } else {
+                    Verdict::Delay(wait)
+                }
+            }
+            RateLimitAction::Drop => {
+                // Negotiations are dropped by the codec, see `drops_negotiations`
+                let is_input = is_line || matches!(event, TerminalEvent::CharacterData { .. });
+                let lines_exceeded = is_line
+                    && state
+                        .lines
+                        .as_mut()
+                        .is_some_and(|bucket| bucket.is_exceeded(now));
+                let bytes_exceeded = is_input
+                    && state
+                        .bytes
+                        .as_mut()
+                        .is_some_and(|bucket| bucket.is_exceeded(now));
+                if lines_exceeded || bytes_exceeded {
+                    Verdict::Drop
+                } else {
+                    Verdict::Pass
+                }
+            }
+            RateLimitAction::Warn => {
+                let mut verdict = Verdict::Pass;
+                for (kind, bucket) in state.buckets_mut() {
+                    if !bucket.is_exceeded(now) {
+                        bucket.warned = false;
+                    } else if !bucket.warned && verdict == Verdict::Pass {
+                        bucket.warned = true;
+                        verdict = Verdict::Warn(kind);
+                    }
+                }
+                verdict
+            }
+            RateLimitAction::Disconnect => {
+                let exceeded = state
+                    .buckets_mut()
+                    .find_map(|(kind, bucket)| bucket.is_exceeded(now).then_some(kind));
+                state.disconnected = exceeded;
+                exceeded.map_or(Verdict::Pass, Verdict::Disconnect)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use termionix_terminal::CursorPosition;
+
+    fn line() -> TerminalEvent {
+        TerminalEvent::LineCompleted {
+            cursor: CursorPosition::new(0, 0),
+            line: "spam".into(),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lines_refill_over_time() {
+        let limiter = RateLimiter::new(
+            &RateLimit::new()
+                .with_lines_per_second(2)
+                .with_action(RateLimitAction::Drop),
+        );
+        assert_eq!(limiter.check(&line()), Verdict::Pass);
+        assert_eq!(limiter.check(&line()), Verdict::Pass);
+        assert_eq!(limiter.check(&line()), Verdict::Drop);
+        assert_eq!(limiter.check(&TerminalEvent::NoOperation), Verdict::Pass);
+
+        // Dropped lines count, but debt is capped at one second
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(limiter.check(&line()), Verdict::Pass);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bytes_delay_until_paid() {
+        let limiter = RateLimiter::new(&RateLimit::new().with_bytes_per_second(10));
+        limiter.charge_input(&BytesMut::from(&[b'x'; 30][..]));
+        assert_eq!(
+            limiter.check(&TerminalEvent::NoOperation),
+            Verdict::Delay(Duration::from_secs(2))
+        );
+
+        // Bytes still in the buffer are not charged twice
+        limiter.charge_input(&BytesMut::from(&[b'x'; 30][..]));
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(limiter.check(&TerminalEvent::NoOperation), Verdict::Pass);
+    }
+
+    #[test]
+    fn test_negotiations_counted_across_reads() {
+        let limiter = RateLimiter::new(
+            &RateLimit::new()
+                .with_negotiations_per_second(1)
+                .with_action(RateLimitAction::Warn),
+        );
+
+        // IAC DO ECHO, then an escaped IAC followed by a data byte that looks like WILL
+        let mut src = BytesMut::from(&[IAC, 253, 1, IAC, IAC, 251][..]);
+        limiter.charge_input(&src);
+        assert_eq!(limiter.check(&TerminalEvent::NoOperation), Verdict::Pass);
+        limiter.consumed(src.len());
+        src.clear();
+
+        // IAC WILL SGA split across two reads
+        src.extend_from_slice(&[IAC]);
+        limiter.charge_input(&src);
+        src.extend_from_slice(&[251, 3]);
+        limiter.charge_input(&src);
+        assert_eq!(
+            limiter.check(&TerminalEvent::NoOperation),
+            Verdict::Warn(RateLimitKind::Negotiations)
+        );
+        assert_eq!(limiter.check(&TerminalEvent::NoOperation), Verdict::Pass);
+    }
+
+    #[test]
+    fn test_disconnect_is_permanent() {
+        let limiter = RateLimiter::new(
+            &RateLimit::new()
+                .with_lines_per_second(1)
+                .with_action(RateLimitAction::Disconnect),
+        );
+        assert_eq!(limiter.check(&line()), Verdict::Pass);
+        assert_eq!(
+            limiter.check(&line()),
+            Verdict::Disconnect(RateLimitKind::Lines)
+        );
+        assert_eq!(limiter.disconnected(), Some(RateLimitKind::Lines));
+        assert_eq!(
+            limiter.check(&TerminalEvent::NoOperation),
+            Verdict::Disconnect(RateLimitKind::Lines)
+        );
+    }
+
+    #[test]
+    fn test_drop_negotiations_only_when_dropping() {
+        let limit = RateLimit::new().with_negotiations_per_second(1);
+        let storm = BytesMut::from(&[IAC, 253, 1, IAC, 254, 1][..]);
+
+        let limiter = RateLimiter::new(&limit.with_action(RateLimitAction::Drop));
+        limiter.charge_input(&storm);
+        assert!(limiter.drops_negotiations());
+
+        let limiter = RateLimiter::new(&limit);
+        limiter.charge_input(&storm);
+        assert!(!limiter.drops_negotiations());
+    }
+}
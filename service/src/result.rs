@@ -71,4 +71,44 @@ pub enum ConnectionError {
     /// typically because it has been shut down or encountered a fatal error.
     #[error("Receive failed: {0}")]
     ReceiveFailed(String),
+
+    /// The write queue is full
+    ///
+    /// This error is returned by [`OverflowPolicy::Error`](crate::OverflowPolicy::Error)
+    /// connections when output is sent faster than the peer reads it.
+    #[error("Write queue full")]
+    QueueFull,
+
+    /// The peer did not answer a keepalive probe
+    ///
+    /// Returned by reads once a probe sent after
+    /// [`ConnectionConfig::keepalive_interval`](crate::ConnectionConfig::keepalive_interval)
+    /// of silence got no traffic back within
+    /// [`ConnectionConfig::keepalive_timeout`](crate::ConnectionConfig::keepalive_timeout).
+    #[error("Keepalive timeout")]
+    KeepaliveTimeout,
+
+    /// The peer went over a rate limit set to disconnect
+    ///
+    /// Returned by reads on connections whose
+    /// [`RateLimit::action`](crate::RateLimit::action) is
+    /// [`RateLimitAction::Disconnect`](crate::RateLimitAction::Disconnect).
+    #[error("Rate limit exceeded: {0}")]
+    RateLimited(termionix_terminal::RateLimitKind),
+
+    /// The peer went over one of the Telnet codec's limits
+    ///
+    /// Returned by reads once decoding fails with a [`TelnetCodecError`](crate::TelnetCodecError)
+    /// for which [`is_limit_exceeded`](crate::TelnetCodecError::is_limit_exceeded) is true,
+    /// such as an oversized subnegotiation or a negotiation loop.
+    #[error("Codec limit exceeded: {0}")]
+    LimitExceeded(termionix_terminal::TelnetCodecError),
+
+    /// Upgrading the transport for START_TLS failed
+    ///
+    /// Returned by the read that received the peer's `FOLLOWS` when the TLS
+    /// handshake or the hand-over of the transport failed. The connection is
+    /// unusable afterwards.
+    #[error("START_TLS failed: {0}")]
+    StartTls(#[source] std::io::Error),
 }
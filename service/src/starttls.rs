@@ -0,0 +1,145 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! In-place transport upgrades for START_TLS
+//!
+//! A [`SplitTerminalConnection`](crate::SplitTerminalConnection) with START_TLS
+//! enabled hands its transport to a [`StartTlsUpgrade`] once both peers have
+//! sent `IAC SB START_TLS FOLLOWS IAC SE`. The upgrade runs the TLS handshake
+//! over a [`StartTlsStream`] and returns the halves of the encrypted stream,
+//! which the connection keeps using with the same codec.
+
+use bytes::{Buf, BytesMut};
+use futures_util::future::BoxFuture;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Upgrade run on a connection's transport once START_TLS is agreed
+///
+/// The upgrade performs the TLS handshake over the given stream, as a server
+/// or a client, and returns the read and write halves of the encrypted stream.
+/// A failed upgrade closes the connection.
+pub type StartTlsUpgrade<R, W> =
+    Box<dyn FnOnce(StartTlsStream<R, W>) -> BoxFuture<'static, io::Result<(R, W)>> + Send>;
+
+/// Read and write halves of a connection joined back into a single stream
+///
+/// Bytes the connection had already read past the peer's `FOLLOWS` (usually
+/// the start of the TLS handshake) are returned by the first reads, before
+/// anything else is read from the transport.
+#[derive(Debug)]
+pub struct StartTlsStream<R, W> {
+    prefix: BytesMut,
+    reader: R,
+    writer: W,
+}
+
+impl<R, W> StartTlsStream<R, W> {
+    /// Join `reader` and `writer`, replaying `prefix` before reading from `reader`
+    pub fn new(prefix: BytesMut, reader: R, writer: W) -> Self {
+        Self {
+            prefix,
+            reader,
+            writer,
+        }
+    }
+
+    /// Bytes read ahead of the transport that have not been read from this stream yet
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    /// Split this stream back into its halves and the unread prefix
+    pub fn into_parts(self) -> (BytesMut, R, W) {
+        (self.prefix, self.reader, self.writer)
+    }
+}
+
+impl<R, W> AsyncRead for StartTlsStream<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.prefix.is_empty() {
+            return Pin::new(&mut self.reader).poll_read(cx, buf);
+        }
+        let count = self.prefix.len().min(buf.remaining());
+        buf.put_slice(&self.prefix[..count]);
+        self.prefix.advance(count);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R, W> AsyncWrite for StartTlsStream<R, W>
+where
+    R: Unpin,
+    W: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.writer).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.writer).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_prefix_is_read_first() {
+        let (mut peer, local) = tokio::io::duplex(64);
+        let (reader, writer) = tokio::io::split(local);
+        let mut stream = StartTlsStream::new(BytesMut::from(&b"hello "[..]), reader, writer);
+
+        peer.write_all(b"world").await.unwrap();
+        drop(peer);
+        let mut received = String::new();
+        stream.read_to_string(&mut received).await.unwrap();
+        assert_eq!(received, "hello world");
+        assert!(stream.prefix().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_writes_reach_writer() {
+        let (mut peer, local) = tokio::io::duplex(64);
+        let (reader, writer) = tokio::io::split(local);
+        let mut stream = StartTlsStream::new(BytesMut::new(), reader, writer);
+
+        stream.write_all(b"ping").await.unwrap();
+        let mut received = [0u8; 4];
+        peer.read_exact(&mut received).await.unwrap();
+        assert_eq!(&received, b"ping");
+    }
+}
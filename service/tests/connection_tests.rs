@@ -17,10 +17,13 @@
 //! Comprehensive tests for SplitTerminalConnection
 
 use termionix_ansicodec::{AnsiCodec, AnsiConfig, SegmentedString};
-use termionix_service::{FlushStrategy, SplitTerminalConnection};
-use termionix_telnetcodec::TelnetCodec;
+use termionix_service::{
+    ConnectionConfig, ConnectionError, FlushStrategy, KeepaliveProbe, OverflowPolicy, RateLimit,
+    RateLimitAction, RateLimitKind, SplitTerminalConnection, StartTlsStream, StartTlsUpgrade,
+};
+use termionix_telnetcodec::{OptionPolicy, TelnetArgument, TelnetCodec, TelnetOption};
 use termionix_terminal::{TerminalCodec, TerminalCommand, TerminalEvent};
-use tokio::io::duplex;
+use tokio::io::{AsyncRead, AsyncWrite, duplex};
 use tokio::time::{Duration, timeout};
 
 type TestCodecStack = TerminalCodec<AnsiCodec<TelnetCodec>>;
@@ -54,8 +57,8 @@ fn create_test_connection() -> (
     let (r1, w1) = tokio::io::split(stream1);
     let (r2, w2) = tokio::io::split(stream2);
 
-    let conn1 = SplitTerminalConnection::new(r1, w1, codec1.clone(), codec1);
-    let conn2 = SplitTerminalConnection::new(r2, w2, codec2.clone(), codec2);
+    let conn1 = SplitTerminalConnection::new(r1, w1, codec1);
+    let conn2 = SplitTerminalConnection::new(r2, w2, codec2);
 
     (conn1, conn2)
 }
@@ -305,8 +308,8 @@ async fn test_large_message() {
     let (r1, w1) = tokio::io::split(stream1);
     let (r2, w2) = tokio::io::split(stream2);
 
-    let conn1 = SplitTerminalConnection::new(r1, w1, codec1.clone(), codec1);
-    let conn2 = SplitTerminalConnection::new(r2, w2, codec2.clone(), codec2);
+    let conn1 = SplitTerminalConnection::new(r1, w1, codec1);
+    let conn2 = SplitTerminalConnection::new(r2, w2, codec2);
 
     // Send a large message (32KB) with newline
     let mut large_msg = vec![b'A'; 32768];
@@ -495,6 +498,633 @@ async fn test_send_without_force_flush() {
     assert!(result.is_some());
 }
 
+#[tokio::test]
+async fn test_send_raw_bypasses_codec() {
+    use tokio::io::AsyncReadExt;
+
+    let (stream, mut peer) = duplex(8192);
+    let codec = create_codec();
+    let (reader, writer) = tokio::io::split(stream);
+    let conn = SplitTerminalConnection::new(reader, writer, codec);
+
+    conn.send(TerminalCommand::text("a"), false).await.unwrap();
+    // IAC WILL ECHO must not be escaped by the codec
+    conn.send_raw(vec![0xFF, 0xFB, 0x01], false).unwrap();
+    conn.send(TerminalCommand::text("b"), true).await.unwrap();
+
+    let mut buf = [0u8; 5];
+    timeout(Duration::from_secs(1), peer.read_exact(&mut buf))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(buf, [b'a', 0xFF, 0xFB, 0x01, b'b']);
+}
+
+#[tokio::test]
+async fn test_negotiation_reply_written_without_send() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (stream, mut peer) = duplex(8192);
+    let codec = create_codec();
+    let (reader, writer) = tokio::io::split(stream);
+    let conn = SplitTerminalConnection::new(reader, writer, codec);
+
+    // The reader must be driven for the offer to be decoded
+    let reader_conn = conn.clone();
+    tokio::spawn(async move { reader_conn.next().await });
+
+    // IAC WILL <unsupported option>
+    peer.write_all(&[0xFF, 0xFB, 0x99]).await.unwrap();
+
+    // IAC DONT <unsupported option> arrives without any send on our side
+    let mut buf = [0u8; 3];
+    timeout(Duration::from_secs(1), peer.read_exact(&mut buf))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(buf, [0xFF, 0xFE, 0x99]);
+}
+
+#[tokio::test]
+async fn test_with_codec_shares_negotiation_state() {
+    use termionix_telnetcodec::TelnetOption;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (stream, mut peer) = duplex(8192);
+    let codec = create_codec();
+    let (reader, writer) = tokio::io::split(stream);
+    let conn = SplitTerminalConnection::new(reader, writer, codec);
+
+    let offer = conn
+        .with_codec(|codec| {
+            codec
+                .codec_mut()
+                .inner_mut()
+                .enable_local(TelnetOption::Echo)
+        })
+        .expect("echo offer");
+    let mut encoded = bytes::BytesMut::new();
+    tokio_util::codec::Encoder::encode(&mut TelnetCodec::new(), offer, &mut encoded).unwrap();
+    conn.send_raw(encoded.to_vec(), true).unwrap();
+
+    let mut buf = [0u8; 3];
+    timeout(Duration::from_secs(1), peer.read_exact(&mut buf))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(buf, [0xFF, 0xFB, 0x01]);
+
+    // The reader half sees the offer made through the writer half
+    peer.write_all(&[0xFF, 0xFD, 0x01]).await.unwrap();
+    let event = timeout(Duration::from_secs(1), conn.next()).await.unwrap();
+    assert!(event.is_ok());
+    assert!(conn.with_codec(|codec| codec.codec().inner().is_enabled_local(TelnetOption::Echo)));
+}
+
+/// Helper to create a connection with a two-command write queue whose peer
+/// does not read until told to
+fn create_bounded_connection(
+    policy: OverflowPolicy,
+) -> (
+    SplitTerminalConnection<
+        tokio::io::ReadHalf<tokio::io::DuplexStream>,
+        tokio::io::WriteHalf<tokio::io::DuplexStream>,
+        TestCodecStack,
+    >,
+    tokio::io::DuplexStream,
+) {
+    let (stream, peer) = duplex(16);
+    let config = ConnectionConfig::default()
+        .with_buffer_size(2)
+        .with_overflow_policy(policy);
+    let (reader, writer) = tokio::io::split(stream);
+    let conn = SplitTerminalConnection::new_with_config(reader, writer, create_codec(), &config);
+    (conn, peer)
+}
+
+/// Send a message the writer blocks on, then fill the queue behind it
+async fn fill_write_queue(
+    conn: &SplitTerminalConnection<
+        tokio::io::ReadHalf<tokio::io::DuplexStream>,
+        tokio::io::WriteHalf<tokio::io::DuplexStream>,
+        TestCodecStack,
+    >,
+) {
+    let blocker = "A".repeat(64);
+    conn.send(TerminalCommand::text(&blocker), true)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    conn.send(TerminalCommand::text("B"), true).await.unwrap();
+    conn.send(TerminalCommand::text("C"), true).await.unwrap();
+    assert_eq!(conn.queue_depth(), 2);
+    assert_eq!(conn.queue_capacity(), 2);
+}
+
+/// Read from the peer until `last` arrives
+async fn read_until(peer: &mut tokio::io::DuplexStream, last: u8) -> Vec<u8> {
+    use tokio::io::AsyncReadExt;
+
+    let mut received = Vec::new();
+    let mut buf = [0u8; 64];
+    while !received.contains(&last) {
+        let n = timeout(Duration::from_secs(1), peer.read(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(n > 0, "connection closed early");
+        received.extend_from_slice(&buf[..n]);
+    }
+    received
+}
+
+#[tokio::test]
+async fn test_overflow_error_rejects_output() {
+    let (conn, _peer) = create_bounded_connection(OverflowPolicy::Error);
+    fill_write_queue(&conn).await;
+
+    let result = conn.send(TerminalCommand::text("D"), true).await;
+    assert!(matches!(result, Err(ConnectionError::QueueFull)));
+    assert_eq!(conn.queue_depth(), 2);
+}
+
+#[tokio::test]
+async fn test_overflow_wait_applies_backpressure() {
+    let (conn, mut peer) = create_bounded_connection(OverflowPolicy::Wait);
+    fill_write_queue(&conn).await;
+
+    let blocked = timeout(
+        Duration::from_millis(50),
+        conn.send(TerminalCommand::text("D"), true),
+    )
+    .await;
+    assert!(blocked.is_err(), "send should wait while the queue is full");
+
+    let sender = conn.clone();
+    let send = tokio::spawn(async move { sender.send(TerminalCommand::text("D"), true).await });
+    let received = read_until(&mut peer, b'D').await;
+    send.await.unwrap().unwrap();
+    assert!(received.ends_with(b"BCD"));
+}
+
+#[tokio::test]
+async fn test_overflow_drop_oldest_discards_queued_output() {
+    let (conn, mut peer) = create_bounded_connection(OverflowPolicy::DropOldest);
+    fill_write_queue(&conn).await;
+
+    conn.send(TerminalCommand::text("D"), true).await.unwrap();
+    assert_eq!(conn.queue_depth(), 2);
+
+    let received = read_until(&mut peer, b'D').await;
+    assert!(!received.contains(&b'B'));
+    assert!(received.ends_with(b"CD"));
+}
+
+#[tokio::test]
+async fn test_overflow_disconnect_closes_connection() {
+    let (conn, _peer) = create_bounded_connection(OverflowPolicy::Disconnect);
+    fill_write_queue(&conn).await;
+
+    let result = conn.send(TerminalCommand::text("D"), true).await;
+    assert!(matches!(result, Err(ConnectionError::Closed)));
+    assert_eq!(conn.queue_depth(), 0);
+
+    let result = conn.send(TerminalCommand::text("E"), true).await;
+    assert!(matches!(result, Err(ConnectionError::Closed)));
+}
+
+/// Helper to create a connection with the given flush strategy and a raw peer
+async fn create_flush_connection(
+    strategy: FlushStrategy,
+) -> (
+    SplitTerminalConnection<
+        tokio::io::ReadHalf<tokio::io::DuplexStream>,
+        tokio::io::WriteHalf<tokio::io::DuplexStream>,
+        TestCodecStack,
+    >,
+    tokio::io::DuplexStream,
+) {
+    let (stream, peer) = duplex(8192);
+    let (reader, writer) = tokio::io::split(stream);
+    let conn = SplitTerminalConnection::new(reader, writer, create_codec());
+    conn.set_flush_strategy(strategy).await;
+    (conn, peer)
+}
+
+/// Assert that nothing reaches the peer within `wait`
+async fn assert_nothing_written(peer: &mut tokio::io::DuplexStream, wait: Duration) {
+    use tokio::io::AsyncReadExt;
+
+    let mut buf = [0u8; 64];
+    let read = timeout(wait, peer.read(&mut buf)).await;
+    assert!(read.is_err(), "unexpected output: {:?}", read);
+}
+
+#[tokio::test]
+async fn test_flush_on_newline_holds_partial_lines() {
+    let (conn, mut peer) = create_flush_connection(FlushStrategy::OnNewline).await;
+
+    conn.send(TerminalCommand::text("abc"), false)
+        .await
+        .unwrap();
+    assert_nothing_written(&mut peer, Duration::from_millis(50)).await;
+
+    conn.send(TerminalCommand::text("def\n"), false)
+        .await
+        .unwrap();
+    let received = read_until(&mut peer, b'\n').await;
+    assert!(received.starts_with(b"abcdef"));
+}
+
+#[tokio::test]
+async fn test_flush_manual_waits_for_flush() {
+    let (conn, mut peer) = create_flush_connection(FlushStrategy::Manual).await;
+
+    conn.send(TerminalCommand::text("line\n"), false)
+        .await
+        .unwrap();
+    assert_nothing_written(&mut peer, Duration::from_millis(50)).await;
+
+    conn.flush().await.unwrap();
+    let received = read_until(&mut peer, b'\n').await;
+    assert!(received.starts_with(b"line"));
+}
+
+#[tokio::test]
+async fn test_flush_immediate_writes_every_send() {
+    let (conn, mut peer) = create_flush_connection(FlushStrategy::Immediate).await;
+
+    conn.send(TerminalCommand::text("x"), false).await.unwrap();
+    assert_eq!(read_until(&mut peer, b'x').await, b"x");
+}
+
+#[tokio::test]
+async fn test_flush_on_threshold_waits_for_size() {
+    let (conn, mut peer) = create_flush_connection(FlushStrategy::OnThreshold(8)).await;
+
+    conn.send(TerminalCommand::text("abcd"), false)
+        .await
+        .unwrap();
+    assert_nothing_written(&mut peer, Duration::from_millis(50)).await;
+
+    conn.send(TerminalCommand::text("efgh"), false)
+        .await
+        .unwrap();
+    assert_eq!(read_until(&mut peer, b'h').await, b"abcdefgh");
+}
+
+#[tokio::test]
+async fn test_flush_batched_max_latency() {
+    let (conn, mut peer) = create_flush_connection(FlushStrategy::Batched {
+        max_bytes: 1024,
+        max_latency: Duration::from_millis(200),
+    })
+    .await;
+
+    conn.send(TerminalCommand::text("ab"), false).await.unwrap();
+    conn.send(TerminalCommand::text("cd"), false).await.unwrap();
+    assert_nothing_written(&mut peer, Duration::from_millis(50)).await;
+
+    // The timer started with the first write flushes both
+    assert_eq!(read_until(&mut peer, b'd').await, b"abcd");
+}
+
+#[tokio::test]
+async fn test_flush_batched_max_bytes() {
+    let (conn, mut peer) = create_flush_connection(FlushStrategy::Batched {
+        max_bytes: 4,
+        max_latency: Duration::from_secs(60),
+    })
+    .await;
+
+    conn.send(TerminalCommand::text("ab"), false).await.unwrap();
+    assert_nothing_written(&mut peer, Duration::from_millis(50)).await;
+
+    conn.send(TerminalCommand::text("cd"), false).await.unwrap();
+    assert_eq!(read_until(&mut peer, b'd').await, b"abcd");
+}
+
+/// Helper to create a connection probing after 50ms of silence
+fn create_keepalive_connection(
+    probe: KeepaliveProbe,
+) -> (
+    SplitTerminalConnection<
+        tokio::io::ReadHalf<tokio::io::DuplexStream>,
+        tokio::io::WriteHalf<tokio::io::DuplexStream>,
+        TestCodecStack,
+    >,
+    tokio::io::DuplexStream,
+) {
+    let (stream, peer) = duplex(8192);
+    let config = ConnectionConfig::default()
+        .with_keepalive_interval(Duration::from_millis(50))
+        .with_keepalive_timeout(Duration::from_millis(50))
+        .with_keepalive_probe(probe);
+    let (reader, writer) = tokio::io::split(stream);
+    let conn = SplitTerminalConnection::new_with_config(reader, writer, create_codec(), &config);
+    (conn, peer)
+}
+
+#[tokio::test]
+async fn test_keepalive_sends_nop_when_idle() {
+    let (conn, mut peer) = create_keepalive_connection(KeepaliveProbe::NoOperation);
+
+    // IAC NOP
+    assert_eq!(read_until(&mut peer, 241).await, [255, 241]);
+
+    // NOP expects no answer, so the connection stays up
+    let result = timeout(Duration::from_millis(200), conn.next()).await;
+    assert!(result.is_err(), "unexpected result: {:?}", result);
+}
+
+#[tokio::test]
+async fn test_keepalive_times_out_unanswered_probe() {
+    let (conn, mut peer) = create_keepalive_connection(KeepaliveProbe::TimingMark);
+
+    // IAC DO TIMING-MARK
+    assert_eq!(read_until(&mut peer, 6).await, [255, 253, 6]);
+
+    let result = timeout(Duration::from_secs(1), conn.next()).await.unwrap();
+    assert!(matches!(result, Err(ConnectionError::KeepaliveTimeout)));
+
+    // The connection stays dead
+    let result = conn.next().await;
+    assert!(matches!(result, Err(ConnectionError::KeepaliveTimeout)));
+}
+
+#[tokio::test]
+async fn test_keepalive_answered_probes_keep_connection() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (conn, mut peer) = create_keepalive_connection(KeepaliveProbe::TimingMark);
+    let answer = tokio::spawn(async move {
+        let mut buf = [0u8; 64];
+        while let Ok(n) = peer.read(&mut buf).await {
+            if n == 0 {
+                break;
+            }
+            // IAC WONT TIMING-MARK
+            if buf[..n].ends_with(&[255, 253, 6]) && peer.write_all(&[255, 252, 6]).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(400);
+    while let Ok(result) = tokio::time::timeout_at(deadline, conn.next()).await {
+        assert!(
+            !matches!(result, Err(ConnectionError::KeepaliveTimeout)),
+            "answered probes timed out"
+        );
+    }
+    answer.abort();
+}
+
+#[tokio::test]
+async fn test_keepalive_counts_answers_while_not_reading() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (conn, mut peer) = create_keepalive_connection(KeepaliveProbe::TimingMark);
+    let answer = tokio::spawn(async move {
+        let mut buf = [0u8; 64];
+        while let Ok(n) = peer.read(&mut buf).await {
+            if n == 0 {
+                break;
+            }
+            // IAC WILL TIMING-MARK, answered every time
+            if buf[..n].ends_with(&[255, 253, 6]) && peer.write_all(&[255, 251, 6]).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // The application doesn't read for several probe intervals
+    tokio::time::sleep(Duration::from_millis(400)).await;
+
+    let result = timeout(Duration::from_millis(200), conn.next()).await;
+    assert!(
+        !matches!(result, Ok(Err(ConnectionError::KeepaliveTimeout))),
+        "answered probes timed out while not reading"
+    );
+    answer.abort();
+}
+
+/// Helper to create a rate limited connection with a raw peer
+fn create_rate_limited_connection(
+    limit: RateLimit,
+) -> (
+    SplitTerminalConnection<
+        tokio::io::ReadHalf<tokio::io::DuplexStream>,
+        tokio::io::WriteHalf<tokio::io::DuplexStream>,
+        TestCodecStack,
+    >,
+    tokio::io::DuplexStream,
+) {
+    let (stream, peer) = duplex(8192);
+    let config = ConnectionConfig::default()
+        .with_keepalive(false)
+        .with_rate_limit(Some(limit));
+    let (reader, writer) = tokio::io::split(stream);
+    let conn = SplitTerminalConnection::new_with_config(reader, writer, create_codec(), &config);
+    (conn, peer)
+}
+
+/// Write `count` numbered lines to the connection in one go
+async fn flood_lines(peer: &mut tokio::io::DuplexStream, count: usize) {
+    use tokio::io::AsyncWriteExt;
+
+    let flood: String = (0..count).map(|i| format!("l{}\n", i)).collect();
+    peer.write_all(flood.as_bytes()).await.unwrap();
+}
+
+/// Read events until nothing arrives for `quiet`, keeping lines and warnings
+async fn read_lines_and_warnings(
+    conn: &SplitTerminalConnection<
+        tokio::io::ReadHalf<tokio::io::DuplexStream>,
+        tokio::io::WriteHalf<tokio::io::DuplexStream>,
+        TestCodecStack,
+    >,
+    quiet: Duration,
+) -> Vec<String> {
+    let mut received = Vec::new();
+    while let Ok(result) = timeout(quiet, conn.next()).await {
+        match result.unwrap() {
+            Some(TerminalEvent::LineCompleted { line, .. }) => received.push(line.to_string()),
+            Some(TerminalEvent::RateLimited(kind)) => received.push(format!("<{}>", kind)),
+            Some(_) => {}
+            None => break,
+        }
+    }
+    received
+}
+
+#[tokio::test]
+async fn test_rate_limit_drop_discards_flooded_lines() {
+    let (conn, mut peer) = create_rate_limited_connection(
+        RateLimit::new()
+            .with_lines_per_second(3)
+            .with_action(RateLimitAction::Drop),
+    );
+
+    flood_lines(&mut peer, 10).await;
+    let received = read_lines_and_warnings(&conn, Duration::from_millis(100)).await;
+    assert_eq!(received, ["l0", "l1", "l2"]);
+}
+
+#[tokio::test]
+async fn test_rate_limit_warn_reports_before_input() {
+    let (conn, mut peer) = create_rate_limited_connection(
+        RateLimit::new()
+            .with_lines_per_second(2)
+            .with_action(RateLimitAction::Warn),
+    );
+
+    flood_lines(&mut peer, 4).await;
+    let received = read_lines_and_warnings(&conn, Duration::from_millis(100)).await;
+    assert_eq!(received, ["l0", "l1", "<lines>", "l2", "l3"]);
+}
+
+#[tokio::test]
+async fn test_rate_limit_disconnect_fails_reads() {
+    let (conn, mut peer) = create_rate_limited_connection(
+        RateLimit::new()
+            .with_lines_per_second(1)
+            .with_action(RateLimitAction::Disconnect),
+    );
+
+    flood_lines(&mut peer, 2).await;
+    loop {
+        match timeout(Duration::from_secs(1), conn.next()).await.unwrap() {
+            Ok(Some(TerminalEvent::LineCompleted { line, .. })) => {
+                assert_eq!(line.to_string(), "l0")
+            }
+            Ok(Some(_)) => {}
+            Err(ConnectionError::RateLimited(kind)) => {
+                assert_eq!(kind, RateLimitKind::Lines);
+                break;
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    let result = conn.next().await;
+    assert!(matches!(
+        result,
+        Err(ConnectionError::RateLimited(RateLimitKind::Lines))
+    ));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_limit_delay_slows_reads() {
+    let (conn, mut peer) =
+        create_rate_limited_connection(RateLimit::new().with_lines_per_second(5));
+
+    let start = tokio::time::Instant::now();
+    flood_lines(&mut peer, 10).await;
+    let received = read_lines_and_warnings(&conn, Duration::from_secs(5)).await;
+    assert_eq!(received.len(), 10);
+
+    // Five lines fit the burst and the other five take a second, followed
+    // by the five-second quiet period that ends the read
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_secs(6), "elapsed {:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(7), "elapsed {:?}", elapsed);
+}
+
+#[tokio::test]
+async fn test_rate_limit_drops_negotiation_storm_replies() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (conn, mut peer) = create_rate_limited_connection(
+        RateLimit::new()
+            .with_negotiations_per_second(2)
+            .with_action(RateLimitAction::Drop),
+    );
+    let reader_conn = conn.clone();
+    tokio::spawn(async move { while let Ok(Some(_)) = reader_conn.next().await {} });
+
+    // IAC WILL <unsupported option> within the limit is refused as usual
+    peer.write_all(&[0xFF, 0xFB, 0x99]).await.unwrap();
+    let mut buf = [0u8; 3];
+    timeout(Duration::from_secs(1), peer.read_exact(&mut buf))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(buf, [0xFF, 0xFE, 0x99]);
+
+    // A storm over the limit gets no replies
+    let storm: Vec<u8> = [0xFF, 0xFB, 0x99].repeat(10);
+    peer.write_all(&storm).await.unwrap();
+    assert_nothing_written(&mut peer, Duration::from_millis(100)).await;
+}
+
+/// Decode replies the connection sent to `client`
+fn collect_replies(client: &mut TelnetCodec, bytes: &[u8]) {
+    use tokio_util::codec::Decoder;
+
+    let mut src = bytes::BytesMut::from(bytes);
+    while client.decode(&mut src).unwrap().is_some() {}
+}
+
+#[tokio::test]
+async fn test_rate_limit_drop_keeps_option_state_in_agreement() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_util::codec::Encoder;
+
+    let (conn, mut peer) = create_rate_limited_connection(
+        RateLimit::new()
+            .with_negotiations_per_second(2)
+            .with_action(RateLimitAction::Drop),
+    );
+    let reader_conn = conn.clone();
+    tokio::spawn(async move { while let Ok(Some(_)) = reader_conn.next().await {} });
+
+    // Offers every option in both directions from a codec of our own
+    let mut client = TelnetCodec::new();
+    let options = [
+        TelnetOption::SuppressGoAhead,
+        TelnetOption::Echo,
+        TelnetOption::TransmitBinary,
+        TelnetOption::GMCP,
+    ];
+    for (sent, option) in options.into_iter().enumerate() {
+        let mut requests = bytes::BytesMut::new();
+        for frame in [client.enable_local(option), client.enable_remote(option)] {
+            client.encode(frame.unwrap(), &mut requests).unwrap();
+        }
+        peer.write_all(&requests).await.unwrap();
+
+        // The first option fits the limit; wait for its replies before the storm
+        if sent == 0 {
+            let mut buf = [0u8; 6];
+            timeout(Duration::from_secs(1), peer.read_exact(&mut buf))
+                .await
+                .unwrap()
+                .unwrap();
+            collect_replies(&mut client, &buf);
+        }
+    }
+
+    let mut buf = [0u8; 256];
+    while let Ok(Ok(read)) = timeout(Duration::from_millis(100), peer.read(&mut buf)).await {
+        assert!(read > 0);
+        collect_replies(&mut client, &buf[..read]);
+    }
+
+    assert!(client.is_enabled_local(TelnetOption::SuppressGoAhead));
+    for option in options {
+        let (local, remote) = conn.with_codec(|codec| {
+            let server = codec.codec().inner();
+            (
+                server.is_enabled_local(option),
+                server.is_enabled_remote(option),
+            )
+        });
+        assert_eq!(client.is_enabled_local(option), remote, "{:?}", option);
+        assert_eq!(client.is_enabled_remote(option), local, "{:?}", option);
+    }
+}
+
 #[tokio::test]
 async fn test_multiple_clones() {
     let (conn1, conn2) = create_test_connection();
@@ -588,4 +1218,308 @@ async fn test_alternating_send_receive() {
     }
 }
 
+type BoxedReader = Box<dyn AsyncRead + Unpin + Send>;
+type BoxedWriter = Box<dyn AsyncWrite + Unpin + Send>;
+
+/// Writer recording everything written through it
+struct RecordingWriter {
+    inner: BoxedWriter,
+    written: std::sync::Arc<std::sync::Mutex<Vec<u8>>>,
+}
+
+impl AsyncWrite for RecordingWriter {
+    fn poll_write(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        let result = std::pin::Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let std::task::Poll::Ready(Ok(count)) = result {
+            self.written
+                .lock()
+                .unwrap()
+                .extend_from_slice(&buf[..count]);
+        }
+        result
+    }
+
+    fn poll_flush(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// Stand-in for a TLS handshake that keeps the stream and records what is written after it
+fn passthrough_upgrade(
+    written: std::sync::Arc<std::sync::Mutex<Vec<u8>>>,
+) -> StartTlsUpgrade<BoxedReader, BoxedWriter> {
+    Box::new(move |stream: StartTlsStream<BoxedReader, BoxedWriter>| {
+        Box::pin(async move {
+            let (reader, writer) = tokio::io::split(stream);
+            let writer = RecordingWriter {
+                inner: Box::new(writer),
+                written,
+            };
+            Ok((
+                Box::new(reader) as BoxedReader,
+                Box::new(writer) as BoxedWriter,
+            ))
+        }) as _
+    })
+}
+
+/// Connection over `stream` whose codec negotiates according to `policy`
+fn create_policy_connection(
+    stream: tokio::io::DuplexStream,
+    policy: OptionPolicy,
+) -> SplitTerminalConnection<BoxedReader, BoxedWriter, TestCodecStack> {
+    let mut telnet_codec = TelnetCodec::with_policy(policy);
+    let mut requests = bytes::BytesMut::new();
+    for frame in telnet_codec.request_policy_options() {
+        tokio_util::codec::Encoder::encode(&mut telnet_codec, frame, &mut requests).unwrap();
+    }
+    let codec = TerminalCodec::new(AnsiCodec::new(AnsiConfig::default(), telnet_codec));
+    let (reader, writer) = tokio::io::split(stream);
+    let conn = SplitTerminalConnection::new(
+        Box::new(reader) as BoxedReader,
+        Box::new(writer) as BoxedWriter,
+        codec,
+    );
+    conn.send_raw(requests.freeze(), true).unwrap();
+    conn
+}
+
+/// Read events from `conn` until the peer's START_TLS FOLLOWS arrives
+async fn read_until_follows(
+    conn: &SplitTerminalConnection<BoxedReader, BoxedWriter, TestCodecStack>,
+) {
+    loop {
+        let event = timeout(Duration::from_secs(2), conn.next())
+            .await
+            .expect("timed out waiting for FOLLOWS")
+            .unwrap()
+            .expect("connection closed before FOLLOWS");
+        if let TerminalEvent::Subnegotiation(TelnetArgument::StartTLSFollows) = event {
+            return;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_start_tls_upgrades_both_sides_and_rearms_compression() {
+    let (server_stream, client_stream) = duplex(8192);
+    let server = create_policy_connection(
+        server_stream,
+        OptionPolicy::new()
+            .request_remote(TelnetOption::StartTLS)
+            .request_local(TelnetOption::Compress2),
+    );
+    let client = create_policy_connection(
+        client_stream,
+        OptionPolicy::new()
+            .request_local(TelnetOption::StartTLS)
+            .accept_remote(TelnetOption::Compress2),
+    );
+    let server_written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let client_written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    server
+        .enable_start_tls(passthrough_upgrade(server_written.clone()))
+        .unwrap();
+    client
+        .enable_start_tls(passthrough_upgrade(client_written.clone()))
+        .unwrap();
+
+    // Half a line before the upgrade, the rest after it
+    client
+        .send(TerminalCommand::Text("hel".to_string()), true)
+        .await
+        .unwrap();
+    tokio::join!(read_until_follows(&server), read_until_follows(&client));
+
+    client
+        .send(TerminalCommand::Text("lo\r\n".to_string()), true)
+        .await
+        .unwrap();
+    server
+        .send(TerminalCommand::Text("welcome\r\n".to_string()), true)
+        .await
+        .unwrap();
+
+    let line = loop {
+        match timeout(Duration::from_secs(2), server.next())
+            .await
+            .unwrap()
+        {
+            Ok(Some(TerminalEvent::LineCompleted { line, .. })) => break line,
+            Ok(Some(_)) => continue,
+            other => panic!("expected a line, got {:?}", other),
+        }
+    };
+    assert_eq!(line.stripped(), "hello");
+    let line = loop {
+        match timeout(Duration::from_secs(2), client.next())
+            .await
+            .unwrap()
+        {
+            Ok(Some(TerminalEvent::LineCompleted { line, .. })) => break line,
+            Ok(Some(_)) => continue,
+            other => panic!("expected a line, got {:?}", other),
+        }
+    };
+    assert_eq!(line.stripped(), "welcome");
+
+    // The server restarted MCCP2 inside the upgraded stream, the client never compressed
+    let server_written = server_written.lock().unwrap().clone();
+    assert!(server_written.starts_with(&[255, 250, 86, 255, 240]));
+    assert!(!server_written.windows(7).any(|w| w == b"welcome"));
+    assert_eq!(&client_written.lock().unwrap()[..], b"lo\r\n");
+}
 
+/// Writer that fails once asked to write START_TLS FOLLOWS
+struct RefusingFollowsWriter {
+    inner: BoxedWriter,
+}
+
+impl AsyncWrite for RefusingFollowsWriter {
+    fn poll_write(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        if buf.windows(3).any(|w| w == [250, 46, 1]) {
+            return std::task::Poll::Ready(Err(std::io::Error::other("FOLLOWS refused")));
+        }
+        std::pin::Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[tokio::test]
+async fn test_start_tls_reports_writer_failure_to_reader() {
+    use tokio::io::AsyncWriteExt;
+
+    let (stream, mut peer) = duplex(8192);
+    let (reader, writer) = tokio::io::split(stream);
+    let writer = RefusingFollowsWriter {
+        inner: Box::new(writer),
+    };
+    let codec = TerminalCodec::new(AnsiCodec::new(
+        AnsiConfig::default(),
+        TelnetCodec::with_policy(OptionPolicy::new().accept_remote(TelnetOption::StartTLS)),
+    ));
+    let conn = SplitTerminalConnection::new(
+        Box::new(reader) as BoxedReader,
+        Box::new(writer) as BoxedWriter,
+        codec,
+    );
+    conn.enable_start_tls(passthrough_upgrade(Default::default()))
+        .unwrap();
+
+    // IAC WILL START_TLS, then IAC SB START_TLS FOLLOWS IAC SE
+    peer.write_all(&[255, 251, 46, 255, 250, 46, 1, 255, 240])
+        .await
+        .unwrap();
+    let error = loop {
+        match timeout(Duration::from_secs(2), conn.next()).await.unwrap() {
+            Ok(Some(_)) => continue,
+            Err(error) => break error,
+            Ok(None) => panic!("connection closed without an error"),
+        }
+    };
+    match error {
+        ConnectionError::StartTls(e) => assert_eq!(e.to_string(), "FOLLOWS refused"),
+        other => panic!("expected a START_TLS error, got {:?}", other),
+    }
+}
+
+/// Writer that fails every write
+struct BrokenWriter;
+
+impl AsyncWrite for BrokenWriter {
+    fn poll_write(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        _buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        std::task::Poll::Ready(Err(std::io::Error::other("transport broken")))
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn test_start_tls_reports_rearm_failure_to_reader() {
+    let (server_stream, client_stream) = duplex(8192);
+    let server = create_policy_connection(
+        server_stream,
+        OptionPolicy::new()
+            .request_remote(TelnetOption::StartTLS)
+            .request_local(TelnetOption::Compress2),
+    );
+    let client = create_policy_connection(
+        client_stream,
+        OptionPolicy::new()
+            .request_local(TelnetOption::StartTLS)
+            .accept_remote(TelnetOption::Compress2),
+    );
+    // The upgraded transport can't be written, so restarting MCCP2 fails
+    server
+        .enable_start_tls(Box::new(
+            |stream: StartTlsStream<BoxedReader, BoxedWriter>| {
+                Box::pin(async move {
+                    let (reader, _) = tokio::io::split(stream);
+                    Ok((
+                        Box::new(reader) as BoxedReader,
+                        Box::new(BrokenWriter) as BoxedWriter,
+                    ))
+                }) as _
+            },
+        ))
+        .unwrap();
+    client
+        .enable_start_tls(passthrough_upgrade(Default::default()))
+        .unwrap();
+    tokio::join!(read_until_follows(&server), read_until_follows(&client));
+
+    match timeout(Duration::from_secs(2), server.next())
+        .await
+        .unwrap()
+    {
+        Err(ConnectionError::StartTls(e)) => assert_eq!(e.to_string(), "transport broken"),
+        other => panic!("expected a START_TLS error, got {:?}", other),
+    }
+}
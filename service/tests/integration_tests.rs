@@ -55,8 +55,8 @@ fn create_test_connection() -> (
     let (r1, w1) = tokio::io::split(stream1);
     let (r2, w2) = tokio::io::split(stream2);
 
-    let conn1 = SplitTerminalConnection::new(r1, w1, codec1.clone(), codec1);
-    let conn2 = SplitTerminalConnection::new(r2, w2, codec2.clone(), codec2);
+    let conn1 = SplitTerminalConnection::new(r1, w1, codec1);
+    let conn2 = SplitTerminalConnection::new(r2, w2, codec2);
 
     (conn1, conn2)
 }
@@ -286,8 +286,8 @@ async fn test_large_data_transfer() {
     let (r1, w1) = tokio::io::split(stream1);
     let (r2, w2) = tokio::io::split(stream2);
 
-    let conn1 = SplitTerminalConnection::new(r1, w1, codec1.clone(), codec1);
-    let conn2 = SplitTerminalConnection::new(r2, w2, codec2.clone(), codec2);
+    let conn1 = SplitTerminalConnection::new(r1, w1, codec1);
+    let conn2 = SplitTerminalConnection::new(r2, w2, codec2);
 
     // Send a large message (64KB) with newline
     let large_msg = format!("{}\n", "A".repeat(65536));
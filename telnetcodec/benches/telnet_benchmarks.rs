@@ -280,6 +280,27 @@ fn bench_decode_data_sizes(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_decode_data_chunks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_data_chunks");
+
+    for size in [10, 100, 1000, 10000].iter() {
+        group.throughput(Throughput::Bytes(*size as u64));
+
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+            let mut codec = TelnetCodec::new();
+            codec.set_data_chunks(true);
+            let data: Vec<u8> = (0..size).map(|i| (i % 255) as u8).collect(); // Avoid 0xFF
+
+            b.iter(|| {
+                let mut buffer = BytesMut::from(&data[..]);
+                while codec.decode(black_box(&mut buffer)).unwrap().is_some() {}
+            });
+        });
+    }
+
+    group.finish();
+}
+
 fn bench_decode_commands(c: &mut Criterion) {
     let mut group = c.benchmark_group("decode_commands");
 
@@ -567,6 +588,7 @@ criterion_group!(
     decoding_benches,
     bench_decode_single_byte,
     bench_decode_data_sizes,
+    bench_decode_data_chunks,
     bench_decode_commands,
     bench_decode_negotiation,
     bench_decode_subnegotiation,
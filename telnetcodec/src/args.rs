@@ -14,15 +14,24 @@
 // limitations under the License.
 //
 
-use crate::TelnetOption;
+use crate::args::atcp::AtcpMessage;
 use crate::args::gmcp::GmcpMessage;
+use crate::args::linemode::LineModeOption;
+use crate::args::naocrd::NAOCRD;
+use crate::args::naohts::NAOHTS;
 use crate::args::naws::WindowSize;
+use crate::args::new_environ::NewEnviron;
+use crate::args::status::TelnetOptionStatus;
+use crate::args::zmp::ZmpMessage;
 use crate::msdp::MudServerData;
 use crate::mssp::MudServerStatus;
 use crate::result::TelnetCodecResult;
-use bytes::{BufMut, BytesMut};
+use crate::{SubnegotiationErrorKind, TelnetCodecError, TelnetOption, consts};
+use bytes::{Buf, BufMut, BytesMut};
 use std::fmt::Formatter;
 
+/// `ATCP` (Achaea Telnet Client Protocol) argument parsing and handling
+pub mod atcp;
 /// GMCP (Generic Mud Communication Protocol) argument parsing and handling
 pub mod gmcp;
 pub mod linemode;
@@ -31,7 +40,16 @@ pub mod mssp;
 pub mod naocrd;
 pub mod naohts;
 pub mod naws;
+pub mod new_environ;
 pub mod status;
+/// ZMP (Zenith MUD Protocol) messages and command registry
+pub mod zmp;
+
+/// Separator used between charset names when encoding a CHARSET REQUEST.
+const CHARSET_SEPARATOR: u8 = b';';
+
+/// Marker indicating a translation table version in a CHARSET REQUEST.
+const CHARSET_TTABLE_MARKER: &[u8] = b"[TTABLE]";
 
 ///
 /// Telnet Subnegotiation Argument
@@ -56,12 +74,35 @@ pub enum TelnetArgument {
     /// GMCP (Generic Mud Communication Protocol) message.
     /// Contains a package name and optional JSON data payload.
     GMCP(GmcpMessage),
+    /// `ATCP` (Achaea Telnet Client Protocol) message.
+    /// Contains a package name and a plain-text value.
+    ATCP(AtcpMessage),
+    /// Empty `MXP` subnegotiation the server sends once the client agrees to
+    /// `MXP` (`IAC SB MXP IAC SE`), switching the client into `MXP` mode.
+    MXPStart,
+    /// ZMP (Zenith MUD Protocol) message.
+    /// Contains a command name and its arguments.
+    ZMP(ZmpMessage),
     /// Mud Server Data message.
     MudServerData(MudServerData),
     /// Mud Server Status message.
     MudServerStatus(MudServerStatus),
-    /// Terminal type subnegotiation.
+    /// Terminal type subnegotiation (`TTYPE IS <name>`).
     TerminalType(String),
+    /// Request for the peer's next terminal type (`TTYPE SEND`).
+    TerminalTypeSend,
+    /// Line mode subnegotiation (MODE, FORWARDMASK or SLC).
+    LineMode(Box<LineModeOption>),
+    /// Telnet option status subnegotiation (STATUS IS or STATUS SEND).
+    Status(TelnetOptionStatus),
+    /// Output carriage-return disposition subnegotiation.
+    NAOCRD(NAOCRD),
+    /// Output horizontal tab stops subnegotiation.
+    NAOHTS(NAOHTS),
+    /// START_TLS announcement that the TLS handshake follows (`START_TLS FOLLOWS`).
+    StartTLSFollows,
+    /// Environment variable subnegotiation (NEW-ENVIRON IS, SEND or INFO).
+    NewEnviron(NewEnviron),
     /// A subnegotiation for an unknown option.
     Unknown(TelnetOption, BytesMut),
 }
@@ -80,8 +121,9 @@ impl TelnetArgument {
     /// # Behavior by Variant
     ///
     /// - `NAWSWindowSize(inner)` - Returns the encoded length of the window size data
+    /// - `Charset*` - Returns the command byte plus any separator and charset names
+    /// - `TerminalType(name)` / `TerminalTypeSend` - Returns the command byte plus the name
     /// - `Unknown(option, payload)` - Returns the length of the payload bytes
-    /// - Other variants - Currently unimplemented
     ///
     /// # Performance
     ///
@@ -100,9 +142,30 @@ impl TelnetArgument {
     pub fn len(&self) -> usize {
         match self {
             TelnetArgument::NAWSWindowSize(inner) => inner.len(),
+            TelnetArgument::CharsetRequest(charsets) => {
+                1 + charsets
+                    .iter()
+                    .map(|charset| 1 + charset.len())
+                    .sum::<usize>()
+            }
+            TelnetArgument::CharsetAccepted(charset) => 1 + charset.len(),
+            TelnetArgument::CharsetRejected
+            | TelnetArgument::CharsetTTableRejected
+            | TelnetArgument::TerminalTypeSend
+            | TelnetArgument::StartTLSFollows => 1,
             TelnetArgument::GMCP(inner) => inner.len(),
+            TelnetArgument::ATCP(inner) => inner.len(),
+            TelnetArgument::MXPStart => 0,
+            TelnetArgument::ZMP(inner) => inner.len(),
+            TelnetArgument::MudServerData(inner) => inner.len(),
+            TelnetArgument::MudServerStatus(inner) => inner.len(),
+            TelnetArgument::TerminalType(name) => 1 + name.len(),
+            TelnetArgument::LineMode(inner) => inner.len(),
+            TelnetArgument::Status(inner) => inner.len(),
+            TelnetArgument::NAOCRD(inner) => inner.len(),
+            TelnetArgument::NAOHTS(inner) => inner.len(),
+            TelnetArgument::NewEnviron(inner) => inner.len(),
             TelnetArgument::Unknown(_option, inner) => inner.len(),
-            _ => unimplemented!(),
         }
     }
     /// Encodes this `TelnetArgument` to a `BufMut` buffer.
@@ -171,8 +234,17 @@ impl TelnetArgument {
     /// # Encoding Behavior by Variant
     ///
     /// - `NAWSWindowSize(inner)` - Delegates to the window size's `write()` method
-    /// - `Unknown(option, payload)` - Writes the raw payload bytes as-is
-    /// - Other variants - Will panic (currently unimplemented)
+    /// - `CharsetRequest(charsets)` - Writes `REQUEST` followed by `;`-separated names
+    /// - `CharsetAccepted(charset)` - Writes `ACCEPTED` followed by the charset name
+    /// - `TerminalType(name)` - Writes `IS` followed by the terminal name
+    /// - `TerminalTypeSend` - Writes `SEND`
+    /// - `MXPStart` - Writes nothing
+    /// - `StartTLSFollows` - Writes `FOLLOWS`
+    /// - `LineMode`, `Status`, `NAOCRD`, `NAOHTS`, `NewEnviron` - Delegate to the inner `write()` method
+    /// - `Unknown(option, payload)` - Writes the raw payload bytes
+    ///
+    /// Any IAC (0xFF) byte produced by a variant is escaped as `IAC IAC`, so the
+    /// returned count may exceed [`TelnetArgument::len`].
     ///
     /// # Examples
     ///
@@ -190,25 +262,208 @@ impl TelnetArgument {
     /// # Ok::<(), std::io::Error>(())
     /// ```
     pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
+        let mut writer = IacEscapingWriter::new(writer);
+        self.write_payload(&mut writer)?;
+        Ok(writer.written)
+    }
+
+    /// Writes the unescaped subnegotiation payload of this argument.
+    fn write_payload<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
         match self {
             TelnetArgument::NAWSWindowSize(inner) => inner.write(writer),
+            TelnetArgument::CharsetRequest(charsets) => {
+                writer.write_all(&[consts::option::charset::REQUEST])?;
+                for charset in charsets {
+                    writer.write_all(&[CHARSET_SEPARATOR])?;
+                    writer.write_all(charset)?;
+                }
+                Ok(self.len())
+            }
+            TelnetArgument::CharsetAccepted(charset) => {
+                writer.write_all(&[consts::option::charset::ACCEPTED])?;
+                writer.write_all(charset)?;
+                Ok(1 + charset.len())
+            }
+            TelnetArgument::CharsetRejected => {
+                writer.write_all(&[consts::option::charset::REJECTED])?;
+                Ok(1)
+            }
+            TelnetArgument::CharsetTTableRejected => {
+                writer.write_all(&[consts::option::charset::TTABLE_REJECTED])?;
+                Ok(1)
+            }
             TelnetArgument::GMCP(inner) => inner.write(writer),
+            TelnetArgument::ATCP(inner) => inner.write(writer),
+            TelnetArgument::MXPStart => Ok(0),
+            TelnetArgument::ZMP(inner) => inner.write(writer),
+            TelnetArgument::MudServerData(inner) => inner.write(writer),
+            TelnetArgument::MudServerStatus(inner) => inner.write(writer),
+            TelnetArgument::TerminalType(name) => {
+                writer.write_all(&[consts::option::ttype::IS])?;
+                writer.write_all(name.as_bytes())?;
+                Ok(1 + name.len())
+            }
+            TelnetArgument::TerminalTypeSend => {
+                writer.write_all(&[consts::option::ttype::SEND])?;
+                Ok(1)
+            }
+            TelnetArgument::LineMode(inner) => inner.write(writer),
+            TelnetArgument::Status(inner) => inner.write(writer),
+            TelnetArgument::NAOCRD(inner) => inner.write(writer),
+            TelnetArgument::NAOHTS(inner) => inner.write(writer),
+            TelnetArgument::StartTLSFollows => {
+                writer.write_all(&[consts::option::start_tls::FOLLOWS])?;
+                Ok(1)
+            }
+            TelnetArgument::NewEnviron(inner) => inner.write(writer),
             TelnetArgument::Unknown(_option, payload) => {
-                // Write payload with IAC escaping
-                let mut written = 0;
-                for &byte in payload.iter() {
-                    if byte == 0xFF {
-                        // IAC byte must be escaped as IAC IAC
-                        writer.write_all(&[0xFF, 0xFF])?;
-                        written += 2;
-                    } else {
-                        writer.write_all(&[byte])?;
-                        written += 1;
-                    }
+                writer.write_all(payload)?;
+                Ok(payload.len())
+            }
+        }
+    }
+
+    /// Decodes a subnegotiation payload for the given option.
+    ///
+    /// This is the inverse of [`TelnetArgument::encode`]: the payload is the
+    /// unescaped data between `IAC SB <option>` and `IAC SE`. Options without
+    /// a typed argument decode to `TelnetArgument::Unknown`.
+    ///
+    /// # Arguments
+    ///
+    /// * `option` - The option the subnegotiation was received for
+    /// * `src` - The unescaped subnegotiation payload
+    ///
+    /// # Errors
+    ///
+    /// Returns `TelnetCodecError::SubnegotiationError` when the payload is
+    /// malformed for a typed option.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bytes::BytesMut;
+    /// use termionix_telnetcodec::{TelnetArgument, TelnetOption};
+    ///
+    /// let mut payload = BytesMut::from(&b"\x00xterm-256color"[..]);
+    /// let arg = TelnetArgument::decode(TelnetOption::TTYPE, &mut payload)?;
+    /// assert_eq!(arg, TelnetArgument::TerminalType("xterm-256color".to_string()));
+    /// # Ok::<(), termionix_telnetcodec::TelnetCodecError>(())
+    /// ```
+    pub fn decode(option: TelnetOption, src: &mut BytesMut) -> TelnetCodecResult<TelnetArgument> {
+        match option {
+            TelnetOption::NAWS => Ok(TelnetArgument::NAWSWindowSize(WindowSize::decode(src)?)),
+            TelnetOption::Charset => Self::decode_charset(src),
+            TelnetOption::GMCP => match GmcpMessage::parse(src) {
+                Some(message) => Ok(TelnetArgument::GMCP(message)),
+                None => Err(TelnetCodecError::SubnegotiationError {
+                    option: Some(consts::option::GMCP),
+                    reason: SubnegotiationErrorKind::Other {
+                        description: "invalid GMCP message".to_string(),
+                    },
+                }),
+            },
+            TelnetOption::ATCP => Ok(TelnetArgument::ATCP(AtcpMessage::parse(&src.split()))),
+            TelnetOption::MXP if src.is_empty() => Ok(TelnetArgument::MXPStart),
+            TelnetOption::ZMP => Ok(TelnetArgument::ZMP(ZmpMessage::decode(src)?)),
+            TelnetOption::MSDP => Ok(TelnetArgument::MudServerData(MudServerData::decode(src)?)),
+            TelnetOption::MSSP => Ok(TelnetArgument::MudServerStatus(MudServerStatus::decode(
+                src,
+            )?)),
+            TelnetOption::TTYPE => Self::decode_terminal_type(src),
+            TelnetOption::Linemode => Ok(TelnetArgument::LineMode(Box::new(
+                LineModeOption::decode(src)?,
+            ))),
+            TelnetOption::Status => Ok(TelnetArgument::Status(TelnetOptionStatus::decode(src)?)),
+            TelnetOption::NAOCRD => Ok(TelnetArgument::NAOCRD(NAOCRD::decode(src)?)),
+            TelnetOption::NAOHTS => Ok(TelnetArgument::NAOHTS(NAOHTS::decode(src)?)),
+            TelnetOption::StartTLS if src[..] == [consts::option::start_tls::FOLLOWS] => {
+                src.clear();
+                Ok(TelnetArgument::StartTLSFollows)
+            }
+            TelnetOption::NewEnvironment => {
+                Ok(TelnetArgument::NewEnviron(NewEnviron::decode(src)?))
+            }
+            _ => Ok(TelnetArgument::Unknown(option, src.split())),
+        }
+    }
+
+    /// Decodes a CHARSET (RFC 2066) subnegotiation payload.
+    fn decode_charset(src: &mut BytesMut) -> TelnetCodecResult<TelnetArgument> {
+        if !src.has_remaining() {
+            return Err(TelnetCodecError::SubnegotiationError {
+                option: Some(consts::option::CHARSET),
+                reason: SubnegotiationErrorKind::InsufficientData {
+                    required: 1,
+                    available: 0,
+                },
+            });
+        }
+        match src.get_u8() {
+            consts::option::charset::REQUEST => {
+                // An optional "[TTABLE]" marker and version byte precede the separator
+                if src.starts_with(CHARSET_TTABLE_MARKER) {
+                    src.advance((CHARSET_TTABLE_MARKER.len() + 1).min(src.len()));
                 }
-                Ok(written)
+                if !src.has_remaining() {
+                    return Err(TelnetCodecError::SubnegotiationError {
+                        option: Some(consts::option::CHARSET),
+                        reason: SubnegotiationErrorKind::IncompleteData {
+                            description: "CHARSET REQUEST without separator".to_string(),
+                        },
+                    });
+                }
+                let separator = src.get_u8();
+                let charsets = src[..]
+                    .split(|byte| *byte == separator)
+                    .filter(|name| !name.is_empty())
+                    .map(BytesMut::from)
+                    .collect();
+                src.clear();
+                Ok(TelnetArgument::CharsetRequest(charsets))
+            }
+            consts::option::charset::ACCEPTED => Ok(TelnetArgument::CharsetAccepted(src.split())),
+            consts::option::charset::REJECTED => Ok(TelnetArgument::CharsetRejected),
+            consts::option::charset::TTABLE_REJECTED => Ok(TelnetArgument::CharsetTTableRejected),
+            command => Err(TelnetCodecError::SubnegotiationError {
+                option: Some(consts::option::CHARSET),
+                reason: SubnegotiationErrorKind::InvalidCommand {
+                    command,
+                    expected: Some(vec![
+                        consts::option::charset::REQUEST,
+                        consts::option::charset::ACCEPTED,
+                        consts::option::charset::REJECTED,
+                        consts::option::charset::TTABLE_REJECTED,
+                    ]),
+                },
+            }),
+        }
+    }
+
+    /// Decodes a TTYPE (RFC 1091) subnegotiation payload.
+    fn decode_terminal_type(src: &mut BytesMut) -> TelnetCodecResult<TelnetArgument> {
+        if !src.has_remaining() {
+            return Err(TelnetCodecError::SubnegotiationError {
+                option: Some(consts::option::TTYPE),
+                reason: SubnegotiationErrorKind::InsufficientData {
+                    required: 1,
+                    available: 0,
+                },
+            });
+        }
+        match src.get_u8() {
+            consts::option::ttype::IS => {
+                let name = String::from_utf8_lossy(&src.split()).to_string();
+                Ok(TelnetArgument::TerminalType(name))
             }
-            _ => unimplemented!(),
+            consts::option::ttype::SEND => Ok(TelnetArgument::TerminalTypeSend),
+            command => Err(TelnetCodecError::SubnegotiationError {
+                option: Some(consts::option::TTYPE),
+                reason: SubnegotiationErrorKind::InvalidCommand {
+                    command,
+                    expected: Some(vec![consts::option::ttype::IS, consts::option::ttype::SEND]),
+                },
+            }),
         }
     }
 
@@ -260,9 +515,20 @@ impl TelnetArgument {
             TelnetArgument::CharsetRejected => TelnetOption::Charset,
             TelnetArgument::CharsetTTableRejected => TelnetOption::Charset,
             TelnetArgument::GMCP(_) => TelnetOption::GMCP,
+            TelnetArgument::ATCP(_) => TelnetOption::ATCP,
+            TelnetArgument::MXPStart => TelnetOption::MXP,
+            TelnetArgument::ZMP(_) => TelnetOption::ZMP,
             TelnetArgument::MudServerData(_) => TelnetOption::MSDP,
             TelnetArgument::MudServerStatus(_) => TelnetOption::MSSP,
-            TelnetArgument::TerminalType(_) => TelnetOption::TTYPE,
+            TelnetArgument::TerminalType(_) | TelnetArgument::TerminalTypeSend => {
+                TelnetOption::TTYPE
+            }
+            TelnetArgument::LineMode(_) => TelnetOption::Linemode,
+            TelnetArgument::Status(_) => TelnetOption::Status,
+            TelnetArgument::NAOCRD(_) => TelnetOption::NAOCRD,
+            TelnetArgument::NAOHTS(_) => TelnetOption::NAOHTS,
+            TelnetArgument::StartTLSFollows => TelnetOption::StartTLS,
+            TelnetArgument::NewEnviron(_) => TelnetOption::NewEnvironment,
             TelnetArgument::Unknown(option, _) => TelnetOption::Unknown(option.to_u8()),
         }
     }
@@ -277,10 +543,50 @@ impl std::fmt::Display for TelnetArgument {
             TelnetArgument::CharsetRejected => write!(f, "CharsetRejected"),
             TelnetArgument::CharsetTTableRejected => write!(f, "CharsetTableRejected"),
             TelnetArgument::GMCP(msg) => write!(f, "GMCP({msg})"),
+            TelnetArgument::ATCP(msg) => write!(f, "ATCP({msg})"),
+            TelnetArgument::MXPStart => write!(f, "MXPStart"),
+            TelnetArgument::ZMP(msg) => write!(f, "ZMP({msg})"),
             TelnetArgument::MudServerData(msg) => write!(f, "MudServerData({msg})"),
             TelnetArgument::MudServerStatus(msg) => write!(f, "MudServerStatus({msg})"),
             TelnetArgument::TerminalType(ttype) => write!(f, "TerminalType({ttype})"),
+            TelnetArgument::TerminalTypeSend => write!(f, "TerminalTypeSend"),
+            TelnetArgument::LineMode(mode) => write!(f, "LineMode({mode})"),
+            TelnetArgument::Status(status) => write!(f, "Status({status:?})"),
+            TelnetArgument::NAOCRD(naocrd) => write!(f, "NAOCRD({naocrd:?})"),
+            TelnetArgument::NAOHTS(naohts) => write!(f, "NAOHTS({naohts:?})"),
+            TelnetArgument::StartTLSFollows => write!(f, "StartTLSFollows"),
+            TelnetArgument::NewEnviron(environ) => write!(f, "NewEnviron({environ})"),
             TelnetArgument::Unknown(o, v) => write!(f, "{o}-{v:?}"),
         }
     }
 }
+
+/// `std::io::Write` adapter that escapes IAC bytes as `IAC IAC`.
+struct IacEscapingWriter<'a, W: std::io::Write> {
+    inner: &'a mut W,
+    written: usize,
+}
+
+impl<'a, W: std::io::Write> IacEscapingWriter<'a, W> {
+    fn new(inner: &'a mut W) -> Self {
+        Self { inner, written: 0 }
+    }
+}
+
+impl<W: std::io::Write> std::io::Write for IacEscapingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for chunk in buf.split_inclusive(|byte| *byte == consts::IAC) {
+            self.inner.write_all(chunk)?;
+            self.written += chunk.len();
+            if chunk.last() == Some(&consts::IAC) {
+                self.inner.write_all(&[consts::IAC])?;
+                self.written += 1;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,190 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use bytes::BytesMut;
+use std::fmt;
+
+///
+/// `ATCP` (Achaea Telnet Client Protocol) Message
+///
+/// `ATCP` is the plain-text predecessor of `GMCP`. Each message consists of a
+/// package name followed by a free-form value, which may span several lines.
+///
+/// Format: `<package.command> <value>`
+///
+/// # Examples
+///
+/// ```text
+/// hello Nexus 3.0
+/// Char.Vitals H:100/120 M:50/80
+/// Room.Num 1234
+/// ```
+///
+/// # References
+///
+/// - [ATCP Protocol Specification](https://www.ironrealms.com/rapture/manual/files/FeatATCP-txt.html)
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AtcpMessage {
+    /// The package name (e.g., "Char.Vitals", "Room.Num")
+    package: String,
+
+    /// The value following the package, empty when the message has none
+    value: String,
+}
+
+impl AtcpMessage {
+    /// Creates a new `ATCP` message with a package name and value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use termionix_telnetcodec::atcp::AtcpMessage;
+    ///
+    /// let msg = AtcpMessage::new("Char.Vitals", "H:100/120 M:50/80");
+    /// assert_eq!(msg.package(), "Char.Vitals");
+    /// ```
+    #[must_use]
+    pub fn new<S: Into<String>, V: Into<String>>(package: S, value: V) -> Self {
+        Self {
+            package: package.into(),
+            value: value.into(),
+        }
+    }
+
+    /// Parses an `ATCP` message from raw bytes.
+    ///
+    /// The package ends at the first space; everything after it is the value.
+    /// Invalid UTF-8 is replaced rather than rejected, as older clients often
+    /// send Latin-1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bytes::BytesMut;
+    /// use termionix_telnetcodec::atcp::AtcpMessage;
+    ///
+    /// let data = BytesMut::from(&b"Room.Num 1234"[..]);
+    /// let msg = AtcpMessage::parse(&data);
+    /// assert_eq!(msg.package(), "Room.Num");
+    /// assert_eq!(msg.value(), "1234");
+    /// ```
+    #[must_use]
+    pub fn parse(bytes: &BytesMut) -> Self {
+        let text = String::from_utf8_lossy(bytes);
+        match text.split_once(' ') {
+            Some((package, value)) => Self::new(package, value),
+            None => Self::new(text, String::new()),
+        }
+    }
+
+    /// Returns the package name.
+    #[must_use]
+    pub fn package(&self) -> &str {
+        &self.package
+    }
+
+    /// Returns the value, empty when the message has none.
+    #[must_use]
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Encodes the `ATCP` message to bytes.
+    #[must_use]
+    pub fn encode(&self) -> BytesMut {
+        let mut bytes = BytesMut::with_capacity(self.len());
+        bytes.extend_from_slice(self.package.as_bytes());
+        if !self.value.is_empty() {
+            bytes.extend_from_slice(b" ");
+            bytes.extend_from_slice(self.value.as_bytes());
+        }
+        bytes
+    }
+
+    /// Returns the encoded byte length of this message.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        if self.value.is_empty() {
+            self.package.len()
+        } else {
+            self.package.len() + 1 + self.value.len()
+        }
+    }
+
+    /// Checks if the message is empty (has no package name).
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.package.is_empty()
+    }
+
+    /// Writes the `ATCP` message to a writer.
+    ///
+    /// Returns the number of bytes written.
+    pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
+        writer.write_all(&self.encode())?;
+        Ok(self.len())
+    }
+}
+
+impl fmt::Display for AtcpMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.package)?;
+        if !self.value.is_empty() {
+            write!(f, " {}", self.value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atcp_parse_with_value() {
+        let data = BytesMut::from(&b"Char.Vitals H:100/120 M:50/80"[..]);
+        let msg = AtcpMessage::parse(&data);
+        assert_eq!(msg.package(), "Char.Vitals");
+        assert_eq!(msg.value(), "H:100/120 M:50/80");
+    }
+
+    #[test]
+    fn test_atcp_parse_without_value() {
+        let msg = AtcpMessage::parse(&BytesMut::from(&b"Client.Map"[..]));
+        assert_eq!(msg.package(), "Client.Map");
+        assert_eq!(msg.value(), "");
+        assert_eq!(msg.len(), 10);
+    }
+
+    #[test]
+    fn test_atcp_multiline_value() {
+        let data = BytesMut::from(&b"hello Nexus 3.0\nauth 1\nchar_vitals 1"[..]);
+        let msg = AtcpMessage::parse(&data);
+        assert_eq!(msg.package(), "hello");
+        assert_eq!(msg.value(), "Nexus 3.0\nauth 1\nchar_vitals 1");
+    }
+
+    #[test]
+    fn test_atcp_roundtrip() {
+        let original = AtcpMessage::new("Room.Num", "1234");
+        let encoded = original.encode();
+        assert_eq!(&encoded[..], b"Room.Num 1234");
+        assert_eq!(encoded.len(), original.len());
+        assert_eq!(AtcpMessage::parse(&encoded), original);
+        assert_eq!(original.to_string(), "Room.Num 1234");
+    }
+}
@@ -203,6 +203,59 @@ impl GmcpMessage {
     }
 }
 
+#[cfg(feature = "json")]
+impl GmcpMessage {
+    /// Creates a GMCP message whose data is `value` serialized as JSON.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use termionix_telnetcodec::gmcp::GmcpMessage;
+    ///
+    /// let msg = GmcpMessage::from_json("Char.Vitals", &serde_json::json!({"hp": 100})).unwrap();
+    /// assert_eq!(msg.data(), Some(r#"{"hp":100}"#));
+    /// ```
+    pub fn from_json<S: Into<String>, T: serde::Serialize + ?Sized>(
+        package: S,
+        value: &T,
+    ) -> serde_json::Result<Self> {
+        Ok(Self {
+            package: package.into(),
+            data: Some(serde_json::to_string(value)?),
+        })
+    }
+
+    /// Deserializes the JSON data into `T`.
+    ///
+    /// A message without data deserializes as JSON `null`, so `Option<T>`
+    /// and `()` accept commands such as `Core.Ping`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use termionix_telnetcodec::gmcp::{CoreHello, GmcpMessage};
+    ///
+    /// let msg = GmcpMessage::new("Core.Hello", Some(r#"{"client":"Mudlet","version":"4.17"}"#));
+    /// let hello: CoreHello = msg.json().unwrap();
+    /// assert_eq!(hello.client, "Mudlet");
+    /// ```
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_str(self.data.as_deref().unwrap_or("null"))
+    }
+}
+
+/// Data of the `Core.Hello` message a client sends first
+#[cfg(feature = "json")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CoreHello {
+    /// Client name
+    #[serde(default)]
+    pub client: String,
+    /// Client version
+    #[serde(default)]
+    pub version: String,
+}
+
 impl fmt::Display for GmcpMessage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.package)?;
@@ -299,4 +352,23 @@ mod tests {
         let parsed = GmcpMessage::parse(&encoded).unwrap();
         assert_eq!(original, parsed);
     }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_gmcp_json_round_trip() {
+        let hello = CoreHello {
+            client: "TinTin++".to_string(),
+            version: "2.02.0".to_string(),
+        };
+        let msg = GmcpMessage::from_json("Core.Hello", &hello).unwrap();
+        assert_eq!(msg.json::<CoreHello>().unwrap(), hello);
+
+        let ping = GmcpMessage::command("Core.Ping");
+        assert_eq!(ping.json::<Option<CoreHello>>().unwrap(), None);
+        assert!(
+            GmcpMessage::new("Core.Hello", Some("not json"))
+                .json::<CoreHello>()
+                .is_err()
+        );
+    }
 }
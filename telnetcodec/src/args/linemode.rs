@@ -16,8 +16,8 @@
 
 //! Line Mode Options
 
-use crate::consts;
-use bytes::BufMut;
+use crate::{SubnegotiationErrorKind, TelnetCodecError, TelnetCodecResult, consts};
+use bytes::{Buf, BufMut};
 
 /// Telnet Line Mode Option subnegotiation commands and arguments (RFC 1184)
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -120,6 +120,47 @@ impl LineModeOption {
             }
         }
     }
+
+    /// Returns the encoded length of this line mode option in bytes
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Mode { .. } => 2,
+            Self::ForwardMask { .. } => 33,
+            Self::Slc { chars } => 1 + chars.len() * 3,
+        }
+    }
+
+    /// Returns `true` if this line mode option encodes to no bytes
+    ///
+    /// Every option starts with its function byte, so this is always `false`.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Encode line mode option to a `BufMut` buffer
+    pub fn encode<T: BufMut>(&self, dst: &mut T) -> TelnetCodecResult<usize> {
+        Ok(self.write(&mut dst.writer())?)
+    }
+
+    /// Write line mode option to a `std::io::Write` writer
+    pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
+        let mut buf = Vec::with_capacity(self.len());
+        self.serialize(&mut buf);
+        writer.write_all(&buf)?;
+        Ok(buf.len())
+    }
+
+    /// Decode line mode option from a `Buf` buffer
+    pub fn decode<T: Buf>(src: &mut T) -> TelnetCodecResult<Self> {
+        let data = src.copy_to_bytes(src.remaining());
+        Self::parse(&data).map_err(|description| TelnetCodecError::SubnegotiationError {
+            option: Some(consts::option::LINEMODE),
+            reason: SubnegotiationErrorKind::Other {
+                description: description.to_string(),
+            },
+        })
+    }
 }
 
 impl std::fmt::Display for LineModeOption {
@@ -782,6 +823,13 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_empty_slc_len() {
+        let option = LineModeOption::Slc { chars: vec![] };
+        assert_eq!(option.len(), 1);
+        assert!(!option.is_empty());
+    }
+
     #[test]
     fn test_slc_roundtrip() {
         let original = LineModeOption::Slc {
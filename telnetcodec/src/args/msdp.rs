@@ -120,6 +120,16 @@ impl MudServerData {
     pub fn get_mut(&mut self, key: &str) -> Option<&mut MudServerDataValue> {
         self.0.get_mut(key)
     }
+
+    /// Iterates over the variables and their values.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &MudServerDataValue)> {
+        self.0.iter()
+    }
+
+    /// Returns `true` if there are no variables.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
     /// Gets the encoded length of this `MudServerData` structure.
     ///
     /// Returns the number of bytes that would be written when encoding this
@@ -135,7 +145,12 @@ impl MudServerData {
     /// let encoded_len = msd.len();
     /// ```
     pub fn len(&self) -> usize {
-        self.0.len()
+        // Top-level variables are not wrapped in TABLE_OPEN/TABLE_CLOSE
+        self.0
+            .0
+            .iter()
+            .map(|(key, value)| 2 + key.len() + value.len())
+            .sum()
     }
 
     /// Encodes `MudServerData` into the provided mutable buffer.
@@ -173,6 +188,8 @@ impl MudServerData {
     /// Writes `MudServerData` to the provided writer.
     ///
     /// Low-level method for writing the encoded data to a generic writer.
+    /// Top-level variables are written as bare `VAR name VAL value` pairs,
+    /// as required by the MSDP specification.
     ///
     /// # Arguments
     ///
@@ -183,7 +200,14 @@ impl MudServerData {
     /// * `Ok(usize)` - The number of bytes written
     /// * `Err(std::io::Error)` - If writing fails
     pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
-        self.0.write(writer)
+        let mut len = 0;
+        for (key, value) in &self.0.0 {
+            writer.write_u8(consts::option::msdp::VAR)?;
+            writer.write_all(key.as_bytes())?;
+            writer.write_u8(consts::option::msdp::VAL)?;
+            len += 2 + key.len() + value.write(writer)?;
+        }
+        Ok(len)
     }
 
     /// Decodes `MudServerData` from the provided buffer.
@@ -502,6 +526,11 @@ impl MudServerDataArray {
         self.0.get_mut(index)
     }
 
+    /// Iterates over the elements in order.
+    pub fn iter(&self) -> impl Iterator<Item = &MudServerDataValue> {
+        self.0.iter()
+    }
+
     /// Gets the encoded length of this array.
     ///
     /// Returns the total number of bytes that would be written when encoding
@@ -705,6 +734,16 @@ impl MudServerDataTable {
         self.0.get_mut(key)
     }
 
+    /// Iterates over the variables and their values.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &MudServerDataValue)> {
+        self.0.iter()
+    }
+
+    /// Returns `true` if there are no variables.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     /// Gets the encoded length of this table.
     ///
     /// Returns the total number of bytes that would be written when encoding
@@ -829,10 +868,24 @@ impl MudServerDataTable {
                 }
                 let key = String::from_utf8_lossy(&key_bytes).to_string();
 
-                // Expect VAL marker
+                // Expect VAL marker; several VALs after one VAR form an array
                 if src.has_remaining() && src.chunk()[0] == consts::option::msdp::VAL {
                     src.advance(1);
-                    table.set(&key, MudServerDataValue::decode(src)?);
+                    let mut values = vec![MudServerDataValue::decode(src)?];
+                    while src.has_remaining() && src.chunk()[0] == consts::option::msdp::VAL {
+                        src.advance(1);
+                        values.push(MudServerDataValue::decode(src)?);
+                    }
+                    let value = if values.len() == 1 {
+                        values.remove(0)
+                    } else {
+                        let mut array = MudServerDataArray::new();
+                        for value in values {
+                            array.push(value);
+                        }
+                        MudServerDataValue::Array(array)
+                    };
+                    table.set(&key, value);
                 }
             } else {
                 // Unexpected byte or we've reached the end
@@ -26,7 +26,7 @@
 //!
 use crate::{TelnetCodecResult, consts};
 use byteorder::WriteBytesExt;
-use bytes::BufMut;
+use bytes::{Buf, BufMut};
 use std::collections::HashMap;
 
 /// Mud Server Status Protocol handler for TELNET negotiation.
@@ -46,7 +46,7 @@ use std::collections::HashMap;
 /// let mut status = MudServerStatus::new();
 /// // Add server information...
 /// ```
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct MudServerStatus(HashMap<String, Vec<String>>);
 
 impl MudServerStatus {
@@ -70,6 +70,58 @@ impl MudServerStatus {
         MudServerStatus(HashMap::new())
     }
 
+    /// Sets the values of a variable, replacing any existing values.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The variable name (e.g. `"NAME"`, `"PLAYERS"`)
+    /// * `values` - The values to associate with the variable
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use termionix_telnetcodec::mssp::MudServerStatus;
+    ///
+    /// let mut status = MudServerStatus::new();
+    /// status.set("NAME", vec!["Termionix".to_string()]);
+    /// assert_eq!(status.get("NAME"), Some(&["Termionix".to_string()][..]));
+    /// ```
+    pub fn set(&mut self, key: &str, values: Vec<String>) {
+        self.0.insert(key.to_string(), values);
+    }
+
+    /// Appends a value to a variable, creating the variable if needed.
+    ///
+    /// MSSP allows a variable to carry several values, such as multiple
+    /// `PORT` or `CODEBASE` entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use termionix_telnetcodec::mssp::MudServerStatus;
+    ///
+    /// let mut status = MudServerStatus::new();
+    /// status.add("PORT", "4000");
+    /// status.add("PORT", "4001");
+    /// assert_eq!(status.get("PORT").map(<[String]>::len), Some(2));
+    /// ```
+    pub fn add(&mut self, key: &str, value: &str) {
+        self.0
+            .entry(key.to_string())
+            .or_default()
+            .push(value.to_string());
+    }
+
+    /// Returns the values associated with a variable, if present.
+    pub fn get(&self, key: &str) -> Option<&[String]> {
+        self.0.get(key).map(Vec::as_slice)
+    }
+
+    /// Returns an iterator over all variables and their values.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Vec<String>)> {
+        self.0.iter()
+    }
+
     /// Returns the encoded length of the `MudServerStatus` in bytes.
     ///
     /// Calculates the total number of bytes that will be needed to encode this
@@ -213,6 +265,63 @@ impl MudServerStatus {
         }
         Ok(len)
     }
+
+    /// Decodes a `MudServerStatus` from a subnegotiation payload.
+    ///
+    /// The payload is a series of `VAR name VAL value [VAL value...]` groups.
+    /// Variables without any `VAL` are kept with an empty value list. Bytes
+    /// preceding the first `VAR` marker are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `src` - A buffer implementing `Buf` containing the MSSP payload
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(MudServerStatus)` - The decoded status
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bytes::BytesMut;
+    /// use termionix_telnetcodec::mssp::MudServerStatus;
+    ///
+    /// let mut buf = BytesMut::from(&b"\x01NAME\x02Termionix\x01PORT\x024000\x024001"[..]);
+    /// let status = MudServerStatus::decode(&mut buf)?;
+    /// assert_eq!(status.get("NAME"), Some(&["Termionix".to_string()][..]));
+    /// assert_eq!(status.get("PORT").map(<[String]>::len), Some(2));
+    /// # Ok::<(), termionix_telnetcodec::TelnetCodecError>(())
+    /// ```
+    pub fn decode<T: Buf>(src: &mut T) -> TelnetCodecResult<MudServerStatus> {
+        let mut status = MudServerStatus::new();
+        let mut key: Option<String> = None;
+
+        // Skip anything before the first VAR marker
+        while src.has_remaining() && src.chunk()[0] != consts::option::mssp::VAR {
+            src.advance(1);
+        }
+
+        while src.has_remaining() {
+            let marker = src.get_u8();
+            let mut bytes = Vec::new();
+            while src.has_remaining() {
+                let byte = src.chunk()[0];
+                if byte == consts::option::mssp::VAR || byte == consts::option::mssp::VAL {
+                    break;
+                }
+                bytes.push(src.get_u8());
+            }
+            let text = String::from_utf8_lossy(&bytes).to_string();
+            if marker == consts::option::mssp::VAR {
+                status.0.entry(text.clone()).or_default();
+                key = Some(text);
+            } else if let Some(key) = &key {
+                status.add(key, &text);
+            }
+        }
+
+        Ok(status)
+    }
 }
 
 impl std::fmt::Display for MudServerStatus {
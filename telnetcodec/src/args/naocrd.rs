@@ -59,7 +59,7 @@ use bytes::{Buf, BufMut};
 /// // Decode from buffer
 /// let decoded = NAOCRD::decode(&mut buf)?;
 /// ```
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum NAOCRD {
     /// Carriage-return disposition from the data sender
     Sender(u8),
@@ -301,7 +301,7 @@ mod tests {
     #[test]
     fn test_naohts_default_tabs_very_small() {
         let naohts = NAOHTS::default_tabs(8);
-        assert_eq!(naohts.tab_stops, vec![]);
+        assert!(naohts.tab_stops.is_empty());
     }
 
     #[test]
@@ -366,7 +366,7 @@ mod tests {
         assert!(result.is_ok());
 
         let naohts = result.unwrap();
-        assert_eq!(naohts.tab_stops, vec![]);
+        assert!(naohts.tab_stops.is_empty());
     }
 
     #[test]
@@ -0,0 +1,543 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Telnet Environment Option (NEW-ENVIRON)
+//!
+//! <https://tools.ietf.org/html/rfc1572>
+//!
+//! NEW-ENVIRON lets a server ask the client for environment variables (`SEND`) and the client
+//! answer them (`IS`) or report later changes (`INFO`). Names and values are introduced by
+//! `VAR`, `USERVAR` and `VALUE` bytes; any of those bytes, or `ESC`, inside a name or value is
+//! prefixed with `ESC`.
+//!
+//! The [`mnes`] module lists the variables of the Mud New-Environ Standard.
+
+use crate::{SubnegotiationErrorKind, TelnetCodecError, TelnetCodecResult, consts};
+use bytes::{Buf, BufMut};
+
+/// Variables defined by the Mud New-Environ Standard (MNES).
+///
+/// See `doc/mnes.md`. Servers request them with [`NewEnviron::send`] and clients answer with
+/// [`NewEnviron::answer`].
+pub mod mnes {
+    /// Character set of the client, e.g. `UTF-8`
+    pub const CHARSET: &str = "CHARSET";
+    /// Name of the client
+    pub const CLIENT_NAME: &str = "CLIENT_NAME";
+    /// Version of the client
+    pub const CLIENT_VERSION: &str = "CLIENT_VERSION";
+    /// The client's real IP address, reported by proxies
+    pub const IPADDRESS: &str = "IPADDRESS";
+    /// The MTTS bitvector of the client
+    pub const MTTS: &str = "MTTS";
+    /// The terminal type, as reported through MTTS
+    pub const TERMINAL_TYPE: &str = "TERMINAL_TYPE";
+
+    /// Every MNES variable
+    pub const VARIABLES: [&str; 6] = [
+        CLIENT_NAME,
+        CLIENT_VERSION,
+        CHARSET,
+        MTTS,
+        IPADDRESS,
+        TERMINAL_TYPE,
+    ];
+}
+
+/// NEW-ENVIRON subnegotiation command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EnvironCommand {
+    /// Answer to a `SEND` request
+    Is,
+    /// Request for variables
+    Send,
+    /// Unsolicited update of variables
+    Info,
+}
+
+impl EnvironCommand {
+    /// Returns the command byte.
+    #[must_use]
+    pub fn to_u8(self) -> u8 {
+        match self {
+            EnvironCommand::Is => consts::option::new_environment::IS,
+            EnvironCommand::Send => consts::option::new_environment::SEND,
+            EnvironCommand::Info => consts::option::new_environment::INFO,
+        }
+    }
+}
+
+/// Whether a NEW-ENVIRON variable is well-known (`VAR`) or user-defined (`USERVAR`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EnvironVariableKind {
+    /// Well-known variable such as `USER` or the MNES variables
+    Var,
+    /// User-defined variable
+    UserVar,
+}
+
+impl EnvironVariableKind {
+    /// Returns the type byte introducing a variable of this kind.
+    #[must_use]
+    pub fn to_u8(self) -> u8 {
+        match self {
+            EnvironVariableKind::Var => consts::option::new_environment::VAR,
+            EnvironVariableKind::UserVar => consts::option::new_environment::USERVAR,
+        }
+    }
+}
+
+/// A variable in a NEW-ENVIRON subnegotiation.
+///
+/// In `SEND` requests only the name is used, and an empty name asks for every variable of that
+/// kind. In `IS` and `INFO` replies a `None` value means the variable is not defined, while
+/// `Some("")` means it is defined but empty.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvironVariable {
+    /// Well-known or user-defined
+    pub kind: EnvironVariableKind,
+    /// Variable name
+    pub name: String,
+    /// Variable value, `None` if undefined
+    pub value: Option<String>,
+}
+
+impl EnvironVariable {
+    /// Creates a well-known variable.
+    #[must_use]
+    pub fn var(name: impl Into<String>, value: Option<String>) -> EnvironVariable {
+        EnvironVariable {
+            kind: EnvironVariableKind::Var,
+            name: name.into(),
+            value,
+        }
+    }
+
+    /// Creates a user-defined variable.
+    #[must_use]
+    pub fn user_var(name: impl Into<String>, value: Option<String>) -> EnvironVariable {
+        EnvironVariable {
+            kind: EnvironVariableKind::UserVar,
+            name: name.into(),
+            value,
+        }
+    }
+
+    /// Returns the encoded length of this variable, including `ESC` bytes.
+    fn len(&self) -> usize {
+        1 + escaped_len(self.name.as_bytes())
+            + self
+                .value
+                .as_ref()
+                .map_or(0, |value| 1 + escaped_len(value.as_bytes()))
+    }
+
+    /// Writes this variable with its type byte and escaped name and value.
+    fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&[self.kind.to_u8()])?;
+        write_escaped(writer, self.name.as_bytes())?;
+        if let Some(value) = &self.value {
+            writer.write_all(&[consts::option::new_environment::VALUE])?;
+            write_escaped(writer, value.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// A NEW-ENVIRON (RFC 1572) subnegotiation.
+///
+/// # Example
+///
+/// ```
+/// use bytes::BytesMut;
+/// use termionix_telnetcodec::new_environ::{EnvironCommand, NewEnviron, mnes};
+///
+/// // The server asks for the client name
+/// let request = NewEnviron::send([mnes::CLIENT_NAME]);
+/// let mut buf = BytesMut::new();
+/// request.encode(&mut buf)?;
+/// assert_eq!(&buf[..], b"\x01\x00CLIENT_NAME");
+///
+/// // The client answers with what it knows
+/// let reply = request.answer([(mnes::CLIENT_NAME, "termionix")]).unwrap();
+/// assert_eq!(reply.command, EnvironCommand::Is);
+/// assert_eq!(reply.get(mnes::CLIENT_NAME), Some("termionix"));
+/// # Ok::<(), termionix_telnetcodec::TelnetCodecError>(())
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NewEnviron {
+    /// The subnegotiation command
+    pub command: EnvironCommand,
+    /// The variables, in the order they were sent
+    pub variables: Vec<EnvironVariable>,
+}
+
+impl NewEnviron {
+    /// Creates a `SEND` request for the named well-known variables.
+    ///
+    /// An empty list requests every variable, which is how MNES servers ask for everything.
+    #[must_use]
+    pub fn send<I, S>(names: I) -> NewEnviron
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        NewEnviron {
+            command: EnvironCommand::Send,
+            variables: names
+                .into_iter()
+                .map(|name| EnvironVariable::var(name, None))
+                .collect(),
+        }
+    }
+
+    /// Creates an `IS` reply.
+    #[must_use]
+    pub fn is(variables: Vec<EnvironVariable>) -> NewEnviron {
+        NewEnviron {
+            command: EnvironCommand::Is,
+            variables,
+        }
+    }
+
+    /// Creates an `INFO` update.
+    #[must_use]
+    pub fn info(variables: Vec<EnvironVariable>) -> NewEnviron {
+        NewEnviron {
+            command: EnvironCommand::Info,
+            variables,
+        }
+    }
+
+    /// Returns the value of a variable of either kind, if present and defined.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.variables
+            .iter()
+            .find(|variable| variable.name == name)
+            .and_then(|variable| variable.value.as_deref())
+    }
+
+    /// Returns whether a `SEND` request asks for every variable of `kind`.
+    ///
+    /// That is the case for a request without variables, or one containing `kind` with an empty
+    /// name.
+    #[must_use]
+    pub fn requests_all(&self, kind: EnvironVariableKind) -> bool {
+        self.command == EnvironCommand::Send
+            && (self.variables.is_empty()
+                || self
+                    .variables
+                    .iter()
+                    .any(|variable| variable.kind == kind && variable.name.is_empty()))
+    }
+
+    /// Builds the `IS` reply to a `SEND` request from the variables the client defines.
+    ///
+    /// `defined` holds the well-known variables. Requests for every variable are answered with
+    /// all of them; variables requested by name but not defined are answered without a value,
+    /// as RFC 1572 requires. Returns `None` if this is not a `SEND` request.
+    pub fn answer<'a, I>(&self, defined: I) -> Option<NewEnviron>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        if self.command != EnvironCommand::Send {
+            return None;
+        }
+        let defined: Vec<(&str, &str)> = defined.into_iter().collect();
+        let lookup = |name: &str| {
+            defined
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        };
+
+        let mut variables = Vec::new();
+        if self.requests_all(EnvironVariableKind::Var) {
+            variables.extend(
+                defined
+                    .iter()
+                    .map(|(name, value)| EnvironVariable::var(*name, Some(value.to_string()))),
+            );
+        }
+        for request in self.variables.iter().filter(|v| !v.name.is_empty()) {
+            let answered = variables
+                .iter()
+                .any(|v: &EnvironVariable| v.kind == request.kind && v.name == request.name);
+            if answered {
+                continue;
+            }
+            let value = match request.kind {
+                EnvironVariableKind::Var => lookup(&request.name),
+                EnvironVariableKind::UserVar => None,
+            };
+            variables.push(EnvironVariable {
+                kind: request.kind,
+                name: request.name.clone(),
+                value,
+            });
+        }
+        Some(NewEnviron::is(variables))
+    }
+
+    /// Returns the encoded length of this subnegotiation, including `ESC` bytes.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        1 + self
+            .variables
+            .iter()
+            .map(EnvironVariable::len)
+            .sum::<usize>()
+    }
+
+    /// Returns `true` if there are no variables.
+    ///
+    /// A `SEND` without variables requests every variable; see
+    /// [`requests_all`](Self::requests_all).
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+
+    /// Encodes this subnegotiation into `dst`.
+    pub fn encode<T: BufMut>(&self, dst: &mut T) -> TelnetCodecResult<usize> {
+        Ok(self.write(&mut dst.writer())?)
+    }
+
+    /// Writes the command byte followed by every variable.
+    pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
+        writer.write_all(&[self.command.to_u8()])?;
+        for variable in &self.variables {
+            variable.write(writer)?;
+        }
+        Ok(self.len())
+    }
+
+    /// Decodes a NEW-ENVIRON subnegotiation payload.
+    ///
+    /// Bytes before the first `VAR` or `USERVAR` are ignored, as is a `VALUE` without a
+    /// variable.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bytes::BytesMut;
+    /// use termionix_telnetcodec::new_environ::{EnvironCommand, NewEnviron};
+    ///
+    /// // IS VAR "USER" VALUE "alice" USERVAR "TZ"
+    /// let mut buf = BytesMut::from(&b"\x00\x00USER\x01alice\x03TZ"[..]);
+    /// let environ = NewEnviron::decode(&mut buf)?;
+    /// assert_eq!(environ.command, EnvironCommand::Is);
+    /// assert_eq!(environ.get("USER"), Some("alice"));
+    /// assert_eq!(environ.variables[1].value, None);
+    /// # Ok::<(), termionix_telnetcodec::TelnetCodecError>(())
+    /// ```
+    pub fn decode<T: Buf>(src: &mut T) -> TelnetCodecResult<NewEnviron> {
+        use consts::option::new_environment::{ESC, INFO, IS, SEND, USERVAR, VALUE, VAR};
+
+        if !src.has_remaining() {
+            return Err(TelnetCodecError::SubnegotiationError {
+                option: Some(consts::option::NEW_ENVIRONMENT),
+                reason: SubnegotiationErrorKind::InsufficientData {
+                    required: 1,
+                    available: 0,
+                },
+            });
+        }
+        let command = match src.get_u8() {
+            IS => EnvironCommand::Is,
+            SEND => EnvironCommand::Send,
+            INFO => EnvironCommand::Info,
+            command => {
+                return Err(TelnetCodecError::SubnegotiationError {
+                    option: Some(consts::option::NEW_ENVIRONMENT),
+                    reason: SubnegotiationErrorKind::InvalidCommand {
+                        command,
+                        expected: Some(vec![IS, SEND, INFO]),
+                    },
+                });
+            }
+        };
+
+        // Name and value bytes of the variable being decoded
+        let mut current: Option<(EnvironVariableKind, Vec<u8>, Option<Vec<u8>>)> = None;
+        let mut variables = Vec::new();
+        let mut finish = |current: Option<(EnvironVariableKind, Vec<u8>, Option<Vec<u8>>)>| {
+            if let Some((kind, name, value)) = current {
+                variables.push(EnvironVariable {
+                    kind,
+                    name: String::from_utf8_lossy(&name).to_string(),
+                    value: value.map(|value| String::from_utf8_lossy(&value).to_string()),
+                });
+            }
+        };
+
+        while src.has_remaining() {
+            let byte = match src.get_u8() {
+                VAR => {
+                    finish(current.replace((EnvironVariableKind::Var, Vec::new(), None)));
+                    continue;
+                }
+                USERVAR => {
+                    finish(current.replace((EnvironVariableKind::UserVar, Vec::new(), None)));
+                    continue;
+                }
+                VALUE => {
+                    if let Some((_, _, value)) = &mut current {
+                        *value = Some(Vec::new());
+                    }
+                    continue;
+                }
+                ESC if src.has_remaining() => src.get_u8(),
+                byte => byte,
+            };
+            match &mut current {
+                Some((_, _, Some(value))) => value.push(byte),
+                Some((_, name, None)) => name.push(byte),
+                None => {}
+            }
+        }
+        finish(current);
+
+        Ok(NewEnviron { command, variables })
+    }
+}
+
+impl std::fmt::Display for NewEnviron {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Whether `byte` must be prefixed with `ESC` inside a name or value.
+fn needs_escape(byte: u8) -> bool {
+    use consts::option::new_environment::{ESC, USERVAR, VALUE, VAR};
+    matches!(byte, VAR | VALUE | ESC | USERVAR)
+}
+
+/// Length of `bytes` once escaped.
+fn escaped_len(bytes: &[u8]) -> usize {
+    bytes.len() + bytes.iter().filter(|byte| needs_escape(**byte)).count()
+}
+
+/// Writes `bytes`, prefixing `VAR`, `VALUE`, `ESC` and `USERVAR` bytes with `ESC`.
+fn write_escaped<W: std::io::Write>(writer: &mut W, bytes: &[u8]) -> std::io::Result<()> {
+    for chunk in bytes.split_inclusive(|byte| needs_escape(*byte)) {
+        match chunk.split_last() {
+            Some((last, rest)) if needs_escape(*last) => {
+                writer.write_all(rest)?;
+                writer.write_all(&[consts::option::new_environment::ESC, *last])?;
+            }
+            _ => writer.write_all(chunk)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+
+    fn roundtrip(environ: &NewEnviron) -> NewEnviron {
+        let mut buf = BytesMut::new();
+        let written = environ.encode(&mut buf).unwrap();
+        assert_eq!(written, buf.len());
+        assert_eq!(written, environ.len());
+        NewEnviron::decode(&mut buf).unwrap()
+    }
+
+    #[test]
+    fn test_send_encoding() {
+        let mut buf = BytesMut::new();
+        NewEnviron::send(["CHARSET", "IPADDRESS"])
+            .encode(&mut buf)
+            .unwrap();
+        assert_eq!(&buf[..], b"\x01\x00CHARSET\x00IPADDRESS");
+
+        let mut buf = BytesMut::new();
+        let send_all = NewEnviron::send(Vec::<String>::new());
+        assert!(send_all.is_empty());
+        send_all.encode(&mut buf).unwrap();
+        assert_eq!(&buf[..], b"\x01");
+    }
+
+    #[test]
+    fn test_roundtrip_with_escapes() {
+        let environ = NewEnviron::info(vec![
+            EnvironVariable::var("ODD\x00NAME", Some("a\x01b\x02c\x03".to_string())),
+            EnvironVariable::user_var("EMPTY", Some(String::new())),
+            EnvironVariable::var("UNDEFINED", None),
+        ]);
+        let mut buf = BytesMut::new();
+        environ.encode(&mut buf).unwrap();
+        assert_eq!(
+            &buf[..],
+            b"\x02\x00ODD\x02\x00NAME\x01a\x02\x01b\x02\x02c\x02\x03\x03EMPTY\x01\x00UNDEFINED"
+        );
+        assert_eq!(roundtrip(&environ), environ);
+    }
+
+    #[test]
+    fn test_decode_requests_all() {
+        let mut buf = BytesMut::from(&b"\x01\x00"[..]);
+        let request = NewEnviron::decode(&mut buf).unwrap();
+        assert!(request.requests_all(EnvironVariableKind::Var));
+        assert!(!request.requests_all(EnvironVariableKind::UserVar));
+
+        let mut buf = BytesMut::from(&b"\x01"[..]);
+        let request = NewEnviron::decode(&mut buf).unwrap();
+        assert!(request.requests_all(EnvironVariableKind::UserVar));
+    }
+
+    #[test]
+    fn test_decode_invalid_command() {
+        let mut buf = BytesMut::from(&b"\x07\x00USER"[..]);
+        assert!(matches!(
+            NewEnviron::decode(&mut buf),
+            Err(TelnetCodecError::SubnegotiationError {
+                option: Some(consts::option::NEW_ENVIRONMENT),
+                reason: SubnegotiationErrorKind::InvalidCommand { command: 7, .. },
+            })
+        ));
+        assert!(NewEnviron::decode(&mut BytesMut::new()).is_err());
+    }
+
+    #[test]
+    fn test_answer_named_and_all() {
+        let defined = [(mnes::CLIENT_NAME, "termionix"), (mnes::CHARSET, "UTF-8")];
+
+        let reply = NewEnviron::send([mnes::CHARSET, mnes::IPADDRESS])
+            .answer(defined)
+            .unwrap();
+        assert_eq!(
+            reply,
+            NewEnviron::is(vec![
+                EnvironVariable::var(mnes::CHARSET, Some("UTF-8".to_string())),
+                EnvironVariable::var(mnes::IPADDRESS, None),
+            ])
+        );
+
+        let reply = NewEnviron::send(Vec::<String>::new())
+            .answer(defined)
+            .unwrap();
+        assert_eq!(reply.get(mnes::CLIENT_NAME), Some("termionix"));
+        assert_eq!(reply.get(mnes::CHARSET), Some("UTF-8"));
+        assert_eq!(reply.variables.len(), 2);
+
+        assert!(NewEnviron::is(vec![]).answer(defined).is_none());
+    }
+}
@@ -31,7 +31,7 @@ use bytes::{Buf, BufMut};
 use std::collections::HashMap;
 
 /// Status subnegotiation command types
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum StatusCommand {
     /// SEND - Request status information
     Send,
@@ -69,7 +69,7 @@ impl StatusCommand {
 /// Telnet Option Status
 ///
 /// Represents the status of codec options as pairs of (option, DO/DONT, WILL/WONT)
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TelnetOptionStatus {
     ///
     pub command: StatusCommand,
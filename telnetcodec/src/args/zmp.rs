@@ -0,0 +1,464 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use crate::result::TelnetCodecResult;
+use crate::{SubnegotiationErrorKind, TelnetCodecError, consts};
+use bytes::{BufMut, BytesMut};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+///
+/// ZMP (Zenith MUD Protocol) Message
+///
+/// A ZMP message is a command followed by any number of arguments, each
+/// terminated by a NUL byte. Command names are grouped into packages by
+/// dots, e.g. `zmp.ping` belongs to the `zmp.` package.
+///
+/// Format: `<command> NUL [<argument> NUL]*`
+///
+/// # References
+///
+/// - [ZMP Specification](http://discworld.starturtle.net/external/protocols/zmp.html)
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZmpMessage {
+    /// The command name (e.g., "zmp.ping", "zmp.check")
+    command: String,
+
+    /// The arguments following the command
+    arguments: Vec<String>,
+}
+
+impl ZmpMessage {
+    /// Creates a new ZMP message without arguments.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use termionix_telnetcodec::zmp::ZmpMessage;
+    ///
+    /// let msg = ZmpMessage::new("zmp.check").with_argument("zmp.");
+    /// assert_eq!(msg.command(), "zmp.check");
+    /// assert_eq!(msg.arguments(), ["zmp."]);
+    /// ```
+    #[must_use]
+    pub fn new<S: Into<String>>(command: S) -> Self {
+        Self {
+            command: command.into(),
+            arguments: Vec::new(),
+        }
+    }
+
+    /// Adds an argument to the message.
+    #[must_use]
+    pub fn with_argument<S: Into<String>>(mut self, argument: S) -> Self {
+        self.arguments.push(argument.into());
+        self
+    }
+
+    /// Decodes a ZMP message from a subnegotiation payload.
+    ///
+    /// Invalid UTF-8 is replaced rather than rejected.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TelnetCodecError::SubnegotiationError` when the command is
+    /// empty or a string is not NUL-terminated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bytes::BytesMut;
+    /// use termionix_telnetcodec::zmp::ZmpMessage;
+    ///
+    /// let mut data = BytesMut::from(&b"zmp.check\0zmp.ping\0"[..]);
+    /// let msg = ZmpMessage::decode(&mut data)?;
+    /// assert_eq!(msg, ZmpMessage::new("zmp.check").with_argument("zmp.ping"));
+    /// # Ok::<(), termionix_telnetcodec::TelnetCodecError>(())
+    /// ```
+    pub fn decode(src: &mut BytesMut) -> TelnetCodecResult<ZmpMessage> {
+        let data = src.split();
+        let Some(body) = data.strip_suffix(&[0]) else {
+            return Err(Self::error(SubnegotiationErrorKind::IncompleteData {
+                description: "ZMP string is not NUL-terminated".to_string(),
+            }));
+        };
+        let mut strings = body
+            .split(|byte| *byte == 0)
+            .map(|string| String::from_utf8_lossy(string).into_owned());
+        let command = strings.next().unwrap_or_default();
+        if command.is_empty() {
+            return Err(Self::error(SubnegotiationErrorKind::Other {
+                description: "empty ZMP command".to_string(),
+            }));
+        }
+        Ok(Self {
+            command,
+            arguments: strings.collect(),
+        })
+    }
+
+    fn error(reason: SubnegotiationErrorKind) -> TelnetCodecError {
+        TelnetCodecError::SubnegotiationError {
+            option: Some(consts::option::ZMP),
+            reason,
+        }
+    }
+
+    /// Returns the command name.
+    #[must_use]
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Returns the arguments.
+    #[must_use]
+    pub fn arguments(&self) -> &[String] {
+        &self.arguments
+    }
+
+    /// Returns the argument at `index`.
+    #[must_use]
+    pub fn argument(&self, index: usize) -> Option<&str> {
+        self.arguments.get(index).map(String::as_str)
+    }
+
+    /// Encodes the ZMP message to bytes.
+    #[must_use]
+    pub fn encode(&self) -> BytesMut {
+        let mut bytes = BytesMut::with_capacity(self.len());
+        for string in std::iter::once(&self.command).chain(&self.arguments) {
+            bytes.extend_from_slice(string.as_bytes());
+            bytes.put_u8(0);
+        }
+        bytes
+    }
+
+    /// Returns the encoded byte length of this message.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        std::iter::once(&self.command)
+            .chain(&self.arguments)
+            .map(|string| string.len() + 1)
+            .sum()
+    }
+
+    /// Checks if the message is empty (has no command).
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.command.is_empty()
+    }
+
+    /// Writes the ZMP message to a writer.
+    ///
+    /// Returns the number of bytes written.
+    pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
+        writer.write_all(&self.encode())?;
+        Ok(self.len())
+    }
+}
+
+impl fmt::Display for ZmpMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.command)?;
+        for argument in &self.arguments {
+            write!(f, " {argument:?}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Handler for a ZMP command, returning the reply to send, if any
+///
+/// The registry is passed along so handlers can look up other commands.
+pub type ZmpHandler = Arc<dyn Fn(&ZmpRegistry, &ZmpMessage) -> Option<ZmpMessage> + Send + Sync>;
+
+///
+/// ZMP Command Registry
+///
+/// Maps ZMP commands to the handlers that answer them. [`TelnetCodec`](crate::TelnetCodec)
+/// runs the matching handler for every ZMP message received while ZMP is
+/// enabled and queues its reply; the message is still passed on as a
+/// [`TelnetEvent::Subnegotiate`](crate::TelnetEvent::Subnegotiate).
+///
+/// The default registry implements the standard `zmp.` package:
+///
+/// | Command          | Reply                                           |
+/// |------------------|-------------------------------------------------|
+/// | `zmp.ping`       | `zmp.time` with the current UTC time            |
+/// | `zmp.time`       | None                                            |
+/// | `zmp.ident`      | None                                            |
+/// | `zmp.check`      | `zmp.support` or `zmp.no-support` with the name |
+/// | `zmp.support`    | None                                            |
+/// | `zmp.no-support` | None                                            |
+///
+/// # Examples
+///
+/// ```
+/// use termionix_telnetcodec::zmp::{ZmpMessage, ZmpRegistry};
+///
+/// let registry = ZmpRegistry::default().with_handler("game.echo", |_, msg| {
+///     Some(ZmpMessage::new("game.echo").with_argument(msg.argument(0)?))
+/// });
+///
+/// let check = ZmpMessage::new("zmp.check").with_argument("game.");
+/// assert_eq!(
+///     registry.handle(&check),
+///     Some(ZmpMessage::new("zmp.support").with_argument("game."))
+/// );
+/// ```
+#[derive(Clone)]
+pub struct ZmpRegistry {
+    handlers: BTreeMap<String, ZmpHandler>,
+    ident: Option<(String, String, String)>,
+}
+
+impl ZmpRegistry {
+    /// Creates a registry with the standard `zmp.` commands.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry without any commands.
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            handlers: BTreeMap::new(),
+            ident: None,
+        }
+    }
+
+    /// Registers a handler for `command`, replacing any previous one.
+    pub fn register<S, F>(&mut self, command: S, handler: F)
+    where
+        S: Into<String>,
+        F: Fn(&ZmpRegistry, &ZmpMessage) -> Option<ZmpMessage> + Send + Sync + 'static,
+    {
+        self.handlers.insert(command.into(), Arc::new(handler));
+    }
+
+    /// Registers a handler for `command`, replacing any previous one.
+    #[must_use]
+    pub fn with_handler<S, F>(mut self, command: S, handler: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&ZmpRegistry, &ZmpMessage) -> Option<ZmpMessage> + Send + Sync + 'static,
+    {
+        self.register(command, handler);
+        self
+    }
+
+    /// Removes the handler for `command`.
+    pub fn unregister(&mut self, command: &str) -> bool {
+        self.handlers.remove(command).is_some()
+    }
+
+    /// Sends `zmp.ident` with `name`, `version` and `description` once ZMP is enabled.
+    #[must_use]
+    pub fn with_ident<N, V, D>(mut self, name: N, version: V, description: D) -> Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+        D: Into<String>,
+    {
+        self.ident = Some((name.into(), version.into(), description.into()));
+        self
+    }
+
+    /// The `zmp.ident` message sent once ZMP is enabled, if any.
+    #[must_use]
+    pub fn ident(&self) -> Option<ZmpMessage> {
+        self.ident.as_ref().map(|(name, version, description)| {
+            ZmpMessage::new("zmp.ident")
+                .with_argument(name)
+                .with_argument(version)
+                .with_argument(description)
+        })
+    }
+
+    /// Registered command names, in order.
+    pub fn commands(&self) -> impl Iterator<Item = &str> {
+        self.handlers.keys().map(String::as_str)
+    }
+
+    /// Checks whether a command, or a package when `name` ends in `.`, is supported.
+    #[must_use]
+    pub fn supports(&self, name: &str) -> bool {
+        if name.ends_with('.') {
+            self.commands().any(|command| command.starts_with(name))
+        } else {
+            self.handlers.contains_key(name)
+        }
+    }
+
+    /// Runs the handler for `message`, returning its reply.
+    ///
+    /// Unknown commands are ignored, as the specification requires.
+    #[must_use]
+    pub fn handle(&self, message: &ZmpMessage) -> Option<ZmpMessage> {
+        let handler = self.handlers.get(message.command())?;
+        handler(self, message)
+    }
+}
+
+impl Default for ZmpRegistry {
+    fn default() -> Self {
+        Self::empty()
+            .with_handler("zmp.ping", |_, _| {
+                Some(ZmpMessage::new("zmp.time").with_argument(utc_timestamp(SystemTime::now())))
+            })
+            .with_handler("zmp.time", |_, _| None)
+            .with_handler("zmp.ident", |_, _| None)
+            .with_handler("zmp.check", |registry, message| {
+                let name = message.argument(0)?;
+                let reply = if registry.supports(name) {
+                    "zmp.support"
+                } else {
+                    "zmp.no-support"
+                };
+                Some(ZmpMessage::new(reply).with_argument(name))
+            })
+            .with_handler("zmp.support", |_, _| None)
+            .with_handler("zmp.no-support", |_, _| None)
+    }
+}
+
+impl fmt::Debug for ZmpRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZmpRegistry")
+            .field("commands", &self.handlers.keys().collect::<Vec<_>>())
+            .field("ident", &self.ident)
+            .finish()
+    }
+}
+
+/// Formats `time` as `YYYY-MM-DD HH:MM:SS` in UTC, as `zmp.time` requires
+fn utc_timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let days = seconds / 86_400;
+    let rest = seconds % 86_400;
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rest / 3_600,
+        rest % 3_600 / 60,
+        rest % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_zmp_decode_with_arguments() {
+        let mut data = BytesMut::from(&b"zmp.ident\0termionix\x000.1\0A telnet library\0"[..]);
+        let msg = ZmpMessage::decode(&mut data).unwrap();
+        assert_eq!(msg.command(), "zmp.ident");
+        assert_eq!(msg.arguments(), ["termionix", "0.1", "A telnet library"]);
+    }
+
+    #[test]
+    fn test_zmp_decode_empty_argument() {
+        let mut data = BytesMut::from(&b"game.say\0\0"[..]);
+        let msg = ZmpMessage::decode(&mut data).unwrap();
+        assert_eq!(msg, ZmpMessage::new("game.say").with_argument(""));
+    }
+
+    #[test]
+    fn test_zmp_decode_rejects_malformed() {
+        assert!(ZmpMessage::decode(&mut BytesMut::from(&b"zmp.ping"[..])).is_err());
+        assert!(ZmpMessage::decode(&mut BytesMut::from(&b"\0"[..])).is_err());
+        assert!(ZmpMessage::decode(&mut BytesMut::new()).is_err());
+    }
+
+    #[test]
+    fn test_zmp_roundtrip() {
+        let original = ZmpMessage::new("zmp.check").with_argument("zmp.ping");
+        let mut encoded = original.encode();
+        assert_eq!(&encoded[..], b"zmp.check\0zmp.ping\0");
+        assert_eq!(encoded.len(), original.len());
+        assert_eq!(ZmpMessage::decode(&mut encoded).unwrap(), original);
+        assert_eq!(original.to_string(), "zmp.check \"zmp.ping\"");
+    }
+
+    #[test]
+    fn test_registry_ping_replies_with_time() {
+        let reply = ZmpRegistry::default()
+            .handle(&ZmpMessage::new("zmp.ping"))
+            .unwrap();
+        assert_eq!(reply.command(), "zmp.time");
+        assert_eq!(reply.argument(0).unwrap().len(), 19);
+    }
+
+    #[test]
+    fn test_registry_check() {
+        let registry = ZmpRegistry::default();
+        let check = |name: &str| registry.handle(&ZmpMessage::new("zmp.check").with_argument(name));
+        assert_eq!(
+            check("zmp.ping"),
+            Some(ZmpMessage::new("zmp.support").with_argument("zmp.ping"))
+        );
+        assert_eq!(
+            check("zmp."),
+            Some(ZmpMessage::new("zmp.support").with_argument("zmp."))
+        );
+        assert_eq!(
+            check("color."),
+            Some(ZmpMessage::new("zmp.no-support").with_argument("color."))
+        );
+        assert_eq!(registry.handle(&ZmpMessage::new("zmp.check")), None);
+    }
+
+    #[test]
+    fn test_registry_ignores_unknown_and_informational_commands() {
+        let registry = ZmpRegistry::default();
+        assert_eq!(registry.handle(&ZmpMessage::new("game.unknown")), None);
+        assert_eq!(
+            registry.handle(&ZmpMessage::new("zmp.time").with_argument("2026-01-01 00:00:00")),
+            None
+        );
+        assert!(ZmpRegistry::empty().commands().next().is_none());
+    }
+
+    #[test]
+    fn test_utc_timestamp() {
+        assert_eq!(utc_timestamp(UNIX_EPOCH), "1970-01-01 00:00:00");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_827_696);
+        assert_eq!(utc_timestamp(leap_day), "2000-02-29 12:34:56");
+    }
+}
@@ -14,11 +14,15 @@
 // limitations under the License.
 //
 
-use super::{TelnetCodecError, TelnetEvent, TelnetFrame, TelnetOption, consts};
+use super::{
+    CodecLimits, NegotiationGate, OptionPolicy, TelnetCodecError, TelnetEvent, TelnetFrame,
+    TelnetOption, consts,
+};
 use crate::args::TelnetArgument;
-use crate::args::gmcp::GmcpMessage;
+use crate::args::zmp::ZmpRegistry;
+use crate::limits::NegotiationCounter;
 use crate::options::{TelnetOptions, TelnetSide};
-use bytes::{Buf, BufMut, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use tokio_util::codec::{Decoder, Encoder};
 use tracing::warn;
 
@@ -30,6 +34,10 @@ use tracing::warn;
 ///
 /// This struct is typically paired with a transport-like implementation
 /// to facilitate stream I/O management for the Telnet sidechannel.
+///
+/// Data follows the NVT newline rules of RFC 854 in each direction until TRANSMIT-BINARY is
+/// enabled for it: the encoder sends `LF` as `CR LF` and a bare `CR` as `CR NUL`, and the decoder
+/// delivers `CR LF` as `LF` and `CR NUL` as `CR`. Binary data is passed through unchanged.
 #[derive(Clone, Debug)]
 pub struct TelnetCodec {
     decoder_buffer: BytesMut,
@@ -37,6 +45,18 @@ pub struct TelnetCodec {
     options: TelnetOptions,
     /// Queue of response frames to send (from sidechannel negotiations)
     response_queue: std::collections::VecDeque<TelnetFrame>,
+    /// Emit runs of data as `TelnetEvent::DataChunk` instead of one `Data` per byte
+    data_chunks: bool,
+    /// Limits on subnegotiation length, negotiation rate and queued responses
+    limits: CodecLimits,
+    /// Negotiations received per option, for `limits.max_negotiations_per_option`
+    negotiations: NegotiationCounter,
+    /// Decides whether to act on requests the peer starts
+    negotiation_gate: Option<NegotiationGate>,
+    /// ZMP commands answered automatically while ZMP is enabled
+    zmp: ZmpRegistry,
+    /// A CR was sent in NVT mode and the LF or NUL that must follow it has not been sent yet
+    encoder_cr: bool,
 }
 
 impl TelnetCodec {
@@ -61,6 +81,187 @@ impl TelnetCodec {
         TelnetCodec::default()
     }
 
+    /// Creates a new `TelnetCodec` that negotiates options according to `policy`.
+    ///
+    /// # Example
+    /// ```
+    /// use termionix_telnetcodec::{OptionPolicy, TelnetCodec, TelnetOption};
+    ///
+    /// let codec = TelnetCodec::with_policy(OptionPolicy::default().refuse(TelnetOption::GMCP));
+    /// assert!(!codec.is_supported_local(TelnetOption::GMCP));
+    /// ```
+    pub fn with_policy(policy: OptionPolicy) -> TelnetCodec {
+        TelnetCodec {
+            options: TelnetOptions::with_policy(policy),
+            ..TelnetCodec::default()
+        }
+    }
+
+    /// Returns the option negotiation policy of this codec
+    pub fn policy(&self) -> &OptionPolicy {
+        self.options.policy()
+    }
+
+    /// Replaces the option negotiation policy of this codec
+    pub fn set_policy(&mut self, policy: OptionPolicy) {
+        self.options.set_policy(policy);
+    }
+
+    /// Returns the resource limits of this codec
+    #[must_use]
+    pub fn limits(&self) -> &CodecLimits {
+        &self.limits
+    }
+
+    /// Replaces the resource limits of this codec
+    ///
+    /// Decoding fails with a [`TelnetCodecError`] once a limit is exceeded; see [`CodecLimits`].
+    ///
+    /// # Example
+    /// ```
+    /// use bytes::BytesMut;
+    /// use termionix_telnetcodec::{CodecLimits, TelnetCodec};
+    /// use tokio_util::codec::Decoder;
+    ///
+    /// let mut codec = TelnetCodec::new();
+    /// codec.set_limits(CodecLimits::default().with_max_subnegotiation_length(Some(4)));
+    ///
+    /// // IAC SB GMCP "Core.Hello" never fits in four bytes
+    /// let mut src = BytesMut::from(&b"\xFF\xFA\xC9Core.Hello"[..]);
+    /// let error = codec.decode(&mut src).unwrap_err();
+    /// assert!(error.is_limit_exceeded());
+    /// ```
+    pub fn set_limits(&mut self, limits: CodecLimits) {
+        self.limits = limits;
+    }
+
+    /// Returns the gate deciding whether to act on requests the peer starts
+    #[must_use]
+    pub fn negotiation_gate(&self) -> Option<&NegotiationGate> {
+        self.negotiation_gate.as_ref()
+    }
+
+    /// Replaces the gate deciding whether to act on requests the peer starts
+    ///
+    /// Requests the gate refuses are ignored; see [`NegotiationGate`].
+    pub fn set_negotiation_gate(&mut self, gate: Option<NegotiationGate>) {
+        self.negotiation_gate = gate;
+    }
+
+    /// Returns whether `frame` is a request from the peer the gate refuses
+    ///
+    /// Only `DO` and `WILL` for an option that is off on that side are
+    /// requests; anything else answers or cancels our own request.
+    fn is_gated(&self, frame: &TelnetFrame) -> bool {
+        let state = match frame {
+            TelnetFrame::Do(option) => self.options.local_qstate(*option),
+            TelnetFrame::Will(option) => self.options.remote_qstate(*option),
+            _ => return false,
+        };
+        state == crate::options::QState::No
+            && self
+                .negotiation_gate
+                .as_ref()
+                .is_some_and(|gate| !gate.allows(frame))
+    }
+
+    /// Returns the ZMP commands this codec answers
+    pub fn zmp(&self) -> &ZmpRegistry {
+        &self.zmp
+    }
+
+    /// Returns the ZMP commands this codec answers, for registering more
+    pub fn zmp_mut(&mut self) -> &mut ZmpRegistry {
+        &mut self.zmp
+    }
+
+    /// Replaces the ZMP commands this codec answers
+    ///
+    /// While ZMP is enabled in either direction, every `zmp.ping`, `zmp.check`
+    /// or other registered command received is answered with its handler's
+    /// reply; see [`ZmpRegistry`]. Once ZMP is enabled the registry's
+    /// `zmp.ident`, if any, is sent.
+    ///
+    /// # Example
+    /// ```
+    /// use bytes::BytesMut;
+    /// use termionix_telnetcodec::zmp::ZmpRegistry;
+    /// use termionix_telnetcodec::{TelnetCodec, TelnetEvent, TelnetOption, TelnetSide};
+    /// use tokio_util::codec::Decoder;
+    ///
+    /// let mut codec = TelnetCodec::new();
+    /// codec.set_zmp(ZmpRegistry::default().with_ident("mybot", "1.0", "An example bot"));
+    ///
+    /// // IAC WILL ZMP
+    /// let mut src = BytesMut::from(&[0xFF, 0xFB, 93][..]);
+    /// assert_eq!(
+    ///     codec.decode(&mut src).unwrap(),
+    ///     Some(TelnetEvent::OptionStatus(TelnetOption::ZMP, TelnetSide::Remote, true))
+    /// );
+    ///
+    /// let mut replies = BytesMut::new();
+    /// codec.flush_responses(&mut replies).unwrap();
+    /// assert!(replies.ends_with(b"zmp.ident\0mybot\x001.0\0An example bot\0\xFF\xF0"));
+    /// ```
+    pub fn set_zmp(&mut self, zmp: ZmpRegistry) {
+        self.zmp = zmp;
+    }
+
+    /// Returns whether data is decoded into `TelnetEvent::DataChunk` runs
+    pub fn data_chunks(&self) -> bool {
+        self.data_chunks
+    }
+
+    /// Enables or disables decoding data into `TelnetEvent::DataChunk` runs.
+    ///
+    /// When enabled, each `decode` call returns every data byte up to the next IAC as a single
+    /// event instead of one `TelnetEvent::Data` per byte. An escaped `IAC IAC` is returned as a
+    /// one byte chunk.
+    ///
+    /// # Example
+    /// ```
+    /// use bytes::BytesMut;
+    /// use termionix_telnetcodec::{TelnetCodec, TelnetEvent};
+    /// use tokio_util::codec::Decoder;
+    ///
+    /// let mut codec = TelnetCodec::new();
+    /// codec.set_data_chunks(true);
+    /// let mut src = BytesMut::from(&b"Hello"[..]);
+    /// assert_eq!(
+    ///     codec.decode(&mut src).unwrap(),
+    ///     Some(TelnetEvent::DataChunk("Hello".into()))
+    /// );
+    /// ```
+    pub fn set_data_chunks(&mut self, enabled: bool) {
+        self.data_chunks = enabled;
+    }
+
+    /// Starts negotiation for every option the policy requests proactively.
+    ///
+    /// Returns the `WILL` and `DO` frames that should be sent to the remote side, normally right
+    /// after the connection is established. Options that are already enabled or being negotiated
+    /// are skipped.
+    ///
+    /// # Example
+    /// ```
+    /// use termionix_telnetcodec::{OptionPolicy, TelnetCodec, TelnetFrame, TelnetOption};
+    ///
+    /// let mut codec =
+    ///     TelnetCodec::with_policy(OptionPolicy::default().request_local(TelnetOption::Echo));
+    /// assert_eq!(codec.request_policy_options(), vec![TelnetFrame::Will(TelnetOption::Echo)]);
+    /// ```
+    pub fn request_policy_options(&mut self) -> Vec<TelnetFrame> {
+        let policy = self.options.policy().clone();
+        let mut frames = Vec::new();
+        for option in policy.requested_local() {
+            frames.extend(self.options.enable_local(option));
+        }
+        for option in policy.requested_remote() {
+            frames.extend(self.options.enable_remote(option));
+        }
+        frames
+    }
+
     /// Check if there are pending sidechannel responses
     pub fn has_pending_responses(&self) -> bool {
         !self.response_queue.is_empty()
@@ -237,6 +438,49 @@ impl TelnetCodec {
     }
 }
 
+/// A codec that queues Telnet negotiation responses while decoding
+///
+/// Receiving `DO`, `DONT`, `WILL` or `WONT` may call for a reply, but a decoder
+/// cannot write to the connection. Replies are queued instead, and whoever owns
+/// the write side drains them with [`flush_responses`](Self::flush_responses).
+///
+/// Wrapping codecs forward these calls to the [`TelnetCodec`] they contain.
+pub trait TelnetResponder {
+    /// Check if there are negotiation responses waiting to be written
+    fn has_pending_responses(&self) -> bool;
+
+    /// Encode all pending responses into `dst`
+    fn flush_responses(&mut self, dst: &mut BytesMut) -> Result<(), TelnetCodecError>;
+
+    /// Queue a subnegotiation to be written with the pending responses
+    ///
+    /// Lets a decoder answer a subnegotiation, e.g. with another TTYPE `SEND`.
+    fn queue_subnegotiation(&mut self, argument: TelnetArgument) -> Result<(), TelnetCodecError>;
+
+    /// Replace the gate deciding whether to act on requests the peer starts
+    ///
+    /// See [`TelnetCodec::set_negotiation_gate`].
+    fn set_negotiation_gate(&mut self, gate: Option<NegotiationGate>);
+}
+
+impl TelnetResponder for TelnetCodec {
+    fn has_pending_responses(&self) -> bool {
+        TelnetCodec::has_pending_responses(self)
+    }
+
+    fn flush_responses(&mut self, dst: &mut BytesMut) -> Result<(), TelnetCodecError> {
+        TelnetCodec::flush_responses(self, dst)
+    }
+
+    fn queue_subnegotiation(&mut self, argument: TelnetArgument) -> Result<(), TelnetCodecError> {
+        self.queue_response(TelnetFrame::Subnegotiate(argument))
+    }
+
+    fn set_negotiation_gate(&mut self, gate: Option<NegotiationGate>) {
+        TelnetCodec::set_negotiation_gate(self, gate);
+    }
+}
+
 impl Default for TelnetCodec {
     fn default() -> Self {
         TelnetCodec {
@@ -244,6 +488,12 @@ impl Default for TelnetCodec {
             decoder_state: DecoderState::NormalData,
             options: TelnetOptions::default(),
             response_queue: std::collections::VecDeque::new(),
+            data_chunks: false,
+            limits: CodecLimits::default(),
+            negotiations: NegotiationCounter::default(),
+            negotiation_gate: None,
+            zmp: ZmpRegistry::default(),
+            encoder_cr: false,
         }
     }
 }
@@ -322,11 +572,46 @@ impl Decoder for TelnetCodec {
     ///   no new frame yet.
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<TelnetEvent>, Self::Error> {
         while src.remaining() > 0 {
+            if matches!(self.decoder_state, DecoderState::CarriageReturn) {
+                // NVT: CR LF is a newline, CR NUL a bare carriage return
+                self.decoder_state = DecoderState::NormalData;
+                let byte = match src[0] {
+                    consts::LF => consts::LF,
+                    consts::NUL => consts::CR,
+                    _ => return Ok(Some(self.data_event(consts::CR))),
+                };
+                src.advance(1);
+                return Ok(Some(self.data_event(byte)));
+            }
+            let nvt = !self.options.remote_enabled(TelnetOption::TransmitBinary);
+            if self.data_chunks
+                && matches!(self.decoder_state, DecoderState::NormalData)
+                && src[0] != consts::IAC
+                && !(nvt && src[0] == consts::CR)
+            {
+                let len = src
+                    .iter()
+                    .position(|&b| b == consts::IAC || (nvt && b == consts::CR))
+                    .unwrap_or(src.len());
+                let chunk = src.split_to(len).freeze();
+                if !self.options.local_enabled(TelnetOption::TransmitBinary)
+                    && let Some(byte) = chunk.iter().find(|&&b| b > 0x7F)
+                {
+                    warn!(
+                        "TransmitBinary option is disabled but received non-ASCII byte: 0x{:02X}",
+                        byte
+                    );
+                }
+                return Ok(Some(TelnetEvent::DataChunk(chunk)));
+            }
             let byte = src.get_u8();
             match (self.decoder_state, byte) {
                 (DecoderState::NormalData, consts::IAC) => {
                     self.decoder_state = DecoderState::InterpretAsCommand;
                 }
+                (DecoderState::NormalData, consts::CR) if nvt => {
+                    self.decoder_state = DecoderState::CarriageReturn;
+                }
                 (DecoderState::NormalData, _) => {
                     self.decoder_state = DecoderState::NormalData;
                     if !self.options.local_enabled(TelnetOption::TransmitBinary) && byte > 0x7F {
@@ -380,7 +665,7 @@ impl Decoder for TelnetCodec {
                 }
                 (DecoderState::InterpretAsCommand, consts::IAC) => {
                     self.decoder_state = DecoderState::NormalData;
-                    return Ok(Some(TelnetEvent::Data(consts::IAC)));
+                    return Ok(Some(self.data_event(consts::IAC)));
                 }
                 (DecoderState::InterpretAsCommand, consts::DO) => {
                     self.decoder_state = DecoderState::NegotiateDo;
@@ -405,8 +690,12 @@ impl Decoder for TelnetCodec {
                 }
                 (DecoderState::NegotiateDo, _) => {
                     self.decoder_state = DecoderState::NormalData;
+                    self.negotiations.record(byte, &self.limits)?;
                     let option: TelnetOption = byte.into();
                     let frame = TelnetFrame::Do(option);
+                    if self.is_gated(&frame) {
+                        continue;
+                    }
                     // Check QState before processing - DO affects LOCAL side
                     let was_yes = matches!(
                         self.options.local_qstate(option),
@@ -414,13 +703,20 @@ impl Decoder for TelnetCodec {
                     );
                     // Process through QState machine and queue any response
                     if let Some(response) = self.options.handle_received(frame)? {
-                        self.response_queue.push_back(response);
+                        self.queue_response(response)?;
                     }
                     // Check if we transitioned to/from Yes state
                     let is_yes = matches!(
                         self.options.local_qstate(option),
                         crate::options::QState::Yes
                     );
+                    if is_yes && !was_yes && option == TelnetOption::MXP {
+                        // MXP starts once the server follows the agreement with an empty subnegotiation
+                        self.queue_response(TelnetFrame::Subnegotiate(TelnetArgument::MXPStart))?;
+                    }
+                    if is_yes && !was_yes && option == TelnetOption::ZMP {
+                        self.start_zmp(TelnetSide::Local)?;
+                    }
                     if is_yes != was_yes {
                         return Ok(Some(TelnetEvent::OptionStatus(
                             option,
@@ -432,6 +728,7 @@ impl Decoder for TelnetCodec {
                 }
                 (DecoderState::NegotiateDont, _) => {
                     self.decoder_state = DecoderState::NormalData;
+                    self.negotiations.record(byte, &self.limits)?;
                     let option: TelnetOption = byte.into();
                     let frame = TelnetFrame::Dont(option);
                     // Check QState before processing - DONT affects LOCAL side
@@ -441,7 +738,7 @@ impl Decoder for TelnetCodec {
                     );
                     // Process through QState machine and queue any response
                     if let Some(response) = self.options.handle_received(frame)? {
-                        self.response_queue.push_back(response);
+                        self.queue_response(response)?;
                     }
                     // Check if we transitioned to/from Yes state
                     let is_yes = matches!(
@@ -459,8 +756,12 @@ impl Decoder for TelnetCodec {
                 }
                 (DecoderState::NegotiateWill, _) => {
                     self.decoder_state = DecoderState::NormalData;
+                    self.negotiations.record(byte, &self.limits)?;
                     let option: TelnetOption = byte.into();
                     let frame = TelnetFrame::Will(option);
+                    if self.is_gated(&frame) {
+                        continue;
+                    }
                     // Check QState before processing - WILL affects REMOTE side
                     let was_yes = matches!(
                         self.options.remote_qstate(option),
@@ -468,13 +769,16 @@ impl Decoder for TelnetCodec {
                     );
                     // Process through QState machine and queue any response
                     if let Some(response) = self.options.handle_received(frame)? {
-                        self.response_queue.push_back(response);
+                        self.queue_response(response)?;
                     }
                     // Check if we transitioned to/from Yes state
                     let is_yes = matches!(
                         self.options.remote_qstate(option),
                         crate::options::QState::Yes
                     );
+                    if is_yes && !was_yes && option == TelnetOption::ZMP {
+                        self.start_zmp(TelnetSide::Remote)?;
+                    }
                     if is_yes != was_yes {
                         return Ok(Some(TelnetEvent::OptionStatus(
                             option,
@@ -486,6 +790,7 @@ impl Decoder for TelnetCodec {
                 }
                 (DecoderState::NegotiateWont, _) => {
                     self.decoder_state = DecoderState::NormalData;
+                    self.negotiations.record(byte, &self.limits)?;
                     let option: TelnetOption = byte.into();
                     let frame = TelnetFrame::Wont(option);
                     // Check QState before processing - WONT affects REMOTE side
@@ -495,7 +800,7 @@ impl Decoder for TelnetCodec {
                     );
                     // Process through QState machine and queue any response
                     if let Some(response) = self.options.handle_received(frame)? {
-                        self.response_queue.push_back(response);
+                        self.queue_response(response)?;
                     }
                     // Check if we transitioned to/from Yes state
                     let is_yes = matches!(
@@ -517,44 +822,39 @@ impl Decoder for TelnetCodec {
                 (DecoderState::SubnegotiateArgument(option), consts::IAC) => {
                     self.decoder_state = DecoderState::SubnegotiateArgumentIAC(option);
                 }
-                (DecoderState::SubnegotiateArgument(_option), _) => {
-                    self.decoder_buffer.put_u8(byte);
+                (DecoderState::SubnegotiateArgument(option), _) => {
+                    self.buffer_subnegotiation(option, byte)?;
                 }
                 (DecoderState::SubnegotiateArgumentIAC(option), consts::IAC) => {
                     self.decoder_state = DecoderState::SubnegotiateArgument(option);
-                    self.decoder_buffer.put_u8(consts::IAC);
+                    self.buffer_subnegotiation(option, consts::IAC)?;
                 }
                 (DecoderState::SubnegotiateArgumentIAC(option), consts::SE) => {
                     self.decoder_state = DecoderState::NormalData;
                     let option = TelnetOption::from_u8(option);
                     let mut buffer = BytesMut::from(self.decoder_buffer.as_ref());
-                    let argument = match option {
-                        TelnetOption::GMCP => {
-                            // Parse GMCP message from buffer
-                            if let Some(gmcp_msg) = GmcpMessage::parse(&buffer) {
-                                TelnetArgument::GMCP(gmcp_msg)
-                            } else {
-                                // If parsing fails, treat as unknown
-                                warn!("Failed to parse GMCP message, treating as unknown");
-                                TelnetArgument::Unknown(option, buffer)
-                            }
+                    let argument = match TelnetArgument::decode(option, &mut buffer) {
+                        Ok(argument) => argument,
+                        Err(e) => {
+                            // If parsing fails, hand the raw payload through as unknown
+                            warn!(
+                                "Failed to parse {} subnegotiation: {}, treating as unknown",
+                                option, e
+                            );
+                            TelnetArgument::Unknown(
+                                option,
+                                BytesMut::from(self.decoder_buffer.as_ref()),
+                            )
                         }
-                        TelnetOption::NAWS => {
-                            // Parse NAWS window size from buffer
-                            match crate::args::naws::WindowSize::decode(&mut buffer) {
-                                Ok(window_size) => TelnetArgument::NAWSWindowSize(window_size),
-                                Err(e) => {
-                                    warn!(
-                                        "Failed to parse NAWS window size: {}, treating as unknown",
-                                        e
-                                    );
-                                    TelnetArgument::Unknown(option, buffer)
-                                }
-                            }
-                        }
-                        _ => TelnetArgument::Unknown(option, buffer),
                     };
                     self.decoder_buffer.clear();
+                    if let TelnetArgument::ZMP(message) = &argument
+                        && (self.is_enabled_local(TelnetOption::ZMP)
+                            || self.is_enabled_remote(TelnetOption::ZMP))
+                        && let Some(reply) = self.zmp.handle(message)
+                    {
+                        self.queue_response(TelnetFrame::Subnegotiate(TelnetArgument::ZMP(reply)))?;
+                    }
                     return Ok(Some(TelnetEvent::Subnegotiate(argument)));
                 }
                 (DecoderState::SubnegotiateArgumentIAC(_), _) => {
@@ -567,6 +867,9 @@ impl Decoder for TelnetCodec {
                     );
                     return Ok(Some(TelnetEvent::NoOperation));
                 }
+                (DecoderState::CarriageReturn, _) => {
+                    unreachable!("a pending CR is resolved before the next byte is read")
+                }
             }
         }
         Ok(None)
@@ -577,17 +880,9 @@ impl Encoder<char> for TelnetCodec {
     type Error = TelnetCodecError;
 
     fn encode(&mut self, item: char, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        dst.reserve(4);
-        if item.is_ascii() {
-            let ch = item as u8;
-            if ch == consts::IAC {
-                dst.put_u8(consts::IAC);
-            }
-            dst.put_u8(ch);
-        } else {
-            let mut buf = [0; 4];
-            item.encode_utf8(&mut buf);
-            dst.put_slice(&buf[0..item.len_utf8()]);
+        let mut buf = [0; 4];
+        for byte in item.encode_utf8(&mut buf).bytes() {
+            self.encode_data(byte, dst);
         }
         Ok(())
     }
@@ -597,12 +892,8 @@ impl Encoder<u8> for TelnetCodec {
     type Error = TelnetCodecError;
 
     fn encode(&mut self, item: u8, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        // Encode a raw byte, escaping IAC if necessary
-        dst.reserve(2);
-        if item == consts::IAC {
-            dst.put_u8(consts::IAC);
-        }
-        dst.put_u8(item);
+        // Encode a data byte, escaping IAC and translating newlines if necessary
+        self.encode_data(item, dst);
         Ok(())
     }
 }
@@ -618,20 +909,93 @@ impl Encoder<&str> for TelnetCodec {
 }
 
 impl TelnetCodec {
+    /// Queues a negotiation response, enforcing `limits.max_pending_responses`
+    fn queue_response(&mut self, response: TelnetFrame) -> Result<(), TelnetCodecError> {
+        if let Some(limit) = self.limits.max_pending_responses
+            && self.response_queue.len() >= limit
+        {
+            return Err(TelnetCodecError::TooManyPendingResponses { limit });
+        }
+        self.response_queue.push_back(response);
+        Ok(())
+    }
+
+    /// Sends our `zmp.ident` when ZMP becomes enabled on `side` and was not enabled on the other
+    fn start_zmp(&mut self, side: TelnetSide) -> Result<(), TelnetCodecError> {
+        let already_enabled = match side {
+            TelnetSide::Local => self.is_enabled_remote(TelnetOption::ZMP),
+            TelnetSide::Remote => self.is_enabled_local(TelnetOption::ZMP),
+        };
+        if let (false, Some(ident)) = (already_enabled, self.zmp.ident()) {
+            self.queue_response(TelnetFrame::Subnegotiate(TelnetArgument::ZMP(ident)))?;
+        }
+        Ok(())
+    }
+
+    /// Appends a subnegotiation payload byte, enforcing `limits.max_subnegotiation_length`
+    ///
+    /// An oversized subnegotiation is discarded and decoding resumes as normal data.
+    fn buffer_subnegotiation(&mut self, option: u8, byte: u8) -> Result<(), TelnetCodecError> {
+        if let Some(limit) = self.limits.max_subnegotiation_length
+            && self.decoder_buffer.len() >= limit
+        {
+            self.decoder_state = DecoderState::NormalData;
+            self.decoder_buffer.clear();
+            return Err(TelnetCodecError::SubnegotiationTooLong { option, limit });
+        }
+        self.decoder_buffer.put_u8(byte);
+        Ok(())
+    }
+
+    /// Builds the event for a decoded data byte, as a one byte chunk when `data_chunks` is set
+    fn data_event(&self, byte: u8) -> TelnetEvent {
+        if self.data_chunks {
+            TelnetEvent::DataChunk(Bytes::copy_from_slice(&[byte]))
+        } else {
+            TelnetEvent::Data(byte)
+        }
+    }
+
+    /// Writes a data byte, escaping IAC
+    ///
+    /// Unless TRANSMIT-BINARY is enabled locally, newlines follow the NVT rules
+    /// of RFC 854: `LF` is sent as `CR LF` and a bare `CR` as `CR NUL`. A `CR`
+    /// is written straight away; the `NUL` is only added once the next byte
+    /// turns out not to be `LF`, so `CR LF` written by the caller stays as is.
+    fn encode_data(&mut self, byte: u8, dst: &mut BytesMut) {
+        dst.reserve(3);
+        if std::mem::take(&mut self.encoder_cr) {
+            if byte == consts::LF {
+                dst.put_u8(consts::LF);
+                return;
+            }
+            dst.put_u8(consts::NUL);
+        }
+        if !self.options.local_enabled(TelnetOption::TransmitBinary) {
+            match byte {
+                consts::CR => self.encoder_cr = true,
+                consts::LF => dst.put_u8(consts::CR),
+                _ => {}
+            }
+        }
+        if byte == consts::IAC {
+            dst.put_u8(consts::IAC);
+        }
+        dst.put_u8(byte);
+    }
+
     /// Internal method to encode a single frame without processing the response queue
     fn encode_frame(
         &mut self,
         item: TelnetFrame,
         dst: &mut BytesMut,
     ) -> Result<(), TelnetCodecError> {
+        if !matches!(item, TelnetFrame::Data(_)) && std::mem::take(&mut self.encoder_cr) {
+            // Complete a pending CR NUL before the command
+            dst.put_u8(consts::NUL);
+        }
         match item {
-            TelnetFrame::Data(ch) => {
-                dst.reserve(2);
-                if ch == consts::IAC {
-                    dst.put_u8(consts::IAC);
-                }
-                dst.put_u8(ch);
-            }
+            TelnetFrame::Data(ch) => self.encode_data(ch, dst),
             TelnetFrame::NoOperation => {
                 dst.reserve(2);
                 dst.put_u8(consts::IAC);
@@ -796,6 +1160,12 @@ impl Encoder<TelnetEvent> for TelnetCodec {
     fn encode(&mut self, item: TelnetEvent, dst: &mut BytesMut) -> Result<(), Self::Error> {
         match item {
             TelnetEvent::Data(byte) => self.encode(TelnetFrame::Data(byte), dst),
+            TelnetEvent::DataChunk(bytes) => {
+                for byte in bytes {
+                    self.encode(TelnetFrame::Data(byte), dst)?;
+                }
+                Ok(())
+            }
             TelnetEvent::NoOperation => self.encode(TelnetFrame::NoOperation, dst),
             TelnetEvent::DataMark => self.encode(TelnetFrame::DataMark, dst),
             TelnetEvent::Break => self.encode(TelnetFrame::Break, dst),
@@ -861,6 +1231,10 @@ impl Encoder<TelnetEvent> for TelnetCodec {
 ///   during subnegotiation. Contains the option identifier (as u8). The next byte is expected to
 ///   indicate a Telnet command or other subnegotiation-specific action.
 ///
+/// - `CarriageReturn`:
+///   This state is entered when a CR is received while TRANSMIT-BINARY is disabled for the remote
+///   side. The next byte decides whether it was a newline (`CR LF`) or a carriage return (`CR NUL`).
+///
 /// # Usage
 ///
 /// This enum is designed to guide the processing logic in a Telnet decoder, ensuring proper
@@ -887,11 +1261,15 @@ enum DecoderState {
     SubnegotiateArgument(u8),
     /// Received IAC during Subnegotiation, Next Byte is command
     SubnegotiateArgumentIAC(u8),
+    /// Received CR in NVT mode, Next Byte decides between newline and carriage return
+    CarriageReturn,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::atcp::AtcpMessage;
+    use crate::zmp::{ZmpMessage, ZmpRegistry};
     use bytes::BytesMut;
 
     // ============================================================================
@@ -967,6 +1345,44 @@ mod tests {
         assert_eq!(&dst[..], b"Hi\r\n");
     }
 
+    #[test]
+    fn encode_nvt_newlines() {
+        let mut codec = TelnetCodec::new();
+        let mut dst = BytesMut::new();
+        codec.encode("a\nb\rc\r", &mut dst).unwrap();
+        codec.encode(TelnetFrame::GoAhead, &mut dst).unwrap();
+        codec.encode('\n', &mut dst).unwrap();
+        assert_eq!(&dst[..], b"a\r\nb\r\0c\r\0\xFF\xF9\r\n");
+    }
+
+    #[test]
+    fn encode_binary_passes_newlines_through() {
+        let mut codec = TelnetCodec::new();
+        collect_all(
+            &mut codec,
+            BytesMut::from(&[consts::IAC, consts::DO, consts::option::BINARY][..]),
+        );
+        let mut dst = BytesMut::new();
+        codec.encode(b'\n', &mut dst).unwrap();
+        codec.encode(b'\r', &mut dst).unwrap();
+        codec.encode(b'x', &mut dst).unwrap();
+        // The queued WILL BINARY reply goes out with the first frame
+        codec.encode(TelnetFrame::NoOperation, &mut dst).unwrap();
+        assert_eq!(
+            &dst[..],
+            &[
+                b'\n',
+                b'\r',
+                b'x',
+                consts::IAC,
+                consts::WILL,
+                consts::option::BINARY,
+                consts::IAC,
+                consts::NOP
+            ]
+        );
+    }
+
     // ============================================================================
     // Encoding Tests - Control Commands
     // ============================================================================
@@ -1148,6 +1564,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn encode_subnegotiation_escapes_iac_in_typed_args() {
+        // A 255 column window encodes its low byte as IAC and must be escaped
+        let dst = encode_frame(TelnetFrame::Subnegotiate(TelnetArgument::NAWSWindowSize(
+            crate::naws::WindowSize::new(255, 24),
+        )));
+        assert_eq!(
+            &dst[..],
+            &[
+                consts::IAC,
+                consts::SB,
+                consts::option::NAWS,
+                0x00,
+                consts::IAC,
+                consts::IAC,
+                0x00,
+                24,
+                consts::IAC,
+                consts::SE,
+            ]
+        );
+    }
+
     // ============================================================================
     // Encoding Tests - String Encoder
     // ============================================================================
@@ -1209,12 +1648,51 @@ mod tests {
                 TelnetEvent::Data(b'i'),
                 TelnetEvent::Data(b'n'),
                 TelnetEvent::Data(b'e'),
+                TelnetEvent::Data(consts::LF),
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_nvt_carriage_returns() {
+        let mut codec = TelnetCodec::new();
+        let mut src = BytesMut::from(&b"a\r\0b\rc\r"[..]);
+        let mut frames = Vec::new();
+        while let Some(event) = codec.decode(&mut src).unwrap() {
+            frames.push(event);
+        }
+        // The trailing CR waits for the next buffer
+        src.extend_from_slice(b"\n");
+        frames.extend(collect_all(&mut codec, src));
+        assert_eq!(
+            frames,
+            vec![
+                TelnetEvent::Data(b'a'),
+                TelnetEvent::Data(consts::CR),
+                TelnetEvent::Data(b'b'),
                 TelnetEvent::Data(consts::CR),
+                TelnetEvent::Data(b'c'),
                 TelnetEvent::Data(consts::LF),
             ]
         );
     }
 
+    #[test]
+    fn decode_nvt_carriage_return_before_command() {
+        let mut codec = TelnetCodec::new();
+        codec.set_data_chunks(true);
+        let src = BytesMut::from(&b"ok\r\xFF\xF9"[..]);
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![
+                TelnetEvent::DataChunk(Bytes::from_static(b"ok")),
+                TelnetEvent::DataChunk(Bytes::from_static(b"\r")),
+                TelnetEvent::GoAhead,
+            ]
+        );
+    }
+
     #[test]
     fn decode_empty_buffer() {
         let mut codec = TelnetCodec::new();
@@ -1474,57 +1952,444 @@ mod tests {
         assert_eq!(frames, vec![TelnetEvent::NoOperation]);
     }
 
-    // ============================================================================
-    // Decoding Tests - Mixed Data and Commands
-    // ============================================================================
+    #[test]
+    fn decode_subnegotiation_ttype_is() {
+        let mut codec = TelnetCodec::new();
+        let mut src = BytesMut::from(&[consts::IAC, consts::SB, consts::option::TTYPE][..]);
+        src.extend_from_slice(&[consts::option::ttype::IS]);
+        src.extend_from_slice(b"MTTS 137");
+        src.extend_from_slice(&[consts::IAC, consts::SE]);
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![TelnetEvent::Subnegotiate(TelnetArgument::TerminalType(
+                "MTTS 137".to_string()
+            ))]
+        );
+    }
 
     #[test]
-    fn decode_data_with_interspersed_commands() {
+    fn decode_subnegotiation_new_environ_is() {
+        use crate::args::new_environ::{EnvironVariable, NewEnviron};
+
+        let mut codec = TelnetCodec::new();
+        let mut src =
+            BytesMut::from(&[consts::IAC, consts::SB, consts::option::NEW_ENVIRONMENT][..]);
+        src.extend_from_slice(&[
+            consts::option::new_environment::IS,
+            consts::option::new_environment::VAR,
+        ]);
+        src.extend_from_slice(b"CHARSET");
+        src.extend_from_slice(&[consts::option::new_environment::VALUE]);
+        src.extend_from_slice(b"UTF-8");
+        src.extend_from_slice(&[consts::IAC, consts::SE]);
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![TelnetEvent::Subnegotiate(TelnetArgument::NewEnviron(
+                NewEnviron::is(vec![EnvironVariable::var(
+                    "CHARSET",
+                    Some("UTF-8".to_string())
+                )])
+            ))]
+        );
+    }
+
+    #[test]
+    fn decode_subnegotiation_ttype_send() {
         let mut codec = TelnetCodec::new();
         let src = BytesMut::from(
             &[
-                b'H',
-                b'i',
                 consts::IAC,
-                consts::NOP,
-                b'!',
+                consts::SB,
+                consts::option::TTYPE,
+                consts::option::ttype::SEND,
                 consts::IAC,
-                consts::DO,
-                consts::option::KERMIT,
-                b'B',
-                b'y',
-                b'e',
+                consts::SE,
             ][..],
         );
         let frames = collect_all(&mut codec, src);
         assert_eq!(
             frames,
-            vec![
-                TelnetEvent::Data(b'H'),
-                TelnetEvent::Data(b'i'),
-                TelnetEvent::NoOperation,
-                TelnetEvent::Data(b'!'),
-                // Receiving DO Echo -> QState responds with WONT (Kermit not supported by default)
-                // No event emitted since negotiation failed
-                TelnetEvent::Data(b'B'),
-                TelnetEvent::Data(b'y'),
-                TelnetEvent::Data(b'e'),
-            ]
+            vec![TelnetEvent::Subnegotiate(TelnetArgument::TerminalTypeSend)]
         );
     }
 
     #[test]
-    fn decode_login_sequence_from_rfc() {
+    fn decode_subnegotiation_atcp() {
         let mut codec = TelnetCodec::new();
-        let src = BytesMut::from(
-            &[
-                b'L',
-                b'o',
-                b'g',
-                b'i',
-                b'n',
-                b':',
-                consts::CR,
+        let mut src = BytesMut::from(&[consts::IAC, consts::SB, consts::option::ATCP][..]);
+        src.extend_from_slice(b"Char.Vitals H:100/120 M:50/80");
+        src.extend_from_slice(&[consts::IAC, consts::SE]);
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![TelnetEvent::Subnegotiate(TelnetArgument::ATCP(
+                AtcpMessage::new("Char.Vitals", "H:100/120 M:50/80")
+            ))]
+        );
+    }
+
+    #[test]
+    fn zmp_check_is_answered_once_enabled() {
+        let mut codec = TelnetCodec::new();
+        let check = |codec: &mut TelnetCodec| {
+            let mut src = BytesMut::from(&[consts::IAC, consts::SB, consts::option::ZMP][..]);
+            src.extend_from_slice(b"zmp.check\0zmp.ping\0");
+            src.extend_from_slice(&[consts::IAC, consts::SE]);
+            let events = collect_all(codec, src);
+            assert_eq!(
+                events,
+                vec![TelnetEvent::Subnegotiate(TelnetArgument::ZMP(
+                    ZmpMessage::new("zmp.check").with_argument("zmp.ping")
+                ))]
+            );
+        };
+
+        // Not answered before ZMP is negotiated
+        check(&mut codec);
+        assert!(!codec.has_pending_responses());
+
+        let src = BytesMut::from(&[consts::IAC, consts::WILL, consts::option::ZMP][..]);
+        collect_all(&mut codec, src);
+        let mut dst = BytesMut::new();
+        codec.flush_responses(&mut dst).unwrap();
+
+        check(&mut codec);
+        let mut dst = BytesMut::new();
+        codec.flush_responses(&mut dst).unwrap();
+        let mut expected = BytesMut::from(&[consts::IAC, consts::SB, consts::option::ZMP][..]);
+        expected.extend_from_slice(b"zmp.support\0zmp.ping\0");
+        expected.extend_from_slice(&[consts::IAC, consts::SE]);
+        assert_eq!(dst, expected);
+    }
+
+    #[test]
+    fn zmp_agreement_sends_ident() {
+        let mut codec = TelnetCodec::new();
+        codec.set_zmp(ZmpRegistry::default().with_ident("termionix", "1.0", "test"));
+        codec.enable_local(TelnetOption::ZMP);
+        let src = BytesMut::from(&[consts::IAC, consts::DO, consts::option::ZMP][..]);
+        collect_all(&mut codec, src);
+
+        let mut dst = BytesMut::new();
+        codec.flush_responses(&mut dst).unwrap();
+        let mut expected = BytesMut::from(&[consts::IAC, consts::SB, consts::option::ZMP][..]);
+        expected.extend_from_slice(b"zmp.ident\0termionix\x001.0\0test\0");
+        expected.extend_from_slice(&[consts::IAC, consts::SE]);
+        assert_eq!(dst, expected);
+
+        // The peer agreeing in the other direction does not repeat it
+        let src = BytesMut::from(&[consts::IAC, consts::WILL, consts::option::ZMP][..]);
+        collect_all(&mut codec, src);
+        let mut dst = BytesMut::new();
+        codec.flush_responses(&mut dst).unwrap();
+        assert_eq!(&dst[..], &[consts::IAC, consts::DO, consts::option::ZMP]);
+    }
+
+    #[test]
+    fn mxp_agreement_sends_start_subnegotiation() {
+        let mut codec = TelnetCodec::new();
+        assert_eq!(
+            codec.enable_local(TelnetOption::MXP),
+            Some(TelnetFrame::Will(TelnetOption::MXP))
+        );
+        let src = BytesMut::from(&[consts::IAC, consts::DO, consts::option::MXP][..]);
+        let events = collect_all(&mut codec, src);
+        assert_eq!(
+            events,
+            vec![TelnetEvent::OptionStatus(
+                TelnetOption::MXP,
+                TelnetSide::Local,
+                true
+            )]
+        );
+
+        let mut dst = BytesMut::new();
+        codec.flush_responses(&mut dst).unwrap();
+        assert_eq!(
+            &dst[..],
+            &[
+                consts::IAC,
+                consts::SB,
+                consts::option::MXP,
+                consts::IAC,
+                consts::SE
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_subnegotiation_ttype_invalid_command_is_unknown() {
+        let mut codec = TelnetCodec::new();
+        let src = BytesMut::from(
+            &[
+                consts::IAC,
+                consts::SB,
+                consts::option::TTYPE,
+                0x07,
+                consts::IAC,
+                consts::SE,
+            ][..],
+        );
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![TelnetEvent::Subnegotiate(TelnetArgument::Unknown(
+                TelnetOption::TTYPE,
+                BytesMut::from(&[0x07][..])
+            ))]
+        );
+    }
+
+    #[test]
+    fn decode_subnegotiation_charset_request() {
+        let mut codec = TelnetCodec::new();
+        let mut src = BytesMut::from(&[consts::IAC, consts::SB, consts::option::CHARSET][..]);
+        src.extend_from_slice(&[consts::option::charset::REQUEST]);
+        src.extend_from_slice(b" UTF-8 ISO-8859-1");
+        src.extend_from_slice(&[consts::IAC, consts::SE]);
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![TelnetEvent::Subnegotiate(TelnetArgument::CharsetRequest(
+                vec![BytesMut::from("UTF-8"), BytesMut::from("ISO-8859-1")]
+            ))]
+        );
+    }
+
+    #[test]
+    fn decode_subnegotiation_charset_request_with_ttable_version() {
+        let mut codec = TelnetCodec::new();
+        let mut src = BytesMut::from(&[consts::IAC, consts::SB, consts::option::CHARSET][..]);
+        src.extend_from_slice(&[consts::option::charset::REQUEST]);
+        src.extend_from_slice(b"[TTABLE]\x01;UTF-8");
+        src.extend_from_slice(&[consts::IAC, consts::SE]);
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![TelnetEvent::Subnegotiate(TelnetArgument::CharsetRequest(
+                vec![BytesMut::from("UTF-8")]
+            ))]
+        );
+    }
+
+    #[test]
+    fn decode_subnegotiation_charset_replies() {
+        let mut codec = TelnetCodec::new();
+        let mut src = BytesMut::from(&[consts::IAC, consts::SB, consts::option::CHARSET][..]);
+        src.extend_from_slice(&[consts::option::charset::ACCEPTED]);
+        src.extend_from_slice(b"UTF-8");
+        src.extend_from_slice(&[consts::IAC, consts::SE]);
+        src.extend_from_slice(&[
+            consts::IAC,
+            consts::SB,
+            consts::option::CHARSET,
+            consts::option::charset::REJECTED,
+            consts::IAC,
+            consts::SE,
+            consts::IAC,
+            consts::SB,
+            consts::option::CHARSET,
+            consts::option::charset::TTABLE_REJECTED,
+            consts::IAC,
+            consts::SE,
+        ]);
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![
+                TelnetEvent::Subnegotiate(TelnetArgument::CharsetAccepted(BytesMut::from("UTF-8"))),
+                TelnetEvent::Subnegotiate(TelnetArgument::CharsetRejected),
+                TelnetEvent::Subnegotiate(TelnetArgument::CharsetTTableRejected),
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_subnegotiation_msdp() {
+        let mut codec = TelnetCodec::new();
+        let mut src = BytesMut::from(&[consts::IAC, consts::SB, consts::option::MSDP][..]);
+        src.extend_from_slice(&[consts::option::msdp::VAR]);
+        src.extend_from_slice(b"HEALTH");
+        src.extend_from_slice(&[consts::option::msdp::VAL]);
+        src.extend_from_slice(b"100");
+        src.extend_from_slice(&[consts::IAC, consts::SE]);
+        let frames = collect_all(&mut codec, src);
+
+        let mut expected = crate::msdp::MudServerData::new();
+        expected.set("HEALTH", crate::msdp::MudServerDataValue::string("100"));
+        assert_eq!(
+            frames,
+            vec![TelnetEvent::Subnegotiate(TelnetArgument::MudServerData(
+                expected
+            ))]
+        );
+    }
+
+    #[test]
+    fn decode_subnegotiation_mssp() {
+        let mut codec = TelnetCodec::new();
+        let mut src = BytesMut::from(&[consts::IAC, consts::SB, consts::option::MSSP][..]);
+        src.extend_from_slice(&[consts::option::mssp::VAR]);
+        src.extend_from_slice(b"NAME");
+        src.extend_from_slice(&[consts::option::mssp::VAL]);
+        src.extend_from_slice(b"Termionix");
+        src.extend_from_slice(&[consts::option::mssp::VAR]);
+        src.extend_from_slice(b"PORT");
+        src.extend_from_slice(&[consts::option::mssp::VAL]);
+        src.extend_from_slice(b"4000");
+        src.extend_from_slice(&[consts::option::mssp::VAL]);
+        src.extend_from_slice(b"4001");
+        src.extend_from_slice(&[consts::IAC, consts::SE]);
+        let frames = collect_all(&mut codec, src);
+
+        let mut expected = crate::mssp::MudServerStatus::new();
+        expected.add("NAME", "Termionix");
+        expected.add("PORT", "4000");
+        expected.add("PORT", "4001");
+        assert_eq!(
+            frames,
+            vec![TelnetEvent::Subnegotiate(TelnetArgument::MudServerStatus(
+                expected
+            ))]
+        );
+    }
+
+    #[test]
+    fn decode_subnegotiation_linemode_mode() {
+        let mut codec = TelnetCodec::new();
+        let src = BytesMut::from(
+            &[
+                consts::IAC,
+                consts::SB,
+                consts::option::LINEMODE,
+                consts::option::linemode::MODE,
+                0x03,
+                consts::IAC,
+                consts::SE,
+            ][..],
+        );
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![TelnetEvent::Subnegotiate(TelnetArgument::LineMode(
+                Box::new(crate::linemode::LineModeOption::Mode {
+                    mode: crate::linemode::LineModeFlags::from_byte(0x03)
+                })
+            ))]
+        );
+    }
+
+    #[test]
+    fn decode_subnegotiation_status_send() {
+        let mut codec = TelnetCodec::new();
+        let src = BytesMut::from(
+            &[
+                consts::IAC,
+                consts::SB,
+                consts::option::STATUS,
+                consts::option::status::SEND,
+                consts::IAC,
+                consts::SE,
+            ][..],
+        );
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![TelnetEvent::Subnegotiate(TelnetArgument::Status(
+                crate::status::TelnetOptionStatus {
+                    command: crate::status::StatusCommand::Send,
+                    options: std::collections::HashMap::new(),
+                }
+            ))]
+        );
+    }
+
+    #[test]
+    fn decode_subnegotiation_naocrd_and_naohts() {
+        let mut codec = TelnetCodec::new();
+        let src = BytesMut::from(
+            &[
+                consts::IAC,
+                consts::SB,
+                consts::option::NAOCRD,
+                consts::option::naocrd::DS,
+                0x05,
+                consts::IAC,
+                consts::SE,
+                consts::IAC,
+                consts::SB,
+                consts::option::NAOHTS,
+                8,
+                16,
+                consts::IAC,
+                consts::SE,
+            ][..],
+        );
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![
+                TelnetEvent::Subnegotiate(TelnetArgument::NAOCRD(crate::naocrd::NAOCRD::Sender(5))),
+                TelnetEvent::Subnegotiate(TelnetArgument::NAOHTS(crate::naohts::NAOHTS::new(
+                    vec![8, 16]
+                ))),
+            ]
+        );
+    }
+
+    // ============================================================================
+    // Decoding Tests - Mixed Data and Commands
+    // ============================================================================
+
+    #[test]
+    fn decode_data_with_interspersed_commands() {
+        let mut codec = TelnetCodec::new();
+        let src = BytesMut::from(
+            &[
+                b'H',
+                b'i',
+                consts::IAC,
+                consts::NOP,
+                b'!',
+                consts::IAC,
+                consts::DO,
+                consts::option::KERMIT,
+                b'B',
+                b'y',
+                b'e',
+            ][..],
+        );
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![
+                TelnetEvent::Data(b'H'),
+                TelnetEvent::Data(b'i'),
+                TelnetEvent::NoOperation,
+                TelnetEvent::Data(b'!'),
+                // Receiving DO Echo -> QState responds with WONT (Kermit not supported by default)
+                // No event emitted since negotiation failed
+                TelnetEvent::Data(b'B'),
+                TelnetEvent::Data(b'y'),
+                TelnetEvent::Data(b'e'),
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_login_sequence_from_rfc() {
+        let mut codec = TelnetCodec::new();
+        let src = BytesMut::from(
+            &[
+                b'L',
+                b'o',
+                b'g',
+                b'i',
+                b'n',
+                b':',
+                consts::CR,
                 consts::LF,
                 consts::IAC,
                 consts::DO,
@@ -1563,7 +2428,6 @@ mod tests {
                 TelnetEvent::Data(b'i'),
                 TelnetEvent::Data(b'n'),
                 TelnetEvent::Data(b':'),
-                TelnetEvent::Data(consts::CR),
                 TelnetEvent::Data(consts::LF),
                 // Receiving DO Binary -> QState responds with WILL Binary and emits OptionStatus
                 TelnetEvent::OptionStatus(TelnetOption::TransmitBinary, TelnetSide::Local, true),
@@ -1576,7 +2440,6 @@ mod tests {
                 TelnetEvent::Data(b'r'),
                 TelnetEvent::Data(b'd'),
                 TelnetEvent::Data(b':'),
-                TelnetEvent::Data(consts::CR),
                 TelnetEvent::Data(consts::LF),
                 // Receiving WILL Binary -> QState responds with DO Binary and emits OptionStatus
                 TelnetEvent::OptionStatus(TelnetOption::TransmitBinary, TelnetSide::Remote, true),
@@ -1592,6 +2455,77 @@ mod tests {
         );
     }
 
+    // ============================================================================
+    // Decoding Tests - Data Chunks
+    // ============================================================================
+
+    #[test]
+    fn decode_data_chunk_runs_until_iac() {
+        let mut codec = TelnetCodec::new();
+        codec.set_data_chunks(true);
+        let src = BytesMut::from(&b"Hello\r\n\xFF\xF1World"[..]);
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![
+                TelnetEvent::DataChunk(Bytes::from_static(b"Hello")),
+                TelnetEvent::DataChunk(Bytes::from_static(b"\n")),
+                TelnetEvent::NoOperation,
+                TelnetEvent::DataChunk(Bytes::from_static(b"World")),
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_data_chunk_escaped_iac() {
+        let mut codec = TelnetCodec::new();
+        codec.set_data_chunks(true);
+        let src = BytesMut::from(&b"a\xFF\xFFb"[..]);
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![
+                TelnetEvent::DataChunk(Bytes::from_static(b"a")),
+                TelnetEvent::DataChunk(Bytes::from_static(&[consts::IAC])),
+                TelnetEvent::DataChunk(Bytes::from_static(b"b")),
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_data_chunk_split_command() {
+        let mut codec = TelnetCodec::new();
+        codec.set_data_chunks(true);
+        let mut src = BytesMut::from(&b"ab\xFF"[..]);
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            Some(TelnetEvent::DataChunk(Bytes::from_static(b"ab")))
+        );
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        src.extend_from_slice(&[consts::GA, b'c']);
+        let frames = collect_all(&mut codec, src);
+        assert_eq!(
+            frames,
+            vec![
+                TelnetEvent::GoAhead,
+                TelnetEvent::DataChunk(Bytes::from_static(b"c")),
+            ]
+        );
+    }
+
+    #[test]
+    fn encode_data_chunk_escapes_iac() {
+        let mut codec = TelnetCodec::new();
+        let mut dst = BytesMut::new();
+        codec
+            .encode(
+                TelnetEvent::DataChunk(Bytes::from_static(&[b'a', consts::IAC])),
+                &mut dst,
+            )
+            .unwrap();
+        assert_eq!(&dst[..], &[b'a', consts::IAC, consts::IAC]);
+    }
+
     // ============================================================================
     // Round-trip Tests (Encode then Decode)
     // ============================================================================
@@ -1663,6 +2597,101 @@ mod tests {
         assert_eq!(expected_events, decoded);
     }
 
+    #[test]
+    fn roundtrip_typed_subnegotiations() {
+        let mut msdp = crate::msdp::MudServerData::new();
+        msdp.set(
+            "ROOM",
+            crate::msdp::MudServerDataValue::string("Town Square"),
+        );
+        let mut mssp = crate::mssp::MudServerStatus::new();
+        mssp.add("PLAYERS", "12");
+        let arguments = vec![
+            TelnetArgument::TerminalType("xterm-256color".to_string()),
+            TelnetArgument::TerminalTypeSend,
+            TelnetArgument::CharsetRequest(vec![
+                BytesMut::from("UTF-8"),
+                BytesMut::from("ISO-8859-1"),
+            ]),
+            TelnetArgument::CharsetAccepted(BytesMut::from("UTF-8")),
+            TelnetArgument::CharsetRejected,
+            TelnetArgument::CharsetTTableRejected,
+            TelnetArgument::MudServerData(msdp),
+            TelnetArgument::MudServerStatus(mssp),
+        ];
+        let frames = arguments
+            .iter()
+            .cloned()
+            .map(TelnetFrame::Subnegotiate)
+            .collect();
+        let expected_events: Vec<TelnetEvent> = arguments
+            .into_iter()
+            .map(TelnetEvent::Subnegotiate)
+            .collect();
+        let encoded = encode_frames(frames);
+        let mut codec = TelnetCodec::new();
+        let decoded = collect_all(&mut codec, encoded);
+        assert_eq!(expected_events, decoded);
+    }
+
+    #[test]
+    fn roundtrip_linemode_status_naocrd_naohts() {
+        let mut options = std::collections::HashMap::new();
+        options.insert(TelnetOption::Echo, (true, false));
+        let arguments = vec![
+            TelnetArgument::LineMode(Box::new(crate::linemode::LineModeOption::Slc {
+                chars: vec![crate::linemode::SlcDefinition {
+                    function: crate::linemode::SlcFunction::Ip,
+                    flags: crate::linemode::SlcFlags::from_byte(0x62),
+                    value: consts::IAC,
+                }],
+            })),
+            TelnetArgument::LineMode(Box::new(crate::linemode::LineModeOption::ForwardMask {
+                mask: [true; 256],
+            })),
+            TelnetArgument::Status(crate::status::TelnetOptionStatus {
+                command: crate::status::StatusCommand::Is,
+                options,
+            }),
+            TelnetArgument::NAOCRD(crate::naocrd::NAOCRD::Receiver(consts::IAC)),
+            TelnetArgument::NAOHTS(crate::naohts::NAOHTS::new(vec![8, 16, 24])),
+        ];
+        let frames = arguments
+            .iter()
+            .cloned()
+            .map(TelnetFrame::Subnegotiate)
+            .collect();
+        let expected_events: Vec<TelnetEvent> = arguments
+            .into_iter()
+            .map(TelnetEvent::Subnegotiate)
+            .collect();
+        let encoded = encode_frames(frames);
+        let mut codec = TelnetCodec::new();
+        let decoded = collect_all(&mut codec, encoded);
+        assert_eq!(expected_events, decoded);
+    }
+
+    #[test]
+    fn roundtrip_start_tls_follows() {
+        let encoded = encode_frame(TelnetFrame::Subnegotiate(TelnetArgument::StartTLSFollows));
+        assert_eq!(
+            &encoded[..],
+            &[
+                consts::IAC,
+                consts::SB,
+                consts::option::START_TLS,
+                consts::option::start_tls::FOLLOWS,
+                consts::IAC,
+                consts::SE
+            ]
+        );
+        let mut codec = TelnetCodec::new();
+        assert_eq!(
+            collect_all(&mut codec, encoded),
+            vec![TelnetEvent::Subnegotiate(TelnetArgument::StartTLSFollows)]
+        );
+    }
+
     #[test]
     fn test_two_codec_negotiation() {
         // Simulate a client-server negotiation between two codecs
@@ -1798,6 +2827,54 @@ mod tests {
         assert!(server.is_enabled_remote(TelnetOption::SuppressGoAhead));
     }
 
+    #[test]
+    fn test_negotiation_gate_ignores_only_peer_requests() {
+        let mut codec = TelnetCodec::new();
+        codec.set_negotiation_gate(Some(NegotiationGate::new(|_| false)));
+
+        // Requests from the peer are ignored without a reply
+        let events = collect_all(
+            &mut codec,
+            encode_frames(vec![
+                TelnetFrame::Will(TelnetOption::Echo),
+                TelnetFrame::Do(TelnetOption::SuppressGoAhead),
+            ]),
+        );
+        assert!(events.is_empty());
+        assert!(!codec.has_pending_responses());
+        assert!(!codec.is_enabled_remote(TelnetOption::Echo));
+        assert!(!codec.is_enabled_local(TelnetOption::SuppressGoAhead));
+
+        // Answers to our own requests still complete them
+        assert!(codec.enable_remote(TelnetOption::Echo).is_some());
+        let events = collect_all(
+            &mut codec,
+            encode_frame(TelnetFrame::Will(TelnetOption::Echo)),
+        );
+        assert_eq!(
+            events,
+            vec![TelnetEvent::OptionStatus(
+                TelnetOption::Echo,
+                TelnetSide::Remote,
+                true
+            )]
+        );
+
+        // Disabling an enabled option is never refused
+        let events = collect_all(
+            &mut codec,
+            encode_frame(TelnetFrame::Wont(TelnetOption::Echo)),
+        );
+        assert_eq!(
+            events,
+            vec![TelnetEvent::OptionStatus(
+                TelnetOption::Echo,
+                TelnetSide::Remote,
+                false
+            )]
+        );
+    }
+
     #[test]
     fn roundtrip_control_commands() {
         let frames = vec![
@@ -2338,4 +3415,181 @@ mod tests {
         );
         assert!(!codec.is_enabled_remote(TelnetOption::SuppressGoAhead));
     }
+
+    // ============================================================================
+    // Option Policy Tests
+    // ============================================================================
+
+    #[test]
+    fn policy_refused_option_replies_wont_and_dont() {
+        let policy = OptionPolicy::default().refuse(TelnetOption::GMCP);
+        let mut codec = TelnetCodec::with_policy(policy);
+        let src = BytesMut::from(
+            &[
+                consts::IAC,
+                consts::DO,
+                consts::option::GMCP,
+                consts::IAC,
+                consts::WILL,
+                consts::option::GMCP,
+            ][..],
+        );
+        let events = collect_all(&mut codec, src);
+        assert_eq!(events, vec![]);
+        assert!(!codec.is_enabled_local(TelnetOption::GMCP));
+        assert!(!codec.is_enabled_remote(TelnetOption::GMCP));
+
+        let mut dst = BytesMut::new();
+        codec.flush_responses(&mut dst).unwrap();
+        assert_eq!(
+            &dst[..],
+            &[
+                consts::IAC,
+                consts::WONT,
+                consts::option::GMCP,
+                consts::IAC,
+                consts::DONT,
+                consts::option::GMCP,
+            ]
+        );
+    }
+
+    #[test]
+    fn policy_accepts_option_outside_support_table() {
+        assert!(!TelnetOption::NAWS.supported_remote());
+        let policy = OptionPolicy::default().accept_remote(TelnetOption::NAWS);
+        let mut codec = TelnetCodec::with_policy(policy);
+        let src = BytesMut::from(&[consts::IAC, consts::WILL, consts::option::NAWS][..]);
+        let events = collect_all(&mut codec, src);
+        assert_eq!(
+            events,
+            vec![TelnetEvent::OptionStatus(
+                TelnetOption::NAWS,
+                TelnetSide::Remote,
+                true
+            )]
+        );
+    }
+
+    #[test]
+    fn policy_requests_options_once() {
+        let policy = OptionPolicy::default()
+            .request_local(TelnetOption::Echo)
+            .request_remote(TelnetOption::NAWS);
+        let mut codec = TelnetCodec::with_policy(policy);
+        assert_eq!(
+            codec.request_policy_options(),
+            vec![
+                TelnetFrame::Will(TelnetOption::Echo),
+                TelnetFrame::Do(TelnetOption::NAWS),
+            ]
+        );
+        // Negotiation is already in progress, nothing new to send
+        assert_eq!(codec.request_policy_options(), vec![]);
+
+        let src = BytesMut::from(
+            &[
+                consts::IAC,
+                consts::DO,
+                consts::option::ECHO,
+                consts::IAC,
+                consts::WILL,
+                consts::option::NAWS,
+            ][..],
+        );
+        collect_all(&mut codec, src);
+        assert!(codec.is_enabled_local(TelnetOption::Echo));
+        assert!(codec.is_enabled_remote(TelnetOption::NAWS));
+        // Accepting our own requests does not queue a second WILL/DO
+        assert!(!codec.has_pending_responses());
+    }
+
+    #[test]
+    fn policy_can_be_replaced() {
+        let mut codec = TelnetCodec::new();
+        assert!(codec.is_supported_local(TelnetOption::Echo));
+        codec.set_policy(OptionPolicy::new());
+        assert!(!codec.is_supported_local(TelnetOption::Echo));
+        assert_eq!(codec.policy(), &OptionPolicy::new());
+    }
+
+    /// Decode until the codec returns an error or runs out of input
+    fn decode_until_error(codec: &mut TelnetCodec, src: &mut BytesMut) -> Option<TelnetCodecError> {
+        loop {
+            match codec.decode(src) {
+                Ok(Some(_)) => {}
+                Ok(None) => return None,
+                Err(e) => return Some(e),
+            }
+        }
+    }
+
+    #[test]
+    fn subnegotiation_length_is_limited() {
+        let mut codec = TelnetCodec::new();
+        codec.set_limits(CodecLimits::unlimited().with_max_subnegotiation_length(Some(8)));
+
+        // Exactly at the limit is fine
+        let mut src = BytesMut::from(&[consts::IAC, consts::SB, consts::option::GMCP][..]);
+        src.extend_from_slice(b"Core.Hel");
+        src.extend_from_slice(&[consts::IAC, consts::SE]);
+        assert_eq!(decode_until_error(&mut codec, &mut src), None);
+
+        // An unterminated subnegotiation is cut off at the limit
+        let mut src = BytesMut::from(&[consts::IAC, consts::SB, consts::option::GMCP][..]);
+        src.extend_from_slice(&[b'x'; 100]);
+        let error = decode_until_error(&mut codec, &mut src).unwrap();
+        assert_eq!(
+            error,
+            TelnetCodecError::SubnegotiationTooLong {
+                option: consts::option::GMCP,
+                limit: 8
+            }
+        );
+        assert!(error.is_limit_exceeded());
+        assert!(codec.decoder_buffer.is_empty());
+    }
+
+    #[test]
+    fn negotiation_flips_are_limited() {
+        let mut codec = TelnetCodec::new();
+        codec.set_limits(
+            CodecLimits::unlimited()
+                .with_max_negotiations_per_option(Some(4), std::time::Duration::from_secs(60)),
+        );
+
+        let mut src = BytesMut::new();
+        for _ in 0..3 {
+            src.extend_from_slice(&[consts::IAC, consts::DO, consts::option::ECHO]);
+            src.extend_from_slice(&[consts::IAC, consts::DONT, consts::option::ECHO]);
+        }
+        assert_eq!(
+            decode_until_error(&mut codec, &mut src),
+            Some(TelnetCodecError::NegotiationLimitExceeded {
+                option: consts::option::ECHO,
+                limit: 4
+            })
+        );
+    }
+
+    #[test]
+    fn pending_responses_are_limited() {
+        let mut codec = TelnetCodec::new();
+        codec.set_limits(CodecLimits::unlimited().with_max_pending_responses(Some(2)));
+
+        // Every refused WILL queues a DONT
+        let refusal = [consts::IAC, consts::WILL, 0x99];
+        let mut src = BytesMut::from(&refusal.repeat(2)[..]);
+        assert_eq!(decode_until_error(&mut codec, &mut src), None);
+        let mut src = BytesMut::from(&refusal[..]);
+        assert_eq!(
+            decode_until_error(&mut codec, &mut src),
+            Some(TelnetCodecError::TooManyPendingResponses { limit: 2 })
+        );
+
+        // Flushing makes room again
+        codec.flush_responses(&mut BytesMut::new()).unwrap();
+        let mut src = BytesMut::from(&refusal[..]);
+        assert_eq!(decode_until_error(&mut codec, &mut src), None);
+    }
 }
@@ -175,6 +175,12 @@ pub mod option {
     pub const COMPRESS1: u8 = 85;
     /// Mud Client Compression Protocol v2 Option Code
     pub const COMPRESS2: u8 = 86;
+    /// Mud Client Compression Protocol v3 Option Code
+    pub const COMPRESS3: u8 = 87;
+    /// Mud Sound Protocol Option Code
+    pub const MSP: u8 = 90;
+    /// Mud eXtension Protocol (`MXP`) Option Code
+    pub const MXP: u8 = 91;
     /// Zenith Mud Protocol Option Code
     pub const ZMP: u8 = 93;
     /// Telnet Pragma Login Option Code
@@ -183,6 +189,8 @@ pub mod option {
     pub const SSPI_LOGIN: u8 = 139;
     /// Telnet Pragma Heartbeat Option Code
     pub const PRAGMA_HEARTBEAT: u8 = 140;
+    /// Achaea Telnet Client Protocol (`ATCP`) Option Code
+    pub const ATCP: u8 = 200;
     /// Generic Mud Communication Protocol Option Code
     pub const GMCP: u8 = 201;
     /// Extended-Options-List Option Code
@@ -277,12 +285,24 @@ pub mod option {
     }
 
     pub mod new_environment {
+        /// New Environment Subnegotiation IS command.
+        pub const IS: u8 = 0;
+        /// New Environment Subnegotiation SEND command.
+        pub const SEND: u8 = 1;
+        /// New Environment Subnegotiation INFO command.
+        pub const INFO: u8 = 2;
+
         pub const VAR: u8 = 0;
         pub const VALUE: u8 = 1;
         pub const ESC: u8 = 2;
         pub const USERVAR: u8 = 3;
     }
 
+    pub mod start_tls {
+        /// START_TLS Subnegotiation FOLLOWS command.
+        pub const FOLLOWS: u8 = 1;
+    }
+
     pub mod status {
         /// Status Subnegotiation IS command.
         pub const IS: u8 = 0;
@@ -290,6 +310,13 @@ pub mod option {
         pub const SEND: u8 = 1;
     }
 
+    pub mod ttype {
+        /// Terminal Type Subnegotiation IS command.
+        pub const IS: u8 = 0;
+        /// Terminal Type Subnegotiation SEND command.
+        pub const SEND: u8 = 1;
+    }
+
     ///
     /// Telnet Options supported by this library.
     /// Set to True when the implementation exists.
@@ -384,13 +411,13 @@ pub mod option {
         (false, false), //  84 -
         (false, false), //  85 - Compress1
         (false, false), //  86 - Compress2
-        (false, false), //  87 -
+        (false, false), //  87 - Compress3
         (false, false), //  88 -
         (false, false), //  89 -
-        (false, false), //  90 -
-        (false, false), //  91 -
+        ( true,  true), //  90 - MSP
+        ( true,  true), //  91 - MXP
         (false, false), //  92 -
-        (false, false), //  93 - ZMP
+        ( true,  true), //  93 - ZMP
         (false, false), //  94 -
         (false, false), //  95 -
         (false, false), //  96 -
@@ -497,7 +524,7 @@ pub mod option {
         (false, false), // 197 -
         (false, false), // 198 -
         (false, false), // 199 -
-        (false, false), // 200 -
+        ( true,  true), // 200 - ATCP
         ( true,  true), // 201 - GMCP
         (false, false), // 202 -
         (false, false), // 203 -
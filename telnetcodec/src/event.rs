@@ -17,6 +17,7 @@
 use super::TelnetOption;
 use crate::args::TelnetArgument;
 use crate::options::TelnetSide;
+use bytes::Bytes;
 
 ///
 /// `TelnetEvent` represents user-facing events from the Telnet codec.
@@ -27,6 +28,9 @@ use crate::options::TelnetSide;
 pub enum TelnetEvent {
     /// Telnet Data Byte
     Data(u8),
+    /// Run of Telnet data bytes up to the next IAC, with `IAC IAC` already unescaped.
+    /// Emitted instead of `Data` when the codec has data chunking enabled.
+    DataChunk(Bytes),
     /// No Operation
     NoOperation,
     /// End of urgent Data Stream
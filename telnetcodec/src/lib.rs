@@ -204,14 +204,21 @@ mod codec;
 mod consts;
 mod event;
 mod frame;
+mod limits;
 mod options;
+mod policy;
 mod result;
 
-pub use self::args::{TelnetArgument, gmcp, linemode, msdp, mssp, naocrd, naohts, naws, status};
-pub use self::codec::TelnetCodec;
+pub use self::args::{
+    TelnetArgument, atcp, gmcp, linemode, msdp, mssp, naocrd, naohts, naws, new_environ, status,
+    zmp,
+};
+pub use self::codec::{TelnetCodec, TelnetResponder};
 pub use self::event::TelnetEvent;
 pub use self::frame::TelnetFrame;
+pub use self::limits::{CodecLimits, NegotiationGate};
 pub use self::options::{TelnetOption, TelnetSide};
+pub use self::policy::OptionPolicy;
 pub use self::result::{SubnegotiationErrorKind, TelnetCodecError, TelnetCodecResult};
 
 #[cfg(test)]
@@ -241,7 +248,6 @@ mod tests {
             TelnetEvent::Data(b'i'),
             TelnetEvent::Data(b'n'),
             TelnetEvent::Data(b'e'),
-            TelnetEvent::Data(b'\r'),
             TelnetEvent::Data(b'\n'),
         ];
         let mut actual_output = Vec::new();
@@ -333,7 +339,6 @@ mod tests {
             TelnetEvent::Data(b'i'),
             TelnetEvent::Data(b'n'),
             TelnetEvent::Data(b':'),
-            TelnetEvent::Data(consts::CR),
             TelnetEvent::Data(consts::LF),
             // Received DO Binary -> QState responds with WILL Binary and emits OptionStatus
             TelnetEvent::OptionStatus(TelnetOption::TransmitBinary, TelnetSide::Local, true),
@@ -347,7 +352,6 @@ mod tests {
             TelnetEvent::Data(b'r'),
             TelnetEvent::Data(b'd'),
             TelnetEvent::Data(b':'),
-            TelnetEvent::Data(consts::CR),
             TelnetEvent::Data(consts::LF),
             // Received WILL Binary -> QState responds with DO Binary and emits OptionStatus
             TelnetEvent::OptionStatus(TelnetOption::TransmitBinary, TelnetSide::Remote, true),
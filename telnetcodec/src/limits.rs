@@ -0,0 +1,231 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Resource limits protecting a codec from misbehaving peers
+
+use crate::{TelnetCodecError, TelnetFrame};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Limits on what a peer can make a [`TelnetCodec`](crate::TelnetCodec) buffer or process.
+///
+/// Without limits, a peer that sends `IAC SB` and never `IAC SE` grows the subnegotiation buffer
+/// forever, and a peer flipping an option with `DO`/`DONT` keeps the codec answering. A codec
+/// whose limit is exceeded returns a [`TelnetCodecError`] for which
+/// [`is_limit_exceeded`](TelnetCodecError::is_limit_exceeded) is true; the connection should be
+/// closed.
+///
+/// Limits set to `None` are not enforced.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use termionix_telnetcodec::{CodecLimits, TelnetCodec};
+///
+/// let limits = CodecLimits::default()
+///     .with_max_subnegotiation_length(Some(8 * 1024))
+///     .with_max_negotiations_per_option(Some(10), Duration::from_secs(60));
+/// let mut codec = TelnetCodec::new();
+/// codec.set_limits(limits);
+/// assert_eq!(codec.limits().max_subnegotiation_length, Some(8 * 1024));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodecLimits {
+    /// Longest subnegotiation payload accepted, in bytes
+    pub max_subnegotiation_length: Option<usize>,
+    /// Most `DO`, `DONT`, `WILL` and `WONT` commands accepted for one option per
+    /// [`negotiation_window`](Self::negotiation_window)
+    pub max_negotiations_per_option: Option<u32>,
+    /// Time window over which negotiations per option are counted
+    pub negotiation_window: Duration,
+    /// Most negotiation responses queued and not yet flushed
+    pub max_pending_responses: Option<usize>,
+}
+
+impl CodecLimits {
+    /// Creates limits that enforce nothing.
+    #[must_use]
+    pub fn unlimited() -> CodecLimits {
+        CodecLimits {
+            max_subnegotiation_length: None,
+            max_negotiations_per_option: None,
+            negotiation_window: Duration::from_secs(10),
+            max_pending_responses: None,
+        }
+    }
+
+    /// Sets the longest subnegotiation payload accepted.
+    #[must_use]
+    pub fn with_max_subnegotiation_length(mut self, max: Option<usize>) -> CodecLimits {
+        self.max_subnegotiation_length = max;
+        self
+    }
+
+    /// Sets how many negotiations per option are accepted within `window`.
+    #[must_use]
+    pub fn with_max_negotiations_per_option(
+        mut self,
+        max: Option<u32>,
+        window: Duration,
+    ) -> CodecLimits {
+        self.max_negotiations_per_option = max;
+        self.negotiation_window = window;
+        self
+    }
+
+    /// Sets how many negotiation responses may be queued.
+    #[must_use]
+    pub fn with_max_pending_responses(mut self, max: Option<usize>) -> CodecLimits {
+        self.max_pending_responses = max;
+        self
+    }
+}
+
+impl Default for CodecLimits {
+    /// 64 KiB subnegotiations, 50 negotiations per option every 10 seconds and 1024 pending
+    /// responses; far above what well-behaved peers need.
+    fn default() -> Self {
+        CodecLimits {
+            max_subnegotiation_length: Some(64 * 1024),
+            max_negotiations_per_option: Some(50),
+            negotiation_window: Duration::from_secs(10),
+            max_pending_responses: Some(1024),
+        }
+    }
+}
+
+/// Decides whether a [`TelnetCodec`](crate::TelnetCodec) acts on a negotiation the peer started.
+///
+/// The gate is asked about every `DO` and `WILL` that is not a reply to our own request, before
+/// the option state changes. A refused request is ignored: it is neither answered nor recorded,
+/// so both sides keep the option disabled. This lets a caller throttle negotiation storms without
+/// the two sides disagreeing about which options are enabled.
+///
+/// # Example
+///
+/// ```
+/// use bytes::BytesMut;
+/// use termionix_telnetcodec::{NegotiationGate, TelnetCodec, TelnetOption};
+/// use tokio_util::codec::Decoder;
+///
+/// let mut codec = TelnetCodec::new();
+/// codec.set_negotiation_gate(Some(NegotiationGate::new(|_| false)));
+///
+/// // IAC WILL ECHO
+/// let mut src = BytesMut::from(&[0xFF, 0xFB, 0x01][..]);
+/// assert_eq!(codec.decode(&mut src).unwrap(), None);
+/// assert!(!codec.has_pending_responses());
+/// assert!(!codec.is_enabled_remote(TelnetOption::Echo));
+/// ```
+#[derive(Clone)]
+pub struct NegotiationGate(Arc<dyn Fn(&TelnetFrame) -> bool + Send + Sync>);
+
+impl NegotiationGate {
+    /// Creates a gate that accepts a request when `allow` returns true.
+    #[must_use]
+    pub fn new<F>(allow: F) -> NegotiationGate
+    where
+        F: Fn(&TelnetFrame) -> bool + Send + Sync + 'static,
+    {
+        NegotiationGate(Arc::new(allow))
+    }
+
+    /// Returns whether the codec should act on `frame`.
+    #[must_use]
+    pub fn allows(&self, frame: &TelnetFrame) -> bool {
+        (self.0)(frame)
+    }
+}
+
+impl std::fmt::Debug for NegotiationGate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("NegotiationGate(..)")
+    }
+}
+
+/// Counts received negotiations per option over fixed windows.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct NegotiationCounter {
+    windows: HashMap<u8, (Instant, u32)>,
+}
+
+impl NegotiationCounter {
+    /// Records a negotiation for `option`, failing once it exceeds the limit for the window.
+    pub(crate) fn record(
+        &mut self,
+        option: u8,
+        limits: &CodecLimits,
+    ) -> Result<(), TelnetCodecError> {
+        let Some(limit) = limits.max_negotiations_per_option else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let (start, count) = self.windows.entry(option).or_insert((now, 0));
+        if now.duration_since(*start) >= limits.negotiation_window {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        if *count > limit {
+            return Err(TelnetCodecError::NegotiationLimitExceeded { option, limit });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_limits_per_option() {
+        let limits = CodecLimits::unlimited()
+            .with_max_negotiations_per_option(Some(2), Duration::from_secs(60));
+        let mut counter = NegotiationCounter::default();
+        assert!(counter.record(1, &limits).is_ok());
+        assert!(counter.record(1, &limits).is_ok());
+        assert!(counter.record(3, &limits).is_ok());
+        assert_eq!(
+            counter.record(1, &limits),
+            Err(TelnetCodecError::NegotiationLimitExceeded {
+                option: 1,
+                limit: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_counter_window_resets() {
+        let limits = CodecLimits::unlimited()
+            .with_max_negotiations_per_option(Some(1), Duration::from_millis(20));
+        let mut counter = NegotiationCounter::default();
+        assert!(counter.record(1, &limits).is_ok());
+        assert!(counter.record(1, &limits).is_err());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(counter.record(1, &limits).is_ok());
+    }
+
+    #[test]
+    fn test_unlimited_counter() {
+        let limits = CodecLimits::unlimited();
+        let mut counter = NegotiationCounter::default();
+        for _ in 0..1000 {
+            assert!(counter.record(1, &limits).is_ok());
+        }
+    }
+}
@@ -14,7 +14,7 @@
 // limitations under the License.
 //
 
-use crate::{TelnetCodecError, TelnetCodecResult, TelnetFrame, consts};
+use crate::{OptionPolicy, TelnetCodecError, TelnetCodecResult, TelnetFrame, consts};
 use std::fmt::Formatter;
 
 ///
@@ -132,6 +132,12 @@ pub enum TelnetOption {
     Compress1,
     /// [`consts::option::COMPRESS2`] Mud Client Compression Protocol version 2 [MCCPv2](https://tintin.sourceforge.io/protocols/mccp/)
     Compress2,
+    /// [`consts::option::COMPRESS3`] Mud Client Compression Protocol version 3 [MCCPv3](https://tintin.mudhalla.net/protocols/mccp/)
+    Compress3,
+    /// [`consts::option::MSP`] Mud Sound Protocol [MSP](https://www.zuggsoft.com/zmud/msp.htm)
+    MSP,
+    /// [`consts::option::MXP`] Mud eXtension Protocol [MXP](https://www.zuggsoft.com/zmud/mxp.htm)
+    MXP,
     /// [`consts::option::ZMP`] Zenith Mud Protocol [ZMP](http://discworld.starturtle.net/external/protocols/zmp.html)
     ZMP,
     /// [`consts::option::PRAGMA_LOGIN`] Telnet Option Pragma Logon [Steve_McGregory](https://www.iana.org/assignments/telnet-options/telnet-options.xhtml#Steve_McGregory)
@@ -140,6 +146,8 @@ pub enum TelnetOption {
     SSPILogon,
     /// [`consts::option::PRAGMA_HEARTBEAT`] Telnet Option Pragma Heartbeat [Steve_McGregory](https://www.iana.org/assignments/telnet-options/telnet-options.xhtml#Steve_McGregory)
     PragmaHeartbeat,
+    /// [`consts::option::ATCP`] Achaea Telnet Client Protocol [ATCP](https://www.ironrealms.com/rapture/manual/files/FeatATCP-txt.html)
+    ATCP,
     /// [`consts::option::GMCP`] Generic Mud Communication Protocol [GMCP Protocol](https://www.gammon.com.au/gmcp)
     GMCP,
     /// [`consts::option::EXOPL`] Extended-Options-List [RFC861](http://www.iana.org/go/rfc861)
@@ -213,10 +221,14 @@ impl TelnetOption {
             TelnetOption::MSSP => consts::option::MSSP,
             TelnetOption::Compress1 => consts::option::COMPRESS1,
             TelnetOption::Compress2 => consts::option::COMPRESS2,
+            TelnetOption::Compress3 => consts::option::COMPRESS3,
+            TelnetOption::MSP => consts::option::MSP,
+            TelnetOption::MXP => consts::option::MXP,
             TelnetOption::ZMP => consts::option::ZMP,
             TelnetOption::PragmaLogon => consts::option::PRAGMA_LOGIN,
             TelnetOption::SSPILogon => consts::option::SSPI_LOGIN,
             TelnetOption::PragmaHeartbeat => consts::option::PRAGMA_HEARTBEAT,
+            TelnetOption::ATCP => consts::option::ATCP,
             TelnetOption::GMCP => consts::option::GMCP,
             TelnetOption::EXOPL => consts::option::EXOPL,
             TelnetOption::Unknown(byte) => *byte,
@@ -288,20 +300,29 @@ impl TelnetOption {
             consts::option::MSSP => TelnetOption::MSSP,
             consts::option::COMPRESS1 => TelnetOption::Compress1,
             consts::option::COMPRESS2 => TelnetOption::Compress2,
+            consts::option::COMPRESS3 => TelnetOption::Compress3,
+            consts::option::MSP => TelnetOption::MSP,
+            consts::option::MXP => TelnetOption::MXP,
             consts::option::ZMP => TelnetOption::ZMP,
             consts::option::PRAGMA_LOGIN => TelnetOption::PragmaLogon,
             consts::option::SSPI_LOGIN => TelnetOption::SSPILogon,
             consts::option::PRAGMA_HEARTBEAT => TelnetOption::PragmaHeartbeat,
+            consts::option::ATCP => TelnetOption::ATCP,
             consts::option::GMCP => TelnetOption::GMCP,
             consts::option::EXOPL => TelnetOption::EXOPL,
             byte => TelnetOption::Unknown(byte),
         }
     }
     /// Whether we support this option from us -> them.
+    ///
+    /// This is the library default used by [`OptionPolicy::default`]; individual codecs
+    /// negotiate according to their own [`OptionPolicy`].
     pub fn supported_local(&self) -> bool {
         consts::option::SUPPORT[self.to_u8() as usize].0
     }
     /// Whether we support this option from them -> us.
+    ///
+    /// This is the library default used by [`OptionPolicy::default`].
     pub fn supported_remote(&self) -> bool {
         consts::option::SUPPORT[self.to_u8() as usize].1
     }
@@ -364,10 +385,14 @@ impl std::fmt::Display for TelnetOption {
             TelnetOption::MSSP => write!(f, "MSSP"),
             TelnetOption::Compress1 => write!(f, "Compress1"),
             TelnetOption::Compress2 => write!(f, "Compress2"),
+            TelnetOption::Compress3 => write!(f, "Compress3"),
+            TelnetOption::MSP => write!(f, "MSP"),
+            TelnetOption::MXP => write!(f, "MXP"),
             TelnetOption::ZMP => write!(f, "ZMP"),
             TelnetOption::PragmaLogon => write!(f, "PragmaLogon"),
             TelnetOption::SSPILogon => write!(f, "SSPILogon"),
             TelnetOption::PragmaHeartbeat => write!(f, "PragmaHeartbeat"),
+            TelnetOption::ATCP => write!(f, "ATCP"),
             TelnetOption::GMCP => write!(f, "GMCP"),
             TelnetOption::EXOPL => write!(f, "EXOPL"),
             TelnetOption::Unknown(option) => write!(f, "Unknown({option})"),
@@ -395,10 +420,8 @@ impl From<TelnetOption> for u8 {
 ///
 /// # Fields
 ///
-/// * `config` - An array of `SupportState` values with a fixed size of 255, representing
-///              the support state of each Telnet option. Each index corresponds to the
-///              associated Telnet option code, determining whether the option is supported
-///              and in what manner.
+/// * `policy` - The [`OptionPolicy`] deciding which options are accepted locally and
+///              remotely, and which ones are requested proactively on connect.
 ///
 /// * `state` - An array of `OptionState` values with a fixed size of 255, representing
 ///             the negotiation state of each Telnet option. Each index corresponds to the
@@ -408,23 +431,44 @@ impl From<TelnetOption> for u8 {
 /// # Notes
 ///
 /// The Telnet sidechannel uses option codes ranging from 0 to 254, which makes the size
-/// of both arrays precisely 255 to represent all potential options. The fields ensure
+/// of the state array precisely 255 to represent all potential options. The fields ensure
 /// the ability to handle and manage all standard Telnet options.
 #[derive(Clone, Debug)]
 pub struct TelnetOptions {
-    config: [SupportState; 255],
+    policy: OptionPolicy,
     state: [OptionState; 255],
 }
 
 impl TelnetOptions {
+    /// Creates option state that negotiates according to `policy`
+    pub fn with_policy(policy: OptionPolicy) -> TelnetOptions {
+        TelnetOptions {
+            policy,
+            state: core::array::from_fn(|_| OptionState::default()),
+        }
+    }
+
+    /// Returns the negotiation policy
+    pub fn policy(&self) -> &OptionPolicy {
+        &self.policy
+    }
+
+    /// Replaces the negotiation policy.
+    ///
+    /// Options that are already enabled stay enabled; the new policy applies to
+    /// subsequent requests from either side.
+    pub fn set_policy(&mut self, policy: OptionPolicy) {
+        self.policy = policy;
+    }
+
     /// Checks if we support the given option locally
     pub fn is_supported_local(&self, option: TelnetOption) -> bool {
-        self.config[option.to_u8() as usize].local
+        self.policy.accepts_local(option)
     }
 
     /// Checks if we support the given option remotely
     pub fn is_supported_remote(&self, option: TelnetOption) -> bool {
-        self.config[option.to_u8() as usize].remote
+        self.policy.accepts_remote(option)
     }
 
     /// Gets the local QState for an option
@@ -583,6 +627,10 @@ impl TelnetOptions {
     /// - `TelnetFrame::Wont`: Calls `recv_wont` to process the frame.
     /// - Other frame types result in a `TerminalError::NegotationError`.
     ///
+    /// TIMING-MARK is a one-off request rather than a mode (RFC 860), so it never
+    /// changes state: `DO TIMING-MARK` is answered every time it is received, and
+    /// the peer's `WILL`/`WONT` answering ours is accepted without a reply.
+    ///
     /// # Arguments
     ///
     /// * `frame` - A `TelnetFrame` that represents the received Telnet command or negotiation option.
@@ -605,6 +653,16 @@ impl TelnetOptions {
         frame: TelnetFrame,
     ) -> TelnetCodecResult<Option<TelnetFrame>> {
         match frame {
+            TelnetFrame::Do(TelnetOption::TimingMark) => Ok(Some(
+                if self.policy.accepts_local(TelnetOption::TimingMark) {
+                    TelnetFrame::Will(TelnetOption::TimingMark)
+                } else {
+                    TelnetFrame::Wont(TelnetOption::TimingMark)
+                },
+            )),
+            TelnetFrame::Dont(TelnetOption::TimingMark)
+            | TelnetFrame::Will(TelnetOption::TimingMark)
+            | TelnetFrame::Wont(TelnetOption::TimingMark) => Ok(None),
             TelnetFrame::Do(option) => Ok(self.recv_do(option)),
             TelnetFrame::Dont(option) => Ok(self.recv_dont(option)),
             TelnetFrame::Will(option) => Ok(self.recv_will(option)),
@@ -622,9 +680,13 @@ impl TelnetOptions {
     /// Returns frames (commands) you should send on the wire as a result.
     fn request_will(&mut self, option: TelnetOption) -> Option<TelnetFrame> {
         // First check if we support providing this option
-        if !self.config[option.to_u8() as usize].local {
+        if !self.policy.accepts_local(option) {
             return None; // Don't try to enable unsupported options
         }
+        if option == TelnetOption::TimingMark {
+            // Stateless, see `handle_received`
+            return Some(TelnetFrame::Will(option));
+        }
         match self.state[option.to_u8() as usize].local {
             QState::Yes | QState::WantYes | QState::WantYesOpposite => {
                 // already enabled or in-progress to enable
@@ -675,9 +737,13 @@ impl TelnetOptions {
     /// Request remote to enable the option (i.e. send DO).
     fn request_do(&mut self, option: TelnetOption) -> Option<TelnetFrame> {
         // First check if we support providing this option
-        if !self.config[option.to_u8() as usize].remote {
+        if !self.policy.accepts_remote(option) {
             return None; // Don't try to enable unsupported options
         }
+        if option == TelnetOption::TimingMark {
+            // Stateless, see `handle_received`
+            return Some(TelnetFrame::Do(option));
+        }
         match self.state[option.to_u8() as usize].remote {
             QState::Yes | QState::WantYes | QState::WantYesOpposite => None,
             QState::No => {
@@ -721,19 +787,17 @@ impl TelnetOptions {
     fn recv_will(&mut self, option: TelnetOption) -> Option<TelnetFrame> {
         let option_idx = option.to_u8() as usize;
         // Handle out-of-bounds option (e.g., Unknown option with value 255)
-        if option_idx >= self.config.len() {
+        if option_idx >= self.state.len() {
             return Some(TelnetFrame::Dont(option));
         }
         // First, check if we support providing this option
-        if !self.config[option_idx].remote {
+        if !self.policy.accepts_remote(option) {
             // If we don't support it, reject with `DONT`
             return Some(TelnetFrame::Dont(option));
         }
         match self.state[option_idx].remote {
             QState::No => {
-                // remote offers WILL -> if we accept, send DO and move to YES
-                // For a generic engine we accept by default; caller can override by sending DONT.
-                // Here we accept. If you want to implement policy, change this branch.
+                // remote offers WILL -> the policy accepts it, send DO and move to YES
                 self.state[option_idx].remote = QState::Yes;
                 Some(TelnetFrame::Do(option))
             }
@@ -804,11 +868,11 @@ impl TelnetOptions {
     fn recv_do(&mut self, option: TelnetOption) -> Option<TelnetFrame> {
         let option_idx = option.to_u8() as usize;
         // Handle out-of-bounds option (e.g., Unknown option with value 255)
-        if option_idx >= self.config.len() {
+        if option_idx >= self.state.len() {
             return Some(TelnetFrame::Wont(option));
         }
         // First, check if we support providing this option
-        if !self.config[option_idx].local {
+        if !self.policy.accepts_local(option) {
             // If we don't support it, reject with `WONT`
             return Some(TelnetFrame::Wont(option));
         }
@@ -873,24 +937,7 @@ impl TelnetOptions {
 
 impl Default for TelnetOptions {
     fn default() -> Self {
-        TelnetOptions {
-            config: core::array::from_fn(|idx| {
-                let option = TelnetOption::from_u8(idx as u8);
-                SupportState {
-                    local: if option.supported_local() {
-                        true
-                    } else {
-                        false
-                    },
-                    remote: if option.supported_remote() {
-                        true
-                    } else {
-                        false
-                    },
-                }
-            }),
-            state: core::array::from_fn(|_| OptionState::default()),
-        }
+        TelnetOptions::with_policy(OptionPolicy::default())
     }
 }
 
@@ -1057,17 +1104,6 @@ impl std::fmt::Display for QState {
     }
 }
 
-/// The `SupportState` struct represents the state of a support entity,
-/// containing both local and remote state information.
-///
-#[derive(Clone, Debug, Default)]
-struct SupportState {
-    /// Whether we support this option from us -> them.
-    pub local: bool,
-    /// Whether we support this option from them -> us.
-    pub remote: bool,
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1559,4 +1595,27 @@ mod tests {
         assert_eq!(server.state[opt.to_u8() as usize].local, QState::No);
         assert!(!server.local_enabled(opt));
     }
+
+    #[test]
+    fn test_timing_mark_is_answered_every_time() {
+        let opt = TelnetOption::TimingMark;
+        let mut client = TelnetOptions::with_policy(OptionPolicy::default().accept_remote(opt));
+        let mut server = TelnetOptions::with_policy(OptionPolicy::default().accept_local(opt));
+
+        for _ in 0..3 {
+            let do_frame = client.enable_remote(opt).unwrap();
+            assert_eq!(do_frame, TelnetFrame::Do(opt));
+            let will = server.handle_received(do_frame).unwrap();
+            assert_eq!(will, Some(TelnetFrame::Will(opt)));
+            assert_eq!(client.handle_received(will.unwrap()).unwrap(), None);
+        }
+        assert_eq!(client.state[opt.to_u8() as usize].remote, QState::No);
+        assert_eq!(server.state[opt.to_u8() as usize].local, QState::No);
+
+        // Refused by default, still without a state change
+        let mut refusing = TelnetOptions::default();
+        let answer = refusing.handle_received(TelnetFrame::Do(opt)).unwrap();
+        assert_eq!(answer, Some(TelnetFrame::Wont(opt)));
+        assert_eq!(refusing.state[opt.to_u8() as usize].local, QState::No);
+    }
 }
@@ -0,0 +1,246 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Per-codec Telnet option negotiation policy
+
+use crate::TelnetOption;
+
+/// Decides which Telnet options a codec accepts and which it requests on its own.
+///
+/// Every [`TelnetCodec`](crate::TelnetCodec) consults its policy when the peer sends `WILL` or
+/// `DO`: options that are not accepted are refused with `DONT` or `WONT`. The policy also lists
+/// the options a connection should request proactively (`WILL` for local options, `DO` for
+/// remote options) as soon as it is established.
+///
+/// [`OptionPolicy::default`] mirrors the options implemented by this library and requests nothing,
+/// which matches the behavior of a codec created with [`TelnetCodec::new`](crate::TelnetCodec::new).
+///
+/// # Example
+///
+/// ```
+/// use termionix_telnetcodec::{OptionPolicy, TelnetOption};
+///
+/// // A plain admin port: no GMCP, but ask the client for its window size.
+/// let policy = OptionPolicy::default()
+///     .refuse(TelnetOption::GMCP)
+///     .request_remote(TelnetOption::NAWS);
+///
+/// assert!(!policy.accepts_local(TelnetOption::GMCP));
+/// assert!(policy.requests_remote(TelnetOption::NAWS));
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct OptionPolicy {
+    accept_local: [bool; 256],
+    accept_remote: [bool; 256],
+    request_local: [bool; 256],
+    request_remote: [bool; 256],
+}
+
+impl OptionPolicy {
+    /// Creates a policy that refuses every option and requests nothing.
+    #[must_use]
+    pub fn new() -> OptionPolicy {
+        OptionPolicy {
+            accept_local: [false; 256],
+            accept_remote: [false; 256],
+            request_local: [false; 256],
+            request_remote: [false; 256],
+        }
+    }
+
+    /// Accept requests for us to enable `option` (the peer sends `DO`).
+    #[must_use]
+    pub fn accept_local(mut self, option: TelnetOption) -> Self {
+        self.accept_local[option.to_u8() as usize] = true;
+        self
+    }
+
+    /// Accept offers from the peer to enable `option` (the peer sends `WILL`).
+    #[must_use]
+    pub fn accept_remote(mut self, option: TelnetOption) -> Self {
+        self.accept_remote[option.to_u8() as usize] = true;
+        self
+    }
+
+    /// Accept `option` on both sides of the connection.
+    #[must_use]
+    pub fn accept(self, option: TelnetOption) -> Self {
+        self.accept_local(option).accept_remote(option)
+    }
+
+    /// Refuse to enable `option` locally. Also removes it from the local requests.
+    #[must_use]
+    pub fn refuse_local(mut self, option: TelnetOption) -> Self {
+        self.accept_local[option.to_u8() as usize] = false;
+        self.request_local[option.to_u8() as usize] = false;
+        self
+    }
+
+    /// Refuse to let the peer enable `option`. Also removes it from the remote requests.
+    #[must_use]
+    pub fn refuse_remote(mut self, option: TelnetOption) -> Self {
+        self.accept_remote[option.to_u8() as usize] = false;
+        self.request_remote[option.to_u8() as usize] = false;
+        self
+    }
+
+    /// Refuse `option` on both sides of the connection.
+    #[must_use]
+    pub fn refuse(self, option: TelnetOption) -> Self {
+        self.refuse_local(option).refuse_remote(option)
+    }
+
+    /// Offer `option` (`WILL`) when the connection is established. Implies [`accept_local`](Self::accept_local).
+    #[must_use]
+    pub fn request_local(mut self, option: TelnetOption) -> Self {
+        self.accept_local[option.to_u8() as usize] = true;
+        self.request_local[option.to_u8() as usize] = true;
+        self
+    }
+
+    /// Ask the peer to enable `option` (`DO`) when the connection is established. Implies
+    /// [`accept_remote`](Self::accept_remote).
+    #[must_use]
+    pub fn request_remote(mut self, option: TelnetOption) -> Self {
+        self.accept_remote[option.to_u8() as usize] = true;
+        self.request_remote[option.to_u8() as usize] = true;
+        self
+    }
+
+    /// Whether we agree to enable `option` locally.
+    #[must_use]
+    pub fn accepts_local(&self, option: TelnetOption) -> bool {
+        self.accept_local[option.to_u8() as usize]
+    }
+
+    /// Whether we agree to let the peer enable `option`.
+    #[must_use]
+    pub fn accepts_remote(&self, option: TelnetOption) -> bool {
+        self.accept_remote[option.to_u8() as usize]
+    }
+
+    /// Whether `option` is offered locally when the connection is established.
+    #[must_use]
+    pub fn requests_local(&self, option: TelnetOption) -> bool {
+        self.request_local[option.to_u8() as usize]
+    }
+
+    /// Whether the peer is asked to enable `option` when the connection is established.
+    #[must_use]
+    pub fn requests_remote(&self, option: TelnetOption) -> bool {
+        self.request_remote[option.to_u8() as usize]
+    }
+
+    /// Options offered locally on connect, in ascending option code order.
+    pub fn requested_local(&self) -> impl Iterator<Item = TelnetOption> + '_ {
+        Self::options_in(&self.request_local)
+    }
+
+    /// Options requested from the peer on connect, in ascending option code order.
+    pub fn requested_remote(&self) -> impl Iterator<Item = TelnetOption> + '_ {
+        Self::options_in(&self.request_remote)
+    }
+
+    fn options_in(table: &[bool; 256]) -> impl Iterator<Item = TelnetOption> + '_ {
+        table
+            .iter()
+            .zip(0..=u8::MAX)
+            .filter(|(set, _)| **set)
+            .map(|(_, code)| TelnetOption::from_u8(code))
+    }
+}
+
+impl Default for OptionPolicy {
+    fn default() -> Self {
+        let mut policy = OptionPolicy::new();
+        for code in 0..=u8::MAX {
+            let option = TelnetOption::from_u8(code);
+            policy.accept_local[code as usize] = option.supported_local();
+            policy.accept_remote[code as usize] = option.supported_remote();
+        }
+        policy
+    }
+}
+
+impl std::fmt::Debug for OptionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OptionPolicy")
+            .field(
+                "accept_local",
+                &Self::options_in(&self.accept_local).collect::<Vec<_>>(),
+            )
+            .field(
+                "accept_remote",
+                &Self::options_in(&self.accept_remote).collect::<Vec<_>>(),
+            )
+            .field("request_local", &self.requested_local().collect::<Vec<_>>())
+            .field(
+                "request_remote",
+                &self.requested_remote().collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_matches_support_table() {
+        let policy = OptionPolicy::default();
+        for code in 0..=u8::MAX {
+            let option = TelnetOption::from_u8(code);
+            assert_eq!(policy.accepts_local(option), option.supported_local());
+            assert_eq!(policy.accepts_remote(option), option.supported_remote());
+        }
+        assert_eq!(policy.requested_local().count(), 0);
+        assert_eq!(policy.requested_remote().count(), 0);
+    }
+
+    #[test]
+    fn test_new_refuses_everything() {
+        let policy = OptionPolicy::new();
+        assert!(!policy.accepts_local(TelnetOption::Echo));
+        assert!(!policy.accepts_remote(TelnetOption::NAWS));
+    }
+
+    #[test]
+    fn test_refuse_removes_request() {
+        let policy = OptionPolicy::default()
+            .request_local(TelnetOption::GMCP)
+            .request_remote(TelnetOption::GMCP)
+            .refuse(TelnetOption::GMCP);
+        assert!(!policy.accepts_local(TelnetOption::GMCP));
+        assert!(!policy.accepts_remote(TelnetOption::GMCP));
+        assert!(!policy.requests_local(TelnetOption::GMCP));
+        assert!(!policy.requests_remote(TelnetOption::GMCP));
+    }
+
+    #[test]
+    fn test_request_implies_accept() {
+        let policy = OptionPolicy::new()
+            .request_local(TelnetOption::Echo)
+            .request_remote(TelnetOption::NAWS)
+            .request_remote(TelnetOption::TTYPE);
+        assert!(policy.accepts_local(TelnetOption::Echo));
+        assert!(!policy.accepts_remote(TelnetOption::Echo));
+        assert_eq!(
+            policy.requested_remote().collect::<Vec<_>>(),
+            vec![TelnetOption::TTYPE, TelnetOption::NAWS]
+        );
+    }
+}
@@ -55,6 +55,47 @@ pub enum TelnetCodecError {
     ///
     /// Contains the invalid command byte value.
     UnknownCommand(u8),
+
+    /// A subnegotiation payload grew past
+    /// [`CodecLimits::max_subnegotiation_length`](crate::CodecLimits::max_subnegotiation_length).
+    SubnegotiationTooLong {
+        /// The telnet option being subnegotiated
+        option: u8,
+        /// The maximum payload length in bytes
+        limit: usize,
+    },
+
+    /// An option was negotiated more often than
+    /// [`CodecLimits::max_negotiations_per_option`](crate::CodecLimits::max_negotiations_per_option)
+    /// allows within one window.
+    NegotiationLimitExceeded {
+        /// The telnet option being negotiated
+        option: u8,
+        /// The maximum number of negotiations per window
+        limit: u32,
+    },
+
+    /// More negotiation responses were queued than
+    /// [`CodecLimits::max_pending_responses`](crate::CodecLimits::max_pending_responses) allows.
+    TooManyPendingResponses {
+        /// The maximum number of queued responses
+        limit: usize,
+    },
+}
+
+impl TelnetCodecError {
+    /// Returns true if the error is a violated [`CodecLimits`](crate::CodecLimits) limit.
+    ///
+    /// The peer is misbehaving and the connection should be closed.
+    #[must_use]
+    pub fn is_limit_exceeded(&self) -> bool {
+        matches!(
+            self,
+            TelnetCodecError::SubnegotiationTooLong { .. }
+                | TelnetCodecError::NegotiationLimitExceeded { .. }
+                | TelnetCodecError::TooManyPendingResponses { .. }
+        )
+    }
 }
 
 /// Specific kinds of subnegotiation errors with structured context.
@@ -140,6 +181,21 @@ impl std::fmt::Display for TelnetCodecError {
             TelnetCodecError::UnknownCommand(cmd) => {
                 write!(f, "Unknown telnet command: 0x{:02X}", cmd)
             }
+            TelnetCodecError::SubnegotiationTooLong { option, limit } => {
+                write!(
+                    f,
+                    "Subnegotiation for option {option} exceeds {limit} bytes"
+                )
+            }
+            TelnetCodecError::NegotiationLimitExceeded { option, limit } => {
+                write!(
+                    f,
+                    "Option {option} negotiated more than {limit} times per window"
+                )
+            }
+            TelnetCodecError::TooManyPendingResponses { limit } => {
+                write!(f, "More than {limit} negotiation responses pending")
+            }
         }
     }
 }
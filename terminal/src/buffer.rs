@@ -17,7 +17,7 @@
 use crate::types::{CursorPosition, TerminalSize};
 use std::collections::BTreeMap;
 use termionix_ansicodec::utility::StyledString;
-use termionix_ansicodec::{AnsiConfig, SegmentedString};
+use termionix_ansicodec::{AnsiConfig, AnsiControlSequenceIntroducer, SegmentedString};
 use tracing::instrument;
 
 /// Virtual Terminal Buffer
@@ -184,6 +184,22 @@ impl TerminalBuffer {
         self.environment.get(key)
     }
 
+    /// Removes an environment variable, returning its value if it was set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use termionix_terminal::TerminalBuffer;
+    /// let mut buffer = TerminalBuffer::new();
+    /// buffer.set_environment("USER", "alice");
+    ///
+    /// assert_eq!(buffer.remove_environment("USER"), Some("alice".to_string()));
+    /// assert_eq!(buffer.get_environment("USER"), None);
+    /// ```
+    pub fn remove_environment(&mut self, key: &str) -> Option<String> {
+        self.environment.remove(key)
+    }
+
     /// Moves the cursor by a relative offset in both column and row directions.
     ///
     /// This method adjusts the cursor position by the specified column and row deltas,
@@ -251,6 +267,14 @@ impl TerminalBuffer {
         self.cursor
     }
 
+    /// Appends a control sequence to the current line without moving the cursor
+    ///
+    /// Used for sequences that consumers of completed lines need to see, such
+    /// as MXP line-mode changes.
+    pub fn append_csi(&mut self, csi: AnsiControlSequenceIntroducer) {
+        self.current_line.push_ansi_csi(csi);
+    }
+
     /// Erases the last character from the current line buffer
     pub fn erase_character(&mut self) {
         if self.current_line.pop().is_some() {
@@ -0,0 +1,308 @@
+//
+// Copyright 2017-2026 Hans W. Uhlig. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Client terminal capabilities gathered from TTYPE and the Mud Terminal Type Standard
+
+use termionix_ansicodec::ColorMode;
+
+/// Mud Terminal Type Standard (MTTS) bit values
+///
+/// A client reports the sum of its capabilities as `MTTS <bitvector>` in its
+/// third TTYPE response. See `doc/mtts.md`.
+pub mod mtts {
+    /// Client supports all common ANSI color codes
+    pub const ANSI: u32 = 1;
+    /// Client supports all common VT100 codes
+    pub const VT100: u32 = 2;
+    /// Client is using UTF-8 character encoding
+    pub const UTF8: u32 = 4;
+    /// Client supports all 256 color codes
+    pub const COLORS_256: u32 = 8;
+    /// Client supports xterm mouse tracking
+    pub const MOUSE_TRACKING: u32 = 16;
+    /// Client supports OSC and the OSC color palette
+    pub const OSC_COLOR_PALETTE: u32 = 32;
+    /// Client is using a screen reader
+    pub const SCREEN_READER: u32 = 64;
+    /// Client is a proxy allowing different users to connect from the same IP address
+    pub const PROXY: u32 = 128;
+    /// Client supports truecolor codes using semicolon notation
+    pub const TRUECOLOR: u32 = 256;
+    /// Client supports the Mud New Environment Standard
+    pub const MNES: u32 = 512;
+    /// Client supports the Mud Server Link Protocol
+    pub const MSLP: u32 = 1024;
+    /// Client supports SSL for data encryption
+    pub const SSL: u32 = 2048;
+
+    /// Parses an `MTTS <bitvector>` terminal type, returning the bitvector
+    ///
+    /// ```
+    /// use termionix_terminal::mtts;
+    ///
+    /// assert_eq!(mtts::parse("MTTS 137"), Some(137));
+    /// assert_eq!(mtts::parse("XTERM"), None);
+    /// ```
+    pub fn parse(terminal_type: &str) -> Option<u32> {
+        let (prefix, bits) = terminal_type.trim().split_once(' ')?;
+        if !prefix.eq_ignore_ascii_case("MTTS") {
+            return None;
+        }
+        bits.trim().parse().ok()
+    }
+}
+
+/// Most TTYPE responses requested before the list is treated as complete
+const MAX_TERMINAL_TYPES: usize = 8;
+
+/// Capabilities of the client terminal
+///
+/// Filled in from the terminal types a client reports while the server cycles
+/// TTYPE `SEND` requests. A client following the Mud Terminal Type Standard
+/// reports its name, its terminal type and an `MTTS` bitvector, which is
+/// authoritative. For other clients the flags are guessed from the first
+/// terminal type.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TerminalCapabilities {
+    /// Client name, reported by MTTS clients in their first response
+    pub client_name: Option<String>,
+    /// Terminal type, e.g. `XTERM` or `ANSI-256COLOR`
+    pub terminal_type: Option<String>,
+    /// The raw `MTTS` bitvector, if the client reported one
+    pub mtts: Option<u32>,
+    /// Common ANSI color codes
+    pub ansi: bool,
+    /// Common VT100 codes
+    pub vt100: bool,
+    /// UTF-8 character encoding
+    pub utf8: bool,
+    /// 256 color codes
+    pub color_256: bool,
+    /// Truecolor codes
+    pub truecolor: bool,
+    /// xterm mouse tracking
+    pub mouse_tracking: bool,
+    /// OSC color palette
+    pub osc_color_palette: bool,
+    /// The client is using a screen reader
+    pub screen_reader: bool,
+    /// The client is a proxy for several users
+    pub proxy: bool,
+    /// Mud New Environment Standard
+    pub mnes: bool,
+    /// Mud Server Link Protocol
+    pub mslp: bool,
+    /// SSL/TLS encryption
+    pub ssl: bool,
+    /// The client has repeated a terminal type, so no more will be reported
+    pub complete: bool,
+}
+
+impl TerminalCapabilities {
+    /// Creates capabilities from an MTTS bitvector
+    ///
+    /// ```
+    /// use termionix_terminal::{ColorMode, TerminalCapabilities};
+    ///
+    /// let caps = TerminalCapabilities::from_mtts(1 | 4 | 8);
+    /// assert!(caps.ansi && caps.utf8 && caps.color_256);
+    /// assert_eq!(caps.color_mode(), ColorMode::FixedColor);
+    /// ```
+    pub fn from_mtts(bits: u32) -> Self {
+        TerminalCapabilities {
+            mtts: Some(bits),
+            ansi: bits & mtts::ANSI != 0,
+            vt100: bits & mtts::VT100 != 0,
+            utf8: bits & mtts::UTF8 != 0,
+            color_256: bits & mtts::COLORS_256 != 0,
+            truecolor: bits & mtts::TRUECOLOR != 0,
+            mouse_tracking: bits & mtts::MOUSE_TRACKING != 0,
+            osc_color_palette: bits & mtts::OSC_COLOR_PALETTE != 0,
+            screen_reader: bits & mtts::SCREEN_READER != 0,
+            proxy: bits & mtts::PROXY != 0,
+            mnes: bits & mtts::MNES != 0,
+            mslp: bits & mtts::MSLP != 0,
+            ssl: bits & mtts::SSL != 0,
+            ..Default::default()
+        }
+    }
+
+    /// Guesses capabilities from a terminal type name
+    ///
+    /// Recognizes the generic MTTS types `DUMB`, `ANSI`, `VT100` and `XTERM`,
+    /// common terminal emulators, and the `-256COLOR` and `-TRUECOLOR` suffixes.
+    ///
+    /// ```
+    /// use termionix_terminal::{ColorMode, TerminalCapabilities};
+    ///
+    /// let caps = TerminalCapabilities::from_terminal_type("xterm-truecolor");
+    /// assert!(caps.mouse_tracking);
+    /// assert_eq!(caps.color_mode(), ColorMode::TrueColor);
+    /// ```
+    pub fn from_terminal_type(terminal_type: &str) -> Self {
+        let name = terminal_type.to_ascii_uppercase();
+        let mut caps = TerminalCapabilities {
+            terminal_type: Some(terminal_type.to_string()),
+            ..Default::default()
+        };
+        if name.starts_with("XTERM") {
+            caps.ansi = true;
+            caps.vt100 = true;
+            caps.color_256 = true;
+            caps.mouse_tracking = true;
+        } else if ["VT1", "VT2", "LINUX", "SCREEN", "TMUX", "RXVT"]
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+        {
+            caps.ansi = true;
+            caps.vt100 = true;
+        } else if name.starts_with("ANSI") {
+            caps.ansi = true;
+        }
+        if name.contains("256COLOR") {
+            caps.ansi = true;
+            caps.color_256 = true;
+        }
+        if name.contains("TRUECOLOR") || name.contains("DIRECT") {
+            caps.ansi = true;
+            caps.color_256 = true;
+            caps.truecolor = true;
+        }
+        caps
+    }
+
+    /// The richest [`ColorMode`] the client supports
+    pub fn color_mode(&self) -> ColorMode {
+        if self.truecolor {
+            ColorMode::TrueColor
+        } else if self.color_256 {
+            ColorMode::FixedColor
+        } else if self.ansi {
+            ColorMode::Basic
+        } else {
+            ColorMode::None
+        }
+    }
+}
+
+/// Tracks the terminal types a client reports to repeated TTYPE `SEND` requests
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TerminalTypeCycle {
+    responses: Vec<String>,
+    complete: bool,
+}
+
+impl TerminalTypeCycle {
+    /// Forgets all reported terminal types
+    pub(crate) fn reset(&mut self) {
+        self.responses.clear();
+        self.complete = false;
+    }
+
+    /// Records a TTYPE `IS` response, returning whether to send another `SEND`
+    ///
+    /// The list is complete once the client repeats a terminal type.
+    pub(crate) fn record(&mut self, terminal_type: &str) -> bool {
+        if self.complete {
+            return false;
+        }
+        if self.responses.iter().any(|seen| seen == terminal_type) {
+            self.complete = true;
+        } else {
+            self.responses.push(terminal_type.to_string());
+            self.complete = self.responses.len() >= MAX_TERMINAL_TYPES;
+        }
+        !self.complete
+    }
+
+    /// Capabilities described by the terminal types reported so far
+    pub(crate) fn capabilities(&self) -> TerminalCapabilities {
+        let mut caps = match self.responses.iter().find_map(|name| mtts::parse(name)) {
+            Some(bits) => {
+                let mut caps = TerminalCapabilities::from_mtts(bits);
+                let mut names = self
+                    .responses
+                    .iter()
+                    .filter(|name| mtts::parse(name).is_none());
+                caps.client_name = names.next().cloned();
+                caps.terminal_type = names.next().cloned();
+                caps
+            }
+            None => self
+                .responses
+                .first()
+                .map(|name| TerminalCapabilities::from_terminal_type(name))
+                .unwrap_or_default(),
+        };
+        caps.complete = self.complete;
+        caps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mtts_cycle() {
+        let mut cycle = TerminalTypeCycle::default();
+        assert!(cycle.record("TINTIN++"));
+        assert!(cycle.record("XTERM"));
+        assert!(cycle.record("MTTS 137"));
+        assert!(!cycle.record("MTTS 137"));
+
+        let caps = cycle.capabilities();
+        assert!(caps.complete);
+        assert_eq!(caps.client_name.as_deref(), Some("TINTIN++"));
+        assert_eq!(caps.terminal_type.as_deref(), Some("XTERM"));
+        assert_eq!(caps.mtts, Some(137));
+        // 137 = ANSI | 256 COLORS | PROXY
+        assert!(caps.ansi && caps.color_256 && caps.proxy);
+        assert!(!caps.vt100 && !caps.utf8);
+        assert_eq!(caps.color_mode(), ColorMode::FixedColor);
+    }
+
+    #[test]
+    fn test_plain_ttype_cycle() {
+        let mut cycle = TerminalTypeCycle::default();
+        assert!(cycle.record("xterm-256color"));
+        assert!(!cycle.record("xterm-256color"));
+
+        let caps = cycle.capabilities();
+        assert!(caps.complete);
+        assert_eq!(caps.client_name, None);
+        assert_eq!(caps.terminal_type.as_deref(), Some("xterm-256color"));
+        assert_eq!(caps.mtts, None);
+        assert_eq!(caps.color_mode(), ColorMode::FixedColor);
+    }
+
+    #[test]
+    fn test_cycle_is_bounded() {
+        let mut cycle = TerminalTypeCycle::default();
+        for i in 1..MAX_TERMINAL_TYPES {
+            assert!(cycle.record(&format!("TYPE{i}")));
+        }
+        assert!(!cycle.record("LAST"));
+        assert!(cycle.capabilities().complete);
+    }
+
+    #[test]
+    fn test_dumb_terminal() {
+        let caps = TerminalCapabilities::from_terminal_type("DUMB");
+        assert!(!caps.ansi);
+        assert_eq!(caps.color_mode(), ColorMode::None);
+    }
+}
@@ -14,10 +14,15 @@
 // limitations under the License.
 //
 
-use crate::{CursorPosition, TerminalBuffer, TerminalCommand, TerminalError, TerminalEvent};
+use crate::capabilities::TerminalTypeCycle;
+use crate::{
+    CursorPosition, TerminalBuffer, TerminalCapabilities, TerminalCommand, TerminalError,
+    TerminalEvent,
+};
+use termionix_ansicodec::new_environ::EnvironCommand;
 use termionix_ansicodec::{
-    AnsiControlCode, AnsiControlSequenceIntroducer, AnsiSequence, TelnetArgument, TelnetCommand,
-    TelnetSide, status,
+    AnsiControlCode, AnsiControlSequenceIntroducer, AnsiSequence, NegotiationGate, TelnetArgument,
+    TelnetCodecError, TelnetCommand, TelnetOption, TelnetResponder, TelnetSide,
 };
 use tokio_util::bytes::BytesMut;
 use tokio_util::codec::{Decoder, Encoder};
@@ -28,6 +33,14 @@ use tracing::instrument;
 pub struct TerminalCodec<I> {
     buffer: TerminalBuffer,
     codec: I,
+    /// Event returned by the next `decode` call, for inputs producing two events
+    pending: Option<TerminalEvent>,
+    /// Capabilities reported by the peer's terminal types
+    capabilities: TerminalCapabilities,
+    /// Terminal types reported so far
+    terminal_types: TerminalTypeCycle,
+    /// Whether to send TTYPE `SEND` until the peer's list repeats
+    terminal_type_cycling: bool,
 }
 
 impl<I> TerminalCodec<I> {
@@ -36,6 +49,10 @@ impl<I> TerminalCodec<I> {
         TerminalCodec {
             buffer: TerminalBuffer::default(),
             codec,
+            pending: None,
+            capabilities: TerminalCapabilities::default(),
+            terminal_types: TerminalTypeCycle::default(),
+            terminal_type_cycling: false,
         }
     }
 
@@ -63,11 +80,51 @@ impl<I> TerminalCodec<I> {
     pub fn terminal_buffer(&self) -> &TerminalBuffer {
         &self.buffer
     }
+
+    /// Returns the capabilities reported by the peer's terminal types.
+    pub fn capabilities(&self) -> &TerminalCapabilities {
+        &self.capabilities
+    }
+
+    /// Returns whether TTYPE `SEND` requests are cycled automatically.
+    pub fn terminal_type_cycling(&self) -> bool {
+        self.terminal_type_cycling
+    }
+
+    /// Enables or disables cycling TTYPE `SEND` requests.
+    ///
+    /// When enabled, a `SEND` is queued once the peer enables TTYPE and after
+    /// each terminal type it reports, until the peer repeats one. This is how a
+    /// server collects the client name, terminal type and MTTS bitvector.
+    pub fn set_terminal_type_cycling(&mut self, enabled: bool) {
+        self.terminal_type_cycling = enabled;
+    }
+}
+
+impl<I> TelnetResponder for TerminalCodec<I>
+where
+    I: TelnetResponder,
+{
+    fn has_pending_responses(&self) -> bool {
+        self.codec.has_pending_responses()
+    }
+
+    fn flush_responses(&mut self, dst: &mut BytesMut) -> Result<(), TelnetCodecError> {
+        self.codec.flush_responses(dst)
+    }
+
+    fn queue_subnegotiation(&mut self, argument: TelnetArgument) -> Result<(), TelnetCodecError> {
+        self.codec.queue_subnegotiation(argument)
+    }
+
+    fn set_negotiation_gate(&mut self, gate: Option<NegotiationGate>) {
+        self.codec.set_negotiation_gate(gate);
+    }
 }
 
 impl<I> Decoder for TerminalCodec<I>
 where
-    I: Decoder<Item = AnsiSequence>,
+    I: Decoder<Item = AnsiSequence> + TelnetResponder,
     TerminalError: From<I::Error>,
 {
     type Item = TerminalEvent;
@@ -75,6 +132,9 @@ where
 
     #[instrument(skip_all)]
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some(event) = self.pending.take() {
+            return Ok(Some(event));
+        }
         match self.codec.decode(src)? {
             Some(sequence) => {
                 let cursor = self.buffer.cursor_position();
@@ -134,6 +194,11 @@ where
                         // Standalone escape - ignore
                         Ok(None)
                     }
+                    AnsiSequence::AnsiCSI(csi @ AnsiControlSequenceIntroducer::MXPLineMode(_)) => {
+                        // MXP mode changes stay in the line for MxpParser::push_line
+                        self.buffer.append_csi(csi);
+                        Ok(None)
+                    }
                     AnsiSequence::AnsiCSI(csi) => {
                         // Handle CSI commands
                         self.handle_csi(csi, cursor)
@@ -175,7 +240,10 @@ where
     }
 }
 
-impl<I> TerminalCodec<I> {
+impl<I> TerminalCodec<I>
+where
+    I: TelnetResponder,
+{
     /// Handle CSI (Control Sequence Introducer) commands
     fn handle_csi(
         &mut self,
@@ -214,27 +282,72 @@ impl<I> TerminalCodec<I> {
             }
             TelnetCommand::GoAhead => Ok(None),
             TelnetCommand::EndOfRecord => Ok(None),
-            TelnetCommand::OptionStatus(option, side, enabled) => Ok(Some(
-                TerminalEvent::TelnetOptionStatus(status::TelnetOptionStatus {
-                    command: status::StatusCommand::Is,
-                    options: std::collections::HashMap::from([(
-                        option,
-                        (side == TelnetSide::Remote, enabled),
-                    )]),
-                }),
-            )),
+            TelnetCommand::OptionStatus(option, side, enabled) => {
+                if option == TelnetOption::TTYPE && side == TelnetSide::Remote {
+                    // A fresh TTYPE session starts the list over
+                    self.terminal_types.reset();
+                    self.capabilities = TerminalCapabilities::default();
+                    if enabled && self.terminal_type_cycling {
+                        self.codec
+                            .queue_subnegotiation(TelnetArgument::TerminalTypeSend)?;
+                    }
+                }
+                Ok(Some(TerminalEvent::OptionChanged {
+                    option,
+                    side,
+                    enabled,
+                }))
+            }
             TelnetCommand::Subnegotiation(arg) => {
                 // Handle subnegotiation based on the argument type
-                use TelnetArgument;
                 match arg {
                     TelnetArgument::NAWSWindowSize(window_size) => {
                         let old = self.buffer.size();
                         self.buffer
                             .set_size(window_size.cols as usize, window_size.rows as usize);
                         let new = self.buffer.size();
+                        // The raw subnegotiation follows the resize
+                        self.pending = Some(TerminalEvent::Subnegotiation(
+                            TelnetArgument::NAWSWindowSize(window_size),
+                        ));
                         Ok(Some(TerminalEvent::ResizeWindow { old, new }))
                     }
-                    _ => Ok(None),
+                    TelnetArgument::TerminalType(terminal_type) => {
+                        if self.terminal_types.record(&terminal_type) && self.terminal_type_cycling
+                        {
+                            self.codec
+                                .queue_subnegotiation(TelnetArgument::TerminalTypeSend)?;
+                        }
+                        self.capabilities = self.terminal_types.capabilities();
+                        // The raw subnegotiation follows the terminal type
+                        self.pending = Some(TerminalEvent::Subnegotiation(
+                            TelnetArgument::TerminalType(terminal_type.clone()),
+                        ));
+                        Ok(Some(TerminalEvent::TerminalType { terminal_type }))
+                    }
+                    TelnetArgument::NewEnviron(environ) => {
+                        // IS and INFO carry the peer's variables; undefined ones are removed
+                        if environ.command != EnvironCommand::Send {
+                            for variable in &environ.variables {
+                                match &variable.value {
+                                    Some(value) => {
+                                        self.buffer.set_environment(&variable.name, value)
+                                    }
+                                    None => {
+                                        self.buffer.remove_environment(&variable.name);
+                                    }
+                                }
+                            }
+                        }
+                        Ok(Some(TerminalEvent::Subnegotiation(
+                            TelnetArgument::NewEnviron(environ),
+                        )))
+                    }
+                    TelnetArgument::Unknown(
+                        option @ (TelnetOption::Compress2 | TelnetOption::Compress3),
+                        payload,
+                    ) if payload.is_empty() => Ok(Some(TerminalEvent::CompressionStarted(option))),
+                    arg => Ok(Some(TerminalEvent::Subnegotiation(arg))),
                 }
             }
         }
@@ -372,6 +485,15 @@ where
                     dst,
                 )
                 .map_err(From::from),
+            TerminalCommand::NewEnviron(environ) => self
+                .codec
+                .encode(
+                    AnsiSequence::TelnetCommand(TelnetCommand::Subnegotiation(
+                        TelnetArgument::NewEnviron(environ),
+                    )),
+                    dst,
+                )
+                .map_err(From::from),
         }
     }
 }
@@ -617,12 +739,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_decode_line_keeps_mxp_line_mode() {
+        use termionix_ansicodec::mxp::{MxpNode, MxpParser};
+        use tokio_util::codec::Decoder;
+
+        let mut codec = create_test_codec();
+        let mut buffer = BytesMut::from("\x1b[1z<SEND>look</SEND>\n");
+        let mut completed = None;
+        while !buffer.is_empty() {
+            if let Some(TerminalEvent::LineCompleted { line, .. }) =
+                codec.decode(&mut buffer).unwrap()
+            {
+                completed = Some(line);
+            }
+        }
+
+        let mut parser = MxpParser::new();
+        parser.push_line(&completed.expect("Expected LineCompleted event"));
+        match parser.take_nodes().first() {
+            Some(MxpNode::Element(element)) => assert_eq!(element.tag.name(), "send"),
+            other => panic!("Expected SEND element, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_decode_carriage_return() {
         use tokio_util::codec::Decoder;
 
         let mut codec = create_test_codec();
-        let mut buffer = BytesMut::from(&[0x0D][..]); // CR
+        let mut buffer = BytesMut::from(&[0x0D, 0x00][..]); // CR NUL
 
         match codec.decode(&mut buffer) {
             Ok(Some(TerminalEvent::CursorPosition { cursor })) => {
@@ -895,4 +1041,164 @@ mod tests {
         let codec = create_test_codec();
         assert_eq!(codec.buffer().width(), 80);
     }
+
+    #[test]
+    fn test_decode_compression_start_stops_at_boundary() {
+        use tokio_util::codec::Decoder;
+
+        let mut codec = create_test_codec();
+        // IAC SB COMPRESS2 IAC SE followed by (would-be) compressed bytes
+        let mut buffer = BytesMut::from(&[0xFF, 0xFA, 86, 0xFF, 0xF0, 0x78, 0x9C][..]);
+
+        match codec.decode(&mut buffer) {
+            Ok(Some(TerminalEvent::CompressionStarted(option))) => {
+                assert_eq!(option, TelnetOption::Compress2);
+            }
+            other => panic!("Expected CompressionStarted event, got {:?}", other),
+        }
+        // Nothing after the marker has been consumed
+        assert_eq!(&buffer[..], &[0x78, 0x9C]);
+    }
+
+    #[test]
+    fn test_decode_option_changed() {
+        use tokio_util::codec::Decoder;
+
+        let mut codec = create_test_codec();
+        // IAC WILL SGA
+        let mut buffer = BytesMut::from(&[0xFF, 0xFB, 3][..]);
+
+        match codec.decode(&mut buffer) {
+            Ok(Some(TerminalEvent::OptionChanged {
+                option,
+                side,
+                enabled,
+            })) => {
+                assert_eq!(option, TelnetOption::SuppressGoAhead);
+                assert_eq!(side, termionix_ansicodec::TelnetSide::Remote);
+                assert!(enabled);
+            }
+            other => panic!("Expected OptionChanged event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_naws_resizes_then_passes_subnegotiation() {
+        use tokio_util::codec::Decoder;
+
+        let mut codec = create_test_codec();
+        // IAC SB NAWS 0 100 0 40 IAC SE
+        let mut buffer = BytesMut::from(&[0xFF, 0xFA, 31, 0, 100, 0, 40, 0xFF, 0xF0, b'x'][..]);
+
+        match codec.decode(&mut buffer) {
+            Ok(Some(TerminalEvent::ResizeWindow { new, .. })) => {
+                assert_eq!(new.cols, 100);
+                assert_eq!(new.rows, 40);
+            }
+            other => panic!("Expected ResizeWindow event, got {:?}", other),
+        }
+        match codec.decode(&mut buffer) {
+            Ok(Some(TerminalEvent::Subnegotiation(TelnetArgument::NAWSWindowSize(size)))) => {
+                assert_eq!(size.cols, 100);
+                assert_eq!(size.rows, 40);
+            }
+            other => panic!("Expected NAWS subnegotiation, got {:?}", other),
+        }
+        assert!(matches!(
+            codec.decode(&mut buffer),
+            Ok(Some(TerminalEvent::CharacterData { character: 'x', .. }))
+        ));
+    }
+
+    #[test]
+    fn test_decode_new_environ_fills_environment() {
+        use tokio_util::codec::Decoder;
+
+        let mut codec = create_test_codec();
+        codec.buffer_mut().set_environment("IPADDRESS", "10.0.0.1");
+        // IAC SB NEW-ENVIRON IS VAR "CHARSET" VALUE "UTF-8" VAR "IPADDRESS" IAC SE
+        let mut buffer = BytesMut::from(&[0xFF, 0xFA, 39, 0, 0][..]);
+        buffer.extend_from_slice(b"CHARSET\x01UTF-8\x00IPADDRESS");
+        buffer.extend_from_slice(&[0xFF, 0xF0]);
+
+        assert!(matches!(
+            codec.decode(&mut buffer),
+            Ok(Some(TerminalEvent::Subnegotiation(
+                TelnetArgument::NewEnviron(_)
+            )))
+        ));
+        assert_eq!(
+            codec.buffer().get_environment("CHARSET"),
+            Some(&"UTF-8".to_string())
+        );
+        assert_eq!(codec.buffer().get_environment("IPADDRESS"), None);
+    }
+
+    #[test]
+    fn test_terminal_type_cycling() {
+        use termionix_ansicodec::OptionPolicy;
+        use tokio_util::codec::Decoder;
+
+        let policy = OptionPolicy::default().accept_remote(TelnetOption::TTYPE);
+        let ansi_codec = AnsiCodec::new(AnsiConfig::default(), TelnetCodec::with_policy(policy));
+        let mut codec = TerminalCodec::new(ansi_codec);
+        codec.set_terminal_type_cycling(true);
+        let send = [0xFF, 0xFA, 24, 1, 0xFF, 0xF0];
+
+        // IAC WILL TTYPE
+        let mut buffer = BytesMut::from(&[0xFF, 0xFB, 24][..]);
+        while codec.decode(&mut buffer).unwrap().is_some() {}
+        let mut responses = BytesMut::new();
+        codec.flush_responses(&mut responses).unwrap();
+        assert!(responses.ends_with(&send));
+
+        for (terminal_type, more) in [
+            ("MUDLET", true),
+            ("ANSI-TRUECOLOR", true),
+            ("MTTS 2317", true),
+            ("MTTS 2317", false),
+        ] {
+            let mut buffer = BytesMut::from(&[0xFF, 0xFA, 24, 0][..]);
+            buffer.extend_from_slice(terminal_type.as_bytes());
+            buffer.extend_from_slice(&[0xFF, 0xF0]);
+            assert!(matches!(
+                codec.decode(&mut buffer),
+                Ok(Some(TerminalEvent::TerminalType { .. }))
+            ));
+            assert!(matches!(
+                codec.decode(&mut buffer),
+                Ok(Some(TerminalEvent::Subnegotiation(
+                    TelnetArgument::TerminalType(_)
+                )))
+            ));
+            let mut responses = BytesMut::new();
+            codec.flush_responses(&mut responses).unwrap();
+            assert_eq!(&responses[..] == &send[..], more);
+        }
+
+        let caps = codec.capabilities();
+        assert!(caps.complete);
+        assert_eq!(caps.client_name.as_deref(), Some("MUDLET"));
+        assert_eq!(caps.terminal_type.as_deref(), Some("ANSI-TRUECOLOR"));
+        // 2317 = ANSI | VT100 | UTF-8 | 256 COLORS | TRUECOLOR | SSL
+        assert!(caps.utf8 && caps.truecolor && caps.ssl && !caps.mouse_tracking);
+        assert_eq!(caps.color_mode(), termionix_ansicodec::ColorMode::TrueColor);
+    }
+
+    #[test]
+    fn test_decode_gmcp_subnegotiation() {
+        use tokio_util::codec::Decoder;
+
+        let mut codec = create_test_codec();
+        let mut buffer = BytesMut::from(&[0xFF, 0xFA, 201][..]);
+        buffer.extend_from_slice(b"Core.Hello {}");
+        buffer.extend_from_slice(&[0xFF, 0xF0]);
+
+        match codec.decode(&mut buffer) {
+            Ok(Some(TerminalEvent::Subnegotiation(TelnetArgument::GMCP(message)))) => {
+                assert_eq!(message.package(), "Core.Hello");
+            }
+            other => panic!("Expected GMCP subnegotiation, got {:?}", other),
+        }
+    }
 }
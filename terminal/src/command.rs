@@ -17,6 +17,7 @@
 use termionix_ansicodec::gmcp::GmcpMessage;
 use termionix_ansicodec::msdp::MudServerData;
 use termionix_ansicodec::mssp::MudServerStatus;
+use termionix_ansicodec::new_environ::NewEnviron;
 use termionix_ansicodec::{
     AnsiApplicationProgramCommand, AnsiControlCode, AnsiControlSequenceIntroducer,
     AnsiDeviceControlString, AnsiOperatingSystemCommand, AnsiPrivacyMessage,
@@ -226,6 +227,8 @@ pub enum TerminalCommand {
     MSDP(MudServerData),
     /// Mud Server Status Protocol
     MSSP(MudServerStatus),
+    /// NEW-ENVIRON environment variables (MNES)
+    NewEnviron(NewEnviron),
 }
 
 // From trait implementations for convenient conversions
@@ -515,6 +518,7 @@ mod tests {
                 TerminalCommand::GMCP(_) => "gmccp",
                 TerminalCommand::MSDP(_) => "mud_server_data",
                 TerminalCommand::MSSP(_) => "mud_server_status",
+                TerminalCommand::NewEnviron(_) => "new_environ",
             };
         }
     }
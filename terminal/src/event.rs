@@ -17,7 +17,8 @@
 use crate::types::{CursorPosition, TerminalSize};
 use enum_kinds::EnumKind;
 use termionix_ansicodec::{
-    SegmentedString, msdp::MudServerData, mssp::MudServerStatus, status::TelnetOptionStatus,
+    SegmentedString, TelnetArgument, TelnetOption, TelnetSide, msdp::MudServerData,
+    mssp::MudServerStatus, status::TelnetOptionStatus,
 };
 
 /// Terminal Events
@@ -77,9 +78,51 @@ pub enum TerminalEvent {
     MudServerData(MudServerData),
     /// Mud Server Status
     MudServerStatus(MudServerStatus),
+    /// Peer started an MCCP compressed stream (`IAC SB COMPRESS2/COMPRESS3 IAC SE`)
+    ///
+    /// Every byte the peer sends after this event is compressed until the
+    /// compressed stream ends.
+    CompressionStarted(TelnetOption),
+    /// A Telnet option negotiation completed
+    OptionChanged {
+        /// The option that changed
+        option: TelnetOption,
+        /// Whether the option changed on our side or the peer's
+        side: TelnetSide,
+        /// `true` if the option is now enabled
+        enabled: bool,
+    },
+    /// A subnegotiation was received, e.g. GMCP, MSDP, TTYPE or CHARSET
+    Subnegotiation(TelnetArgument),
+    /// Input went over a connection rate limit that is set to warn
+    ///
+    /// Reported once each time the limit is exceeded, before the input that
+    /// exceeded it.
+    RateLimited(RateLimitKind),
     // TODO: Add More User Sendable Sidechannel Data Types
 }
 
+/// Kind of input limited by a connection rate limit
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RateLimitKind {
+    /// Completed lines
+    Lines,
+    /// Raw bytes, including Telnet commands
+    Bytes,
+    /// Telnet `DO`, `DONT`, `WILL` and `WONT` commands
+    Negotiations,
+}
+
+impl std::fmt::Display for RateLimitKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RateLimitKind::Lines => write!(f, "lines"),
+            RateLimitKind::Bytes => write!(f, "bytes"),
+            RateLimitKind::Negotiations => write!(f, "negotiations"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -390,6 +433,10 @@ mod tests {
                 TerminalEvent::TelnetOptionStatus(_) => "telnet_status",
                 TerminalEvent::MudServerData(_) => "msdp",
                 TerminalEvent::MudServerStatus(_) => "mssp",
+                TerminalEvent::CompressionStarted(_) => "mccp",
+                TerminalEvent::OptionChanged { .. } => "option_changed",
+                TerminalEvent::Subnegotiation(_) => "subnegotiation",
+                TerminalEvent::RateLimited(_) => "rate_limited",
             };
         }
     }
@@ -15,6 +15,7 @@
 //
 
 mod buffer;
+mod capabilities;
 mod codec;
 mod command;
 mod event;
@@ -23,9 +24,10 @@ mod types;
 mod utility;
 
 pub use self::buffer::TerminalBuffer;
+pub use self::capabilities::{TerminalCapabilities, mtts};
 pub use self::codec::TerminalCodec;
 pub use self::command::TerminalCommand;
-pub use self::event::TerminalEvent;
+pub use self::event::{RateLimitKind, TerminalEvent};
 pub use self::result::{TerminalError, TerminalResult};
 pub use self::types::{CursorPosition, TerminalSize};
 pub use self::utility::{terminal_word_unwrap, terminal_word_wrap};
@@ -35,11 +37,12 @@ pub use termionix_ansicodec::{
     AnsiApplicationProgramCommand, AnsiCodec, AnsiCodecError, AnsiCodecResult, AnsiConfig,
     AnsiControlCode, AnsiControlSequenceIntroducer, AnsiDeviceControlString,
     AnsiOperatingSystemCommand, AnsiParser, AnsiPrivacyMessage, AnsiSelectGraphicRendition,
-    AnsiSequence, AnsiStartOfString, Blink, Color, ColorMode, Font, Ideogram, Intensity,
-    SGRParameter, Script, Segment, SegmentedString, Span, SpannedString, StyledString,
-    SubnegotiationErrorKind, TelnetArgument, TelnetCodec, TelnetCodecError, TelnetCodecResult,
-    TelnetCommand, TelnetEvent, TelnetFrame, TelnetOption, TelnetSide, Underline, gmcp, linemode,
-    msdp, mssp, naocrd, naohts, naws, status, utility::strip_ansi_codes,
+    AnsiSequence, AnsiStartOfString, Blink, Charset, CodecLimits, Color, ColorMode, Font, Ideogram,
+    Intensity, NegotiationGate, OptionPolicy, SGRParameter, Script, Segment, SegmentedString, Span,
+    SpannedString, StyledString, SubnegotiationErrorKind, TelnetArgument, TelnetCodec,
+    TelnetCodecError, TelnetCodecResult, TelnetCommand, TelnetEvent, TelnetFrame, TelnetOption,
+    TelnetResponder, TelnetSide, Underline, atcp, gmcp, linemode, msdp, mssp, mxp, naocrd, naohts,
+    naws, new_environ, status, utility::strip_ansi_codes, zmp,
 };
 
 #[cfg(test)]
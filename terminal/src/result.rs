@@ -32,7 +32,15 @@ impl std::fmt::Display for TerminalError {
     }
 }
 
-impl std::error::Error for TerminalError {}
+impl std::error::Error for TerminalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TerminalError::IOError(error) => Some(error),
+            TerminalError::CodecError(error) => Some(error),
+            TerminalError::AnsiError(error) => Some(error),
+        }
+    }
+}
 
 impl From<std::io::Error> for TerminalError {
     fn from(error: std::io::Error) -> Self {
@@ -587,13 +587,13 @@ async fn test_reject_unsupported_options() {
         .await
         .unwrap();
 
-    // Send IAC WILL for an unsupported option (e.g., option 200)
-    stream.write_all(&[255, 251, 200]).await.unwrap();
+    // Send IAC WILL for an unsupported option (e.g., option 199)
+    stream.write_all(&[255, 251, 199]).await.unwrap();
 
     // Wait for response
     tokio::time::sleep(Duration::from_millis(100)).await;
 
-    // Read response (should be IAC DONT 200)
+    // Read response (should be IAC DONT 199)
     let mut buffer = vec![0u8; 10];
     let n = tokio::time::timeout(Duration::from_secs(1), stream.read(&mut buffer))
         .await
@@ -603,7 +603,7 @@ async fn test_reject_unsupported_options() {
     assert!(n >= 3);
     assert_eq!(buffer[0], 255); // IAC
     assert_eq!(buffer[1], 254); // DONT
-    assert_eq!(buffer[2], 200); // Option 200
+    assert_eq!(buffer[2], 199); // Option 199
 
     server.shutdown().await.unwrap();
 }